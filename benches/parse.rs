@@ -0,0 +1,66 @@
+// Lines per second through the nginx parser: as it was, compiling its
+// regex for every line; with the regex compiled once; and as it is, split
+// by hand. Then Apache's combined format, also split by hand. Run with
+// cargo bench
+use std::hint::black_box;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use httop::Request;
+use httop::parser::{self, LogFormat};
+use regex::Regex;
+
+const NGINX: &str = r#"(\S+) (?:\S+) (?:\S+) \[([^\]]+)\] "(\S+) (\S+)([^"]+)" (\d+) (\d+) "([^"]*)" "([^"]*)" (?:(\d+\.\d+))?(.*)"#;
+
+static COMPILED: LazyLock<Regex> = LazyLock::new(|| Regex::new(NGINX).unwrap());
+
+fn lines() -> Vec<String> {
+    (0..1000)
+        .map(|i| format!(
+            r#"10.0.{}.{} - - [29/Nov/2021:12:{:02}:{:02} +0000] "GET /api/items/{}?page={} HTTP/1.1" {} {} "https://example.com/" "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 Chrome/120.0" 0.{:03} upstream_response_time=0.010"#,
+            i / 256, i % 256, i / 60 % 60, i % 60, i, i % 7, [200, 200, 304, 404, 500][i % 5], i * 37 % 9000, i % 1000))
+        .collect()
+}
+
+fn with_regex(regex: &Regex, line: &str) -> Option<Request> {
+    let caps = regex.captures(line)?;
+    let timestamp = DateTime::parse_from_str(caps.get(2)?.as_str(), "%d/%b/%Y:%H:%M:%S %z").ok()?.with_timezone(&Utc);
+    let mut request = Request::new(timestamp, caps.get(1)?.as_str(), caps.get(3)?.as_str(), caps.get(4)?.as_str(),
+        caps.get(6)?.as_str().parse().ok()?, caps.get(7)?.as_str().parse().ok()?);
+    request.referrer = caps.get(8)?.as_str().to_string();
+    request.user_agent = caps.get(9)?.as_str().to_string();
+    request.response_time = caps.get(10).map_or(0.0, |m| m.as_str().parse().unwrap_or(0.0));
+    Some(request.with_fields(caps.get(11).map_or_else(Vec::new, |m| parser::parse_fields(m.as_str()))))
+}
+
+// Lines per second, going through `lines` for at least a second
+fn measure(name: &str, lines: &[String], mut parse: impl FnMut(&str) -> Option<Request>) -> f64 {
+    let started = Instant::now();
+    let mut parsed = 0;
+    while started.elapsed() < Duration::from_secs(1) {
+        for line in lines {
+            black_box(parse(black_box(line)).expect("parses"));
+        }
+        parsed += lines.len();
+    }
+    let rate = parsed as f64 / started.elapsed().as_secs_f64();
+    println!("{:<34} {:>10.0} lines/s", name, rate);
+    rate
+}
+
+fn main() {
+    let lines = lines();
+    let per_line = measure("regex compiled for every line", &lines[..100], |line| with_regex(&Regex::new(NGINX).unwrap(), line));
+    let once = measure("regex compiled once", &lines, |line| with_regex(&COMPILED, line));
+    let split = measure("nginx (split by hand)", &lines, parser::parse_log_line);
+    let mut format = LogFormat::Nginx;
+    measure("nginx through LogFormat", &lines, |line| format.parse(line));
+    // The same lines without the response time and fields
+    let apache: Vec<String> = lines.iter().map(|line| line[..line.rfind('"').unwrap() + 1].to_string()).collect();
+    let mut format = LogFormat::ApacheCombined;
+    measure("apache-combined through LogFormat", &apache, |line| format.parse(line));
+    println!();
+    println!("compiled once: {:.0}x, split by hand: {:.0}x the lines of compiling for each, {:.1}x those of the compiled regex",
+        once / per_line, split / per_line, split / once);
+}
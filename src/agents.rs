@@ -0,0 +1,108 @@
+// How the User Agents tab groups its rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    Raw,
+    Browser,
+    Os,
+    Device,
+}
+
+impl Grouping {
+    pub fn next(self) -> Self {
+        match self {
+            Grouping::Raw => Grouping::Browser,
+            Grouping::Browser => Grouping::Os,
+            Grouping::Os => Grouping::Device,
+            Grouping::Device => Grouping::Raw,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Grouping::Raw => "user agent",
+            Grouping::Browser => "browser",
+            Grouping::Os => "OS",
+            Grouping::Device => "device",
+        }
+    }
+
+    // The group `user_agent` falls in, or the string itself
+    pub fn group(self, user_agent: &str) -> &str {
+        match self {
+            Grouping::Raw => user_agent,
+            Grouping::Browser => browser(user_agent),
+            Grouping::Os => os(user_agent),
+            Grouping::Device => device(user_agent),
+        }
+    }
+}
+
+// Order matters: Edge and Opera also claim Chrome, Chrome also claims
+// Safari, and nearly everything claims Mozilla
+const BROWSERS: &[(&str, &str)] = &[
+    ("Edg/", "Edge"),
+    ("Edge/", "Edge"),
+    ("OPR/", "Opera"),
+    ("Opera", "Opera"),
+    ("SamsungBrowser/", "Samsung Internet"),
+    ("YaBrowser/", "Yandex Browser"),
+    ("Firefox/", "Firefox"),
+    ("FxiOS/", "Firefox"),
+    ("CriOS/", "Chrome"),
+    ("Chrome/", "Chrome"),
+    ("Chromium/", "Chrome"),
+    ("Version/", "Safari"),
+    ("MSIE ", "Internet Explorer"),
+    ("Trident/", "Internet Explorer"),
+    ("curl/", "curl"),
+    ("Wget/", "Wget"),
+    ("python-requests/", "Python"),
+    ("Python-urllib/", "Python"),
+    ("aiohttp/", "Python"),
+    ("Go-http-client/", "Go"),
+    ("okhttp/", "OkHttp"),
+    ("Java/", "Java"),
+    ("axios/", "Node.js"),
+    ("node-fetch/", "Node.js"),
+    ("PostmanRuntime/", "Postman"),
+];
+
+// iPads also say Mac OS X, and Android says Linux
+const SYSTEMS: &[(&str, &str)] = &[
+    ("Windows", "Windows"),
+    ("iPhone", "iOS"),
+    ("iPad", "iOS"),
+    ("iPod", "iOS"),
+    ("Android", "Android"),
+    ("CrOS", "ChromeOS"),
+    ("Macintosh", "macOS"),
+    ("Mac OS X", "macOS"),
+    ("Linux", "Linux"),
+    ("FreeBSD", "FreeBSD"),
+];
+
+pub fn browser(user_agent: &str) -> &'static str {
+    BROWSERS.iter()
+        .find(|(marker, _)| user_agent.contains(marker))
+        .map_or("Other", |(_, name)| name)
+}
+
+pub fn os(user_agent: &str) -> &'static str {
+    SYSTEMS.iter()
+        .find(|(marker, _)| user_agent.contains(marker))
+        .map_or("Other", |(_, name)| name)
+}
+
+// Android phones say Mobile, Android tablets do not
+pub fn device(user_agent: &str) -> &'static str {
+    let android = user_agent.contains("Android");
+    if user_agent.contains("iPad") || user_agent.contains("Tablet") || (android && !user_agent.contains("Mobile")) {
+        "Tablet"
+    } else if android || user_agent.contains("Mobi") || user_agent.contains("iPhone") || user_agent.contains("iPod") {
+        "Mobile"
+    } else if ["Windows", "Macintosh", "X11", "CrOS"].iter().any(|marker| user_agent.contains(marker)) {
+        "Desktop"
+    } else {
+        "Other"
+    }
+}
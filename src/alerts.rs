@@ -0,0 +1,274 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Rps,
+    ErrorRate,
+    ClientErrorRate,
+    InFlight,
+    AvgResponseTime,
+    P95ResponseTime,
+    P99ResponseTime,
+    // The busiest client's requests per second
+    RpsFromSingleIp,
+}
+
+impl Metric {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "rps" => Some(Metric::Rps),
+            "error_rate" | "5xx_rate" => Some(Metric::ErrorRate),
+            "4xx_rate" => Some(Metric::ClientErrorRate),
+            "in_flight" => Some(Metric::InFlight),
+            "avg_rt" | "avg_response_time" => Some(Metric::AvgResponseTime),
+            "p95" | "p95_rt" => Some(Metric::P95ResponseTime),
+            "p99" | "p99_rt" => Some(Metric::P99ResponseTime),
+            "rps_from_single_ip" => Some(Metric::RpsFromSingleIp),
+            _ => None,
+        }
+    }
+
+    pub fn format(self, value: f64) -> String {
+        match self {
+            Metric::ErrorRate | Metric::ClientErrorRate => format!("{:.1}%", value),
+            Metric::AvgResponseTime | Metric::P95ResponseTime | Metric::P99ResponseTime => {
+                format!("{:.0}ms", value * 1000.0)
+            }
+            Metric::Rps | Metric::InFlight | Metric::RpsFromSingleIp => format!("{:.2}", value),
+        }
+    }
+}
+
+// Current values of everything a rule can refer to, over the last minute
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Metrics {
+    pub rps: f64,
+    pub error_rate: f64,
+    pub client_error_rate: f64,
+    pub in_flight: f64,
+    pub avg_response_time: f64,
+    pub p95_response_time: f64,
+    pub p99_response_time: f64,
+    pub rps_from_single_ip: f64,
+}
+
+impl Metrics {
+    fn get(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Rps => self.rps,
+            Metric::ErrorRate => self.error_rate,
+            Metric::ClientErrorRate => self.client_error_rate,
+            Metric::InFlight => self.in_flight,
+            Metric::AvgResponseTime => self.avg_response_time,
+            Metric::P95ResponseTime => self.p95_response_time,
+            Metric::P99ResponseTime => self.p99_response_time,
+            Metric::RpsFromSingleIp => self.rps_from_single_ip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Above,
+    AtLeast,
+    Below,
+    AtMost,
+}
+
+impl Op {
+    fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Op::Above => value > threshold,
+            Op::AtLeast => value >= threshold,
+            Op::Below => value < threshold,
+            Op::AtMost => value <= threshold,
+        }
+    }
+}
+
+// A threshold rule such as `error_rate>5%`, `avg_rt>800ms` or `rps<1`,
+// optionally to hold for a while before firing: `error_rate>5% for 2m`
+#[derive(Debug, Clone)]
+pub struct Rule {
+    expr: String,
+    pub metric: Metric,
+    op: Op,
+    threshold: f64,
+    hold: Duration,
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        let (condition, hold) = match expr.trim().rsplit_once(" for ") {
+            Some((condition, hold)) => (condition, parse_duration(hold.trim())
+                .ok_or_else(|| format!("invalid duration '{}' in alert rule '{}' (e.g. 30s, 2m)", hold.trim(), expr))?),
+            None => (expr, Duration::ZERO),
+        };
+        let mut compact: String = condition.chars().filter(|c| !c.is_whitespace()).collect();
+        let op_start = compact.find(['>', '<'])
+            .ok_or_else(|| format!("alert rule '{}' has no comparison (> >= < <=)", expr))?;
+        let (name, rest) = compact.split_at(op_start);
+
+        let (op, value) = if let Some(value) = rest.strip_prefix(">=") {
+            (Op::AtLeast, value)
+        } else if let Some(value) = rest.strip_prefix("<=") {
+            (Op::AtMost, value)
+        } else if let Some(value) = rest.strip_prefix('>') {
+            (Op::Above, value)
+        } else {
+            (Op::Below, &rest[1..])
+        };
+
+        let metric = Metric::parse(name)
+            .ok_or_else(|| format!("unknown alert metric '{}' (rps, error_rate, 4xx_rate, in_flight, avg_rt, p95, p99, rps_from_single_ip)", name))?;
+        let threshold = parse_threshold(metric, value)
+            .ok_or_else(|| format!("invalid threshold '{}' in alert rule '{}'", value, expr))?;
+
+        if !hold.is_zero() {
+            compact.push_str(&format!(" for {}", hold_text(hold)));
+        }
+        Ok(Rule { expr: compact, metric, op, threshold, hold })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
+
+// Times are stored in seconds, rates in percent
+fn parse_threshold(metric: Metric, value: &str) -> Option<f64> {
+    match metric {
+        Metric::AvgResponseTime | Metric::P95ResponseTime | Metric::P99ResponseTime => {
+            if let Some(ms) = value.strip_suffix("ms") {
+                ms.parse::<f64>().ok().map(|ms| ms / 1000.0)
+            } else {
+                value.strip_suffix('s').unwrap_or(value).parse().ok()
+            }
+        }
+        Metric::ErrorRate | Metric::ClientErrorRate => value.strip_suffix('%').unwrap_or(value).parse().ok(),
+        Metric::Rps | Metric::InFlight | Metric::RpsFromSingleIp => value.parse().ok(),
+    }
+}
+
+// 30s, 2m, 1h, or seconds without a unit
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (number, unit) = match value.char_indices().last()? {
+        (at, 's') => (&value[..at], 1.0),
+        (at, 'm') => (&value[..at], 60.0),
+        (at, 'h') => (&value[..at], 3600.0),
+        _ => (value, 1.0),
+    };
+    Duration::try_from_secs_f64(number.parse::<f64>().ok()? * unit).ok()
+}
+
+fn hold_text(hold: Duration) -> String {
+    match hold.as_secs() {
+        seconds if seconds % 3600 == 0 => format!("{}h", seconds / 3600),
+        seconds if seconds % 60 == 0 => format!("{}m", seconds / 60),
+        seconds => format!("{}s", seconds),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub rule: Rule,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone)]
+pub enum Transition {
+    Fired(Alert),
+    Resolved(Alert),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Alerts {
+    rules: Vec<Rule>,
+    firing: Vec<bool>,
+    // Since when each rule's condition has held, if it does
+    breached_since: Vec<Option<Instant>>,
+}
+
+impl Alerts {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        let firing = vec![false; rules.len()];
+        let breached_since = vec![None; rules.len()];
+        Alerts { rules, firing, breached_since }
+    }
+
+    // Updates which rules fire, returning the ones that started or stopped.
+    // A rule with a hold time fires once its condition held that long
+    pub fn evaluate(&mut self, now: Instant, metrics: &Metrics) -> Vec<Transition> {
+        let mut transitions = Vec::new();
+        for ((rule, firing), since) in self.rules.iter().zip(self.firing.iter_mut()).zip(self.breached_since.iter_mut()) {
+            let value = metrics.get(rule.metric);
+            *since = rule.op.holds(value, rule.threshold).then(|| since.unwrap_or(now));
+            let breached = since.is_some_and(|since| now.saturating_duration_since(since) >= rule.hold);
+            let alert = Alert { rule: rule.clone(), value };
+            match (breached, *firing) {
+                (true, false) => transitions.push(Transition::Fired(alert)),
+                (false, true) => transitions.push(Transition::Resolved(alert)),
+                _ => {}
+            }
+            *firing = breached;
+        }
+        transitions
+    }
+
+    pub fn active(&self, metrics: &Metrics) -> Vec<Alert> {
+        self.rules.iter()
+            .zip(&self.firing)
+            .filter(|(_, firing)| **firing)
+            .map(|(rule, _)| Alert { rule: rule.clone(), value: metrics.get(rule.metric) })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules() {
+        let rule: Rule = "error_rate > 5% for 120s".parse().unwrap();
+        assert_eq!(rule.to_string(), "error_rate>5% for 2m");
+        assert_eq!((rule.metric, rule.op, rule.threshold, rule.hold), (Metric::ErrorRate, Op::Above, 5.0, Duration::from_secs(120)));
+        let rule: Rule = "p99<=800ms".parse().unwrap();
+        assert_eq!((rule.metric, rule.op, rule.threshold), (Metric::P99ResponseTime, Op::AtMost, 0.8));
+        let rule: Rule = "avg_rt>=1.5s".parse().unwrap();
+        assert_eq!((rule.op, rule.threshold), (Op::AtLeast, 1.5));
+
+        for expr in ["error_rate=5%", "latency>1s", "rps<many", "rps<1 for ages"] {
+            assert!(expr.parse::<Rule>().is_err(), "{}", expr);
+        }
+    }
+
+    // A rule with a hold time fires once its condition held that long, and
+    // resolves as soon as it no longer holds
+    #[test]
+    fn held() {
+        let mut alerts = Alerts::new(vec!["error_rate>5% for 2m".parse().unwrap(), "rps<1".parse().unwrap()]);
+        let start = Instant::now();
+        let failing = Metrics { error_rate: 8.0, rps: 50.0, ..Metrics::default() };
+        assert!(alerts.evaluate(start, &failing).is_empty());
+        assert!(alerts.evaluate(start + Duration::from_secs(60), &failing).is_empty());
+        let fired = alerts.evaluate(start + Duration::from_secs(120), &failing);
+        assert!(matches!(fired.as_slice(), [Transition::Fired(alert)] if alert.value == 8.0));
+        assert_eq!(alerts.active(&failing).len(), 1);
+        assert!(alerts.evaluate(start + Duration::from_secs(180), &failing).is_empty());
+
+        let quiet = Metrics { error_rate: 1.0, rps: 0.5, ..Metrics::default() };
+        let changed = alerts.evaluate(start + Duration::from_secs(240), &quiet);
+        assert!(matches!(changed.as_slice(), [Transition::Resolved(_), Transition::Fired(alert)] if alert.rule.metric == Metric::Rps));
+
+        // The hold starts over once the condition lapses
+        assert!(alerts.evaluate(start + Duration::from_secs(300), &failing).iter().all(|t| matches!(t, Transition::Resolved(_))));
+        assert!(alerts.evaluate(start + Duration::from_secs(360), &failing).is_empty());
+    }
+}
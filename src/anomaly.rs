@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+
+use crate::notices::Notices;
+
+pub const DEFAULT_SIGMAS: f64 = 3.0;
+// Each sample covers this long, and the baseline is the latest samples
+// up to ten minutes back
+const PERIOD: Duration = Duration::from_secs(10);
+const BASELINE_SAMPLES: usize = 60;
+// Two minutes of traffic before anything counts as unusual
+const MIN_SAMPLES: usize = 12;
+// Error rates over fewer requests than this are noise
+const MIN_REQUESTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Rps,
+    ErrorRate,
+}
+
+impl Metric {
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Rps => "RPS",
+            Metric::ErrorRate => "5xx rate",
+        }
+    }
+
+    pub fn format(self, value: f64) -> String {
+        match self {
+            Metric::Rps => format!("{:.1}/s", value),
+            Metric::ErrorRate => format!("{:.1}%", value),
+        }
+    }
+
+    // Perfectly steady traffic has no spread at all, so a change of a
+    // request per second (or an error rate point), or 5%, is the least
+    // that counts
+    fn min_deviation(self, mean: f64) -> f64 {
+        (mean * 0.05).max(1.0)
+    }
+
+    // A drop in traffic is as telling as a surge; fewer errors are not
+    fn unusual(self, sigmas: f64, limit: f64) -> bool {
+        match self {
+            Metric::Rps => sigmas.abs() > limit,
+            Metric::ErrorRate => sigmas > limit,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Anomaly {
+    pub metric: Metric,
+    pub value: f64,
+    pub mean: f64,
+    pub deviation: f64,
+    pub started: Instant,
+    // History id of the first request in the period it began with
+    pub first: usize,
+}
+
+impl Anomaly {
+    pub fn sigmas(&self) -> f64 {
+        (self.value - self.mean) / self.deviation
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} {}, usual {} ± {} ({:+.1}σ)", self.metric.name(), self.metric.format(self.value),
+            self.metric.format(self.mean), self.metric.format(self.deviation), self.sigmas())
+    }
+}
+
+#[derive(Debug)]
+struct Series {
+    metric: Metric,
+    // Normal periods only, so that a long spike does not become the norm
+    baseline: VecDeque<f64>,
+    active: Option<Anomaly>,
+}
+
+impl Series {
+    fn new(metric: Metric) -> Self {
+        Series { metric, baseline: VecDeque::new(), active: None }
+    }
+
+    // Mean and standard deviation, once there are enough samples
+    fn spread(&self) -> Option<(f64, f64)> {
+        if self.baseline.len() < MIN_SAMPLES {
+            return None;
+        }
+        let count = self.baseline.len() as f64;
+        let mean = self.baseline.iter().sum::<f64>() / count;
+        let variance = self.baseline.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / count;
+        Some((mean, variance.sqrt().max(self.metric.min_deviation(mean))))
+    }
+
+    fn push(&mut self, value: f64) {
+        if self.baseline.len() == BASELINE_SAMPLES {
+            self.baseline.pop_front();
+        }
+        self.baseline.push_back(value);
+    }
+}
+
+// Compares requests per second and the 5xx rate of each period with the
+// periods before, flagging values more than `sigmas` standard deviations
+// off the mean
+#[derive(Debug)]
+pub struct Detector {
+    sigmas: f64,
+    series: [Series; 2],
+    // Start of the current period, with the running totals and history id
+    // at that point
+    period: Option<(Instant, usize, usize, usize)>,
+    log: Option<File>,
+    notices: Arc<Notices>,
+}
+
+impl Detector {
+    pub fn new(sigmas: f64, log: Option<File>, notices: Arc<Notices>) -> Self {
+        Detector {
+            sigmas,
+            series: [Series::new(Metric::Rps), Series::new(Metric::ErrorRate)],
+            period: None,
+            log,
+            notices,
+        }
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &Anomaly> {
+        self.series.iter().filter_map(|series| series.active.as_ref())
+    }
+
+    // Takes the running request and 5xx totals, and the id the next
+    // request in the history will get
+    pub fn sample(&mut self, now: Instant, requests: usize, errors: usize, next_id: usize) {
+        if self.sigmas <= 0.0 {
+            return;
+        }
+        let Some((start, start_requests, start_errors, first)) = self.period else {
+            self.period = Some((now, requests, errors, next_id));
+            return;
+        };
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed < PERIOD {
+            return;
+        }
+        self.period = Some((now, requests, errors, next_id));
+
+        let count = requests.saturating_sub(start_requests);
+        let rps = count as f64 / elapsed.as_secs_f64();
+        self.check(0, now, Some(rps), first);
+        let error_rate = (count >= MIN_REQUESTS)
+            .then(|| errors.saturating_sub(start_errors) as f64 / count as f64 * 100.0);
+        self.check(1, now, error_rate, first);
+    }
+
+    // Without a value, as for the error rate of too few requests, a
+    // running anomaly ends but the baseline stays as it is
+    fn check(&mut self, index: usize, now: Instant, value: Option<f64>, first: usize) {
+        let series = &mut self.series[index];
+        let Some(value) = value else {
+            if let Some(active) = series.active.take() {
+                let line = format!("anomaly over: {} after {}s, too few requests to tell", active.metric.name(),
+                    now.saturating_duration_since(active.started).as_secs());
+                self.write_log(&line);
+            }
+            return;
+        };
+        let Some((mean, deviation)) = series.spread() else {
+            series.push(value);
+            return;
+        };
+        let unusual = series.metric.unusual((value - mean) / deviation, self.sigmas);
+
+        match (unusual, &mut series.active) {
+            (true, Some(active)) => active.value = value,
+            (true, None) => {
+                let anomaly = Anomaly { metric: series.metric, value, mean, deviation, started: now, first };
+                let line = format!("anomaly: {}", anomaly.describe());
+                series.active = Some(anomaly);
+                self.write_log(&line);
+            }
+            (false, Some(active)) => {
+                let line = format!("anomaly over: {} back to {} after {}s", active.metric.name(),
+                    active.metric.format(value), now.saturating_duration_since(active.started).as_secs());
+                series.active = None;
+                series.push(value);
+                self.write_log(&line);
+            }
+            (false, None) => series.push(value),
+        }
+    }
+
+    fn write_log(&mut self, line: &str) {
+        if let Some(file) = self.log.as_mut()
+            && let Err(e) = writeln!(file, "{} {}", Local::now().format("%Y-%m-%d %H:%M:%S"), line) {
+            self.notices.post(format!("anomaly log stopped: {}", e));
+            self.log = None;
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::quantiles::Quantiles;
+
+// Apdex's own suggestion for a target response time, used by the apdex
+// column until --apdex-t sets one
+pub const DEFAULT_T: f64 = 0.5;
+
+// Requests answered within T satisfy, those within 4T are tolerated and
+// the slower rest frustrate; only requests with a logged time count
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Apdex {
+    pub satisfied: usize,
+    pub tolerating: usize,
+    pub frustrated: usize,
+}
+
+impl Apdex {
+    // Read off a sketch, so a bucket straddling T or 4T counts as under it
+    pub fn of_sketch(times: &Quantiles, t: f64) -> Self {
+        let satisfied = times.count_at_most(t);
+        let tolerable = times.count_at_most(4.0 * t);
+        Apdex {
+            satisfied,
+            tolerating: tolerable - satisfied,
+            frustrated: times.count() - tolerable,
+        }
+    }
+
+    pub fn of_times(times: &[f64], t: f64) -> Self {
+        let mut apdex = Apdex::default();
+        for &time in times {
+            if time <= t {
+                apdex.satisfied += 1;
+            } else if time <= 4.0 * t {
+                apdex.tolerating += 1;
+            } else {
+                apdex.frustrated += 1;
+            }
+        }
+        apdex
+    }
+
+    pub fn total(&self) -> usize {
+        self.satisfied + self.tolerating + self.frustrated
+    }
+
+    // From 0 (everyone frustrated) to 1 (everyone satisfied)
+    pub fn score(&self) -> Option<f64> {
+        let total = self.total();
+        (total > 0).then(|| (self.satisfied as f64 + self.tolerating as f64 / 2.0) / total as f64)
+    }
+
+    pub fn score_text(&self) -> String {
+        self.score().map_or("-".to_string(), |score| format!("{:.2}", score))
+    }
+}
+
+// 300ms, 0.3s, or seconds without a unit
+pub fn parse_t(text: &str) -> Option<f64> {
+    let seconds: f64 = match text.strip_suffix("ms") {
+        Some(millis) => millis.parse::<f64>().ok()? / 1000.0,
+        None => text.strip_suffix('s').unwrap_or(text).parse().ok()?,
+    };
+    (seconds.is_finite() && seconds > 0.0).then_some(seconds)
+}
@@ -0,0 +1,1710 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::prelude::*;
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Tabs;
+
+use crate::{
+    anomaly, apdex, attacks, bans, bots, checkpoint, clipboard, columns, curl, daemon, detail, emit, export,
+    follow, highlight, histogram, kube, piped, record, s3, serve, slo, source, statsd, syslog, tenants, terminal,
+    trace,
+};
+use crate::{Request, Stats};
+use crate::agents::Grouping;
+use crate::alerts::{Alerts, Transition};
+use crate::asn::AsnDb;
+use crate::baseline::{Baseline, BaselineRecorder};
+use crate::changes::RowChanges;
+use crate::chart::ChartStyle;
+use crate::columns::Column;
+use crate::compare::{Comparison, Period};
+use crate::config::{Config, Mode};
+use crate::geoip::GeoIp;
+use crate::history::{self, History, Query};
+use crate::paging::Paging;
+use crate::rate::{ClassTimeline, RateHistory};
+use crate::rdap::Rdap;
+use crate::referrers::SpamList;
+use crate::slo::{Slos, State};
+use crate::source::{Health, Ingest, Published, Writers};
+use crate::stats::Panel;
+use crate::tenants::{TenantEntry, TenantStats};
+use crate::toggles::Toggles;
+use crate::visits::VisitStats;
+
+use page::{Frozen, Page};
+
+// The display is drawn by Httop methods kept by what they draw: the status
+// panel and lines around a view, the tables of requests by key, and the
+// other views, each into a Page; keys turns key presses into commands
+mod keys;
+mod page;
+mod panels;
+mod tables;
+mod views;
+
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+pub const DISPLAY_LIMIT: usize = 20;
+// + and - change the limit by this much, down to it at the least
+pub const MIN_DISPLAY_LIMIT: usize = 5;
+// Refresh intervals stepped through with < and >
+const INTERVALS: &[Duration] = &[
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+
+// Requests matching a search, counted from the history as they arrive
+struct Filtered {
+    stats: Stats,
+    next_id: usize,
+}
+
+impl Filtered {
+    fn catch_up(&mut self, history: &History, search: Option<&Query>, toggles: &Toggles) {
+        for request in history.since(self.next_id).filter(|request| shown(request, search, toggles)) {
+            self.stats.update(request.clone());
+        }
+        self.next_id = history.next_id();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortBy {
+    Count,
+    Bytes,
+    // 95th percentile response time, slowest first
+    Latency,
+    // The tab's own key: path, IP, user agent, ...
+    Name,
+    // Cache hit ratio, lowest first; rows without a cache status last
+    CacheHit,
+}
+
+impl SortBy {
+    fn name(&self) -> &'static str {
+        match self {
+            SortBy::Count => "Count",
+            SortBy::Bytes => "Bytes",
+            SortBy::Latency => "Latency",
+            SortBy::Name => "Name",
+            SortBy::CacheHit => "Cache Hits",
+        }
+    }
+}
+
+type SortRow<'a> = (&'a str, usize, usize, f64, Option<f64>);
+
+// Counts, bytes and latency sort largest first, cache hit ratios lowest first
+// and names alphabetically. Ties go to the key sorted by before, then to the
+// name; r flips the whole order
+struct Sort {
+    by: SortBy,
+    then: Option<SortBy>,
+    reversed: bool,
+}
+
+impl Sort {
+    fn select(&mut self, by: SortBy) {
+        if by != self.by {
+            // Names are unique, so nothing is left to break ties after them
+            self.then = Some(self.by).filter(|previous| *previous != SortBy::Name);
+            self.by = by;
+        }
+    }
+
+    // Rows as (name, count, bytes, p95 response time, cache hit ratio)
+    fn compare(&self, a: SortRow, b: SortRow) -> std::cmp::Ordering {
+        let key = |by: SortBy| match by {
+            SortBy::Count => b.1.cmp(&a.1),
+            SortBy::Bytes => b.2.cmp(&a.2),
+            SortBy::Latency => b.3.total_cmp(&a.3),
+            SortBy::Name => a.0.cmp(b.0),
+            SortBy::CacheHit => match (a.4, b.4) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            },
+        };
+        let ordering = key(self.by)
+            .then_with(|| self.then.map_or(std::cmp::Ordering::Equal, key))
+            .then_with(|| a.0.cmp(b.0));
+        if self.reversed { ordering.reverse() } else { ordering }
+    }
+
+    fn name(&self) -> String {
+        let mut name = self.by.name().to_string();
+        if let Some(then) = self.then {
+            name += &format!(" then {}", then.name());
+        }
+        if self.reversed {
+            name += ", reversed";
+        }
+        name
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    // The tabs, reached with 1-6 as well
+    Paths,
+    Ips,
+    UserAgents,
+    Status,
+    NotFound,
+    ServerErrors,
+    Offenders,
+    Attacks,
+    RateLimits,
+    Referrers,
+    Vhosts,
+    Bots,
+    Campaigns,
+    Visits,
+    Tenants,
+    Services,
+    Sources,
+    Hosts,
+    Countries,
+    Networks,
+    Transfer,
+    Sizes,
+    Largest,
+    Content,
+    Tls,
+    Protocols,
+    Preflight,
+    Redirects,
+    Outliers,
+    Slow,
+    Tail,
+    Unparsed,
+    Compare,
+    Slos,
+    // Entered with `/`, not part of the v cycle
+    Search,
+    // Entered with Enter on a row of the Paths, IPs, User Agents, Virtual
+    // Hosts, Bots, Networks or Hosts tab
+    Detail,
+}
+
+impl View {
+    fn next(self) -> Self {
+        match self {
+            View::Paths => View::Ips,
+            View::Ips => View::UserAgents,
+            View::UserAgents => View::Status,
+            View::Status => View::NotFound,
+            View::NotFound => View::ServerErrors,
+            View::ServerErrors => View::Offenders,
+            View::Offenders => View::Attacks,
+            View::Attacks => View::RateLimits,
+            View::RateLimits => View::Referrers,
+            View::Referrers => View::Vhosts,
+            View::Vhosts => View::Bots,
+            View::Bots => View::Campaigns,
+            View::Campaigns => View::Visits,
+            View::Visits => View::Tenants,
+            View::Tenants => View::Services,
+            View::Services => View::Sources,
+            View::Sources => View::Hosts,
+            View::Hosts => View::Countries,
+            View::Countries => View::Networks,
+            View::Networks => View::Transfer,
+            View::Transfer => View::Sizes,
+            View::Sizes => View::Largest,
+            View::Largest => View::Content,
+            View::Content => View::Tls,
+            View::Tls => View::Protocols,
+            View::Protocols => View::Preflight,
+            View::Preflight => View::Redirects,
+            View::Redirects => View::Outliers,
+            View::Outliers => View::Slow,
+            View::Slow => View::Tail,
+            View::Tail => View::Unparsed,
+            View::Unparsed => View::Compare,
+            View::Compare => View::Slos,
+            View::Slos => View::Paths,
+            View::Search | View::Detail => View::Paths,
+        }
+    }
+
+    // The panel of Stats only this view draws
+    fn panel(self) -> Option<Panel> {
+        match self {
+            View::Sizes | View::Largest => Some(Panel::Sizes),
+            View::Visits => Some(Panel::Visits),
+            View::Outliers => Some(Panel::Outliers),
+            View::Compare => Some(Panel::Compare),
+            _ => None,
+        }
+    }
+
+    const TABS: [View; 6] = [View::Paths, View::Ips, View::UserAgents, View::Status, View::Referrers, View::Vhosts];
+
+    fn name(self) -> &'static str {
+        match self {
+            View::Paths => "Paths",
+            View::Ips => "IPs",
+            View::UserAgents => "User Agents",
+            View::Status => "Status",
+            View::NotFound => "404 Paths",
+            View::ServerErrors => "5xx Paths",
+            View::Offenders => "Error Offenders",
+            View::Attacks => "Attacks",
+            View::RateLimits => "Client Rates",
+            View::Campaigns => "Campaigns",
+            View::Visits => "Visits",
+            View::Referrers => "Referrers",
+            View::Vhosts => "Virtual Hosts",
+            View::Bots => "Bots",
+            View::Tenants => "Tenants",
+            View::Services => "Services",
+            View::Sources => "Sources",
+            View::Hosts => "Hosts",
+            View::Countries => "Countries",
+            View::Networks => "Networks",
+            View::Transfer => "Transfer",
+            View::Sizes => "Response Sizes",
+            View::Largest => "Largest Responses",
+            View::Content => "Content Types",
+            View::Tls => "TLS",
+            View::Protocols => "HTTP Versions",
+            View::Preflight => "CORS Preflight",
+            View::Redirects => "Redirects",
+            View::Outliers => "Latency Outliers",
+            View::Slow => "Slow Requests",
+            View::Tail => "Live Tail",
+            View::Unparsed => "Unparsed Lines",
+            View::Compare => "Compare",
+            View::Slos => "SLOs",
+            View::Search => "Search",
+            View::Detail => "Details",
+        }
+    }
+}
+
+enum Command {
+    Sort(SortBy),
+    ReverseSort,
+    NextView,
+    ShowView(View),
+    // Moves the row selection, or the table when it has none, up or down
+    Select(isize),
+    // By a window's height
+    Page(isize),
+    OpenDetail,
+    Back,
+    TogglePause,
+    ToggleHelp,
+    ToggleTailPanel,
+    ToggleStatusPanel,
+    TogglePercent,
+    // Shorter (-1) or longer (1) refresh interval
+    ChangeInterval(isize),
+    SetColumns(String),
+    // The window changed size
+    Redraw,
+    IncreaseLimit,
+    DecreaseLimit,
+    ToggleBaseline,
+    ToggleSpamReferrers,
+    ToggleReferrerDomains,
+    GroupUserAgents,
+    ToggleBots,
+    // All responses, only 4xx or only 5xx
+    ToggleStatusClass,
+    ToggleStaticAssets,
+    ToggleDeltas,
+    ToggleEndpoints,
+    ExportJson,
+    ExportCsv,
+    Search(String),
+    CopyCurl(String),
+    Whois(String),
+    OpenTrace(String),
+    // Text being typed after / y w or o, None once it is entered or dropped
+    Prompt(Option<String>),
+    Quit,
+    Noop,
+}
+
+struct Httop {
+    config: Config,
+    // Owned by the aggregator once ingestion starts
+    stats: Arc<Published>,
+    geoip: Option<Arc<GeoIp>>,
+    asn: Option<Arc<AsnDb>>,
+    history: Arc<Mutex<History>>,
+    // Of the inputs, and the lines they had that did not parse
+    health: Arc<Health>,
+    search: Option<Query>,
+    rdap: Rdap,
+    whois: Option<std::net::IpAddr>,
+    sort: Sort,
+    view: View,
+    // The visible window of tables: how many rows, from which one, and
+    // the length of the longest table last drawn
+    display_limit: usize,
+    scroll: usize,
+    table_len: Cell<usize>,
+    baseline: Option<Baseline>,
+    show_spam_referrers: bool,
+    // Referrers grouped by domain rather than by URL
+    referrer_domains: bool,
+    ua_grouping: Grouping,
+    // Bots, static assets or all but 4xx or 5xx responses left out of
+    // every view, which then counts from the history as a search does
+    toggles: Toggles,
+    row_changes: RowChanges,
+    show_deltas: bool,
+    by_endpoint: bool,
+    alerts: Alerts,
+    paging: Paging,
+    statsd: Option<statsd::Statsd>,
+    last_snapshot: Instant,
+    bans: Option<bans::Bans>,
+    anomalies: anomaly::Detector,
+    // The top paths and client IPs of the current anomalies, highlighted
+    // in the tables
+    spike_keys: HashSet<String>,
+    flash_until: Option<Instant>,
+    chart_style: ChartStyle,
+    rps_history: RateHistory,
+    // Stats of only the requests matching the search and not from a
+    // hidden bot, while either filter is on
+    filtered: Option<Filtered>,
+    // Row selection in the Paths, IPs, User Agents, Bots, Networks and Hosts tabs,
+    // the keys of the rows as last drawn, and the row opened with Enter
+    selected: usize,
+    rows: Vec<detail::Key>,
+    detail: Option<(detail::Key, View)>,
+    // The screen frozen with space, and the request total at that moment
+    paused: Option<(Buffer, usize)>,
+    // The help screen, shown instead of the view
+    help: bool,
+    // The latest requests at the bottom of every view
+    tail_panel: bool,
+    // Requests per status class over time, below the status bar
+    status_panel: bool,
+    columns: Vec<Column>,
+    // Whether any request so far had an upstream time
+    upstream_timed: bool,
+    // Counts and bytes as a share of the table's total
+    percent: bool,
+    interval: Duration,
+    message: Option<String>,
+    prompt: Option<String>,
+}
+
+impl Httop {
+    fn new(config: Config) -> io::Result<Self> {
+        let mut spam_list = SpamList::bundled();
+        if let Some(path) = &config.referrer_spam_list {
+            spam_list.extend_from_file(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("could not read referrer spam list {}: {}", path.display(), e))
+            })?;
+        }
+
+        let geoip = match &config.geoip {
+            Some(path) => Some(Arc::new(GeoIp::load(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("could not read GeoIP database {}: {}", path.display(), e))
+            })?)),
+            None => None,
+        };
+        let asn = match &config.asn {
+            Some(path) => Some(Arc::new(AsnDb::load(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("could not read ASN database {}: {}", path.display(), e))
+            })?)),
+            None => None,
+        };
+
+        let anomaly_log = match &config.anomaly_log {
+            Some(path) => Some(File::options().create(true).append(true).open(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("could not open anomaly log {}: {}", path.display(), e))
+            })?),
+            None => None,
+        };
+
+        let health: Arc<Health> = Arc::default();
+        let statsd = match &config.statsd {
+            Some(addr) => Some(statsd::Statsd::connect(addr, &config.statsd_prefix, &config.statsd_tags, config.statsd_interval,
+                Arc::clone(&health.notices))
+                .map_err(|e| io::Error::new(e.kind(), format!("could not set up statsd at {}: {}", addr, e)))?),
+            None => None,
+        };
+
+        if let Some(dir) = &config.snapshot_dir {
+            fs::create_dir_all(dir).map_err(|e| {
+                io::Error::new(e.kind(), format!("could not create snapshot directory {}: {}", dir.display(), e))
+            })?;
+        }
+
+        let ban_file = match &config.ban_file {
+            Some(path) => Some(File::options().create(true).append(true).open(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("could not open ban file {}: {}", path.display(), e))
+            })?),
+            None => None,
+        };
+        let bans = (ban_file.is_some() || config.ban_command.is_some()).then(|| {
+            bans::Bans::new(config.ban_thresholds.clone(), ban_file, config.ban_format.clone(), config.ban_command.clone(),
+                Arc::clone(&health.notices))
+        });
+
+        let referrer_domains = config.referrer_domains;
+        let toggles = Toggles {
+            hide_bots: config.hide_bots,
+            static_assets: config.static_assets.clone().unwrap_or_default(),
+            ..Toggles::default()
+        };
+        let stats = Stats {
+            window: config.window,
+            static_assets: config.static_assets.clone(),
+            slos: Slos::new(config.slos.clone()),
+            max_keys: config.max_keys,
+            max_memory: config.max_memory - config.max_memory / history::MEMORY_SHARE,
+            visits: VisitStats::new(config.visit_timeout),
+            status_timeline: ClassTimeline::new(Instant::now(), config.status_span),
+            ..Stats::new(spam_list, config.drop_duplicates)
+        };
+
+        Ok(Httop {
+            geoip,
+            asn,
+            alerts: Alerts::new(config.alerts.clone()),
+            paging: Paging::new(config.notifiers.clone(), config.alert_cooldown, Arc::clone(&health.notices)),
+            statsd,
+            last_snapshot: Instant::now(),
+            bans,
+            anomalies: anomaly::Detector::new(config.anomaly_sigmas, anomaly_log, Arc::clone(&health.notices)),
+            spike_keys: HashSet::new(),
+            chart_style: config.chart_style.resolve(),
+            rps_history: RateHistory::default(),
+            filtered: None,
+            selected: 0,
+            rows: Vec::new(),
+            detail: None,
+            paused: None,
+            help: false,
+            tail_panel: false,
+            status_panel: false,
+            percent: false,
+            interval: config.interval,
+            columns: config.columns.clone(),
+            upstream_timed: false,
+            history: Arc::new(Mutex::new(History::new(config.history, config.slow_threshold)
+                .with_max_memory(config.max_memory / history::MEMORY_SHARE))),
+            health,
+            search: None,
+            by_endpoint: config.by_endpoint,
+            rdap: Rdap::default(),
+            whois: None,
+            display_limit: config.display_limit,
+            stats: Arc::new(Published::new(stats, config.interval)),
+            config,
+            sort: Sort { by: SortBy::Count, then: None, reversed: false },
+            view: View::Paths,
+            scroll: 0,
+            table_len: Cell::new(0),
+            baseline: None,
+            show_spam_referrers: false,
+            referrer_domains,
+            ua_grouping: Grouping::Raw,
+            toggles,
+            row_changes: RowChanges::default(),
+            show_deltas: false,
+            flash_until: None,
+            message: None,
+            prompt: None,
+        })
+    }
+
+    fn load_baseline(&mut self) {
+        let Some(path) = self.config.baseline.clone() else {
+            return;
+        };
+        if !path.exists() {
+            return;
+        }
+
+        match Baseline::load(&path) {
+            Ok(baseline) => {
+                self.message = Some(format!("Baseline loaded from {} ({} paths)",
+                    path.display(), baseline.paths.len()));
+                self.baseline = Some(baseline);
+            }
+            Err(e) => {
+                self.message = Some(format!("Could not load baseline {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    fn toggle_baseline(&mut self) {
+        let now = Instant::now();
+        let recorder = self.stats.update(move |stats| {
+            let recorder = stats.baseline_recorder.take();
+            if recorder.is_none() {
+                stats.baseline_recorder = Some(BaselineRecorder::new(now));
+            }
+            recorder
+        });
+
+        let Some(recorder) = recorder else {
+            self.message = Some("Recording baseline, press B again to stop".to_string());
+            return;
+        };
+
+        let baseline = recorder.finish(now);
+        self.message = Some(match &self.config.baseline {
+            Some(path) => match baseline.save(path) {
+                Ok(()) => format!("Baseline of {} paths saved to {}", baseline.paths.len(), path.display()),
+                Err(e) => format!("Could not save baseline to {}: {}", path.display(), e),
+            },
+            None => format!("Baseline of {} paths recorded", baseline.paths.len()),
+        });
+        self.baseline = Some(baseline);
+    }
+
+    // Reads the --compare log into the baseline, or has the stats keep the
+    // --compare-window before the current one
+    fn load_comparison(&self) -> io::Result<()> {
+        let comparison = match (&self.config.compare, self.config.compare_window) {
+            (Some(path), _) => {
+                let name = path.display().to_string();
+                let could_not = |e: io::Error| io::Error::new(e.kind(), format!("could not read --compare {}: {}", name, e));
+                let mut baseline = Period::default();
+                let mut each = |request: Request| {
+                    let assets = self.config.static_assets.as_ref();
+                    let path = assets.and_then(|assets| assets.bucket(&request.path)).unwrap_or(&request.path);
+                    baseline.record(&request, path);
+                };
+                let (geoip, asn) = (self.geoip.as_deref(), self.asn.as_deref());
+                match piped::Piped::decompress(path).map_err(could_not)? {
+                    Some(archive) => source::read_all(io::BufReader::new(archive), &self.config, geoip, asn, &mut each),
+                    None => source::read_all(io::BufReader::new(File::open(path).map_err(could_not)?), &self.config, geoip, asn, &mut each),
+                }.map_err(could_not)?;
+                Comparison::file(name, baseline)
+            }
+            (None, Some(window)) => Comparison::window(window),
+            (None, None) => return Ok(()),
+        };
+        self.stats.update(move |stats| stats.comparison = Some(comparison));
+        Ok(())
+    }
+
+    fn start_ingest(&self) -> io::Result<Ingest> {
+        self.load_comparison()?;
+        let recorder = match &self.config.record {
+            Some(path) => Some(record::Recorder::create(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("could not create recording {}: {}", path.display(), e))
+            })?),
+            None => None,
+        };
+
+        let emitter = match &self.config.emit_parsed {
+            Some(path) => Some(emit::Emitter::create(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("could not create {}: {}", path.display(), e))
+            })?),
+            None => None,
+        };
+
+        let mut bots = bots::Detector::new(self.config.robots_txt_bots);
+        let mut requests = Vec::new();
+        let checkpoint = match &self.config.checkpoint {
+            Some(path) => {
+                let could_not = |action: &str, e: io::Error| {
+                    io::Error::new(e.kind(), format!("could not {} checkpoint {}: {}", action, path.display(), e))
+                };
+                if self.config.resume && path.exists() {
+                    let read = checkpoint::read(path, self.config.window).map_err(|e| could_not("read", e))?;
+                    (requests, bots) = self.resume(read, bots);
+                }
+                Some(checkpoint::Checkpoint::create(path, self.config.window, &requests).map_err(|e| could_not("create", e))?)
+            }
+            None => None,
+        };
+
+        let writers = Writers { checkpoint, recorder, emitter };
+        let mut ingest = Ingest::start(Arc::clone(&self.health), Arc::clone(&self.stats), Arc::clone(&self.history), writers,
+            self.geoip.clone(), self.asn.clone(), bots);
+        ingest.skip_counted(&requests);
+        match &self.config.replay {
+            Some(path) => ingest.spawn_replay(path.clone(), self.config.clone()).map_err(|e| {
+                io::Error::new(e.kind(), format!("could not replay {}: {}", path.display(), e))
+            })?,
+            None if self.config.reads_stdin() => {
+                ingest.spawn_reader("stdin", io::BufReader::new(io::stdin()), self.config.clone());
+            }
+            None => {
+                for path in &self.config.files {
+                    let name = path.display().to_string();
+                    let could_not_open = |e: io::Error| io::Error::new(e.kind(), format!("could not open {}: {}", name, e));
+                    match piped::Piped::decompress(path).map_err(could_not_open)? {
+                        Some(archive) => ingest.spawn_reader(&name, io::BufReader::new(archive), self.config.clone()),
+                        None if matches!(self.config.mode, Mode::Batch) => {
+                            let file = File::open(path).map_err(could_not_open)?;
+                            ingest.spawn_reader(&name, io::BufReader::new(file), self.config.clone());
+                        }
+                        None => {
+                            let file = follow::Follow::open(path.clone()).map_err(could_not_open)?;
+                            ingest.spawn_reader(&name, io::BufReader::new(file), self.config.clone());
+                        }
+                    }
+                }
+                if self.config.journald {
+                    let mut args = ["--follow", "--output=cat"].map(String::from).to_vec();
+                    args.extend(self.config.units.iter().map(|unit| format!("--unit={}", unit)));
+                    let journal = piped::Piped::spawn("journalctl", &args)?;
+                    let name = if self.config.units.is_empty() {
+                        "journald".to_string()
+                    } else {
+                        format!("journald {}", self.config.units.join(","))
+                    };
+                    ingest.spawn_reader(&name, io::BufReader::new(journal), self.config.clone());
+                }
+                for container in &self.config.containers {
+                    let args = ["logs", "--follow", container].map(String::from);
+                    let logs = piped::Piped::spawn("docker", &args)?;
+                    ingest.spawn_reader(&format!("docker {}", container), io::BufReader::new(logs), self.config.clone());
+                }
+                for target in &self.config.kube {
+                    let args = kube::logs_args(target, self.config.namespace.as_deref())
+                        .map_err(io::Error::other)?;
+                    let logs = piped::Piped::spawn("kubectl", &args)?;
+                    ingest.spawn_kube_reader(&format!("kube {}", target), io::BufReader::new(logs), self.config.clone());
+                }
+                for target in &self.config.ssh {
+                    let (host, path) = target.split_once(':').expect("checked by Config");
+                    // The remote shell sees the path, so quote it
+                    let command = format!("tail -n +1 -F '{}'", path.replace('\'', "'\\''"));
+                    let args = ["-o", "BatchMode=yes", host, &command].map(String::from);
+                    let tail = piped::Piped::spawn("ssh", &args)?;
+                    let name = host.rsplit('@').next().unwrap_or(host);
+                    ingest.spawn_reader(name, io::BufReader::new(tail), self.config.clone());
+                }
+                for location in &self.config.s3 {
+                    let poll = self.config.s3_poll.map(Duration::from_secs);
+                    let objects = s3::Objects::open(location, poll).map_err(io::Error::other)?;
+                    ingest.spawn_reader(location, io::BufReader::new(objects), self.config.clone());
+                }
+                for kafka in &self.config.kafka {
+                    let consumer = piped::Piped::spawn("kcat", &kafka.consume_args())?;
+                    ingest.spawn_reader(&format!("kafka {}", kafka.topic), io::BufReader::new(consumer), self.config.clone());
+                }
+                for path in &self.config.unix_sockets {
+                    syslog::listen_unix(&ingest, path, &self.config).map_err(|e| {
+                        io::Error::new(e.kind(), format!("could not listen for syslog on {}: {}", path.display(), e))
+                    })?;
+                }
+                for addr in &self.config.syslog {
+                    syslog::listen(&ingest, addr, &self.config).map_err(|e| {
+                        io::Error::new(e.kind(), format!("could not listen for syslog on {}: {}", addr, e))
+                    })?;
+                }
+            }
+        }
+        Ok(ingest)
+    }
+
+    // Headless: no display, only the health endpoints. As a daemon it also
+    // talks to systemd, reloads on SIGHUP and exits cleanly on SIGTERM
+    fn serve(&mut self, listen: &str, daemon: bool) -> io::Result<()> {
+        let ingest = self.start_ingest()?;
+        let server = serve::Server::bind(listen)?;
+        eprintln!("httop: serving /healthz and /readyz on http://{}", server.local_addr()?);
+
+        let signals = if daemon { Some(daemon::Signals::register()?) } else { None };
+        let watchdog = if daemon { daemon::watchdog_interval() } else { None };
+        if daemon {
+            daemon::notify("READY=1")?;
+        }
+
+        let mut last_ping = Instant::now();
+        let mut last_check = Instant::now();
+        loop {
+            server.poll(&ingest.health);
+
+            if last_check.elapsed() >= Duration::from_secs(1) {
+                for transition in self.check_alerts() {
+                    match transition {
+                        Transition::Fired(alert) => eprintln!("httop: alert firing: {} (now {})",
+                            alert.rule, alert.rule.metric.format(alert.value)),
+                        Transition::Resolved(alert) => eprintln!("httop: alert resolved: {}", alert.rule),
+                    }
+                }
+                self.send_metrics();
+                self.write_snapshot();
+                self.check_bans();
+                last_check = Instant::now();
+            }
+
+            if let Some(signals) = &signals {
+                if signals.terminate_requested() {
+                    daemon::notify("STOPPING=1")?;
+                    eprintln!("httop: shutting down");
+                    return Ok(());
+                }
+                if signals.take_reload() {
+                    daemon::notify("RELOADING=1")?;
+                    self.reload();
+                    daemon::notify("READY=1")?;
+                }
+            }
+
+            // Only pet the watchdog while ingestion is actually working
+            if let Some(interval) = watchdog
+                && last_ping.elapsed() >= interval / 2
+                && ingest.health.aggregator_running() {
+                daemon::notify("WATCHDOG=1")?;
+                last_ping = Instant::now();
+            }
+
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    // Reads the input to its end, then prints the report. Without the
+    // ingest's own sender, the aggregator stops once the readers are done
+    // and everything they read is counted
+    fn batch(&mut self) -> io::Result<()> {
+        let health = Arc::clone(&self.start_ingest()?.health);
+        while health.aggregator_running() {
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let unparsed = self.health.unparsed();
+        if let Some(latest) = unparsed.recent.front() {
+            eprintln!("httop: {} of {} lines not parsed ({}), the last: {}", unparsed.total(), self.health.lines(),
+                unparsed.describe(self.config.format.name()), latest.text);
+        }
+
+        let stats = self.stats.snapshot();
+        // The parsed requests have stdout to themselves
+        if self.config.emit_parsed.as_deref() == Some(Path::new("-")) {
+            return Ok(());
+        }
+        let mut out = io::stdout().lock();
+        match self.config.output {
+            export::Output::Text => export::write_text(&mut out, &stats, &self.config.inputs(), self.by_endpoint),
+            export::Output::Json => export::write_json(&mut out, &stats, &self.config.inputs()),
+            export::Output::Csv => {
+                let dir = self.config.out_dir.as_ref().expect("checked by Config");
+                for file in export::write_csv(dir, &stats, self.by_endpoint)? {
+                    writeln!(out, "{}", dir.join(file).display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Counts the requests of a checkpoint into the stats, before ingestion
+    // takes them over
+    fn resume(&self, requests: Vec<(DateTime<Utc>, Request)>, mut bots: bots::Detector) -> (Vec<(DateTime<Utc>, Request)>, bots::Detector) {
+        let history = Arc::clone(&self.history);
+        let (config, geoip, asn) = (self.config.clone(), self.geoip.clone(), self.asn.clone());
+        self.stats.update(move |stats| {
+            let mut history = history.lock().unwrap();
+            let kept = source::resume(stats, &mut history, requests, &config, geoip.as_deref(), asn.as_deref(), &mut bots);
+            (kept, bots)
+        })
+    }
+
+    // Re-reads the files named in the configuration
+    fn reload(&mut self) {
+        let mut spam_list = SpamList::bundled();
+        if let Some(path) = &self.config.referrer_spam_list
+            && let Err(e) = spam_list.extend_from_file(path) {
+            self.health.notices.post(format!("could not reload referrer spam list {}: {}", path.display(), e));
+            return;
+        }
+        self.stats.update(move |stats| stats.set_spam_list(spam_list));
+        self.load_baseline();
+        self.health.notices.post("configuration reloaded".to_string());
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        let ingest = self.start_ingest()?;
+
+        let (tx, rx) = mpsc::channel();
+        let terminate_tx = tx.clone();
+        if let Err(e) = terminal::on_terminate(move || {
+            let _ = terminate_tx.send(Command::Quit);
+        }) {
+            eprintln!("WARNING: Could not watch for SIGTERM: {}", e);
+        }
+        let terminal = terminal::Terminal::open();
+        if terminal.is_ok() {
+            self.health.notices.hold();
+        }
+        match terminal.as_ref().map(terminal::Terminal::events) {
+            Ok(events) => {
+                let keymap = self.config.keymap;
+                thread::spawn(move || keys::read_keys(events, keymap, tx));
+            }
+            Err(e) => eprintln!("ERROR: Could not open terminal for input, controls disabled: {}", e),
+        }
+
+        self.load_baseline();
+
+        // Main display loop
+        let mut running = true;
+
+        while running {
+            let fired = self.check_alerts().iter().any(|t| matches!(t, Transition::Fired(_)));
+            if fired && self.config.bell {
+                // Terminal bell plus a visual flash of the header
+                print!("\x07");
+                self.flash_until = Some(Instant::now() + Duration::from_secs(2));
+            }
+
+            self.send_metrics();
+            self.write_snapshot();
+            self.check_bans();
+            if let Some(notice) = self.health.notices.take().pop() {
+                self.message = Some(notice);
+            }
+
+            // Update display
+            self.draw_screen()?;
+
+            // Wait for the next refresh, but redraw right away after a key
+            match rx.recv_timeout(self.interval) {
+                Ok(command) => running = self.apply(command),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => thread::sleep(self.interval),
+            }
+            while running && let Ok(command) = rx.try_recv() {
+                running = self.apply(command);
+            }
+            if running && self.input_ended(&ingest.health) {
+                running = false;
+            }
+        }
+
+        // Back on the normal screen before the summary, so it stays in the
+        // scrollback
+        drop(terminal);
+        self.health.notices.release();
+        if self.config.no_summary {
+            return Ok(());
+        }
+        let stats = self.stats.snapshot();
+        export::write_text(&mut io::stdout().lock(), &stats, &self.config.inputs(), self.by_endpoint)
+    }
+
+    // Piped logs have run out and every request is counted. Files are
+    // followed and live inputs never end
+    fn input_ended(&self, health: &Health) -> bool {
+        self.config.reads_stdin() && self.config.replay.is_none()
+            && health.sources().iter().all(|source| source.finished) && health.queued() == 0
+    }
+
+    // False once it is time to quit
+    fn apply(&mut self, command: Command) -> bool {
+        if !matches!(command, Command::Prompt(_) | Command::Redraw) {
+            self.prompt = None;
+        }
+        match command {
+            Command::Quit => return false,
+            Command::Sort(by) => self.sort.select(by),
+            Command::ReverseSort => self.sort.reversed = !self.sort.reversed,
+            Command::NextView => {
+                self.view = self.view.next();
+                (self.selected, self.scroll) = (0, 0);
+            }
+            Command::ShowView(view) => {
+                self.view = view;
+                (self.selected, self.scroll) = (0, 0);
+            }
+            Command::Select(step) => self.move_selection(step),
+            Command::Page(pages) => {
+                // The selection keeps its place in the window
+                let step = pages.saturating_mul(self.display_limit as isize);
+                if self.is_selectable() {
+                    self.scroll = self.first_row(self.rows.len()).saturating_add_signed(step);
+                }
+                self.move_selection(step);
+            }
+            Command::OpenDetail => {
+                if let Some(key) = self.rows.get(self.selected) {
+                    self.detail = Some((key.clone(), self.view));
+                    self.view = View::Detail;
+                }
+            }
+            Command::TogglePause => {
+                self.paused = match self.paused {
+                    Some(_) => None,
+                    None => Some((terminal::last_frame(), self.stats.latest().received)),
+                };
+            }
+            Command::ToggleHelp => self.help = !self.help,
+            Command::ToggleTailPanel => self.tail_panel = !self.tail_panel,
+            Command::ToggleStatusPanel => self.status_panel = !self.status_panel,
+            Command::TogglePercent => self.percent = !self.percent,
+            Command::ChangeInterval(step) => self.change_interval(step),
+            Command::SetColumns(list) => match columns::parse(&list) {
+                Ok(columns) => self.columns = columns,
+                Err(e) => self.message = Some(format!("Columns: {}", e)),
+            },
+            Command::Back if self.help => self.help = false,
+            Command::Back => {
+                if let (View::Detail, Some((_, from))) = (self.view, &self.detail) {
+                    self.view = *from;
+                }
+            }
+            Command::IncreaseLimit => self.display_limit += MIN_DISPLAY_LIMIT,
+            Command::DecreaseLimit => {
+                self.display_limit = self.display_limit.saturating_sub(MIN_DISPLAY_LIMIT).max(MIN_DISPLAY_LIMIT);
+            },
+            Command::ToggleBaseline => self.toggle_baseline(),
+            Command::ToggleSpamReferrers => self.show_spam_referrers = !self.show_spam_referrers,
+            Command::ToggleReferrerDomains => self.referrer_domains = !self.referrer_domains,
+            Command::GroupUserAgents => self.ua_grouping = self.ua_grouping.next(),
+            Command::ToggleBots => {
+                self.toggles.hide_bots = !self.toggles.hide_bots;
+                self.filtered = None;
+            }
+            Command::ToggleStatusClass => {
+                self.toggles.next_status();
+                self.filtered = None;
+            }
+            Command::ToggleStaticAssets => {
+                self.toggles.hide_static = !self.toggles.hide_static;
+                self.filtered = None;
+            }
+            Command::ToggleDeltas => self.show_deltas = !self.show_deltas,
+            Command::ToggleEndpoints => self.by_endpoint = !self.by_endpoint,
+            Command::ExportJson => self.export_json(),
+            Command::ExportCsv => self.export_csv(),
+            Command::Search(text) => self.set_search(&text),
+            Command::CopyCurl(id) => self.copy_curl(&id),
+            Command::Whois(target) => self.show_whois(&target),
+            Command::OpenTrace(id) => self.open_trace(&id),
+            Command::Prompt(text) => self.prompt = text,
+            Command::Redraw | Command::Noop => {},
+        }
+        true
+    }
+
+    // To the next of INTERVALS up or down from the current interval, which
+    // --interval may have set between them
+    fn change_interval(&mut self, step: isize) {
+        let next = if step < 0 {
+            INTERVALS.iter().rev().find(|interval| **interval < self.interval)
+        } else {
+            INTERVALS.iter().find(|interval| **interval > self.interval)
+        };
+        if let Some(interval) = next {
+            self.interval = *interval;
+            self.stats.set_interval(self.interval);
+        }
+        self.message = Some(format!("Refreshing every {:?} (< and > to change)", self.interval));
+    }
+
+    // An empty query leaves the search screen
+    fn set_search(&mut self, text: &str) {
+        if text.is_empty() {
+            self.search = None;
+            self.filtered = None;
+            if self.view == View::Search {
+                self.view = View::Paths;
+            }
+            return;
+        }
+
+        match Query::parse(text) {
+            Ok(query) => {
+                self.search = Some(query);
+                self.filtered = None;
+                self.view = View::Search;
+                self.message = None;
+            }
+            Err(e) => self.message = Some(format!("Search: {}", e)),
+        }
+    }
+
+    // Writes the stats as shown, filtered by a search or without bots, to
+    // httop-TIME.json in the working directory
+    fn export_json(&mut self) {
+        let stats = match &mut self.filtered {
+            Some(filtered) => Arc::new(filtered.stats.snapshot()),
+            None => self.stats.snapshot(),
+        };
+        let path = format!("httop-{}.json", Local::now().format("%Y%m%d-%H%M%S"));
+        let result = File::create(&path)
+            .and_then(|file| export::write_json(&mut io::BufWriter::new(file), &stats, &self.config.inputs()));
+        self.message = Some(match result {
+            Ok(()) => format!("Stats exported to {}", path),
+            Err(e) => format!("Could not export stats to {}: {}", path, e),
+        });
+    }
+
+    // The same, but the paths, IPs, status codes and user agents tables as
+    // CSV files in a new httop-TIME directory
+    fn export_csv(&mut self) {
+        let stats = match &mut self.filtered {
+            Some(filtered) => Arc::new(filtered.stats.snapshot()),
+            None => self.stats.snapshot(),
+        };
+        let dir = format!("httop-{}", Local::now().format("%Y%m%d-%H%M%S"));
+        self.message = Some(match export::write_csv(Path::new(&dir), &stats, self.by_endpoint) {
+            Ok(files) => format!("Tables exported to {}/ ({})", dir, files.join(", ")),
+            Err(e) => format!("Could not export tables to {}: {}", dir, e),
+        });
+    }
+
+    // `y N` copies a curl command reproducing request #N of the search screen
+    fn copy_curl(&mut self, id: &str) {
+        let Ok(id) = id.parse::<usize>() else {
+            self.message = Some("Usage: y N, with N from the # column of the search screen".to_string());
+            return;
+        };
+        let command = match self.history.lock().unwrap().get(id) {
+            Some(request) => curl::command(request),
+            None => {
+                self.message = Some(format!("Request #{} is no longer retained", id));
+                return;
+            }
+        };
+
+        print!("{}", clipboard::osc52(&command));
+        self.message = Some(format!("Copied: {}", command));
+    }
+
+    // `o N` opens the trace or log search for request #N of the search screen
+    fn open_trace(&mut self, id: &str) {
+        let Some(template) = self.config.trace_url.clone() else {
+            self.message = Some("No trace link configured, start with --trace-url URL".to_string());
+            return;
+        };
+        let Ok(id) = id.parse::<usize>() else {
+            self.message = Some("Usage: o N, with N from the # column of the search screen".to_string());
+            return;
+        };
+        let trace_id = match self.history.lock().unwrap().get(id) {
+            Some(request) => trace::trace_id(&request.fields, &self.config.trace_fields),
+            None => {
+                self.message = Some(format!("Request #{} is no longer retained", id));
+                return;
+            }
+        };
+        let Some(trace_id) = trace_id else {
+            self.message = Some(format!("Request #{} has no {} field", id, self.config.trace_fields.join("/")));
+            return;
+        };
+
+        // Shown and copied too, for when there is no browser on this machine
+        let url = trace::link(&template, &trace_id);
+        print!("{}", clipboard::osc52(&url));
+        self.message = Some(match trace::open(&url) {
+            Ok(()) => format!("Opened {}", url),
+            Err(_) => format!("Copied {}", url),
+        });
+    }
+
+    // `w IP` or `w N` (a search result) opens the RDAP popup, `w` closes it
+    fn show_whois(&mut self, target: &str) {
+        if target.is_empty() {
+            self.whois = None;
+            return;
+        }
+
+        let ip = match target.parse::<usize>() {
+            Ok(id) => self.history.lock().unwrap().get(id).and_then(|request| request.ip.parse().ok()),
+            Err(_) => target.parse().ok(),
+        };
+        match ip {
+            Some(ip) => {
+                self.rdap.lookup(ip);
+                self.whois = Some(ip);
+            }
+            None => self.message = Some(format!("Whois: '{}' is neither an IP address nor a retained request #", target)),
+        }
+    }
+
+    fn check_alerts(&mut self) -> Vec<Transition> {
+        let (metrics, received, server_errors) = {
+            let stats = self.stats.latest();
+            (stats.alert_metrics(), stats.received, stats.received_classes[4])
+        };
+        let next_id = self.history.lock().unwrap().next_id();
+        self.anomalies.sample(Instant::now(), received, server_errors, next_id);
+
+        let transitions = self.alerts.evaluate(Instant::now(), &metrics);
+        for transition in &transitions {
+            self.paging.send(Instant::now(), transition);
+        }
+        self.paging.release(Instant::now(), &self.alerts.active(&metrics));
+        transitions
+    }
+
+    // Every --snapshot-interval, the stats as the views count them over the
+    // --window, without a search filter
+    fn write_snapshot(&mut self) {
+        let Some(dir) = &self.config.snapshot_dir else {
+            return;
+        };
+        if self.last_snapshot.elapsed() < self.config.snapshot_interval {
+            return;
+        }
+        self.last_snapshot = Instant::now();
+        let stats = self.stats.snapshot();
+        if let Err(e) = export::write_snapshot(dir, &stats, &self.config.inputs()) {
+            self.health.notices.post(format!("could not write snapshot to {}: {}", dir.display(), e));
+        }
+    }
+
+    fn check_bans(&mut self) {
+        if let Some(bans) = self.bans.as_mut() {
+            bans.check(&self.stats.latest().client_ips);
+        }
+    }
+
+    fn send_metrics(&mut self) {
+        let now = Instant::now();
+        let Some(statsd) = self.statsd.as_mut().filter(|statsd| statsd.due(now)) else {
+            return;
+        };
+        let (totals, response_times) = {
+            let stats = self.stats.latest();
+            let totals = statsd::Totals { classes: stats.received_classes, bytes: stats.received_bytes };
+            let response_times = [("p50", 0.5), ("p90", 0.9), ("p95", 0.95), ("p99", 0.99)]
+                .map(|(name, fraction)| (name, stats.recent_response_times.quantile(now, fraction)));
+            (totals, response_times)
+        };
+        statsd.flush(now, totals, &response_times);
+    }
+
+    fn draw_screen(&mut self) -> io::Result<()> {
+        // While paused, only the banner above the frozen screen changes
+        if let Some((frame, total)) = &self.paused {
+            let now_total = self.stats.latest().received;
+            self.rps_history.sample(Instant::now(), now_total);
+            let mut banner = vec![Span::styled(format!("PAUSED: {} requests arrived since (space to resume)",
+                now_total.saturating_sub(*total)), self.selection())];
+            if let Some(prompt) = &self.prompt {
+                banner.push(Span::raw(format!(" {}_", prompt)));
+            }
+            return terminal::draw(|screen| {
+                let [banner_area, frozen_area] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)])
+                    .areas(screen.area());
+                screen.render_widget(Line::from(banner), banner_area);
+                screen.render_widget(Frozen(frame), frozen_area);
+            });
+        }
+
+        // As the aggregator last published them, rates moved on even if idle
+        let stats = self.stats.frame(self.view.panel());
+        let memory = stats.memory + self.history.lock().unwrap().memory();
+        let max_memory = self.config.max_memory;
+
+        self.rps_history.sample(Instant::now(), stats.received);
+        self.upstream_timed = stats.upstream_requests > 0;
+        let stats = if self.search.is_some() || self.toggles.any() {
+            let filtered = self.filtered.get_or_insert_with(|| Filtered { stats: stats.cleared(), next_id: 0 });
+            filtered.catch_up(&self.history.lock().unwrap(), self.search.as_ref(), &self.toggles);
+            filtered.stats.tick(Instant::now());
+            Arc::new(filtered.stats.frame(self.view.panel()))
+        } else {
+            stats
+        };
+
+        // Display header, in reverse video for a moment after an alert fired
+        let current_time = Local::now().format("%Y-%m-%d %H:%M:%S");
+        // Of the tables, as the aggregator last estimated it, and the requests
+        // kept for search
+        let memory = match max_memory {
+            0 => histogram::human_bytes(memory),
+            max => format!("{} of {}", histogram::human_bytes(memory), histogram::human_bytes(max)),
+        };
+        let title = format!("HTTOP (v{}) - {} | Memory: ~{} | View: {} (? for help)",
+            env!("CARGO_PKG_VERSION"), current_time, memory, self.view.name());
+        let mut header = Page::default();
+        if self.flash_until.is_some_and(|until| Instant::now() < until) {
+            header.line(Line::styled(title, self.selection()));
+        } else {
+            header.line(Line::styled(title, self.style(self.config.theme.header)));
+        }
+        let mut totals = vec![Span::raw(format!("Total Requests: {} | RPS (1m/5m/15m): {:.2} {:.2} {:.2}",
+            stats.total_requests, stats.load.rate(1), stats.load.rate(5), stats.load.rate(15)))];
+        totals.extend(self.response_time_quantiles(&stats));
+        totals.push(Span::raw(format!(" | Total Bytes: {} | Throughput: {} | Unique IPs: ~{} | Bots: {}",
+            histogram::human_bytes(stats.bytes_sent), columns::throughput(stats.throughput.rate()), stats.unique_ips.estimate(),
+            if self.toggles.hide_bots { "hidden".to_string() } else { format!("{:.1}%", stats.bot_percent()) })));
+        header.line(totals);
+        if let Some(window) = stats.window {
+            let span = Instant::now().saturating_duration_since(stats.started).min(window);
+            header.line(format!("Window: tables count the last {} of traffic (--window {})", minutes(span), minutes(window)));
+        }
+        if stats.evicted_keys() > 0 {
+            let limits: Vec<String> = [
+                (stats.max_keys > 0).then(|| format!("--max-keys {}", stats.max_keys)),
+                (max_memory > 0).then(|| format!("--max-memory {}", histogram::human_bytes(max_memory))),
+            ].into_iter().flatten().collect();
+            header.line(format!("Key limit: {} rarely requested keys evicted to keep tables within {}; counts of the rest may be short",
+                stats.evicted_keys(), limits.join(" and ")));
+        }
+        self.render_traffic(&mut header);
+        self.render_status_classes(&mut header, &stats);
+        if self.status_panel {
+            self.render_status_timeline(&mut header, &stats);
+        }
+        if stats.timed_requests > 0 {
+            header.line(format!("In Flight (est., 1m/5m/15m): {:.2} {:.2} {:.2}",
+                stats.concurrency.one.rate(), stats.concurrency.five.rate(), stats.concurrency.fifteen.rate()));
+        } else {
+            header.line("In Flight (est.): n/a (log format has no request time)");
+        }
+        if let Some(t) = self.config.apdex_t {
+            self.render_apdex(&mut header, &stats, t);
+        }
+        if let Some((upstream, overhead)) = tenants::upstream_split(stats.upstream_requests, stats.upstream_time, stats.proxied_time) {
+            header.line(upstream_line(upstream, overhead));
+        }
+        self.render_cache(&mut header, &stats.cache);
+        if let Some(bans) = &self.bans {
+            match &bans.latest {
+                Some(latest) => header.line(format!("Bans: {} clients flagged, latest {} ({})", bans.count(), latest.ip, latest.reason)),
+                None => header.line("Bans: no clients flagged"),
+            }
+        }
+        if let Some(recorder) = &stats.baseline_recorder {
+            header.line(format!("Baseline: recording for {:.0}s (press B to stop)", recorder.elapsed_secs(Instant::now())));
+        } else if let Some(baseline) = &self.baseline {
+            header.line(format!("Baseline: {} paths, RPS%/RT% columns show the last minute vs baseline", baseline.paths.len()));
+        }
+        let warning = self.style(self.config.theme.warning);
+        if let Some(query) = &self.search {
+            header.line(Line::styled(format!("Filter: {} (every view counts only matching requests, / alone to clear)",
+                query.text()), warning));
+        }
+        if self.toggles.any() {
+            header.line(Line::styled(format!("Toggled: {} (every view recounted; f, x and a to change)",
+                self.toggles.describe().join(", ")), warning));
+        }
+        if self.config.sample.is_some() {
+            match stats.sampled {
+                1 => header.line("Sampling: every line read"),
+                every => header.line(Line::styled(format!("Sampling: 1 line in {} read, counts and rates scaled up to match",
+                    every), warning)),
+            }
+        }
+        if let Some(prompt) = &self.prompt {
+            header.line(format!("{}_", prompt));
+        } else if let Some(message) = &self.message {
+            header.line(message.clone());
+        }
+        if stats.duplicates.total > 0 {
+            header.line(Line::styled(format!("WARNING: {} exact duplicate requests ({:.1}% overall, {:.1}% in the last minute); a log loop or double shipping? ({})",
+                stats.duplicates.total,
+                stats.duplicates.total as f64 / stats.duplicates.checked as f64 * 100.0,
+                stats.duplicates.recent_percent(),
+                if stats.duplicates.drop { "dropped" } else { "counted, --drop-duplicates to skip them" }), warning));
+        }
+        let unparsed = self.health.unparsed();
+        if unparsed.total() > 0 {
+            let lines = self.health.lines();
+            // Not one line parsed: most likely the wrong --format
+            let style = if unparsed.total() == lines { self.bold(self.config.theme.alert) } else { warning };
+            header.line(Line::styled(format!("Unparsed: {} of {} lines ({:.1}%): {} (U to see them)",
+                unparsed.total(), lines, unparsed.total() as f64 / lines as f64 * 100.0,
+                unparsed.describe(self.config.format.name())), style));
+        }
+        // Any at all in the last minute: someone is scanning now
+        let attack_rate = stats.attacks.rate.rate() * 60.0;
+        if attack_rate >= 1.0
+            && let Some(latest) = stats.attacks.recent.back() {
+            header.line(Line::styled(format!("Attacks: {:.0}/min now, the latest {} from {} (A to see them)",
+                attack_rate, latest.signature.name(), latest.ip), warning));
+        }
+        let standings = stats.slos.standings();
+        let violated = standings.iter().filter(|(_, compliance)| compliance.state == State::Violated).count();
+        let burning = standings.iter().filter(|(_, compliance)| compliance.state == State::Burning).count();
+        if let Some((slo, worst)) = standings.first()
+            && worst.state != State::Met {
+            header.line(Line::styled(format!("SLOs: {} violated, {} burning, worst {} {} at {:.2}%, burning {} over 1h (L to see them)",
+                violated, burning, slo, worst.objective, worst.percent().unwrap_or(100.0), slo::burn_text(worst.burn_rates[1])),
+                if violated > 0 { self.bold(self.config.theme.alert) } else { warning }));
+        }
+        let metrics = stats.alert_metrics();
+        for alert in self.alerts.active(&metrics) {
+            header.line(Line::styled(format!("ALERT: {} (now {})", alert.rule, alert.rule.metric.format(alert.value)),
+                self.bold(self.config.theme.alert)));
+        }
+        self.render_anomalies(&mut header);
+        header.blank();
+        self.render_whois(&mut header);
+
+        // Status code distribution
+        header.line("Status Codes:");
+        let mut status_codes: Vec<_> = stats.status_codes.iter().collect();
+        status_codes.sort_by(|a, b| b.1.cmp(a.1));
+        for (code, count) in status_codes.iter().take(5) {
+            header.line(vec![Span::raw("  "), self.status_span(code.to_string(), **code), Span::raw(format!(": {}", count))]);
+        }
+        header.blank();
+
+        self.row_changes.update(Instant::now(), if self.by_endpoint { &stats.endpoints } else { &stats.paths });
+        self.rows = self.table_keys(&stats);
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+        self.follow_selection();
+
+        // Tab bar, with the current tab selected
+        let titles: Vec<String> = View::TABS.iter().enumerate()
+            .map(|(index, tab)| format!("{} {}", index + 1, tab.name()))
+            .collect();
+        let tabs_width = titles.iter().map(|title| title.chars().count() as u16 + 3).sum::<u16>().saturating_sub(1);
+        let tabs = Tabs::new(titles)
+            .select(View::TABS.iter().position(|tab| *tab == self.view))
+            .highlight_style(self.selection())
+            .divider("|");
+        let hint = Line::from("  (Tab or v: next view, Up/Down and Enter: details)");
+
+        self.table_len.set(0);
+        let mut body = Page::default();
+        let mut tail = Page::default();
+        if self.help {
+            self.render_help(&mut body);
+        } else {
+            self.render_view(&mut body, &stats);
+            // The panel keeps the bottom of the window, cutting the view short
+            if self.tail_panel && self.view != View::Tail {
+                self.render_tail_panel(&mut tail, &stats);
+            }
+        }
+
+        terminal::draw(|screen| {
+            let [top, tail_area] = Layout::vertical([Constraint::Fill(1), Constraint::Length(tail.height())])
+                .areas(screen.area());
+            let [header_area, tabs_area, body_area] = Layout::vertical([
+                Constraint::Length(header.height()),
+                Constraint::Length(2),
+                Constraint::Fill(1),
+            ]).areas(top);
+            let [tabs_area, hint_area] = Layout::horizontal([Constraint::Length(tabs_width), Constraint::Fill(1)])
+                .areas(tabs_area);
+            screen.render_widget(header, header_area);
+            screen.render_widget(tabs, tabs_area);
+            screen.render_widget(hint, hint_area);
+            screen.render_widget(body, body_area);
+            screen.render_widget(tail, tail_area);
+        })
+    }
+
+    fn render_view(&self, page: &mut Page, stats: &Stats) {
+        match self.view {
+            View::Paths => self.render_paths(page, stats),
+            View::Ips => self.render_ips(page, stats),
+            View::UserAgents => self.render_user_agents(page, stats),
+            View::Status => self.render_statuses(page, stats),
+            View::NotFound => self.render_error_paths(page, &stats.not_found, "404 Paths",
+                "scanners probing for files, or broken links"),
+            View::ServerErrors => self.render_error_paths(page, &stats.server_error_paths, "5xx Paths",
+                "the paths failing most"),
+            View::Offenders => self.render_offenders(page, stats),
+            View::Attacks => self.render_attacks(page, stats),
+            View::RateLimits => self.render_rate_limits(page, stats),
+            View::Campaigns => self.render_campaigns(page, stats),
+            View::Referrers => self.render_referrers(page, stats),
+            View::Bots => self.render_bots(page, stats),
+            View::Vhosts => self.render_vhosts(page, stats),
+            View::Visits => self.render_visits(page, stats),
+            View::Tenants => self.render_tenants(page, stats),
+            View::Services => self.render_services(page, stats),
+            View::Sources => self.render_sources(page, stats),
+            View::Hosts => self.render_hosts(page, stats),
+            View::Countries => self.render_countries(page, stats),
+            View::Networks => self.render_networks(page, stats),
+            View::Transfer => self.render_transfer(page, stats),
+            View::Sizes => self.render_sizes(page, stats),
+            View::Largest => self.render_largest(page, stats),
+            View::Content => self.render_content(page, stats),
+            View::Tls => self.render_tls(page, stats),
+            View::Protocols => self.render_protocols(page, stats),
+            View::Preflight => self.render_preflight(page, stats),
+            View::Redirects => self.render_redirects(page, stats),
+            View::Outliers => self.render_outliers(page, stats),
+            View::Slow => self.render_slow(page),
+            View::Tail => self.render_tail(page, stats),
+            View::Unparsed => self.render_unparsed(page),
+            View::Compare => self.render_compare(page, stats),
+            View::Slos => self.render_slos(page, stats),
+            View::Search => self.render_search(page),
+            View::Detail => self.render_detail(page),
+        }
+
+        // Rows past the window are a scroll away rather than dropped
+        let rows = self.table_len.get();
+        if rows > self.display_limit {
+            let first = self.first_row(rows);
+            page.blank();
+            page.line(format!("Rows {}-{} of {} (Up/Down, PgUp/PgDn, Home/End to scroll, +/- to resize)",
+                first + 1, (first + self.display_limit).min(rows), rows));
+        }
+    }
+
+    fn apdex_t(&self) -> f64 {
+        self.config.apdex_t.unwrap_or(apdex::DEFAULT_T)
+    }
+
+    // With --no-color nothing is styled but reverse video, which selections
+    // keep
+    fn style(&self, color: Option<highlight::Color>) -> Style {
+        match color {
+            Some(highlight::Color::Reverse) => Style::new().reversed(),
+            Some(color) if !self.config.no_color => color.style(),
+            _ => Style::new(),
+        }
+    }
+
+    // In bold as well, for alerts and anomalies
+    fn bold(&self, color: Option<highlight::Color>) -> Style {
+        if self.config.no_color { self.style(color) } else { self.style(color).bold() }
+    }
+
+    fn status_span(&self, text: String, status_code: u16) -> Span<'static> {
+        Span::styled(text, self.style(self.config.theme.status_color(status_code)))
+    }
+
+    fn latency_span(&self, text: String, seconds: f64) -> Span<'static> {
+        Span::styled(text, self.style(self.config.theme.latency_color(seconds, self.config.slow, self.config.very_slow)))
+    }
+
+    // Reverse video is all that is left without colors
+    fn selection(&self) -> Style {
+        if self.config.no_color { Style::new().reversed() } else { self.config.theme.selection.style() }
+    }
+
+    // p50, p90, p99 and max of every timed request, for the header
+    fn response_time_quantiles(&self, stats: &Stats) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        for (name, seconds) in [("p50", 0.5), ("p90", 0.9), ("p99", 0.99)].into_iter()
+            .filter_map(|(name, fraction)| Some((name, stats.response_times.quantile(fraction)?)))
+            .chain(stats.response_times.max().map(|max| ("max", max))) {
+            spans.push(Span::raw(format!(" {} ", name)));
+            spans.push(self.latency_span(format!("{:.3}s", seconds), seconds));
+        }
+        if !spans.is_empty() {
+            spans.insert(0, Span::raw(" | RT:"));
+        }
+        spans
+    }
+
+    // A response time colored by the thresholds
+    fn latency_cell(&self, seconds: f64) -> Span<'static> {
+        self.latency_span(format!("{:.3}s", seconds), seconds)
+    }
+
+    // Rows of the Paths, IPs and User Agents tabs can be selected with the
+    // arrow keys and opened with Enter
+    fn is_selectable(&self) -> bool {
+        matches!(self.view, View::Paths | View::Ips | View::UserAgents | View::NotFound | View::ServerErrors
+            | View::Offenders | View::Attacks | View::RateLimits | View::Vhosts | View::Bots | View::Networks | View::Hosts)
+    }
+
+    fn is_selected(&self, index: usize) -> bool {
+        self.is_selectable() && index == self.selected
+    }
+
+    fn move_selection(&mut self, step: isize) {
+        if self.is_selectable() {
+            self.selected = self.selected.saturating_add_signed(step).min(self.rows.len().saturating_sub(1));
+            self.follow_selection();
+        } else {
+            let rows = self.table_len.get();
+            self.scroll = self.first_row(rows).saturating_add_signed(step).min(rows.saturating_sub(self.display_limit));
+        }
+    }
+
+    // Scrolls just enough for the selected row to be visible
+    fn follow_selection(&mut self) {
+        if !self.is_selectable() {
+            return;
+        }
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + self.display_limit {
+            self.scroll = self.selected + 1 - self.display_limit;
+        }
+    }
+
+    // The first row shown of a table this long, so that a short table is
+    // never scrolled past its end
+    fn first_row(&self, rows: usize) -> usize {
+        self.scroll.min(rows.saturating_sub(self.display_limit))
+    }
+
+    // The rows of a table that fit the window
+    fn visible<I: ExactSizeIterator>(&self, rows: I) -> impl Iterator<Item = I::Item> {
+        self.table_len.set(self.table_len.get().max(rows.len()));
+        let first = self.first_row(rows.len());
+        rows.skip(first).take(self.display_limit)
+    }
+
+    // The selected row, or one of the keys behind an anomaly
+    fn row_style(&self, index: usize, key: &str) -> Style {
+        if self.is_selected(index) {
+            self.selection()
+        } else if self.spike_keys.contains(key) {
+            self.style(self.config.theme.anomaly)
+        } else {
+            Style::new()
+        }
+    }
+
+    // The keys of the rows the current tab shows, in order
+    fn table_keys(&self, stats: &Stats) -> Vec<detail::Key> {
+        let (groups, key): (_, fn(String) -> detail::Key) = match self.view {
+            View::Paths if self.by_endpoint => (&stats.endpoint_totals, detail::Key::Endpoint),
+            View::Paths => (&stats.path_totals, detail::Key::Path),
+            View::Ips => (&stats.client_ips, detail::Key::Ip),
+            View::Vhosts => (&stats.vhosts, detail::Key::Vhost),
+            View::Bots => (&stats.bots, detail::Key::Bot),
+            View::NotFound => (&stats.not_found, detail::Key::Path),
+            View::ServerErrors => (&stats.server_error_paths, detail::Key::Path),
+            View::Offenders => {
+                return error_offenders(&stats.client_ips).into_iter()
+                    .map(|(ip, _)| detail::Key::Ip(ip.clone()))
+                    .collect();
+            }
+            View::Attacks => {
+                return attackers(&stats.client_ips).into_iter()
+                    .map(|(ip, _)| detail::Key::Ip(ip.clone()))
+                    .collect();
+            }
+            View::RateLimits => {
+                return busiest_clients(&stats.client_ips).into_iter()
+                    .map(|(ip, _)| detail::Key::Ip(ip.clone()))
+                    .collect();
+            }
+            View::Networks => (&stats.networks, detail::Key::Network),
+            View::Hosts => (&stats.hosts, detail::Key::Host),
+            View::UserAgents if self.ua_grouping == Grouping::Raw => (&stats.user_agents, detail::Key::UserAgent),
+            View::UserAgents => {
+                return self.sorted_groups(stats.user_agent_groups(self.ua_grouping)).into_iter()
+                    .map(|(name, _)| detail::Key::UserAgentGroup(self.ua_grouping, name.clone()))
+                    .collect();
+            }
+            _ => return Vec::new(),
+        };
+        self.sorted_groups(groups).into_iter()
+            .map(|(name, _)| match &stats.static_assets {
+                // Bucket rows stand for every path in them
+                Some(assets) if name.ends_with(']') && name.contains("[static") =>
+                    detail::Key::StaticAssets(assets.clone(), name.clone()),
+                _ => key(name.clone()),
+            })
+            .collect()
+    }
+}
+
+// Upstream: avg 0.120s | proxy overhead avg 0.015s (11.1% of 0.135s)
+fn upstream_line(upstream: f64, overhead: f64) -> String {
+    let total = upstream + overhead;
+    format!("Upstream: avg {:.3}s | proxy overhead avg {:.3}s ({:.1}% of {:.3}s)",
+        upstream, overhead, if total > 0.0 { overhead / total * 100.0 } else { 0.0 }, total)
+}
+
+// 45s, 7m30s or 2h05m
+fn minutes(span: Duration) -> String {
+    let secs = span.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 if secs.is_multiple_of(60) => format!("{}m", secs / 60),
+        60..3600 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ if secs.is_multiple_of(3600) => format!("{}h", secs / 3600),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+// Requests in the panel toggled with t
+const TAIL_PANEL_ROWS: usize = 8;
+
+// Whether a request counts while searching for `search` with `toggles`
+fn shown(request: &Request, search: Option<&Query>, toggles: &Toggles) -> bool {
+    search.is_none_or(|query| query.matches(request)) && toggles.allows(request)
+}
+
+// Clients with any error responses, most errors first, then most 401/403s
+fn error_offenders(groups: &TenantStats) -> Vec<(&String, &TenantEntry)> {
+    let mut rows: Vec<_> = groups.tenants.iter()
+        .filter(|(_, entry)| entry.client_errors + entry.server_errors > 0)
+        .collect();
+    rows.sort_by(|a, b| (b.1.client_errors + b.1.server_errors).cmp(&(a.1.client_errors + a.1.server_errors))
+        .then(b.1.denied.cmp(&a.1.denied))
+        .then(a.0.cmp(b.0)));
+    rows
+}
+
+// Clients that sent requests matching attack signatures or were refused
+// often enough to be guessing credentials, by the attacks, then refusals
+fn attackers(groups: &TenantStats) -> Vec<(&String, &TenantEntry)> {
+    let mut rows: Vec<_> = groups.tenants.iter()
+        .filter(|(_, entry)| entry.attacks > 0 || entry.denied >= attacks::AUTH_FAILURES)
+        .collect();
+    rows.sort_by(|a, b| b.1.attacks.cmp(&a.1.attacks)
+        .then(b.1.denied.cmp(&a.1.denied))
+        .then(a.0.cmp(b.0)));
+    rows
+}
+
+// Clients by their busiest second, then busiest minute and request count
+fn busiest_clients(groups: &TenantStats) -> Vec<(&String, &TenantEntry)> {
+    let mut clients: Vec<_> = groups.tenants.iter().collect();
+    clients.sort_by(|a, b| b.1.peak_second.max.cmp(&a.1.peak_second.max)
+        .then(b.1.peak_minute.max.cmp(&a.1.peak_minute.max))
+        .then(b.1.count.cmp(&a.1.count))
+        .then(a.0.cmp(b.0)));
+    clients
+}
+
+// Wide enough for the longest address of those shown, IPv6 ones included
+fn ip_width<'a>(ips: impl Iterator<Item = &'a str>) -> usize {
+    ips.map(|ip| ip.chars().count()).max().unwrap_or(0).clamp(16, 39)
+}
+
+fn truncate(value: &str, width: usize) -> String {
+    if value.chars().count() > width {
+        let kept: String = value.chars().take(width - 3).collect();
+        format!("{}...", kept)
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn run() -> io::Result<()> {
+    // --help, --version and usage errors exit here
+    let config = Config::from_args().unwrap_or_else(|e| e.exit());
+
+    let mut app = match Httop::new(config) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("httop: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let result = match app.config.mode.clone() {
+        Mode::Interactive => app.start(),
+        Mode::Serve { listen, daemon } => app.serve(&listen, daemon),
+        Mode::Batch => app.batch(),
+    };
+    if let Err(e) = result {
+        eprintln!("httop: {}", e);
+        std::process::exit(1);
+    }
+    Ok(())
+}
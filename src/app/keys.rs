@@ -0,0 +1,188 @@
+use std::sync::mpsc;
+
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+
+use crate::keymap::KeyMap;
+use crate::terminal;
+
+use super::{Command, SortBy, View};
+
+// What the keys read_keys understands do, for the help screen
+pub(super) const KEYS: &[(&str, &str)] = &[
+    ("1-6", "Paths, IPs, User Agents, Status, Referrers or Virtual Hosts tab"),
+    ("Tab, v", "Next view"),
+    ("Up/Down, k/j", "Select a row (Paths, IPs, User Agents, Virtual Hosts, Bots, Networks, Hosts) or scroll"),
+    ("PgUp/PgDn", "Scroll a window's height"),
+    ("Home/End", "First or last row"),
+    ("Enter, Right", "Details of the selected row"),
+    ("Backspace, Left", "Back from the details or this screen"),
+    ("c / b / l / n", "Sort by count, bytes, p95 latency or name, ties by the previous sort"),
+    ("H", "Sort by cache hit ratio, lowest first"),
+    ("r", "Reverse the sort"),
+    ("+ / -", "Show more or fewer rows at once"),
+    ("m", "Split the Paths table by method, or merge it"),
+    ("d", "Show/hide per-refresh deltas"),
+    ("B", "Start/stop recording a baseline"),
+    ("S", "Show/hide spam referrers"),
+    ("D", "Group referrers by domain or list full referrer URLs"),
+    ("g", "Group user agents by browser, OS or device class, or list them as they are"),
+    ("F / E", "Most frequent 404 paths / paths with the most 5xx responses"),
+    ("O", "Client IPs with the most 4xx/5xx responses"),
+    ("A", "Requests matching attack signatures and the clients sending them"),
+    ("U", "Lines the log format did not parse, the latest first"),
+    ("P", "Compare paths and status codes with the --compare log or the --compare-window before"),
+    ("L", "Compliance, error budget and burn rates of the --slo objectives"),
+    ("x", "Leave bots and crawlers out of every view, or count them again (b sorts by bytes)"),
+    ("f", "Count only 4xx, then only 5xx, then all responses again (4 and 5 open tabs)"),
+    ("a", "Leave static assets out of every view, or count them again"),
+    ("/ QUERY", "Search and filter every view (/ alone to clear)"),
+    ("w IP, w N", "RDAP/WHOIS for an IP or search result"),
+    ("o N", "Open the trace link of search result N"),
+    ("y N", "Copy search result N as a curl command"),
+    ("e", "Export the stats, every table in full, to httop-TIME.json"),
+    ("s", "Save the paths, IPs, status codes and user agents tables as CSV files in httop-TIME/"),
+    ("t", "Show/hide the latest requests below the view"),
+    ("T", "Show/hide a chart of 2xx-5xx requests over time below the status bar"),
+    ("%", "Counts and bytes as percentages of the table's total"),
+    ("< / >", "Refresh more or less often (100ms to 10s)"),
+    ("Space", "Pause the display"),
+    ("C LIST", "Table columns, from count, rps, 4xx, 5xx, latency, p95, max, bytes, bw, uniques, last-seen, apdex, hit, upstream, overhead"),
+    ("?, h", "This help"),
+    ("q, Ctrl-C", "Quit"),
+];
+
+// Turns key presses into commands. / y w o and C take an argument, typed
+// into a prompt and entered with Enter (Esc cancels)
+pub(super) fn read_keys(events: terminal::Events, keymap: KeyMap, tx: mpsc::Sender<Command>) {
+    let mut prompt: Option<(char, String)> = None;
+    for event in events {
+        let key = match event {
+            // Windows reports releases too
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            Event::Resize(..) => {
+                if tx.send(Command::Redraw).is_err() {
+                    break;
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let key = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => '\x03',
+            KeyCode::Char(c) => c,
+            KeyCode::Enter => '\r',
+            KeyCode::Tab => '\t',
+            KeyCode::Backspace => '\x7F',
+            KeyCode::Esc => '\x1B',
+            // Moving around, which a prompt ignores
+            _ if prompt.is_some() => continue,
+            code => {
+                let command = match code {
+                    KeyCode::Up => Command::Select(-1),
+                    KeyCode::Down => Command::Select(1),
+                    KeyCode::PageUp => Command::Page(-1),
+                    KeyCode::PageDown => Command::Page(1),
+                    KeyCode::Home => Command::Select(isize::MIN),
+                    KeyCode::End => Command::Select(isize::MAX),
+                    KeyCode::Right => Command::OpenDetail,
+                    KeyCode::Left => Command::Back,
+                    _ => Command::Noop,
+                };
+                if tx.send(command).is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        // Typed text is taken as it is
+        let key = match prompt {
+            None if key.is_ascii() => keymap.translate(key as u8) as char,
+            _ => key,
+        };
+        let command = match (prompt.take(), key) {
+            (_, '\x03') => Some(Command::Quit),
+            (None, '/' | 'y' | 'w' | 'o' | 'C') => {
+                prompt = Some((key, String::new()));
+                None
+            }
+            (None, key) => Some(match key {
+                'q' => Command::Quit,
+                ' ' => Command::TogglePause,
+                '?' | 'h' => Command::ToggleHelp,
+                't' => Command::ToggleTailPanel,
+                'T' => Command::ToggleStatusPanel,
+                '%' => Command::TogglePercent,
+                '<' => Command::ChangeInterval(-1),
+                '>' => Command::ChangeInterval(1),
+                'k' => Command::Select(-1),
+                'j' => Command::Select(1),
+                '\r' => Command::OpenDetail,
+                '\x7F' => Command::Back,
+                'c' => Command::Sort(SortBy::Count),
+                'b' => Command::Sort(SortBy::Bytes),
+                'n' => Command::Sort(SortBy::Name),
+                'l' => Command::Sort(SortBy::Latency),
+                'H' => Command::Sort(SortBy::CacheHit),
+                'r' => Command::ReverseSort,
+                '+' => Command::IncreaseLimit,
+                '-' => Command::DecreaseLimit,
+                'B' => Command::ToggleBaseline,
+                'v' | '\t' => Command::NextView,
+                '1'..='6' => Command::ShowView(View::TABS[key as usize - '1' as usize]),
+                'S' => Command::ToggleSpamReferrers,
+                'D' => Command::ToggleReferrerDomains,
+                'g' => Command::GroupUserAgents,
+                'x' => Command::ToggleBots,
+                'f' => Command::ToggleStatusClass,
+                'a' => Command::ToggleStaticAssets,
+                'F' => Command::ShowView(View::NotFound),
+                'E' => Command::ShowView(View::ServerErrors),
+                'O' => Command::ShowView(View::Offenders),
+                'A' => Command::ShowView(View::Attacks),
+                'U' => Command::ShowView(View::Unparsed),
+                'P' => Command::ShowView(View::Compare),
+                'L' => Command::ShowView(View::Slos),
+                'd' => Command::ToggleDeltas,
+                'm' => Command::ToggleEndpoints,
+                'e' => Command::ExportJson,
+                's' => Command::ExportCsv,
+                _ => Command::Noop,
+            }),
+            (Some((kind, text)), '\r') => {
+                let text = text.trim().to_string();
+                Some(match kind {
+                    '/' => Command::Search(text),
+                    'y' => Command::CopyCurl(text),
+                    'w' => Command::Whois(text),
+                    'C' => Command::SetColumns(text),
+                    _ => Command::OpenTrace(text),
+                })
+            }
+            (Some(_), '\x1B') => Some(Command::Prompt(None)),
+            (Some((kind, mut text)), '\x7F') => {
+                text.pop();
+                prompt = Some((kind, text));
+                None
+            }
+            (Some((kind, mut text)), key) => {
+                if !key.is_control() {
+                    text.push(key);
+                }
+                prompt = Some((kind, text));
+                None
+            }
+        };
+
+        let command = command.unwrap_or_else(|| {
+            Command::Prompt(prompt.as_ref().map(|(kind, text)| {
+                let separator = if *kind == '/' { "" } else { " " };
+                format!("{}{}{}", keymap.key_for(*kind as u8) as char, separator, text)
+            }))
+        });
+        let quit = matches!(command, Command::Quit);
+        if tx.send(command).is_err() || quit {
+            break;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, Widget};
+
+// A part of the screen as render methods build it: lines of text, tables
+// and boxes stacked top to bottom, each as tall as its content. What does
+// not fit the area it is drawn in is cut off at the bottom
+#[derive(Default)]
+pub(super) struct Page {
+    parts: Vec<Part>,
+}
+
+enum Part {
+    Lines(Vec<Line<'static>>),
+    // A widget and the rows it takes
+    Table(Table<'static>, u16),
+    Boxed(Paragraph<'static>, u16),
+}
+
+impl Part {
+    fn height(&self) -> u16 {
+        match self {
+            Part::Lines(lines) => lines.len() as u16,
+            Part::Table(_, height) | Part::Boxed(_, height) => *height,
+        }
+    }
+}
+
+impl Page {
+    pub(super) fn line(&mut self, line: impl Into<Line<'static>>) {
+        match self.parts.last_mut() {
+            Some(Part::Lines(lines)) => lines.push(line.into()),
+            _ => self.parts.push(Part::Lines(vec![line.into()])),
+        }
+    }
+
+    pub(super) fn blank(&mut self) {
+        self.line(Line::default());
+    }
+
+    // `rows` below the table's header, a line each
+    pub(super) fn table(&mut self, table: Table<'static>, rows: Vec<Row<'static>>) {
+        self.tall_table(table, rows, 1);
+    }
+
+    pub(super) fn tall_table(&mut self, table: Table<'static>, rows: Vec<Row<'static>>, row_height: u16) {
+        let height = 1 + rows.len() as u16 * row_height;
+        let rows = rows.into_iter().map(|row| row.height(row_height));
+        self.parts.push(Part::Table(table.rows(rows), height));
+    }
+
+    // `lines` inside `block`, below its title and within its borders
+    pub(super) fn boxed(&mut self, block: Block<'static>, lines: Vec<Line<'static>>) {
+        let outer = Rect::new(0, 0, 100, 100);
+        let height = lines.len() as u16 + outer.height - block.inner(outer).height;
+        self.parts.push(Part::Boxed(Paragraph::new(lines).block(block), height));
+    }
+
+    pub(super) fn height(&self) -> u16 {
+        self.parts.iter().map(Part::height).sum()
+    }
+}
+
+impl Widget for Page {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut rest = area;
+        for part in self.parts {
+            let height = part.height().min(rest.height);
+            let here = Rect { height, ..rest };
+            match part {
+                Part::Lines(lines) => Paragraph::new(lines).render(here, buf),
+                Part::Table(table, _) => table.render(here, buf),
+                Part::Boxed(paragraph, _) => paragraph.render(here, buf),
+            }
+            rest.y += height;
+            rest.height -= height;
+        }
+    }
+}
+
+// The screen as it was when paused, below the banner
+pub(super) struct Frozen<'a>(pub(super) &'a Buffer);
+
+impl Widget for Frozen<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let shown = self.0.area;
+        for y in 0..area.height.min(shown.height) {
+            for x in 0..area.width.min(shown.width) {
+                buf[(area.x + x, area.y + y)] = self.0[(shown.x + x, shown.y + y)].clone();
+            }
+        }
+    }
+}
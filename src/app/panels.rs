@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use chrono::prelude::*;
+
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Block;
+
+use crate::{anomaly, chart, highlight, terminal};
+use crate::{Request, Stats};
+use crate::anomaly::Anomaly;
+use crate::apdex::Apdex;
+use crate::cache::CacheStats;
+use crate::history::History;
+use crate::rate::ClassTimeline;
+
+use super::{minutes, shown, truncate, Httop, Page, TAIL_PANEL_ROWS};
+
+// Rows of the chart toggled with T
+const STATUS_PANEL_HEIGHT: usize = 6;
+
+// Error rate changes smaller than this, in percentage points, are steady
+const TREND_THRESHOLD: f64 = 0.1;
+
+// Paths and client IPs named in an anomaly banner
+const SPIKE_OFFENDERS: usize = 3;
+
+// Keys and how often they came up, most frequent first
+type TopCounts = Vec<(String, usize)>;
+
+impl Httop {
+    pub(super) fn render_apdex(&self, page: &mut Page, stats: &Stats, t: f64) {
+        let apdex = Apdex::of_sketch(&stats.response_times, t);
+        let Some(score) = apdex.score() else {
+            page.line(format!("Apdex (T={:.0}ms): n/a (log format has no request time)", t * 1000.0));
+            return;
+        };
+        let percent = |count: usize| count as f64 / apdex.total() as f64 * 100.0;
+        page.line(format!("Apdex (T={:.0}ms): {:.2} | satisfied {:.1}% | tolerating {:.1}% | frustrated {:.1}%",
+            t * 1000.0, score, percent(apdex.satisfied), percent(apdex.tolerating), percent(apdex.frustrated)));
+    }
+
+    // Only for logs with a cache status, most common status first
+    pub(super) fn render_cache(&self, page: &mut Page, cache: &CacheStats) {
+        let lookups = cache.lookups();
+        if lookups == 0 {
+            return;
+        }
+        let percent = |count: usize| count as f64 / lookups as f64 * 100.0;
+        let mut statuses: Vec<_> = cache.statuses.iter().collect();
+        statuses.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        page.line(format!("Cache: {:.1}% hits | {}", percent(cache.hits()), statuses.iter()
+            .map(|(status, count)| format!("{} {:.1}%", status, percent(**count)))
+            .collect::<Vec<_>>()
+            .join(" | ")));
+    }
+
+    // A banner per anomaly, naming the paths and clients behind a surge
+    pub(super) fn render_anomalies(&mut self, page: &mut Page) {
+        self.spike_keys.clear();
+        let anomalies: Vec<Anomaly> = self.anomalies.active().cloned().collect();
+        for anomaly in anomalies {
+            let mut banner = format!("ANOMALY: {} for {}s", anomaly.describe(), anomaly.started.elapsed().as_secs());
+            if anomaly.sigmas() > 0.0 {
+                let (paths, ips) = self.spike_offenders(&anomaly);
+                let list = |top: &[(String, usize)]| top.iter()
+                    .map(|(key, count)| format!("{} ({})", key, count))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                banner.push_str(&format!(" | paths: {} | IPs: {}", list(&paths), list(&ips)));
+                self.spike_keys.extend(paths.into_iter().chain(ips).map(|(key, _)| key));
+            }
+            page.line(Line::styled(banner, self.bold(self.config.theme.anomaly)));
+        }
+    }
+
+    // The most frequent paths and client IPs since the anomaly began, of
+    // the 5xx responses for an error rate anomaly
+    fn spike_offenders(&self, anomaly: &Anomaly) -> (TopCounts, TopCounts) {
+        let history = self.history.lock().unwrap();
+        let mut paths: HashMap<&str, usize> = HashMap::new();
+        let mut ips: HashMap<&str, usize> = HashMap::new();
+        for request in history.since(anomaly.first) {
+            if anomaly.metric == anomaly::Metric::ErrorRate && !(500..=599).contains(&request.status_code) {
+                continue;
+            }
+            *paths.entry(request.path.as_str()).or_insert(0) += 1;
+            *ips.entry(request.ip.as_str()).or_insert(0) += 1;
+        }
+        let top = |counts: HashMap<&str, usize>| {
+            let mut counts: TopCounts = counts.into_iter().map(|(key, count)| (key.to_string(), count)).collect();
+            counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            counts.truncate(SPIKE_OFFENDERS);
+            counts
+        };
+        (top(paths), top(ips))
+    }
+
+    // How the last minute's requests split between 2xx, 3xx, 4xx and 5xx
+    pub(super) fn render_status_classes(&self, page: &mut Page, stats: &Stats) {
+        const WIDTH: usize = 30;
+        let counts = stats.status_classes.counts(Instant::now());
+        let total: usize = counts.iter().sum();
+        if total == 0 {
+            return;
+        }
+        let mut bar = vec![Span::raw("Status (1m) [")];
+        let mut legend = vec![Span::raw("]")];
+        for (class, (&count, cells)) in counts.iter().zip(chart::split(&counts, WIDTH)).enumerate() {
+            let code = (class as u16 + 2) * 100;
+            // Without colors the parts are told apart by their digit
+            let fill = if self.config.no_color { char::from(b'2' + class as u8) } else { self.chart_style.full() };
+            bar.push(self.status_span(std::iter::repeat_n(fill, cells).collect(), code));
+            legend.push(Span::raw(if class == 0 { " " } else { "  " }));
+            legend.push(self.status_span(format!("{}xx", class + 2), code));
+            legend.push(Span::raw(format!(" {:.1}% ({})", count as f64 / total as f64 * 100.0, count)));
+        }
+        bar.extend(legend);
+        page.line(bar);
+        self.render_error_trend(page, stats, counts, total);
+    }
+
+    // A column per period, stacked 2xx at the bottom to 5xx at the top and
+    // scaled to the busiest period. 5xx get a cell whenever there are any,
+    // so a few server errors show even next to heavy traffic
+    pub(super) fn render_status_timeline(&self, page: &mut Page, stats: &Stats) {
+        let timeline = &stats.status_timeline;
+        let periods = timeline.periods(Instant::now());
+        let peak = periods.iter().map(|counts| counts.iter().sum::<usize>()).max().unwrap_or(0);
+        page.line(format!("Status over the last {} ({} per column, peak {} requests)",
+            minutes(timeline.span()), minutes(timeline.period()), peak));
+
+        let stacks: Vec<Vec<usize>> = periods.iter()
+            .map(|counts| {
+                let total: usize = counts.iter().sum();
+                if total == 0 {
+                    return vec![0; 4];
+                }
+                let height = (total * STATUS_PANEL_HEIGHT).div_ceil(peak);
+                let mut cells: Vec<usize> = counts.iter().map(|count| (count * height + total / 2) / total).collect();
+                if counts[3] > 0 && cells[3] == 0 {
+                    let largest = (0..3).max_by_key(|&class| cells[class]).unwrap_or(0);
+                    cells[largest] = cells[largest].saturating_sub(1);
+                    cells[3] = 1;
+                }
+                cells
+            })
+            .collect();
+        for row in (0..STATUS_PANEL_HEIGHT).rev() {
+            let mut line = vec![Span::raw("  |")];
+            line.extend(stacks.iter()
+                .map(|cells| {
+                    let mut below = 0;
+                    for (class, &height) in cells.iter().enumerate() {
+                        if row < below + height {
+                            let fill = if self.config.no_color { char::from(b'2' + class as u8) } else { self.chart_style.full() };
+                            return self.status_span(fill.to_string(), (class as u16 + 2) * 100);
+                        }
+                        below += height;
+                    }
+                    Span::raw(" ")
+                }));
+            line.push(Span::raw("|"));
+            page.line(line);
+        }
+        page.line(format!("  -{}{:>width$}", minutes(timeline.span()), "now",
+            width = ClassTimeline::PERIODS as usize + 1 - minutes(timeline.span()).len()));
+    }
+
+    // The 4xx and 5xx share of the last minute against the minute before,
+    // an arrow telling whether an incident is getting worse
+    fn render_error_trend(&self, page: &mut Page, stats: &Stats, counts: [usize; 4], total: usize) {
+        let previous = stats.status_classes.previous_counts(Instant::now());
+        let previous_total: usize = previous.iter().sum();
+        let percent = |count: usize, total: usize| count as f64 / total as f64 * 100.0;
+        let mut line = vec![Span::raw("Error Rate (1m vs the minute before): ")];
+        for (class, name) in [(2, "4xx"), (3, "5xx")] {
+            if class == 3 {
+                line.push(Span::raw(" | "));
+            }
+            let now = percent(counts[class], total);
+            if previous_total == 0 {
+                line.push(Span::raw(format!("{} {:.1}%", name, now)));
+                continue;
+            }
+            let before = percent(previous[class], previous_total);
+            let trend = match now - before {
+                change if change.abs() < TREND_THRESHOLD => std::cmp::Ordering::Equal,
+                change => change.total_cmp(&0.0),
+            };
+            let arrow = self.chart_style.arrow(trend).to_string();
+            let arrow = match trend {
+                std::cmp::Ordering::Greater => self.status_span(arrow, 500),
+                std::cmp::Ordering::Less => self.status_span(arrow, 200),
+                std::cmp::Ordering::Equal => Span::raw(arrow),
+            };
+            line.extend([Span::raw(format!("{} {:.1}% ", name, now)), arrow, Span::raw(format!(" (was {:.1}%)", before))]);
+        }
+        page.line(line);
+    }
+
+    // Requests per second over the last minutes, one column per refresh
+    pub(super) fn render_traffic(&self, page: &mut Page) {
+        let width = terminal::size().map_or(80, |(_, columns)| columns).saturating_sub(36).clamp(10, 600);
+        let samples: Vec<f64> = self.rps_history.recent(width).collect();
+        let Some(&now) = samples.last() else {
+            return;
+        };
+        let peak = samples.iter().copied().fold(0.0, f64::max);
+        // In hundredths, since the sparkline takes whole numbers
+        let scaled: Vec<usize> = samples.iter().map(|rps| (rps * 100.0).round() as usize).collect();
+        page.line(format!("Traffic [{:<width$}] now {:.1}/s peak {:.1}/s",
+            self.chart_style.sparkline(&scaled), now, peak, width = width));
+    }
+
+    pub(super) fn render_tail(&self, page: &mut Page, stats: &Stats) {
+        page.line(format!("Live Tail (newest last, {} highlight rules)", self.config.highlights.len()));
+        page.blank();
+
+        let source_width = Self::source_width(stats);
+        let history = self.history.lock().unwrap();
+        let requests = self.recent_requests(&history, self.display_limit);
+        let client_width = Self::client_width(&requests);
+        for request in requests {
+            page.line(self.tail_line(request, source_width, client_width));
+        }
+    }
+
+    // Always as tall, so the view above does not jump as requests arrive
+    pub(super) fn render_tail_panel(&self, page: &mut Page, stats: &Stats) {
+        let history = self.history.lock().unwrap();
+        let requests = self.recent_requests(&history, TAIL_PANEL_ROWS);
+        let client_width = Self::client_width(&requests);
+        let mut lines: Vec<Line> = requests.into_iter()
+            .map(|request| self.tail_line(request, Self::source_width(stats), client_width))
+            .collect();
+        lines.resize(TAIL_PANEL_ROWS, Line::default());
+        page.boxed(Block::new().title(Line::styled(" Recent Requests (t to hide) ", self.selection())), lines);
+    }
+
+    // The latest `count` requests, or the latest matching the search and
+    // not from a hidden bot, oldest first
+    fn recent_requests<'a>(&'a self, history: &'a History, count: usize) -> Vec<&'a Request> {
+        if self.search.is_none() && !self.toggles.any() {
+            return history.recent(count).collect();
+        }
+        let mut matches: Vec<_> = history.newest_first()
+            .filter(|request| shown(request, self.search.as_ref(), &self.toggles))
+            .take(count)
+            .collect();
+        matches.reverse();
+        matches
+    }
+
+    // Which host or file a line came from, once there is more than one
+    fn source_width(stats: &Stats) -> usize {
+        if stats.sources.tenants.len() > 1 {
+            stats.sources.tenants.keys().map(|source| source.chars().count()).max().unwrap_or(0).min(24)
+        } else {
+            0
+        }
+    }
+
+    // The address and country of each line, lined up for the longest
+    fn client_width(requests: &[&Request]) -> usize {
+        requests.iter().map(|request| tail_client(request).chars().count()).max().unwrap_or(0).clamp(18, 42)
+    }
+
+    fn tail_line(&self, request: &Request, source_width: usize, client_width: usize) -> Line<'static> {
+        let client = tail_client(request);
+        let source = match (&request.source, source_width) {
+            (Some(source), 1..) => format!("{:<width$} ", truncate(source, source_width), width = source_width),
+            _ => String::new(),
+        };
+        let before = format!("{} {}{:<client_width$} {:<7} ",
+            request.timestamp.with_timezone(&Local).format("%H:%M:%S"), source, client, request.method);
+        let (status, response_time) = (request.status_code.to_string(), format!("{:>7.3}s", request.response_time));
+        let between = format!(" {:>9} ", format!("{}B", request.bytes_sent));
+        let after = format!(" {} \"{}\"", request.url(), request.user_agent);
+        let plain = format!("{}{}{}{}{}", before, status, between, response_time, after);
+        // A matching highlight rule colors the whole line instead
+        match highlight::rule_color(&self.config.highlights, &plain) {
+            Some(color) => Line::styled(plain, self.style(Some(color))),
+            None => Line::from(vec![
+                Span::raw(before),
+                self.status_span(status, request.status_code),
+                Span::raw(between),
+                self.latency_span(response_time, request.response_time),
+                Span::raw(after),
+            ]),
+        }
+    }
+}
+
+fn tail_client(request: &Request) -> String {
+    match request.country {
+        Some(country) => format!("{} {}", request.ip, country.code),
+        None => request.ip.clone(),
+    }
+}
@@ -0,0 +1,673 @@
+use std::collections::HashMap;
+
+use chrono::prelude::*;
+use ratatui::layout::Constraint;
+use ratatui::style::Style;
+use ratatui::widgets::{Cell, Row, Table};
+
+use crate::{attacks, baseline, columns, formats, geoip, terminal};
+use crate::Stats;
+use crate::agents::Grouping;
+use crate::apdex::Apdex;
+use crate::changes::Change;
+use crate::columns::{Column, Values};
+use crate::referrers::SpamVerdict;
+use crate::stats::group_totals;
+use crate::tenants::{TenantEntry, TenantStats};
+
+use super::{attackers, busiest_clients, error_offenders, ip_width, minutes, truncate, Httop, Page, SortBy};
+
+// Terminals at least this wide show counts next to percentages
+const WIDE_COLUMNS: usize = 160;
+
+// Columns are dropped until the key has at least this much room
+const MIN_KEY_WIDTH: usize = 12;
+
+// "AS16509 Amazon.com, Inc." and the like, next to client IPs
+const NETWORK_WIDTH: usize = 24;
+
+// Clients the Client Rates view needs before suggesting limit_req values
+const RATE_LIMIT_CLIENTS: usize = 100;
+
+impl Httop {
+    pub(super) fn render_paths(&self, page: &mut Page, stats: &Stats) {
+        // Sorted by latency, the table answers which endpoints are slow, and
+        // by bytes which ones use the bandwidth
+        let keys = if self.by_endpoint { "Endpoints" } else { "Paths" };
+        let title = match (self.sort.by, self.sort.reversed) {
+            (SortBy::Latency, false) => format!("Slowest {}", keys),
+            (SortBy::Bytes, false) => format!("Top Bandwidth Consumers ({})", keys),
+            (SortBy::CacheHit, false) => format!("Worst Cached {}", keys),
+            _ => format!("Top {}", keys),
+        };
+        page.line(format!("{} (Sort: {}, m to {} methods):",
+            title,
+            self.sort.name(),
+            if self.by_endpoint { "merge" } else { "split by" }));
+        let mut methods: Vec<_> = stats.methods.iter().collect();
+        methods.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        if !methods.is_empty() {
+            page.line(format!("Methods: {}", methods.iter()
+                .map(|(method, count)| format!("{} {:.1}%", method, **count as f64 / stats.total_requests as f64 * 100.0))
+                .collect::<Vec<_>>()
+                .join(" | ")));
+        }
+
+        // Table header
+        let show_baseline = self.baseline.is_some();
+        // Deltas and baseline figures follow the count, when it is shown
+        let mut extra = Vec::new();
+        if self.show_deltas {
+            extra.push(("+NEW", 7));
+        }
+        if show_baseline {
+            extra.extend([("RPS%", 7), ("RT%", 7)]);
+        }
+        let columns = self.fitting_columns(extra.iter().map(|(_, width)| width + 1).sum());
+        let at = columns.iter().position(|column| *column == Column::Count).map_or(0, |count| count + 1);
+        let mut headers = self.column_headers(&columns);
+        headers.splice(at..at, extra);
+        page.blank();
+
+        let groups = if self.by_endpoint { &stats.endpoint_totals } else { &stats.path_totals };
+        let totals = group_totals(groups);
+        let mut rows = Vec::new();
+        for (index, (path, entry)) in self.visible(self.sorted_groups(groups).into_iter().enumerate()) {
+            let mut extra: Vec<Cell> = Vec::new();
+            if self.show_deltas {
+                extra.push(match self.row_changes.delta(path) {
+                    0 => Cell::default(),
+                    delta => format!("+{}", delta).into(),
+                });
+            }
+            if show_baseline {
+                // Baselines are per path, whatever the method
+                let path: &str = if self.by_endpoint { path.split_once(' ').map_or(path, |(_, path)| path) } else { path };
+                extra.push(self.baseline_rps_percent(stats, path).into());
+                extra.push(self.baseline_rt_percent(stats, path).into());
+            }
+
+            // A row highlighted as new or grown keeps its one color
+            let highlighted = !self.is_selected(index) && self.row_changes.change(path).is_some();
+            let mut cells = self.column_cells(&columns, entry, totals, highlighted);
+            cells.splice(at..at, extra);
+            cells.push(path.to_string().into());
+            // New rows and rows that just grew stand out for a moment
+            let unkeyed: &str = if self.by_endpoint { path.split_once(' ').map_or(path, |(_, path)| path) } else { path };
+            let style = match self.row_changes.change(path) {
+                _ if self.is_selected(index) || self.spike_keys.contains(unkeyed) => self.row_style(index, unkeyed),
+                Some(Change::New) => self.style(self.config.theme.new_row),
+                Some(Change::Grew) => self.bold(self.config.theme.grown_row),
+                None => Style::new(),
+            };
+            rows.push(Row::new(cells).style(style));
+        }
+        page.table(self.table(&headers, if self.by_endpoint { "ENDPOINT" } else { "PATH" }), rows);
+    }
+
+    pub(super) fn render_ips(&self, page: &mut Page, stats: &Stats) {
+        let title = match (self.sort.by, self.sort.reversed) {
+            (SortBy::Bytes, false) => "Top Bandwidth Consumers (Client IPs)",
+            _ => "Top Client IPs",
+        };
+        page.line(format!("{} (Sort: {}, c/b/l/n to change, w IP for WHOIS)", title, self.sort.name()));
+        page.blank();
+        if self.geoip.is_none() && self.asn.is_none() {
+            self.render_groups(page, &stats.client_ips, "CLIENT IP");
+            return;
+        }
+
+        let mut extra = 0;
+        if self.geoip.is_some() {
+            extra += 8;
+        }
+        if self.asn.is_some() {
+            extra += NETWORK_WIDTH + 1;
+        }
+        let columns = self.fitting_columns(extra);
+        let mut headers = self.column_headers(&columns);
+        if self.geoip.is_some() {
+            headers.push(("GEO", 7));
+        }
+        if self.asn.is_some() {
+            headers.push(("NETWORK", NETWORK_WIDTH));
+        }
+        let totals = group_totals(&stats.client_ips);
+        let mut rows = Vec::new();
+        for (index, (ip, entry)) in self.visible(self.sorted_groups(&stats.client_ips).into_iter().enumerate()) {
+            let mut cells = self.column_cells(&columns, entry, totals, false);
+            if let Some(geoip) = &self.geoip {
+                cells.push(geoip.lookup(ip).map_or("-".to_string(), |c| format!("{}/{}", c.code, c.continent)).into());
+            }
+            if let Some(asn) = &self.asn {
+                let network = asn.lookup(ip);
+                cells.push(truncate(network.as_deref().unwrap_or("-"), NETWORK_WIDTH).into());
+            }
+            rows.push(self.keyed_row(index, ip, cells));
+        }
+        page.table(self.table(&headers, "CLIENT IP"), rows);
+    }
+
+    pub(super) fn render_user_agents(&self, page: &mut Page, stats: &Stats) {
+        let (title, label) = match self.ua_grouping {
+            Grouping::Raw => ("User Agents", "USER AGENT"),
+            Grouping::Browser => ("Browsers", "BROWSER"),
+            Grouping::Os => ("Operating Systems", "OS"),
+            Grouping::Device => ("Devices", "DEVICE"),
+        };
+        let next = match self.ua_grouping.next() {
+            Grouping::Raw => "list them as they are".to_string(),
+            grouping => format!("group by {}", grouping.name()),
+        };
+        page.line(format!("Top {} (Sort: {}, c/b/l/n to change, g to {})", title, self.sort.name(), next));
+        page.blank();
+        self.render_groups(page, stats.user_agent_groups(self.ua_grouping), label);
+    }
+
+    pub(super) fn render_bots(&self, page: &mut Page, stats: &Stats) {
+        if self.toggles.hide_bots {
+            page.line("Bots are left out of every view (x to count them again)");
+            return;
+        }
+        page.line(format!("Top Bots: {} requests, {:.1}% of all (Sort: {}, c/b/l/n to change, x to leave bots out of every view)",
+            group_totals(&stats.bots).0, stats.bot_percent(), self.sort.name()));
+        page.blank();
+        self.render_groups(page, &stats.bots, "BOT");
+    }
+
+    // The paths behind one kind of error response, most frequent first
+    pub(super) fn render_error_paths(&self, page: &mut Page, groups: &TenantStats, title: &str, hint: &str) {
+        page.line(format!("Top {}: {} requests to {} paths, {} (Sort: {}, c/b/l/n to change)",
+            title, group_totals(groups).0, groups.tenants.len(), hint, self.sort.name()));
+        page.blank();
+        self.render_groups(page, groups, "PATH");
+    }
+
+    // Clients by the errors they run into: credential stuffing shows as a
+    // pile of 401/403s, vulnerability scanners as 404s at a steady pace
+    pub(super) fn render_offenders(&self, page: &mut Page, stats: &Stats) {
+        let offenders = error_offenders(&stats.client_ips);
+        page.line(format!("Top Error Offenders: {} clients with 4xx or 5xx responses (by error count, Enter for details)",
+            offenders.len()));
+        page.blank();
+        let mut rows = Vec::new();
+        for (index, (ip, entry)) in self.visible(offenders.into_iter().enumerate()) {
+            let errors = entry.client_errors + entry.server_errors;
+            let cells = vec![
+                errors.to_string().into(),
+                entry.client_errors.to_string().into(),
+                entry.server_errors.to_string().into(),
+                entry.denied.to_string().into(),
+                format!("{:.1}", entry.rate.rate() * 60.0).into(),
+                entry.count.to_string().into(),
+                format!("{:.1}%", entry.error_percent(errors)).into(),
+            ];
+            rows.push(self.keyed_row(index, ip, cells));
+        }
+        page.table(self.table(&[("ERRORS", 8), ("4XX", 8), ("5XX", 8), ("401/403", 9), ("REQ/MIN", 9),
+            ("COUNT", 8), ("ERROR%", 8)], "CLIENT IP"), rows);
+    }
+
+    pub(super) fn render_attacks(&self, page: &mut Page, stats: &Stats) {
+        let attacks = &stats.attacks;
+        let clients = attackers(&stats.client_ips);
+        page.line(format!("Attacks: {} requests matched attack signatures, {:.0}/min now, from {} clients (Enter for details)",
+            attacks.total(), attacks.rate.rate() * 60.0, clients.iter().filter(|(_, entry)| entry.attacks > 0).count()));
+        for signature in attacks::Signature::ALL {
+            page.line(format!("  {:<26} {}", signature.name(), attacks.counts[signature as usize]));
+        }
+        let guessing = clients.iter().filter(|(_, entry)| entry.denied >= attacks::AUTH_FAILURES).count();
+        page.line(format!("  {:<26} {} client{} with {} or more 401/403 responses", "credential guessing",
+            guessing, if guessing == 1 { "" } else { "s" }, attacks::AUTH_FAILURES));
+        page.blank();
+
+        let mut rows = Vec::new();
+        for (index, (ip, entry)) in self.visible(clients.into_iter().enumerate()) {
+            let kinds: Vec<&str> = attacks::Signature::all_in(entry.attack_kinds).map(attacks::Signature::name).collect();
+            let cells = vec![
+                entry.attacks.to_string().into(),
+                entry.denied.to_string().into(),
+                entry.client_errors.to_string().into(),
+                format!("{:.1}", entry.rate.rate() * 60.0).into(),
+                entry.count.to_string().into(),
+                truncate(&kinds.join(", "), 34).into(),
+            ];
+            rows.push(self.keyed_row(index, ip, cells));
+        }
+        page.table(self.table(&[("ATTACKS", 9), ("401/403", 9), ("4XX", 8), ("REQ/MIN", 9), ("COUNT", 8),
+            ("KINDS", 34)], "CLIENT IP"), rows);
+
+        if attacks.recent.is_empty() {
+            return;
+        }
+        page.blank();
+        page.line("Recent Matches (newest first):");
+        let recent: Vec<_> = attacks.recent.iter().rev().take((self.display_limit / 2).max(5)).collect();
+        let ip_width = ip_width(recent.iter().map(|found| found.ip.as_str()));
+        let rows = recent.iter()
+            .map(|found| Row::new([
+                found.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string().into(),
+                found.signature.name().into(),
+                self.status_span(found.status_code.to_string(), found.status_code).into(),
+                truncate(&found.ip, ip_width).into(),
+                Cell::from(found.url.clone()),
+            ]))
+            .collect();
+        page.table(self.table(&[("TIME", 21), ("SIGNATURE", 26), ("STATUS", 8), ("IP", ip_width)], "URL"), rows);
+    }
+
+    // For picking nginx limit_req values: how fast clients go on average,
+    // now, and at their busiest second and minute of log time
+    pub(super) fn render_rate_limits(&self, page: &mut Page, stats: &Stats) {
+        let clients = busiest_clients(&stats.client_ips);
+        if clients.is_empty() {
+            page.line("No requests yet");
+            return;
+        }
+        page.line(format!("Client Request Rates: {} clients (by busiest second of log time, Enter for details)", clients.len()));
+        if clients.len() < RATE_LIMIT_CLIENTS {
+            page.line(format!("Suggested limit_req: needs {} clients or more, so that a few abusive ones do not set it",
+                RATE_LIMIT_CLIENTS));
+        } else {
+            // Nearest rank
+            let p99 = |mut peaks: Vec<usize>| {
+                peaks.sort_unstable();
+                peaks[(peaks.len() as f64 * 0.99).ceil() as usize - 1]
+            };
+            let minute = p99(clients.iter().map(|(_, entry)| entry.peak_minute.max).collect());
+            let second = p99(clients.iter().map(|(_, entry)| entry.peak_second.max).collect());
+            page.line(format!("Busiest minute and second of the 99th percentile client: {} and {} requests; as a starting point, limit_req rate={}r/m burst={}",
+                minute, second, minute, second));
+        }
+        page.blank();
+        let mut rows = Vec::new();
+        for (index, (ip, entry)) in self.visible(clients.into_iter().enumerate()) {
+            let cells = vec![
+                entry.peak_second.max.to_string().into(),
+                entry.peak_minute.max.to_string().into(),
+                format!("{:.1}", entry.average_per_minute()).into(),
+                format!("{:.1}", entry.rate.rate() * 60.0).into(),
+                entry.count.to_string().into(),
+            ];
+            rows.push(self.keyed_row(index, ip, cells));
+        }
+        page.table(self.table(&[("PEAK/S", 8), ("PEAK/MIN", 10), ("AVG/MIN", 9), ("REQ/MIN", 9), ("COUNT", 8)],
+            "CLIENT IP"), rows);
+    }
+
+    pub(super) fn render_statuses(&self, page: &mut Page, stats: &Stats) {
+        page.line(format!("Status Codes (Sort: {}, c/b/l/n to change)", self.sort.name()));
+        page.blank();
+        let rows = self.visible(self.sorted_groups(&stats.statuses).into_iter())
+            .map(|(status, entry)| {
+                let share = entry.count as f64 / stats.total_requests.max(1) as f64 * 100.0;
+                Row::new([
+                    entry.count.to_string().into(),
+                    format!("{:.1}%", share).into(),
+                    format!("{:.2}", entry.rate.rate()).into(),
+                    self.latency_cell(entry.avg_response_time()).into(),
+                    entry.bytes.to_string().into(),
+                    Cell::from(self.status_span(status.clone(), status.parse().unwrap_or(0))),
+                ])
+            })
+            .collect();
+        page.table(self.table(&[("COUNT", 7), ("SHARE", 9), ("REQ/S", 9), ("AVG RT", 10), ("BYTES", 12)], "STATUS"), rows);
+    }
+
+    pub(super) fn render_campaigns(&self, page: &mut Page, stats: &Stats) {
+        let share = if stats.total_requests > 0 {
+            stats.campaigns.tagged_requests as f64 / stats.total_requests as f64 * 100.0
+        } else {
+            0.0
+        };
+        page.line(format!("Campaign Traffic (utm_source/utm_medium/utm_campaign): {} requests ({:.1}% of total)",
+            stats.campaigns.tagged_requests, share));
+        page.blank();
+
+        let mut campaigns: Vec<_> = stats.campaigns.campaigns.iter().collect();
+        campaigns.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.count));
+
+        let rows = self.visible(campaigns.iter())
+            .map(|(campaign, entry)| Row::new([
+                entry.count.to_string(),
+                format!("{:.2}", entry.rate.rate()),
+                entry.bytes.to_string(),
+                truncate(&campaign.source, 22),
+                truncate(&campaign.medium, 22),
+                campaign.name.clone(),
+            ]))
+            .collect();
+        page.table(self.table(&[("COUNT", 7), ("REQ/S", 9), ("BYTES", 12), ("SOURCE", 22), ("MEDIUM", 22)], "CAMPAIGN"), rows);
+    }
+
+    // Entry pages count every visit, exit pages only those already over
+    pub(super) fn render_visits(&self, page: &mut Page, stats: &Stats) {
+        let visits = &stats.visits;
+        if visits.visits() == 0 {
+            page.line("No page views yet (HTML pages answered with 1xx-3xx, bots left out)");
+            return;
+        }
+        page.line(format!("Visits: {} (by client IP and user agent, over after {} without a page view) | Active: {} | Pages per visit: {:.1} | Bounce rate: {:.1}%",
+            visits.visits(), minutes(visits.timeout), visits.active(), visits.pages_per_visit(), visits.bounce_percent()));
+
+        let limit = (self.display_limit / 2).max(5);
+        for (title, pages, total) in [
+            ("ENTRY PAGE", &visits.entry_pages, visits.visits()),
+            ("EXIT PAGE", &visits.exit_pages, visits.closed),
+        ] {
+            page.blank();
+            let mut pages: Vec<_> = pages.iter().collect();
+            pages.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            let rows = pages.iter().take(limit)
+                .map(|(path, count)| Row::new([
+                    count.to_string(),
+                    format!("{:.1}%", **count as f64 / total.max(1) as f64 * 100.0),
+                    path.to_string(),
+                ]))
+                .collect();
+            page.table(self.table(&[("VISITS", 9), ("SHARE", 9)], title), rows);
+        }
+    }
+
+    pub(super) fn render_tenants(&self, page: &mut Page, stats: &Stats) {
+        if !self.config.tenant_filter.is_empty() {
+            page.line(format!("Tenant filter: {}", self.config.tenant_filter.join(", ")));
+        }
+        page.line(format!("Tenants (from log field {})", self.config.tenant_fields.join("/")));
+        page.blank();
+        self.render_groups(page, &stats.tenants, "TENANT");
+    }
+
+    pub(super) fn render_vhosts(&self, page: &mut Page, stats: &Stats) {
+        if stats.vhosts.tenants.is_empty() {
+            page.line("No host field in the log format");
+            page.line("(add host=$host to the nginx log_format, or %v to Apache's LogFormat)");
+            return;
+        }
+        if !self.config.vhost_filter.is_empty() {
+            page.line(format!("Virtual host filter: {}", self.config.vhost_filter.join(", ")));
+        }
+        page.line(format!("Virtual Hosts: {} hosts (from log field {}, search with vhost:NAME, Sort: {})",
+            stats.vhosts.tenants.len(), formats::HOST_FIELDS.join("/"), self.sort.name()));
+        page.blank();
+        self.render_groups(page, &stats.vhosts, "HOST");
+    }
+
+    pub(super) fn render_services(&self, page: &mut Page, stats: &Stats) {
+        page.line(format!("Services (from log field {})", formats::SERVICE_FIELDS.join("/")));
+        page.blank();
+        self.render_groups(page, &stats.services, "SERVICE");
+        page.blank();
+        page.line(format!("Upstreams (from log field {})", formats::UPSTREAM_FIELDS.join("/")));
+        page.blank();
+        self.render_groups(page, &stats.upstreams, "UPSTREAM");
+    }
+
+    pub(super) fn render_sources(&self, page: &mut Page, stats: &Stats) {
+        page.line("Sources (input files, search with file:NAME)");
+        page.blank();
+        self.render_groups(page, &stats.sources, "SOURCE");
+    }
+
+    pub(super) fn render_hosts(&self, page: &mut Page, stats: &Stats) {
+        page.line(format!("Hosts: {} (from the syslog header or {} field, else the input; search with host:NAME, Sort: {})",
+            stats.hosts.tenants.len(), formats::HOSTNAME_FIELD, self.sort.name()));
+        page.blank();
+        self.render_groups(page, &stats.hosts, "HOST");
+    }
+
+    pub(super) fn render_groups(&self, page: &mut Page, groups: &TenantStats, label: &str) {
+        let columns = self.fitting_columns(0);
+        let totals = group_totals(groups);
+        let rows = self.visible(self.sorted_groups(groups).into_iter().enumerate())
+            .map(|(index, (tenant, entry))| self.keyed_row(index, tenant, self.column_cells(&columns, entry, totals, false)))
+            .collect();
+        page.table(self.table(&self.column_headers(&columns), label), rows);
+    }
+
+    // The chosen columns that fit the terminal next to `extra` columns of
+    // other figures and the key. The first always stays; the rest are
+    // dropped from the end on narrow terminals
+    fn fitting_columns(&self, extra: usize) -> Vec<Column> {
+        let available = terminal::size().map_or(usize::MAX, |(_, columns)| columns);
+        let mut used = 1 + extra + MIN_KEY_WIDTH;
+        let mut fitting = Vec::new();
+        for (index, column) in self.shown_columns().iter().enumerate() {
+            used += column.width(self.values()) + 1;
+            if index > 0 && used > available {
+                break;
+            }
+            fitting.push(*column);
+        }
+        fitting
+    }
+
+    // The chosen columns, with each row's throughput next to its bytes
+    // while sorting by bytes, its hit ratio while sorting by cache hits, and
+    // the upstream split next to the average once the log has upstream times
+    fn shown_columns(&self) -> Vec<Column> {
+        let mut columns = self.columns.clone();
+        if self.sort.by == SortBy::Bytes
+            && !columns.contains(&Column::Throughput)
+            && let Some(at) = columns.iter().position(|column| *column == Column::Bytes) {
+            columns.insert(at + 1, Column::Throughput);
+        }
+        if self.sort.by == SortBy::CacheHit && !columns.contains(&Column::CacheHit) {
+            let at = columns.iter().position(|column| *column == Column::Count).map_or(0, |count| count + 1);
+            columns.insert(at, Column::CacheHit);
+        }
+        if self.upstream_timed && !columns.contains(&Column::Upstream) && !columns.contains(&Column::Overhead) {
+            let at = columns.iter().position(|column| *column == Column::Latency).map_or(columns.len(), |avg| avg + 1);
+            columns.splice(at..at, [Column::Upstream, Column::Overhead]);
+        }
+        if self.config.apdex_t.is_some() && !columns.contains(&Column::Apdex) {
+            let at = columns.iter().position(|column| *column == Column::P95Latency).map_or(columns.len(), |p95| p95 + 1);
+            columns.insert(at, Column::Apdex);
+        }
+        columns
+    }
+
+    // Percentages alone, unless the terminal has room for the counts too
+    fn values(&self) -> Values {
+        match terminal::size() {
+            _ if !self.percent => Values::Absolute,
+            Some((_, columns)) if columns >= WIDE_COLUMNS => Values::Both,
+            _ => Values::Percent,
+        }
+    }
+
+    fn column_headers(&self, columns: &[Column]) -> Vec<(&'static str, usize)> {
+        let values = self.values();
+        columns.iter().map(|column| (column.header(values), column.width(values))).collect()
+    }
+
+    // `totals` are the requests and bytes of the whole table, and `plain`
+    // leaves latency uncolored for rows drawn in a color of their own
+    fn column_cells(&self, columns: &[Column], entry: &TenantEntry, totals: (usize, usize), plain: bool) -> Vec<Cell<'static>> {
+        let values = self.values();
+        columns.iter()
+            .map(|column| {
+                let text = match column {
+                    Column::Count => columns::value(entry.count, totals.0, values),
+                    Column::Rate => format!("{:.2}", entry.rate.rate()),
+                    Column::ClientErrors => format!("{:.1}%", entry.error_percent(entry.client_errors)),
+                    Column::ServerErrors => format!("{:.1}%", entry.error_percent(entry.server_errors)),
+                    Column::Latency if !plain => return self.latency_cell(entry.avg_response_time()).into(),
+                    Column::Latency => format!("{:.3}s", entry.avg_response_time()),
+                    Column::P95Latency if !plain => return self.latency_cell(entry.p95_response_time()).into(),
+                    Column::P95Latency => format!("{:.3}s", entry.p95_response_time()),
+                    Column::MaxLatency if !plain => return self.latency_cell(entry.max_response_time).into(),
+                    Column::MaxLatency => format!("{:.3}s", entry.max_response_time),
+                    Column::Bytes => columns::bytes(entry.bytes, totals.1, values),
+                    Column::Throughput => columns::throughput(entry.throughput.rate()),
+                    Column::Uniques => entry.visitors.estimate().to_string(),
+                    Column::LastSeen => columns::age(entry.last_seen.elapsed()),
+                    Column::Apdex => Apdex::of_sketch(&entry.response_times, self.apdex_t()).score_text(),
+                    Column::Upstream | Column::Overhead => match (column, entry.upstream_split()) {
+                        (Column::Upstream, Some((upstream, _))) => format!("{:.3}s", upstream),
+                        (_, Some((_, overhead))) => format!("{:.3}s", overhead),
+                        (_, None) => "-".to_string(),
+                    },
+                    Column::CacheHit => entry.cache_hit_ratio()
+                        .map_or_else(|| "-".to_string(), |ratio| format!("{:.1}%", ratio * 100.0)),
+                };
+                text.into()
+            })
+            .collect()
+    }
+
+    // A table with a header of these columns, each as wide as given, and
+    // the key last, taking the rest of the width
+    pub(super) fn table(&self, columns: &[(&str, usize)], key: &str) -> Table<'static> {
+        let header = Row::new(columns.iter().map(|(name, _)| name.to_string()).chain([key.to_string()]))
+            .style(self.style(self.config.theme.table));
+        let widths = columns.iter().map(|(_, width)| Constraint::Length(*width as u16)).chain([Constraint::Fill(1)]);
+        Table::default().header(header).widths(widths).column_spacing(1)
+    }
+
+    // The cells of a row and its key, in the style of the selected row or
+    // one behind an anomaly
+    pub(super) fn keyed_row(&self, index: usize, key: &str, mut cells: Vec<Cell<'static>>) -> Row<'static> {
+        cells.push(key.to_string().into());
+        Row::new(cells).style(self.row_style(index, key))
+    }
+
+    pub(super) fn sorted_groups<'a>(&self, groups: &'a TenantStats) -> Vec<(&'a String, &'a TenantEntry)> {
+        let mut rows: Vec<_> = groups.tenants.iter().collect();
+        rows.sort_by(|a, b| self.sort.compare(
+            (a.0, a.1.count, a.1.bytes, a.1.p95_response_time(), a.1.cache_hit_ratio()),
+            (b.0, b.1.count, b.1.bytes, b.1.p95_response_time(), b.1.cache_hit_ratio())));
+        rows
+    }
+
+    pub(super) fn render_countries(&self, page: &mut Page, stats: &Stats) {
+        if self.geoip.is_none() {
+            page.line("No GeoIP database loaded (start with --geoip dbip-country-lite.csv)");
+            return;
+        }
+        if !self.config.country_filter.is_empty() {
+            page.line(format!("Country filter: {}", self.config.country_filter.join(", ")));
+        }
+        if !self.config.country_exclude.is_empty() {
+            page.line(format!("Excluded countries: {}", self.config.country_exclude.join(", ")));
+        }
+        let percent = |count: usize| if stats.total_requests > 0 {
+            count as f64 / stats.total_requests as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        page.line(format!("Continents: {}", stats.countries.continents().iter()
+            .map(|(continent, entry)| format!("{} {:.1}%", geoip::continent_name(continent), percent(entry.count)))
+            .collect::<Vec<_>>()
+            .join(" | ")));
+        page.line(format!("Unknown (private or unlisted addresses): {}", stats.countries.unknown));
+        page.blank();
+
+        let mut countries: Vec<_> = stats.countries.countries.iter().collect();
+        countries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.count));
+
+        let rows = self.visible(countries.iter())
+            .map(|(code, entry)| {
+                let (continent, name) = geoip::country(code).map_or(("", ""), |c| (c.continent, c.name));
+                Row::new([
+                    entry.count.to_string(),
+                    format!("{:.1}%", percent(entry.count)),
+                    entry.bytes.to_string(),
+                    code.to_string(),
+                    continent.to_string(),
+                    name.to_string(),
+                ])
+            })
+            .collect();
+        page.table(self.table(&[("COUNT", 7), ("SHARE", 9), ("BYTES", 12), ("CC", 6), ("CONTINENT", 11)], "COUNTRY"), rows);
+    }
+
+    pub(super) fn render_networks(&self, page: &mut Page, stats: &Stats) {
+        if self.asn.is_none() {
+            page.line("No ASN database loaded (start with --asn dbip-asn-lite.csv)");
+            return;
+        }
+        page.line(format!("Top Networks: {} networks, {} requests from unlisted addresses (search with asn:NAME, Sort: {})",
+            stats.networks.tenants.len(), stats.without_network, self.sort.name()));
+        page.blank();
+        self.render_groups(page, &stats.networks, "NETWORK");
+    }
+
+    pub(super) fn render_referrers(&self, page: &mut Page, stats: &Stats) {
+        let referrers = &stats.referrers;
+        // (referrer, domain, count, bytes, verdict), or per domain the
+        // number of distinct referrers in place of the referrer
+        let mut rows: Vec<(String, &str, usize, usize, SpamVerdict)> = Vec::new();
+        if self.referrer_domains {
+            let mut domains: HashMap<&str, (usize, usize, usize, SpamVerdict)> = HashMap::new();
+            for entry in referrers.referrers.values() {
+                let domain = domains.entry(&entry.domain).or_insert((0, 0, 0, referrers.verdict(entry)));
+                domain.0 += 1;
+                domain.1 += entry.count;
+                domain.2 += entry.bytes;
+            }
+            rows.extend(domains.into_iter().map(|(domain, (urls, count, bytes, verdict))| {
+                (format!("{} referrer{}", urls, if urls == 1 { "" } else { "s" }), domain, count, bytes, verdict)
+            }));
+        } else {
+            rows.extend(referrers.referrers.iter()
+                .map(|(referrer, entry)| (referrer.clone(), entry.domain.as_str(), entry.count, entry.bytes, referrers.verdict(entry))));
+        }
+
+        let spam_hits: usize = rows.iter()
+            .filter(|row| row.4 != SpamVerdict::Clean)
+            .map(|row| row.2)
+            .sum();
+        if !self.show_spam_referrers {
+            rows.retain(|row| row.4 == SpamVerdict::Clean);
+        }
+        rows.sort_by(|a, b| {
+            // Grouped by domain, the domain is the name sorted by
+            let (a_name, b_name) = if self.referrer_domains { (a.1, b.1) } else { (a.0.as_str(), b.0.as_str()) };
+            self.sort.compare((a_name, a.2, a.3, 0.0, None), (b_name, b.2, b.3, 0.0, None))
+        });
+
+        page.line(format!("Top Referrers (Sort: {}, c/b/n to change; {} hits from spam referrers {}, S to toggle; D for {})",
+            self.sort.name(), spam_hits, if self.show_spam_referrers { "shown" } else { "hidden" },
+            if self.referrer_domains { "full referrers" } else { "domains" }));
+        let external: usize = referrers.referrers.values().map(|entry| entry.count).sum();
+        let share = |count: usize| count as f64 / (referrers.direct + external).max(1) as f64 * 100.0;
+        page.line(format!("Direct: {} ({:.1}%) | External: {} ({:.1}%)",
+            referrers.direct, share(referrers.direct), external, share(external)));
+        page.blank();
+
+        let rows = self.visible(rows.iter())
+            .map(|(referrer, domain, count, bytes, verdict)| {
+                let spam = match verdict {
+                    SpamVerdict::Clean => "",
+                    SpamVerdict::Listed => "listed",
+                    SpamVerdict::Suspect => "suspect",
+                };
+                Row::new([count.to_string(), bytes.to_string(), spam.to_string(), truncate(domain, 30), referrer.clone()])
+            })
+            .collect();
+        page.table(self.table(&[("COUNT", 7), ("BYTES", 12), ("SPAM", 9), ("DOMAIN", 30)],
+            if self.referrer_domains { "REFERRERS" } else { "REFERRER" }), rows);
+    }
+
+    fn baseline_rps_percent(&self, stats: &Stats, path: &str) -> String {
+        let current = stats.path_activity.get(path).map_or(0.0, |a| a.rps());
+        self.baseline.as_ref()
+            .and_then(|b| b.paths.get(path))
+            .and_then(|b| baseline::percent_of(current, b.rps))
+            .map_or("new".to_string(), |p| format!("{:.0}%", p))
+    }
+
+    fn baseline_rt_percent(&self, stats: &Stats, path: &str) -> String {
+        let current = stats.path_activity.get(path).and_then(|a| a.avg_response_time());
+        let baseline = self.baseline.as_ref()
+            .and_then(|b| b.paths.get(path))
+            .map(|b| b.avg_response_time);
+        match (current, baseline) {
+            (Some(current), Some(baseline)) => baseline::percent_of(current, baseline)
+                .map_or("-".to_string(), |p| format!("{:.0}%", p)),
+            _ => "-".to_string(),
+        }
+    }
+}
@@ -0,0 +1,691 @@
+use chrono::prelude::*;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, Row};
+
+use crate::{compare, detail, histogram, outliers, slo, slow, tenants, tls, trace, unparsed};
+use crate::{Request, Stats};
+use crate::apdex::Apdex;
+use crate::content::ContentClass;
+use crate::rdap::Lookup;
+use crate::slo::State;
+use crate::stats::group_totals;
+
+use super::{ip_width, minutes, shown, truncate, upstream_line, Httop, Page};
+use super::keys::KEYS;
+
+impl Httop {
+    pub(super) fn render_help(&self, page: &mut Page) {
+        let keymap = &self.config.keymap;
+        let mut lines: Vec<Line> = KEYS.iter()
+            .map(|(keys, action)| Line::from(format!("{:<16} {}", keymap.label(keys), action)))
+            .collect();
+        lines.push(Line::default());
+        lines.push(Line::from(format!("Format: {}", self.config.format.name())));
+        lines.push(Line::from(format!("Input: {}", self.config.inputs().join(", "))));
+        if let Some(real_ip) = &self.config.real_ip {
+            lines.push(Line::from(format!("Client addresses: {}", real_ip)));
+        }
+
+        let mut filters = Vec::new();
+        if let Some(query) = &self.search {
+            filters.push(format!("search {}", query.text()));
+        }
+        if !self.config.tenant_filter.is_empty() {
+            filters.push(format!("tenants {}", self.config.tenant_filter.join(", ")));
+        }
+        if !self.config.vhost_filter.is_empty() {
+            filters.push(format!("virtual hosts {}", self.config.vhost_filter.join(", ")));
+        }
+        filters.extend(self.config.filters.describe());
+        if let Some(since) = self.config.since {
+            filters.push(format!("logged since {}", since.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")));
+        }
+        if let Some(until) = self.config.until {
+            filters.push(format!("logged before {}", until.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")));
+        }
+        if !self.config.country_filter.is_empty() {
+            filters.push(format!("countries {}", self.config.country_filter.join(", ")));
+        }
+        if !self.config.country_exclude.is_empty() {
+            filters.push(format!("countries except {}", self.config.country_exclude.join(", ")));
+        }
+        if let Some(window) = self.config.window {
+            filters.push(format!("last {} of traffic", minutes(window)));
+        }
+        if self.config.drop_duplicates {
+            filters.push("duplicates dropped".to_string());
+        }
+        if self.config.anonymize_ips {
+            filters.push("client addresses anonymized".to_string());
+        }
+        if !self.show_spam_referrers {
+            filters.push("spam referrers hidden".to_string());
+        }
+        filters.extend(self.toggles.describe());
+        lines.push(Line::from(format!("Filters: {}", if filters.is_empty() { "none".to_string() } else { filters.join("; ") })));
+        page.boxed(Block::bordered().title(format!(" Keys ({} again to close) ", keymap.label("? or h"))), lines);
+    }
+
+    pub(super) fn render_detail(&self, page: &mut Page) {
+        let Some((key, _)) = &self.detail else {
+            return;
+        };
+        let history = self.history.lock().unwrap();
+        let requests = history.newest_first()
+            .filter(|request| key.matches(request))
+            .filter(|request| shown(request, self.search.as_ref(), &self.toggles));
+        let detail = detail::Detail::collect(requests);
+
+        page.line(format!("Details: {} (Backspace or Left to go back)", key.describe()));
+        page.line(format!("From the last {} retained requests{}", history.len(),
+            self.search.as_ref().map_or(String::new(), |query| format!(", filtered by {}", query.text()))));
+        page.blank();
+        page.line(format!("Requests: {} | Bytes: {}", detail.count, detail.bytes));
+        let seconds = |value: Option<f64>| value.map_or("-".to_string(), |value| format!("{:.3}s", value));
+        page.line(format!("Latency: avg {} | p50 {} | p95 {} | p99 {} | max {}{}",
+            seconds(detail.avg_response_time()), seconds(detail.percentile(0.5)), seconds(detail.percentile(0.95)),
+            seconds(detail.percentile(0.99)), seconds(detail.percentile(1.0)),
+            self.config.apdex_t.map_or(String::new(), |t| format!(" | Apdex {}", Apdex::of_times(&detail.response_times, t).score_text()))));
+        if let Some((upstream, overhead)) = tenants::upstream_split(detail.upstream_requests, detail.upstream_time, detail.proxied_time) {
+            page.line(upstream_line(upstream, overhead));
+        }
+        let mut statuses = vec![Span::raw("Status codes: ")];
+        for (index, (status, count)) in detail.statuses.iter().enumerate() {
+            if index > 0 {
+                statuses.push(Span::raw(" | "));
+            }
+            statuses.push(self.status_span(status.to_string(), *status));
+            statuses.push(Span::raw(format!(" {} ({:.1}%)", count, *count as f64 / detail.count as f64 * 100.0)));
+        }
+        page.line(statuses);
+        // An endpoint has one method by definition
+        if !matches!(key, detail::Key::Endpoint(_)) {
+            page.line(format!("Methods: {}", detail.methods.iter()
+                .map(|(method, count)| format!("{} {} ({:.1}%)", method, count, *count as f64 / detail.count as f64 * 100.0))
+                .collect::<Vec<_>>()
+                .join(" | ")));
+        }
+        self.render_cache(page, &detail.cache);
+
+        let mut tops = Vec::new();
+        if !matches!(key, detail::Key::UserAgent(_)) {
+            tops.push(("USER AGENT", &detail.user_agents));
+        }
+        if !matches!(key, detail::Key::Ip(_)) {
+            tops.push(("CLIENT IP", &detail.ips));
+        }
+        for (label, counts) in tops {
+            page.blank();
+            let rows = counts.iter().take(5)
+                .map(|(name, count)| Row::new([
+                    count.to_string(),
+                    format!("{:.1}%", *count as f64 / detail.count as f64 * 100.0),
+                    name.clone(),
+                ]))
+                .collect();
+            page.table(self.table(&[("COUNT", 7), ("SHARE", 9)], label), rows);
+        }
+
+        page.blank();
+        page.line("Recent requests:");
+        let ip_width = ip_width(detail.recent.iter().map(|request| request.ip.as_str()));
+        for request in &detail.recent {
+            page.line(vec![
+                Span::raw(format!("  {} {:<ip_width$} {:<7} ",
+                    request.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"), request.ip, request.method)),
+                self.status_span(request.status_code.to_string(), request.status_code),
+                Span::raw(" "),
+                self.latency_span(format!("{:>7.3}s", request.response_time), request.response_time),
+                Span::raw(format!(" {:>9} {} \"{}\"", request.bytes_sent, request.url(), truncate(&request.user_agent, 60))),
+            ]);
+        }
+    }
+
+    pub(super) fn render_transfer(&self, page: &mut Page, stats: &Stats) {
+        let total = &stats.transfer.total;
+        if total.count == 0 {
+            page.line("No request size or pre-compression size fields in the log format");
+            page.line("(add request_length=$request_length and gzip_ratio=$gzip_ratio or");
+            page.line("upstream_response_length=$upstream_response_length to the nginx log_format)");
+            return;
+        }
+
+        page.line(format!("Uploads: {} bytes received | Compression ratio: {}",
+            total.received, format_ratio(total.compression_ratio())));
+        page.blank();
+
+        let mut paths: Vec<_> = stats.transfer.paths.iter().collect();
+        paths.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.received));
+
+        let rows = self.visible(paths.iter())
+            .map(|(path, entry)| Row::new([
+                entry.count.to_string(),
+                entry.received.to_string(),
+                entry.avg_received().to_string(),
+                entry.compressed.to_string(),
+                entry.uncompressed.to_string(),
+                format_ratio(entry.compression_ratio()),
+                path.to_string(),
+            ]))
+            .collect();
+        page.table(self.table(&[("COUNT", 7), ("RECEIVED", 12), ("AVG RECV", 10), ("SENT", 12), ("UNCOMPRESSED", 14),
+            ("RATIO", 8)], "PATH"), rows);
+    }
+
+    pub(super) fn render_sizes(&self, page: &mut Page, stats: &Stats) {
+        const BAR_WIDTH: usize = 50;
+
+        let global = &stats.sizes.global;
+        page.line(format!("Response Size Distribution (bytes_sent, p50: {}, p95: {}, largest: {})",
+            histogram::human_bytes(global.percentile(0.5)), histogram::human_bytes(global.percentile(0.95)),
+            histogram::human_bytes(global.max)));
+        let peak = global.buckets.iter().copied().max().unwrap_or(0);
+        for (bucket, &count) in global.buckets.iter().enumerate() {
+            let bar = self.chart_style.bar(count, peak, BAR_WIDTH);
+            page.line(format!("  {:>9} {:<8} {}", histogram::bucket_label(bucket), count, bar));
+        }
+        page.blank();
+
+        page.line("Per Path (one column per bucket above, taller = more responses)");
+
+        let mut paths: Vec<_> = stats.sizes.paths.iter().collect();
+        paths.sort_by_key(|(_, histogram)| std::cmp::Reverse(histogram.count));
+
+        let rows = self.visible(paths.iter())
+            .map(|(path, histogram)| Row::new([
+                histogram.count.to_string(),
+                format!("[{}]", self.chart_style.sparkline(&histogram.buckets)),
+                histogram::human_bytes(histogram.percentile(0.5)),
+                histogram::human_bytes(histogram.percentile(0.95)),
+                histogram::human_bytes(histogram.max),
+                path.to_string(),
+            ]))
+            .collect();
+        page.table(self.table(&[("COUNT", 7), ("SHAPE", 13), ("P50", 10), ("P95", 10), ("MAX", 10)], "PATH"), rows);
+    }
+
+    // The biggest single responses, each against its path's usual size
+    pub(super) fn render_largest(&self, page: &mut Page, stats: &Stats) {
+        let sizes = &stats.sizes;
+        if sizes.largest.is_empty() {
+            page.line("No responses yet");
+            return;
+        }
+        page.line(format!("Largest Responses (bytes_sent; all responses p95: {}, x P95 compares with the path's own p95)",
+            histogram::human_bytes(sizes.global.percentile(0.95))));
+        let responses: Vec<_> = self.visible(sizes.largest.iter()).collect();
+        let ip_width = ip_width(responses.iter().map(|response| response.ip.as_str()));
+
+        let rows = responses.into_iter()
+            .map(|response| {
+                let p95 = sizes.paths.get(stats.path_key(&response.path)).map_or(0, |histogram| histogram.percentile(0.95));
+                Row::new([
+                    response.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string().into(),
+                    histogram::human_bytes(response.bytes).into(),
+                    if p95 > 0 { format!("{:.1}x", response.bytes as f64 / p95 as f64) } else { "-".to_string() }.into(),
+                    self.status_span(response.status_code.to_string(), response.status_code).into(),
+                    response.method.clone().into(),
+                    truncate(&response.ip, ip_width).into(),
+                    Cell::from(response.path.clone()),
+                ])
+            })
+            .collect();
+        page.table(self.table(&[("TIME", 21), ("SIZE", 12), ("x P95", 9), ("STATUS", 8), ("METHOD", 8), ("IP", ip_width)],
+            "PATH"), rows);
+    }
+
+    pub(super) fn render_protocols(&self, page: &mut Page, stats: &Stats) {
+        let total = group_totals(&stats.protocols).0;
+        if total == 0 {
+            page.line("No HTTP versions in the log (the request line's protocol, or a server_protocol field)");
+            return;
+        }
+        let mix: Vec<String> = ["HTTP/1.0", "HTTP/1.1", "HTTP/2", "HTTP/3"].iter()
+            .map(|version| {
+                let count = stats.protocols.tenants.get(*version).map_or(0, |entry| entry.count);
+                format!("{} {:.1}%", version, count as f64 / total as f64 * 100.0)
+            })
+            .collect();
+        page.line(format!("HTTP Versions: {} | Not logged: {} (search with proto:VERSION, Sort: {})",
+            mix.join(" | "), stats.without_protocol, self.sort.name()));
+        page.blank();
+        self.render_groups(page, &stats.protocols, "PROTOCOL");
+    }
+
+    pub(super) fn render_tls(&self, page: &mut Page, stats: &Stats) {
+        let tls = &stats.tls;
+        let total = tls.tls_requests();
+        if total == 0 {
+            page.line("No TLS fields in the log format");
+            page.line("(add ssl_protocol=$ssl_protocol ssl_cipher=$ssl_cipher to the nginx log_format)");
+            return;
+        }
+
+        let percent = |count: usize| count as f64 / total as f64 * 100.0;
+        let legacy = tls.legacy_requests();
+        page.line(format!("TLS Requests: {} | Without TLS: {} | Legacy (SSLv3/TLS 1.0/1.1): {} ({:.2}%)",
+            total, tls.without_tls, legacy, percent(legacy)));
+        page.blank();
+
+        let mut protocols: Vec<_> = tls.protocols.iter().collect();
+        protocols.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+        let rows = protocols.into_iter()
+            .map(|(protocol, count)| {
+                let flag = if tls::is_legacy(protocol) { "  legacy" } else { "" };
+                Row::new([count.to_string(), format!("{:.2}%", percent(*count)), format!("{}{}", protocol, flag)])
+            })
+            .collect();
+        page.table(self.table(&[("COUNT", 9), ("SHARE", 9)], "PROTOCOL"), rows);
+
+        page.blank();
+        let limit = (self.display_limit / 2).max(5);
+        let mut ciphers: Vec<_> = tls.ciphers.iter().collect();
+        ciphers.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+        let rows = ciphers.iter().take(limit)
+            .map(|(cipher, count)| Row::new([count.to_string(), format!("{:.2}%", percent(**count)), cipher.to_string()]))
+            .collect();
+        page.table(self.table(&[("COUNT", 9), ("SHARE", 9)], "CIPHER"), rows);
+
+        if tls.legacy_clients.is_empty() {
+            return;
+        }
+        page.blank();
+        page.line("Legacy TLS Clients (would break if TLS 1.0/1.1 were disabled):");
+
+        let mut clients: Vec<_> = tls.legacy_clients.iter().collect();
+        clients.sort_by_key(|(_, client)| std::cmp::Reverse(client.count));
+        let rows = clients.iter().take(limit)
+            .map(|(user_agent, client)| {
+                let mut protocols: Vec<_> = client.protocols.iter().map(String::as_str).collect();
+                protocols.sort_unstable();
+                Row::new([
+                    client.count.to_string(),
+                    client.ips.estimate().to_string(),
+                    truncate(&protocols.join(","), 19),
+                    user_agent.to_string(),
+                ])
+            })
+            .collect();
+        page.table(self.table(&[("COUNT", 9), ("IPS", 7), ("PROTOCOLS", 19)], "USER AGENT"), rows);
+    }
+
+    pub(super) fn render_content(&self, page: &mut Page, stats: &Stats) {
+        page.line("Content Types (inferred from file extension)");
+        page.blank();
+
+        let percent = |part: usize, total: usize| {
+            if total > 0 { part as f64 / total as f64 * 100.0 } else { 0.0 }
+        };
+
+        let mut classes = ContentClass::ALL.to_vec();
+        classes.sort_by_key(|class| std::cmp::Reverse(stats.content.get(*class).count));
+
+        let rows = classes.into_iter()
+            .map(|class| {
+                let entry = stats.content.get(class);
+                Row::new([
+                    class.name().to_string(),
+                    entry.count.to_string(),
+                    format!("{:.1}%", percent(entry.count, stats.total_requests)),
+                    histogram::human_bytes(entry.bytes),
+                    format!("{:.1}%", percent(entry.bytes, stats.bytes_sent)),
+                    format!("{:.3}s", entry.avg_response_time()),
+                ])
+            })
+            .collect();
+        page.table(self.table(&[("CLASS", 10), ("COUNT", 9), ("REQ %", 9), ("BYTES", 12), ("BYTES %", 9)], "AVG RT"), rows);
+    }
+
+    pub(super) fn render_preflight(&self, page: &mut Page, stats: &Stats) {
+        let preflight = &stats.preflight;
+        let others = stats.total_requests - preflight.total.count;
+        let ratio = |preflights: usize, others: usize| {
+            if others > 0 {
+                format!("{:.2}", preflights as f64 / others as f64)
+            } else {
+                "-".to_string()
+            }
+        };
+
+        page.line(format!("OPTIONS Requests: {} | Per Other Request: {} | Avg RT: {:.3}s | Time Spent: {:.1}s",
+            preflight.total.count,
+            ratio(preflight.total.count, others),
+            preflight.total.avg_response_time(),
+            preflight.total.total_response_time));
+        page.blank();
+
+        let mut paths: Vec<_> = preflight.paths.iter().collect();
+        paths.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.count));
+
+        let rows = self.visible(paths.iter())
+            .map(|(path, entry)| {
+                // A path evicted and counted again may have fewer requests than preflights
+                let path_others = stats.paths.get(*path).copied().unwrap_or(0).saturating_sub(entry.count);
+                Row::new([
+                    entry.count.to_string().into(),
+                    path_others.to_string().into(),
+                    ratio(entry.count, path_others).into(),
+                    self.latency_cell(entry.avg_response_time()).into(),
+                    Cell::from(path.to_string()),
+                ])
+            })
+            .collect();
+        page.table(self.table(&[("OPTIONS", 9), ("OTHER", 9), ("RATIO", 9), ("AVG RT", 10)], "PATH"), rows);
+
+        page.blank();
+        if preflight.origins.is_empty() {
+            page.line("Top Origins: not logged (add origin=$http_origin to the log format)");
+            return;
+        }
+        page.line("Top Origins:");
+        let mut origins: Vec<_> = preflight.origins.iter().collect();
+        origins.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+        for (origin, count) in origins.iter().take(5) {
+            page.line(format!("  {:<8} {}", count, origin));
+        }
+    }
+
+    pub(super) fn render_redirects(&self, page: &mut Page, stats: &Stats) {
+        let redirects = &stats.redirects;
+        let share = if stats.total_requests > 0 {
+            redirects.total as f64 / stats.total_requests as f64 * 100.0
+        } else {
+            0.0
+        };
+        page.line(format!("Redirects: {} ({:.1}% of requests) | Landed on another redirect: {} | Longest chain: {}",
+            redirects.total, share, redirects.chained, redirects.longest_chain));
+        page.blank();
+        page.line("Hot Redirects (fix links pointing here at the source):");
+
+        let mut paths: Vec<_> = redirects.paths.iter().collect();
+        paths.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.count));
+        let limit = (self.display_limit / 2).max(5);
+
+        let rows = paths.iter().take(limit)
+            .map(|(path, entry)| Row::new([
+                entry.count.to_string(),
+                entry.by_code[0].to_string(),
+                entry.by_code[1].to_string(),
+                entry.by_code[2].to_string(),
+                entry.by_code[3].to_string(),
+                path.to_string(),
+            ]))
+            .collect();
+        page.table(self.table(&[("COUNT", 7), ("301", 7), ("302", 7), ("307", 7), ("308", 7)], "PATH"), rows);
+
+        page.blank();
+        page.line("Followed Redirects (next request from the same client within 2s):");
+
+        let mut edges: Vec<_> = redirects.edges.iter().collect();
+        edges.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+
+        let rows = edges.iter().take(limit)
+            .map(|((from, to), count)| {
+                let chain = if redirects.is_redirect(to) { "yes" } else { "" };
+                Row::new([count.to_string(), chain.to_string(), format!("{} -> {}", from, to)])
+            })
+            .collect();
+        page.table(self.table(&[("COUNT", 7), ("CHAIN", 7)], "FROM -> TO"), rows);
+    }
+
+    pub(super) fn render_whois(&self, page: &mut Page) {
+        let Some(ip) = self.whois else {
+            return;
+        };
+
+        let lines = match self.rdap.get(ip) {
+            Some(Lookup::Found(network)) => {
+                let or_unknown = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+                vec![
+                    Line::from(format!("Netblock: {}", network.netblock)),
+                    Line::from(format!("Network:  {}", or_unknown(&network.name))),
+                    Line::from(format!("Org:      {}", or_unknown(&network.org))),
+                    Line::from(format!("Country:  {}", or_unknown(&network.country))),
+                    Line::from(format!("Abuse:    {}", or_unknown(&network.abuse))),
+                ]
+            }
+            Some(Lookup::Failed(e)) => vec![Line::from(format!("Lookup failed: {}", e))],
+            Some(Lookup::Pending) | None => vec![Line::from("Looking up...")],
+        };
+        page.boxed(Block::bordered().title(format!(" WHOIS {} (w to close) ", ip)), lines);
+        page.blank();
+    }
+
+    pub(super) fn render_search(&self, page: &mut Page) {
+        let Some(query) = &self.search else {
+            return;
+        };
+
+        let history = self.history.lock().unwrap();
+        let matches: Vec<(usize, &Request)> = history.search(query)
+            .filter(|(_, request)| shown(request, None, &self.toggles))
+            .collect();
+        page.line(format!("Search: {} | {} matches in the last {} requests (/ QUERY to search again, / alone to leave, y # to copy as curl, o # to open trace)",
+            query.text(), matches.len(), history.len()));
+
+        // The trace id column only appears when the log carries one
+        let shown: Vec<_> = matches.iter().take(self.display_limit)
+            .map(|(id, request)| (id, request, trace::trace_id(&request.fields, &self.config.trace_fields)))
+            .collect();
+        let show_trace = shown.iter().any(|(_, _, trace_id)| trace_id.is_some());
+        let ip_width = ip_width(shown.iter().map(|(_, request, _)| request.ip.as_str()));
+        let mut columns = vec![("#", 9), ("TIME", 21), ("IP", ip_width), ("METHOD", 8), ("STATUS", 8), ("RT", 10)];
+        if show_trace {
+            columns.push(("TRACE ID", 34));
+        }
+        columns.push(("PATH", 39));
+
+        page.blank();
+        let rows = shown.into_iter()
+            .map(|(id, request, trace_id)| {
+                let mut cells: Vec<Cell> = vec![
+                    id.to_string().into(),
+                    request.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string().into(),
+                    truncate(&request.ip, ip_width).into(),
+                    truncate(&request.method, 8).into(),
+                    self.status_span(request.status_code.to_string(), request.status_code).into(),
+                    self.latency_cell(request.response_time).into(),
+                ];
+                if show_trace {
+                    cells.push(truncate(trace_id.as_deref().unwrap_or("-"), 34).into());
+                }
+                cells.push(truncate(&request.url(), 39).into());
+                cells.push(truncate(&request.user_agent, 64).into());
+                Row::new(cells)
+            })
+            .collect();
+        page.table(self.table(&columns, "USER AGENT"), rows);
+    }
+
+    pub(super) fn render_outliers(&self, page: &mut Page, stats: &Stats) {
+        let outliers = &stats.outliers;
+        if stats.timed_requests == 0 {
+            page.line("No response times in the log format, outliers need $request_time");
+            return;
+        }
+        page.line(format!("Latency Outliers: {} (slower than a path's moving average by {} standard deviations or more)",
+            outliers.total, outliers::Z_THRESHOLD));
+        page.blank();
+        page.line("Recent Outliers:");
+        let limit = (self.display_limit / 2).max(5);
+        let recent: Vec<_> = outliers.recent.iter().rev().take(limit).collect();
+        let ip_width = ip_width(recent.iter().map(|outlier| outlier.ip.as_str()));
+
+        let rows = recent.into_iter()
+            .map(|outlier| Row::new([
+                outlier.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string(),
+                format!("{:.3}s", outlier.response_time),
+                format!("{:.3}s", outlier.expected),
+                format!("{:.1}", outlier.z),
+                outlier.status_code.to_string(),
+                truncate(&outlier.ip, ip_width),
+                outlier.path.clone(),
+            ]))
+            .collect();
+        page.table(self.table(&[("TIME", 21), ("RT", 10), ("USUAL", 10), ("Z", 7), ("STATUS", 8), ("IP", ip_width)],
+            "PATH"), rows);
+
+        page.blank();
+        page.line("Paths With Most Outliers:");
+
+        let mut paths: Vec<_> = outliers.paths.iter().filter(|(_, baseline)| baseline.outliers > 0).collect();
+        paths.sort_by_key(|(_, baseline)| std::cmp::Reverse(baseline.outliers));
+        let rows = paths.iter().take(limit)
+            .map(|(path, baseline)| Row::new([
+                baseline.outliers.to_string(),
+                baseline.samples.to_string(),
+                format!("{:.3}s", baseline.mean),
+                format!("{:.3}s", baseline.stddev()),
+                path.to_string(),
+            ]))
+            .collect();
+        page.table(self.table(&[("OUTLIERS", 10), ("COUNT", 9), ("MEAN RT", 10), ("STDDEV", 10)], "PATH"), rows);
+    }
+
+    pub(super) fn render_slow(&self, page: &mut Page) {
+        let history = self.history.lock().unwrap();
+        let slow = &history.slow;
+        let Some(threshold) = slow.threshold else {
+            page.line("No slow request log, start with --slow-threshold 1s to keep every request taking a second or more");
+            return;
+        };
+        page.line(format!("Slow Requests: {} at {:.3}s or slower, the latest {} kept (newest first, at most {})",
+            slow.total, threshold, slow.len(), slow::CAPACITY));
+        let requests: Vec<_> = slow.newest_first().take((self.display_limit / 2).max(5)).collect();
+        let ip_width = ip_width(requests.iter().map(|request| request.ip.as_str()));
+        page.blank();
+
+        // Each request takes two lines, the second under its URL with its
+        // referrer, user agent and custom fields
+        let rows = requests.into_iter()
+            .map(|request| {
+                let fields: Vec<String> = request.fields.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+                Row::new([
+                    request.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string().into(),
+                    self.latency_cell(request.response_time).into(),
+                    self.status_span(request.status_code.to_string(), request.status_code).into(),
+                    truncate(&request.method, 9).into(),
+                    truncate(&request.ip, ip_width).into(),
+                    Cell::from(vec![
+                        Line::from(request.url().to_string()),
+                        Line::from(format!("{} \"{}\" {}", request.referrer, request.user_agent, fields.join(" "))),
+                    ]),
+                ])
+            })
+            .collect();
+        page.tall_table(self.table(&[("TIME", 21), ("RT", 10), ("STATUS", 8), ("METHOD", 9), ("IP", ip_width)], "URL"),
+            rows, 2);
+    }
+
+    pub(super) fn render_unparsed(&self, page: &mut Page) {
+        let unparsed = self.health.unparsed();
+        let format = self.config.format.name();
+        if unparsed.total() == 0 {
+            page.line(format!("No unparsed lines: all {} lines read so far were {}", self.health.lines(), format));
+            return;
+        }
+        page.line(format!("Unparsed Lines: {} of {} lines read (newest first, the latest {} kept)",
+            unparsed.total(), self.health.lines(), unparsed::KEPT));
+        for reason in unparsed::Reason::ALL {
+            page.line(format!("  {:<24} {}", reason.describe(format), unparsed.counts[reason as usize]));
+        }
+        page.blank();
+
+        let reason_width = unparsed::Reason::ALL.iter().map(|reason| reason.describe(format).len()).max().unwrap_or(0);
+        let rows = self.visible(unparsed.recent.iter())
+            .map(|line| Row::new([
+                line.time.format("%H:%M:%S").to_string(),
+                truncate(&line.source, 18),
+                line.reason.describe(format).to_string(),
+                line.text.clone(),
+            ]))
+            .collect();
+        page.table(self.table(&[("TIME", 10), ("SOURCE", 18), ("REASON", reason_width + 2)], "LINE"), rows);
+    }
+
+    pub(super) fn render_compare(&self, page: &mut Page, stats: &Stats) {
+        let Some(comparison) = &stats.comparison else {
+            page.line("Nothing to compare with, start with --compare FILE for a log from before, or --compare-window 1h");
+            return;
+        };
+        let (before, now) = (&comparison.baseline, &comparison.current);
+        page.line(format!("Compare: now ({}) vs {} ({})", now.span(), comparison.describe(), before.span()));
+        let (rate_before, rate_now) = (before.per_minute(before.requests), now.per_minute(now.requests));
+        let (p95_before, p95_now) = (before.response_times.quantile(0.95), now.response_times.quantile(0.95));
+        let seconds = |p95: Option<f64>| p95.map_or("n/a".to_string(), |p95| format!("{:.3}s", p95));
+        page.line(format!("Requests: {:.1}/min vs {:.1}/min ({}) | p95: {} vs {} ({}) | 5xx: {:.1}% vs {:.1}%",
+            rate_now, rate_before, compare::percent_change(rate_before, rate_now),
+            seconds(p95_now), seconds(p95_before), compare::time_change(p95_before, p95_now),
+            now.error_percent(), before.error_percent()));
+        page.blank();
+
+        let rows = comparison.statuses().into_iter()
+            .map(|(code, rate_before, rate_now)| Row::new([
+                self.status_span(code.to_string(), code).into(),
+                format!("{:.1}", rate_before).into(),
+                format!("{:.1}", rate_now).into(),
+                Cell::from(compare::percent_change(rate_before, rate_now)),
+            ]))
+            .collect();
+        page.table(self.table(&[("STATUS", 8), ("BEFORE/MIN", 12), ("NOW/MIN", 12)], "CHANGE"), rows);
+        page.blank();
+
+        let rows = self.visible(comparison.paths().into_iter())
+            .map(|change| Row::new([
+                format!("{:.1}", change.before),
+                format!("{:.1}", change.now),
+                compare::percent_change(change.before, change.now),
+                seconds(change.p95_before),
+                seconds(change.p95_now),
+                compare::time_change(change.p95_before, change.p95_now),
+                change.path.to_string(),
+            ]))
+            .collect();
+        page.table(self.table(&[("BEFORE/MIN", 12), ("NOW/MIN", 12), ("CHANGE", 8), ("P95 BEFORE", 12), ("P95 NOW", 12),
+            ("P95 CHANGE", 12)], "PATH (new and gone paths too)"), rows);
+    }
+
+    pub(super) fn render_slos(&self, page: &mut Page, stats: &Stats) {
+        let standings = stats.slos.standings();
+        if standings.is_empty() {
+            page.line("No SLOs, declare them with --slo 'path=/api/checkout availability=99.9 latency_p99=500ms' (repeatable)");
+            return;
+        }
+        let count = |state: State| standings.iter().filter(|(_, compliance)| compliance.state == state).count();
+        let latest = DateTime::from_timestamp(stats.slos.latest * 60, 0)
+            .map_or("-".to_string(), |time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string());
+        page.line(format!("SLOs: {} objectives, {} violated, {} burning, over logged time up to {} (burn rate 1.0x spends the budget over the window)",
+            standings.len(), count(State::Violated), count(State::Burning), latest));
+        page.blank();
+
+        let rows = self.visible(standings.iter())
+            .map(|(slo, compliance)| {
+                let state = compliance.state.name().to_string();
+                // In the colors of 5xx and 4xx responses
+                let state = match compliance.state {
+                    State::Violated => self.status_span(state, 500),
+                    State::Burning => self.status_span(state, 400),
+                    State::Met => Span::raw(state),
+                };
+                Row::new([
+                    truncate(&compliance.objective, 22).into(),
+                    slo::window_name(slo.window).into(),
+                    compliance.requests.to_string().into(),
+                    compliance.bad.to_string().into(),
+                    compliance.percent().map_or("-".to_string(), |percent| format!("{:.2}%", percent)).into(),
+                    format!("{:.0}%", compliance.budget_left * 100.0).into(),
+                    slo::burn_text(compliance.burn_rates[0]).into(),
+                    slo::burn_text(compliance.burn_rates[1]).into(),
+                    state.into(),
+                    Cell::from(slo.to_string()),
+                ])
+            })
+            .collect();
+        page.table(self.table(&[("OBJECTIVE", 22), ("WINDOW", 8), ("REQUESTS", 10), ("BAD", 8), ("GOOD", 10),
+            ("BUDGET", 9), ("BURN 5M", 9), ("BURN 1H", 9), ("STATE", 10)], "ENDPOINT"), rows);
+    }
+}
+
+fn format_ratio(ratio: Option<f64>) -> String {
+    ratio.map_or("-".to_string(), |r| format!("{:.2}x", r))
+}
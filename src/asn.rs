@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::geoip::ip_number;
+
+// IP ranges from a DB-IP style ASN CSV (`first_ip,last_ip,asn,organisation`,
+// e.g. the free dbip-asn-lite), each network named as "AS16509 Amazon.com"
+#[derive(Debug)]
+pub struct AsnDb {
+    ranges: Vec<(u128, u128, usize)>,
+    networks: Vec<Arc<str>>,
+}
+
+impl AsnDb {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut ranges = Vec::new();
+        let mut networks: Vec<Arc<str>> = Vec::new();
+        let mut by_number: HashMap<u32, usize> = HashMap::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            // The organisation comes last and may itself hold commas
+            let mut columns = line.splitn(4, ',').map(|column| column.trim().trim_matches('"'));
+            let (Some(first), Some(last), Some(number)) = (columns.next(), columns.next(), columns.next()) else {
+                continue;
+            };
+            // Skips headers, comments and ranges announced by no one (AS0)
+            let (Some(first), Some(last), Some(number)) = (ip_number(first), ip_number(last), parse_number(number)) else {
+                continue;
+            };
+            let organisation = columns.next().unwrap_or("");
+            let index = *by_number.entry(number).or_insert_with(|| {
+                networks.push(match organisation {
+                    "" => Arc::from(format!("AS{}", number)),
+                    name => Arc::from(format!("AS{} {}", number, name)),
+                });
+                networks.len() - 1
+            });
+            ranges.push((first, last, index));
+        }
+
+        if ranges.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "no IP ranges found"));
+        }
+        ranges.sort_unstable_by_key(|&(first, _, _)| first);
+        Ok(AsnDb { ranges, networks })
+    }
+
+    pub fn lookup(&self, ip: &str) -> Option<Arc<str>> {
+        let ip = ip_number(ip)?;
+        let after = self.ranges.partition_point(|&(first, _, _)| first <= ip);
+        let &(_, last, index) = self.ranges[..after].last()?;
+        (ip <= last).then(|| Arc::clone(&self.networks[index]))
+    }
+}
+
+// 16509 or AS16509
+fn parse_number(text: &str) -> Option<u32> {
+    let number: u32 = text.strip_prefix("AS").unwrap_or(text).parse().ok()?;
+    (number != 0).then_some(number)
+}
@@ -0,0 +1,178 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use crate::Request;
+use crate::campaigns::percent_decode;
+use crate::rate::MinuteRate;
+
+// Matches kept for the Attacks view
+pub const RECENT: usize = 100;
+// 401 and 403 responses that make a client look like it is guessing
+// credentials
+pub const AUTH_FAILURES: usize = 20;
+
+// Worst first: a request matching several counts as the first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signature {
+    Traversal,
+    Secrets,
+    Injection,
+    Sqli,
+    Xss,
+    // Only when answered with a 4xx, so a WordPress site's own traffic or
+    // a real admin page is not taken for a scan
+    WordPress,
+    Probe,
+}
+
+// Matched against the decoded URL, in any case
+const PATTERNS: &[(Signature, &[&str])] = &[
+    (Signature::Traversal, &["../", "..\\", "/etc/passwd", "/etc/shadow", "/proc/self/", "win.ini", "boot.ini"]),
+    (Signature::Secrets, &[
+        "/.env", "/.git/", "/.svn/", "/.aws/", "/.ssh/", "/.htpasswd", "/.htaccess", "/.ds_store", "/.npmrc", "/id_rsa",
+        "/wp-config.php", "/config.php", "/config.json", "/backup.sql", "/dump.sql", "/db.sql",
+    ]),
+    (Signature::Injection, &[
+        "${jndi:", "/bin/sh", "/bin/bash", "cmd.exe", "powershell", "php://", "data://", "expect://", "allow_url_include",
+        "base64_decode(", "eval(", "system(", "passthru(", "shell_exec(", ";wget ", "|wget ", ";curl ", "|curl ", "$(",
+    ]),
+    (Signature::Sqli, &[
+        "union select", "union all select", "' or '1'='1", "' or 1=1", "\" or 1=1", " or 1=1--", "'--", "sleep(",
+        "benchmark(", "waitfor delay", "information_schema", "@@version", "extractvalue(", "updatexml(", "load_file(",
+        "into outfile",
+    ]),
+    (Signature::Xss, &["<script", "javascript:", "onerror=", "onload=", "<svg", "<iframe", "alert(", "document.cookie"]),
+    (Signature::WordPress, &["/wp-login.php", "/xmlrpc.php", "/wp-admin", "/wp-content/", "/wp-includes/", "/wlwmanifest.xml"]),
+    (Signature::Probe, &[
+        "/phpmyadmin", "/pma/", "/myadmin", "/cgi-bin/", "/vendor/phpunit", "/phpinfo.php", "/shell.php", "/boaform",
+        "/actuator/", "/hnap1", "/server-status", "/solr/", "/owa/", "/autodiscover", "/manager/html", "/jenkins",
+    ]),
+];
+
+// Every pattern at once, as scanning for each in turn held up ingestion
+static ANY: LazyLock<Regex> = LazyLock::new(|| {
+    let patterns: Vec<String> = PATTERNS.iter()
+        .flat_map(|(_, patterns)| patterns.iter().map(|pattern| regex::escape(pattern)))
+        .collect();
+    Regex::new(&format!("(?i){}", patterns.join("|"))).unwrap()
+});
+
+impl Signature {
+    pub const ALL: [Signature; 7] = [
+        Signature::Traversal, Signature::Secrets, Signature::Injection, Signature::Sqli, Signature::Xss,
+        Signature::WordPress, Signature::Probe,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Signature::Traversal => "path traversal",
+            Signature::Secrets => "secrets and config files",
+            Signature::Injection => "command injection",
+            Signature::Sqli => "SQL injection",
+            Signature::Xss => "XSS",
+            Signature::WordPress => "WordPress probe",
+            Signature::Probe => "admin and exploit probe",
+        }
+    }
+
+    pub fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    // Those in `bits`, worst first
+    pub fn all_in(bits: u8) -> impl Iterator<Item = Signature> {
+        Signature::ALL.into_iter().filter(move |signature| bits & signature.bit() != 0)
+    }
+
+    fn of_pattern(pattern: &str) -> Option<Signature> {
+        PATTERNS.iter()
+            .find(|(_, patterns)| patterns.iter().any(|known| known.eq_ignore_ascii_case(pattern)))
+            .map(|(signature, _)| *signature)
+    }
+}
+
+// The signatures a request matches, as bits, 0 for none. Log4Shell and
+// Shellshock also come in the user agent
+pub fn signatures(request: &Request) -> u8 {
+    let url = request.url();
+    let url = if url.contains(['%', '+']) { Cow::Owned(percent_decode(&url)) } else { url };
+    let probed = (400..=499).contains(&request.status_code);
+    let mut bits = 0;
+    for found in ANY.find_iter(&url) {
+        match Signature::of_pattern(found.as_str()) {
+            Some(Signature::WordPress | Signature::Probe) if !probed => {}
+            Some(signature) => bits |= signature.bit(),
+            None => {}
+        }
+    }
+    if request.user_agent.contains("${jndi:") || request.user_agent.starts_with("() {") {
+        bits |= Signature::Injection.bit();
+    }
+    bits
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub timestamp: DateTime<Utc>,
+    pub signature: Signature,
+    pub ip: String,
+    pub status_code: u16,
+    pub url: String,
+}
+
+// Requests matching attack signatures; the clients sending them are
+// counted in the client IP table
+#[derive(Debug, Clone)]
+pub struct AttackStats {
+    // By the worst signature each matched
+    pub counts: [usize; 7],
+    pub rate: MinuteRate,
+    // Newest last
+    pub recent: VecDeque<Match>,
+}
+
+impl AttackStats {
+    pub fn new(now: Instant) -> Self {
+        AttackStats { counts: [0; 7], rate: MinuteRate::new(now), recent: VecDeque::new() }
+    }
+
+    pub fn record(&mut self, now: Instant, request: &Request, bits: u8) {
+        let Some(signature) = Signature::all_in(bits).next() else {
+            return;
+        };
+        self.counts[signature as usize] += request.weight;
+        self.rate.record(now, request.weight as f64);
+        if self.recent.len() == RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(Match {
+            timestamp: request.timestamp,
+            signature,
+            ip: request.ip.clone(),
+            status_code: request.status_code,
+            url: request.url().into_owned(),
+        });
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        self.rate.tick(now);
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    // E.g. "120 path traversal, 4 SQL injection"
+    pub fn describe(&self) -> String {
+        Signature::ALL.iter()
+            .filter(|signature| self.counts[**signature as usize] > 0)
+            .map(|signature| format!("{} {}", self.counts[*signature as usize], signature.name()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
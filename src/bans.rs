@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Write;
+use std::net::IpAddr;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+
+use chrono::Local;
+
+use crate::notices::Notices;
+use crate::tenants::TenantStats;
+
+pub const DEFAULT_RPS: f64 = 20.0;
+pub const DEFAULT_CLIENT_ERROR_PERCENT: f64 = 50.0;
+pub const DEFAULT_PROBES: usize = 5;
+// Fewer requests than this say too little about a client's 4xx ratio
+const MIN_REQUESTS: usize = 20;
+
+// Paths that vulnerability scanners try on every server, lowercased. Only
+// a 4xx response makes one a probe, so a WordPress site's own /wp-login.php
+// does not count
+const PROBE_PATHS: &[&str] = &[
+    "/.env", "/.git/", "/.aws/", "/.ds_store", "/.htaccess", "/.svn/", "/wp-login.php", "/xmlrpc.php",
+    "/wp-admin", "/wp-content/plugins/", "/phpmyadmin", "/pma/", "/myadmin", "/cgi-bin/", "/vendor/phpunit",
+    "/phpinfo.php", "/config.php", "/config.json", "/backup.sql", "/dump.sql", "/shell.php", "/boaform",
+    "/actuator/", "/hnap1", "/server-status", "/etc/passwd", "../", "/solr/", "/owa/", "/autodiscover",
+];
+
+pub fn is_probe(path: &str, status_code: u16) -> bool {
+    if !(400..=499).contains(&status_code) {
+        return false;
+    }
+    let path = path.to_ascii_lowercase();
+    PROBE_PATHS.iter().any(|probe| path.contains(probe))
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum BanFormat {
+    // A log line per ban, for a fail2ban filter to pick up
+    #[default]
+    Fail2ban,
+    // `add SET IP -exist`, for ipset restore
+    Ipset(String),
+}
+
+impl FromStr for BanFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once(':') {
+            None if value == "fail2ban" => Ok(BanFormat::Fail2ban),
+            None if value == "ipset" => Ok(BanFormat::Ipset("httop".to_string())),
+            Some(("ipset", set)) if !set.is_empty() && !set.contains(char::is_whitespace) => {
+                Ok(BanFormat::Ipset(set.to_string()))
+            }
+            _ => Err(format!("unknown ban format '{}' (fail2ban, ipset or ipset:SET)", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Thresholds {
+    pub rps: f64,
+    pub client_error_percent: f64,
+    pub probes: usize,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds { rps: DEFAULT_RPS, client_error_percent: DEFAULT_CLIENT_ERROR_PERCENT, probes: DEFAULT_PROBES }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ban {
+    pub ip: String,
+    pub reason: String,
+}
+
+// Flags client IPs over any threshold, each once, appending them to a
+// file and handing them to a command
+#[derive(Debug)]
+pub struct Bans {
+    thresholds: Thresholds,
+    file: Option<File>,
+    format: BanFormat,
+    command: Option<String>,
+    banned: HashSet<String>,
+    pub latest: Option<Ban>,
+    notices: Arc<Notices>,
+}
+
+impl Bans {
+    pub fn new(
+        thresholds: Thresholds,
+        file: Option<File>,
+        format: BanFormat,
+        command: Option<String>,
+        notices: Arc<Notices>,
+    ) -> Self {
+        Bans { thresholds, file, format, command, banned: HashSet::new(), latest: None, notices }
+    }
+
+    pub fn count(&self) -> usize {
+        self.banned.len()
+    }
+
+    pub fn check(&mut self, clients: &TenantStats) {
+        let mut bans: Vec<Ban> = clients.tenants.iter()
+            // Anything else in the address field could not go on a ban list
+            .filter(|(ip, _)| !self.banned.contains(*ip) && ip.parse::<IpAddr>().is_ok())
+            .filter_map(|(ip, entry)| {
+                let rps = entry.rate.rate();
+                let client_errors = entry.error_percent(entry.client_errors);
+                let reason = if entry.probes >= self.thresholds.probes {
+                    format!("{} scanner probes", entry.probes)
+                } else if rps >= self.thresholds.rps {
+                    format!("{:.1} requests/s", rps)
+                } else if entry.count >= MIN_REQUESTS && client_errors >= self.thresholds.client_error_percent {
+                    format!("{:.0}% 4xx of {} requests", client_errors, entry.count)
+                } else {
+                    return None;
+                };
+                Some(Ban { ip: ip.clone(), reason })
+            })
+            .collect();
+        bans.sort_by(|a, b| a.ip.cmp(&b.ip));
+
+        for ban in bans {
+            self.banned.insert(ban.ip.clone());
+            self.write(&ban);
+            if let Some(command) = &self.command {
+                run(command, &ban, Arc::clone(&self.notices));
+            }
+            self.latest = Some(ban);
+        }
+    }
+
+    fn write(&mut self, ban: &Ban) {
+        let line = match &self.format {
+            BanFormat::Fail2ban => format!("{} httop ban {} ({})",
+                Local::now().format("%Y-%m-%d %H:%M:%S"), ban.ip, ban.reason),
+            BanFormat::Ipset(set) => format!("add {} {} -exist", set, ban.ip),
+        };
+        if let Some(file) = self.file.as_mut()
+            && let Err(e) = writeln!(file, "{}", line).and_then(|()| file.flush()) {
+            self.notices.post(format!("ban list stopped: {}", e));
+            self.file = None;
+        }
+    }
+}
+
+// With sh -c on a thread of its own, like alert commands
+fn run(command: &str, ban: &Ban, notices: Arc<Notices>) {
+    let mut child = Command::new("sh");
+    child.args(["-c", command])
+        .env("HTTOP_BAN_IP", &ban.ip)
+        .env("HTTOP_BAN_REASON", &ban.reason)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    thread::spawn(move || match child.status() {
+        Ok(status) if !status.success() => notices.post(format!("ban command failed: {}", status)),
+        Ok(_) => {}
+        Err(e) => notices.post(format!("could not run ban command: {}", e)),
+    });
+}
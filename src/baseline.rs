@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::memory;
+use crate::rate::MinuteRate;
+
+const HEADER: &str = "# httop baseline: path, requests/s, avg response time (s)";
+
+// Request rate and response time of one path over the last minute
+#[derive(Debug, Clone)]
+pub struct PathActivity {
+    requests: MinuteRate,
+    busy: MinuteRate,
+}
+
+impl PathActivity {
+    pub fn new(now: Instant) -> Self {
+        PathActivity {
+            requests: MinuteRate::new(now),
+            busy: MinuteRate::new(now),
+        }
+    }
+
+    pub fn record(&mut self, now: Instant, response_time: f64, weight: usize) {
+        self.requests.record(now, weight as f64);
+        self.busy.record(now, response_time * weight as f64);
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        self.requests.tick(now);
+        self.busy.tick(now);
+    }
+
+    pub fn rps(&self) -> f64 {
+        self.requests.rate()
+    }
+
+    pub fn avg_response_time(&self) -> Option<f64> {
+        if self.requests.rate() > 0.0 {
+            Some(self.busy.rate() / self.requests.rate())
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PathBaseline {
+    pub rps: f64,
+    pub avg_response_time: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+    pub paths: HashMap<String, PathBaseline>,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut baseline = Baseline::default();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+
+            let mut fields = line.rsplitn(3, '\t');
+            let parsed = (|| {
+                let avg_response_time = fields.next()?.parse().ok()?;
+                let rps = fields.next()?.parse().ok()?;
+                let path = fields.next()?.to_string();
+                Some((path, PathBaseline { rps, avg_response_time }))
+            })();
+
+            match parsed {
+                Some((path, entry)) => {
+                    baseline.paths.insert(path, entry);
+                }
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("malformed baseline line: {}", line),
+                    ));
+                }
+            }
+        }
+
+        Ok(baseline)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", HEADER)?;
+
+        let mut paths: Vec<_> = self.paths.iter().collect();
+        paths.sort_by(|a, b| a.0.cmp(b.0));
+        for (path, entry) in paths {
+            writeln!(writer, "{}\t{}\t{}", path, entry.rps, entry.avg_response_time)?;
+        }
+
+        writer.flush()
+    }
+}
+
+// Collects per-path totals while a baseline period is being recorded
+#[derive(Debug, Clone)]
+pub struct BaselineRecorder {
+    started: Instant,
+    paths: HashMap<String, (usize, f64)>,
+}
+
+impl BaselineRecorder {
+    pub fn new(now: Instant) -> Self {
+        BaselineRecorder {
+            started: now,
+            paths: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, path: &str, response_time: f64, weight: usize) {
+        let entry = self.paths.entry(path.to_string()).or_insert((0, 0.0));
+        entry.0 += weight;
+        entry.1 += response_time * weight as f64;
+    }
+
+    // Paths are only recorded while `kept` has them
+    pub fn forget<T>(&mut self, kept: &HashMap<String, T>) {
+        self.paths.retain(|path, _| kept.contains_key(path));
+    }
+
+    pub fn memory(&self) -> usize {
+        memory::keyed(&self.paths)
+    }
+
+    // Past max_memory, the paths with fewer requests than `threshold`, and
+    // `ties` of those with as many, go
+    pub fn evict(&mut self, threshold: usize, ties: &mut usize) {
+        memory::evict(&mut self.paths, |&(count, _)| count, threshold, ties, |_| {});
+        self.paths.shrink_to_fit();
+    }
+
+    // Each path's requests and bytes, for memory::threshold
+    pub fn weigh(&self, keys: &mut Vec<(usize, usize)>) {
+        keys.extend(self.paths.iter().map(|(path, &(count, _))| (count, memory::entry::<(usize, f64)>(path))));
+    }
+
+    pub fn elapsed_secs(&self, now: Instant) -> f64 {
+        now.saturating_duration_since(self.started).as_secs_f64()
+    }
+
+    pub fn finish(self, now: Instant) -> Baseline {
+        let elapsed = self.elapsed_secs(now).max(1.0);
+        let paths = self.paths.into_iter()
+            .map(|(path, (count, total_time))| {
+                (path, PathBaseline {
+                    rps: count as f64 / elapsed,
+                    avg_response_time: total_time / count as f64,
+                })
+            })
+            .collect();
+
+        Baseline { paths }
+    }
+}
+
+// Current value as a percentage of the baseline value
+pub fn percent_of(current: f64, baseline: f64) -> Option<f64> {
+    if baseline > 0.0 {
+        Some(current / baseline * 100.0)
+    } else {
+        None
+    }
+}
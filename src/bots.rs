@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+// Crawlers, previewers and monitors that say who they are, by a marker in
+// their user agent
+const KNOWN: &[(&str, &str)] = &[
+    ("Googlebot", "Googlebot"),
+    ("AdsBot-Google", "Googlebot"),
+    ("Mediapartners-Google", "Googlebot"),
+    ("Google-InspectionTool", "Googlebot"),
+    ("bingbot", "Bingbot"),
+    ("BingPreview", "Bingbot"),
+    ("Applebot", "Applebot"),
+    ("DuckDuckBot", "DuckDuckBot"),
+    ("YandexBot", "YandexBot"),
+    ("Baiduspider", "Baiduspider"),
+    ("Yahoo! Slurp", "Yahoo Slurp"),
+    ("facebookexternalhit", "Facebook"),
+    ("meta-externalagent", "Facebook"),
+    ("Twitterbot", "Twitterbot"),
+    ("LinkedInBot", "LinkedInBot"),
+    ("Slackbot", "Slackbot"),
+    ("Discordbot", "Discordbot"),
+    ("TelegramBot", "TelegramBot"),
+    ("WhatsApp", "WhatsApp"),
+    ("AhrefsBot", "AhrefsBot"),
+    ("SemrushBot", "SemrushBot"),
+    ("MJ12bot", "MJ12bot"),
+    ("DotBot", "DotBot"),
+    ("PetalBot", "PetalBot"),
+    ("Bytespider", "Bytespider"),
+    ("Amazonbot", "Amazonbot"),
+    ("GPTBot", "GPTBot"),
+    ("ClaudeBot", "ClaudeBot"),
+    ("PerplexityBot", "PerplexityBot"),
+    ("CCBot", "CCBot"),
+    ("UptimeRobot", "UptimeRobot"),
+    ("Pingdom", "Pingdom"),
+    ("HeadlessChrome", "Headless Chrome"),
+];
+
+// Anything else owning up to being automated, matched case-insensitively
+const MARKERS: &[&str] = &["bot", "crawl", "spider", "slurp", "scraper"];
+
+// The bot a user agent belongs to, if it looks like one
+pub fn name(user_agent: &str) -> Option<&'static str> {
+    if let Some((_, name)) = KNOWN.iter().find(|(marker, _)| user_agent.contains(marker)) {
+        return Some(name);
+    }
+    let lower = user_agent.to_ascii_lowercase();
+    MARKERS.iter().any(|marker| lower.contains(marker)).then_some("Other bots")
+}
+
+// Tells bots from people as requests arrive. With --robots-txt-bots, a
+// client that fetched /robots.txt counts as a bot from then on, whatever
+// its user agent claims
+#[derive(Debug, Default)]
+pub struct Detector {
+    robots_txt_readers: Option<HashSet<String>>,
+}
+
+impl Detector {
+    pub fn new(robots_txt: bool) -> Self {
+        Detector { robots_txt_readers: robots_txt.then(HashSet::new) }
+    }
+
+    pub fn classify(&mut self, ip: &str, path: &str, user_agent: &str) -> Option<&'static str> {
+        if let Some(name) = name(user_agent) {
+            return Some(name);
+        }
+        let readers = self.robots_txt_readers.as_mut()?;
+        if path.split('?').next() == Some("/robots.txt") {
+            readers.insert(ip.to_string());
+        }
+        readers.contains(ip).then_some("robots.txt readers")
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use crate::memory;
+
+// nginx $upstream_cache_status, CloudFront's x-edge-result-type and the
+// CF-Cache-Status or X-Cache headers of other CDNs
+pub const STATUS_FIELDS: &[&str] = &["upstream_cache_status", "cache_status", "x_edge_result_type", "cf_cache_status", "x_cache"];
+
+// The cache status uppercased, so CloudFront's Hit and nginx's HIT count
+// together, and without the " from cloudfront" X-Cache adds
+pub fn status(fields: &[(String, String)]) -> Option<String> {
+    let status = STATUS_FIELDS.iter()
+        .find_map(|name| fields.iter().find(|(key, _)| key == name))
+        .and_then(|(_, value)| value.split_whitespace().next())
+        .filter(|status| *status != "-")?;
+    Some(status.to_ascii_uppercase())
+}
+
+// Served from the cache, even if stale or after revalidating with the origin
+pub fn is_hit(status: &str) -> bool {
+    matches!(status, "HIT" | "STALE" | "UPDATING" | "REVALIDATED" | "REFRESHHIT")
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CacheStats {
+    pub statuses: HashMap<String, usize>,
+}
+
+impl CacheStats {
+    pub fn record(&mut self, status: &str, weight: usize) {
+        *self.statuses.entry(status.to_string()).or_insert(0) += weight;
+    }
+
+    // The rarest statuses go past `max_keys`, should a CDN log odd ones
+    pub fn limit(&mut self, max_keys: usize) {
+        if let Some((threshold, mut ties)) = memory::limit(&self.statuses, |&count| count, max_keys) {
+            memory::evict(&mut self.statuses, |&count| count, threshold, &mut ties, |_| {});
+        }
+    }
+
+    pub fn memory(&self) -> usize {
+        memory::keyed(&self.statuses)
+    }
+
+    // Requests with a cache status at all
+    pub fn lookups(&self) -> usize {
+        self.statuses.values().sum()
+    }
+
+    pub fn hits(&self) -> usize {
+        self.statuses.iter()
+            .filter(|(status, _)| is_hit(status))
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
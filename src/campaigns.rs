@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::memory;
+use crate::rate::MinuteRate;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Campaign {
+    pub source: String,
+    pub medium: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CampaignEntry {
+    pub count: usize,
+    pub bytes: usize,
+    pub rate: MinuteRate,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CampaignStats {
+    pub tagged_requests: usize,
+    pub campaigns: HashMap<Campaign, CampaignEntry>,
+}
+
+impl CampaignStats {
+    pub fn record(&mut self, now: Instant, path: &str, bytes: usize, weight: usize) {
+        let Some(campaign) = utm_campaign(path) else {
+            return;
+        };
+
+        self.tagged_requests += weight;
+        let entry = self.campaigns.entry(campaign).or_insert_with(|| CampaignEntry {
+            count: 0,
+            bytes: 0,
+            rate: MinuteRate::new(now),
+        });
+        entry.count += weight;
+        entry.bytes += bytes * weight;
+        entry.rate.record(now, weight as f64);
+    }
+
+    // The campaigns with the fewest requests go past `max_keys`: utm
+    // parameters are whatever links people share
+    pub fn limit(&mut self, max_keys: usize) {
+        if let Some((threshold, mut ties)) = memory::limit(&self.campaigns, |entry| entry.count, max_keys) {
+            memory::evict(&mut self.campaigns, |entry| entry.count, threshold, &mut ties, |_| {});
+        }
+    }
+
+    pub fn memory(&self) -> usize {
+        self.campaigns.keys().map(campaign_memory).sum::<usize>() + memory::spare(&self.campaigns)
+    }
+
+    // Past max_memory, the campaigns with fewer requests than `threshold`,
+    // and `ties` of those with as many, go
+    pub fn evict(&mut self, threshold: usize, ties: &mut usize) {
+        memory::evict(&mut self.campaigns, |entry| entry.count, threshold, ties, |_| {});
+        self.campaigns.shrink_to_fit();
+    }
+
+    // Each campaign's requests and bytes, for memory::threshold
+    pub fn weigh(&self, keys: &mut Vec<(usize, usize)>) {
+        keys.extend(self.campaigns.iter().map(|(campaign, entry)| (entry.count, campaign_memory(campaign))));
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        for entry in self.campaigns.values_mut() {
+            entry.rate.tick(now);
+        }
+    }
+}
+
+fn campaign_memory(campaign: &Campaign) -> usize {
+    size_of::<(Campaign, CampaignEntry)>() + 1
+        + memory::text(&campaign.source) + memory::text(&campaign.medium) + memory::text(&campaign.name)
+}
+
+// Extracts utm_source/utm_medium/utm_campaign, None if the request
+// carries none of them
+pub fn utm_campaign(path: &str) -> Option<Campaign> {
+    let (_, query) = path.split_once('?')?;
+
+    let mut source = None;
+    let mut medium = None;
+    let mut name = None;
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "utm_source" => source = Some(percent_decode(value)),
+            "utm_medium" => medium = Some(percent_decode(value)),
+            "utm_campaign" => name = Some(percent_decode(value)),
+            _ => {}
+        }
+    }
+
+    if source.is_none() && medium.is_none() && name.is_none() {
+        return None;
+    }
+
+    let or_unset = |v: Option<String>| v.filter(|v| !v.is_empty()).unwrap_or_else(|| "(not set)".to_string());
+    Some(Campaign {
+        source: or_unset(source).to_lowercase(),
+        medium: or_unset(medium).to_lowercase(),
+        name: or_unset(name),
+    })
+}
+
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// How long a row stays highlighted after it appeared or moved
+const HIGHLIGHT_FOR: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    New,
+    Grew,
+}
+
+// Compares table counts between refreshes to find new and fast-growing rows
+#[derive(Debug, Default)]
+pub struct RowChanges {
+    previous: HashMap<String, usize>,
+    deltas: HashMap<String, usize>,
+    marked: HashMap<String, (Change, Instant)>,
+    started: bool,
+}
+
+impl RowChanges {
+    pub fn update(&mut self, now: Instant, counts: &HashMap<String, usize>) {
+        self.deltas.clear();
+        for (key, &count) in counts {
+            let previous = self.previous.get(key).copied();
+            let delta = count.saturating_sub(previous.unwrap_or(0));
+            if delta > 0 {
+                self.deltas.insert(key.clone(), delta);
+            }
+
+            // Nothing is new on the first refresh; growth counts when it is
+            // at least a tenth of what the row had
+            let change = match previous {
+                None if self.started => Some(Change::New),
+                Some(previous) if delta > 0 && delta * 10 >= previous => Some(Change::Grew),
+                _ => None,
+            };
+            if let Some(change) = change {
+                self.marked.insert(key.clone(), (change, now));
+            }
+        }
+
+        self.marked.retain(|_, (_, at)| now.duration_since(*at) < HIGHLIGHT_FOR);
+        self.previous.clone_from(counts);
+        self.started = true;
+    }
+
+    pub fn change(&self, key: &str) -> Option<Change> {
+        self.marked.get(key).map(|(change, _)| *change)
+    }
+
+    // Increase since the previous refresh
+    pub fn delta(&self, key: &str) -> usize {
+        self.deltas.get(key).copied().unwrap_or(0)
+    }
+}
@@ -0,0 +1,135 @@
+use std::env;
+use std::str::FromStr;
+
+const BLOCK_LEVELS: &[char] = &[' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const BLOCK_EIGHTHS: &[char] = &['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+const BRAILLE_LEVELS: &[char] = &[' ', '⣀', '⣤', '⣶', '⣿'];
+const ASCII_LEVELS: &[char] = &[' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ChartStyle {
+    // Braille with a UTF-8 locale, ASCII otherwise
+    #[default]
+    Auto,
+    Braille,
+    Block,
+    Ascii,
+}
+
+impl FromStr for ChartStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(ChartStyle::Auto),
+            "braille" => Ok(ChartStyle::Braille),
+            "block" => Ok(ChartStyle::Block),
+            "ascii" => Ok(ChartStyle::Ascii),
+            _ => Err(format!("unknown chart style '{}' (auto, braille, block, ascii)", value)),
+        }
+    }
+}
+
+impl ChartStyle {
+    pub fn resolve(self) -> Self {
+        match self {
+            ChartStyle::Auto if locale_is_utf8() => ChartStyle::Braille,
+            ChartStyle::Auto => ChartStyle::Ascii,
+            style => style,
+        }
+    }
+
+    fn levels(self) -> &'static [char] {
+        match self {
+            ChartStyle::Braille => BRAILLE_LEVELS,
+            ChartStyle::Block => BLOCK_LEVELS,
+            ChartStyle::Ascii | ChartStyle::Auto => ASCII_LEVELS,
+        }
+    }
+
+    // One character per value, height relative to the largest value
+    pub fn sparkline(self, values: &[usize]) -> String {
+        let levels = self.levels();
+        let top = levels.len() - 1;
+        let peak = values.iter().copied().max().unwrap_or(0);
+
+        values.iter()
+            .map(|&value| {
+                if value == 0 || peak == 0 {
+                    levels[0]
+                } else {
+                    levels[(value * top).div_ceil(peak).clamp(1, top)]
+                }
+            })
+            .collect()
+    }
+
+    // A filled cell, for bars that are split into colored parts
+    pub fn full(self) -> char {
+        match self {
+            ChartStyle::Block => '█',
+            ChartStyle::Braille => '⣿',
+            ChartStyle::Ascii | ChartStyle::Auto => '#',
+        }
+    }
+
+    // Which way a figure went since the last period
+    pub fn arrow(self, trend: std::cmp::Ordering) -> char {
+        match (self, trend) {
+            (ChartStyle::Ascii | ChartStyle::Auto, std::cmp::Ordering::Greater) => '^',
+            (ChartStyle::Ascii | ChartStyle::Auto, std::cmp::Ordering::Less) => 'v',
+            (ChartStyle::Ascii | ChartStyle::Auto, std::cmp::Ordering::Equal) => '=',
+            (_, std::cmp::Ordering::Greater) => '↑',
+            (_, std::cmp::Ordering::Less) => '↓',
+            (_, std::cmp::Ordering::Equal) => '→',
+        }
+    }
+
+    // Horizontal bar of up to `width` cells for value/max
+    pub fn bar(self, value: usize, max: usize, width: usize) -> String {
+        if max == 0 || value == 0 {
+            return String::new();
+        }
+
+        let (full, partials): (char, &[char]) = match self {
+            ChartStyle::Block => ('█', BLOCK_EIGHTHS),
+            ChartStyle::Braille => ('⣿', &['⡇']),
+            ChartStyle::Ascii | ChartStyle::Auto => ('#', &['=']),
+        };
+
+        // Measure in sub-cell steps so short bars still show up
+        let steps = partials.len() + 1;
+        let units = (value * width * steps).div_ceil(max).min(width * steps);
+        let mut bar: String = std::iter::repeat_n(full, units / steps).collect();
+        if !units.is_multiple_of(steps) {
+            bar.push(partials[units % steps - 1]);
+        }
+        bar
+    }
+}
+
+// Splits `width` cells between the parts of a stacked bar. Every part that
+// is not zero gets a cell at least, taken from the largest one
+pub fn split(parts: &[usize], width: usize) -> Vec<usize> {
+    let total: usize = parts.iter().sum();
+    if total == 0 {
+        return vec![0; parts.len()];
+    }
+    let mut cells: Vec<usize> = parts.iter()
+        .map(|&part| if part == 0 { 0 } else { (part * width / total).max(1) })
+        .collect();
+    let largest = (0..parts.len()).max_by_key(|&index| parts[index]).unwrap_or(0);
+    let used: usize = cells.iter().sum();
+    cells[largest] = (cells[largest] + width).saturating_sub(used);
+    cells
+}
+
+fn locale_is_utf8() -> bool {
+    // Same precedence as setlocale(3) for LC_CTYPE
+    ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
@@ -0,0 +1,130 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::Request;
+use crate::record;
+
+// The requests the stats were counted from, one a line: when it was
+// counted, in milliseconds since the epoch, the lines it stood for and
+// then the request and its source as recordings have it. Appended to as
+// they come, so a restarted httop can count them again with --resume
+const MAGIC: &str = "httop-checkpoint 2";
+// The source in a column of its own and the request in tab-separated
+// columns, still read
+const MAGIC_COLUMNS: &str = "httop-checkpoint 1";
+
+pub struct Checkpoint {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    // Requests older than the --window are dropped from the file every
+    // quarter of it, as the stats hand over to a younger generation
+    window: Option<Duration>,
+    last_flush: Instant,
+    last_compacted: Instant,
+}
+
+impl Checkpoint {
+    // A new file holding `requests`, in place of any there was
+    pub fn create(path: &Path, window: Option<Duration>, requests: &[(DateTime<Utc>, Request)]) -> io::Result<Self> {
+        let writer = rewrite(path, requests)?;
+        let now = Instant::now();
+        Ok(Checkpoint { path: path.to_path_buf(), writer, window, last_flush: now, last_compacted: now })
+    }
+
+    pub fn write(&mut self, request: &Request) -> io::Result<()> {
+        write_line(&mut self.writer, Utc::now(), request)?;
+        if self.last_flush.elapsed() >= Duration::from_secs(1) {
+            self.flush()?;
+        }
+        if let Some(window) = self.window
+            && self.last_compacted.elapsed() >= window / 4 {
+            self.compact(window)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.writer.flush()
+    }
+
+    fn compact(&mut self, window: Duration) -> io::Result<()> {
+        self.flush()?;
+        let requests = read(&self.path, Some(window))?;
+        self.writer = rewrite(&self.path, &requests)?;
+        self.last_compacted = Instant::now();
+        Ok(())
+    }
+}
+
+impl Drop for Checkpoint {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+// The requests of a checkpoint and when they were counted, but for those
+// counted longer than `window` ago. A line cut short by a crash is skipped
+pub fn read(path: &Path, window: Option<Duration>) -> io::Result<Vec<(DateTime<Utc>, Request)>> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let columns = match lines.next() {
+        Some(Ok(header)) if header == MAGIC => false,
+        Some(Ok(header)) if header == MAGIC_COLUMNS => true,
+        _ => return Err(record::invalid(format!("{} is not an httop checkpoint", path.display()))),
+    };
+    let now = Utc::now();
+    let mut requests = Vec::new();
+    for line in lines {
+        let Some((counted, request)) = parse_line(&line?, columns) else {
+            continue;
+        };
+        let aged_out = window.is_some_and(|window| (now - counted).to_std().is_ok_and(|age| age > window));
+        if !aged_out {
+            requests.push((counted, request));
+        }
+    }
+    Ok(requests)
+}
+
+// Written beside the checkpoint and renamed over it, so a crash midway
+// leaves the old one
+fn rewrite(path: &Path, requests: &[(DateTime<Utc>, Request)]) -> io::Result<BufWriter<File>> {
+    let mut temporary = OsString::from(path);
+    temporary.push(".tmp");
+    let mut writer = BufWriter::new(File::create(&temporary)?);
+    writeln!(writer, "{}", MAGIC)?;
+    for (counted, request) in requests {
+        write_line(&mut writer, *counted, request)?;
+    }
+    writer.flush()?;
+    fs::rename(&temporary, path)?;
+    Ok(writer)
+}
+
+fn write_line(writer: &mut impl Write, counted: DateTime<Utc>, request: &Request) -> io::Result<()> {
+    write!(writer, "{}\t{}\t", counted.timestamp_millis(), request.weight)?;
+    record::write_request(writer, request, request.source.as_deref())
+}
+
+fn parse_line(line: &str, columns: bool) -> Option<(DateTime<Utc>, Request)> {
+    let mut parts = line.splitn(3, '\t');
+    let counted = DateTime::from_timestamp_millis(parts.next()?.parse().ok()?)?;
+    let weight = parts.next()?.parse().ok()?;
+    let (request, source) = if columns {
+        let (source, request) = parts.next()?.split_once('\t')?;
+        (record::parse_columns(request.split('\t'))?, Some(record::unescape(source)))
+    } else {
+        record::parse_request(parts.next()?)?
+    };
+    Some((counted, Request {
+        weight,
+        source: source.filter(|source| !source.is_empty()).map(Arc::from),
+        ..request
+    }))
+}
@@ -0,0 +1,23 @@
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// OSC 52 asks the terminal to set the clipboard, which also works over SSH
+// and inside tmux (with `set -g set-clipboard on`)
+pub fn osc52(text: &str) -> String {
+    format!("\x1B]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+fn base64(input: &[u8]) -> String {
+    let mut encoded = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let group = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(group >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,179 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::histogram::human_bytes;
+
+// The figures shown for each row of the Paths table and the other per-key
+// tables. The key itself always comes last, in what is left of the width
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Count,
+    Rate,
+    ClientErrors,
+    ServerErrors,
+    Latency,
+    P95Latency,
+    MaxLatency,
+    Bytes,
+    // Bytes sent per second over the last minute
+    Throughput,
+    Uniques,
+    LastSeen,
+    // Against --apdex-t
+    Apdex,
+    // Cache hits of the requests with a cache status logged
+    CacheHit,
+    // Of the requests with an upstream time logged, the average upstream
+    // time and the rest of the response time
+    Upstream,
+    Overhead,
+}
+
+pub const DEFAULT: &[Column] = &[
+    Column::Count,
+    Column::Rate,
+    Column::ClientErrors,
+    Column::ServerErrors,
+    Column::Latency,
+    Column::P95Latency,
+    Column::Bytes,
+];
+
+const NAMES: &str = "count, rps, 4xx, 5xx, latency, p95, max, bytes, bw, uniques, last-seen, apdex, hit, upstream or overhead";
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "count" => Ok(Column::Count),
+            "rps" => Ok(Column::Rate),
+            "4xx" => Ok(Column::ClientErrors),
+            "5xx" => Ok(Column::ServerErrors),
+            "latency" | "rt" => Ok(Column::Latency),
+            "p95" => Ok(Column::P95Latency),
+            "max" => Ok(Column::MaxLatency),
+            "bytes" => Ok(Column::Bytes),
+            "bw" => Ok(Column::Throughput),
+            "uniques" => Ok(Column::Uniques),
+            "last-seen" => Ok(Column::LastSeen),
+            "apdex" => Ok(Column::Apdex),
+            "hit" => Ok(Column::CacheHit),
+            "upstream" => Ok(Column::Upstream),
+            "overhead" => Ok(Column::Overhead),
+            _ => Err(format!("unknown column '{}' (expected {})", name, NAMES)),
+        }
+    }
+}
+
+// How counts and bytes are shown: as they are, as a share of the table's
+// total, or both where the terminal is wide enough
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Values {
+    Absolute,
+    Percent,
+    Both,
+}
+
+impl Column {
+    pub fn header(self, values: Values) -> &'static str {
+        match (self, values) {
+            (Column::Count, Values::Percent) => "COUNT%",
+            (Column::Count, Values::Both) => "COUNT (%)",
+            (Column::Bytes, Values::Percent) => "BYTES%",
+            (Column::Bytes, Values::Both) => "BYTES (%)",
+            _ => self.name(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Column::Count => "COUNT",
+            Column::Rate => "REQ/S",
+            Column::ClientErrors => "4XX%",
+            Column::ServerErrors => "5XX%",
+            Column::Latency => "AVG RT",
+            Column::P95Latency => "P95 RT",
+            Column::MaxLatency => "MAX RT",
+            Column::Bytes => "BYTES",
+            Column::Throughput => "BYTES/S",
+            Column::Uniques => "UNIQ IPS",
+            Column::LastSeen => "LAST SEEN",
+            Column::Apdex => "APDEX",
+            Column::CacheHit => "HIT%",
+            Column::Upstream => "UPSTR RT",
+            Column::Overhead => "PROXY RT",
+        }
+    }
+
+    pub fn width(self, values: Values) -> usize {
+        match (self, values) {
+            (Column::Count | Column::Bytes, Values::Percent) => 7,
+            (Column::Count, Values::Both) => 16,
+            (Column::Bytes, Values::Both) => 20,
+            _ => self.absolute_width(),
+        }
+    }
+
+    fn absolute_width(self) -> usize {
+        match self {
+            Column::Count => 7,
+            Column::Rate | Column::ClientErrors | Column::ServerErrors => 9,
+            Column::Latency | Column::P95Latency | Column::MaxLatency => 10,
+            Column::Upstream | Column::Overhead => 10,
+            Column::Bytes => 12,
+            Column::Throughput => 12,
+            Column::Uniques => 9,
+            Column::LastSeen => 11,
+            Column::Apdex | Column::CacheHit => 7,
+        }
+    }
+}
+
+// A comma-separated list such as count,latency,bytes
+pub fn parse(list: &str) -> Result<Vec<Column>, String> {
+    let columns = list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<Column>, String>>()?;
+    if columns.is_empty() {
+        return Err(format!("no columns given (expected {})", NAMES));
+    }
+    Ok(columns)
+}
+
+// A count as `values` shows it
+pub fn value(amount: usize, total: usize, values: Values) -> String {
+    let percent = if total > 0 { amount as f64 / total as f64 * 100.0 } else { 0.0 };
+    match values {
+        Values::Absolute => amount.to_string(),
+        Values::Percent => format!("{:.1}%", percent),
+        Values::Both => format!("{} ({:.1}%)", amount, percent),
+    }
+}
+
+// A byte total likewise, in KiB, MiB or GiB
+pub fn bytes(amount: usize, total: usize, values: Values) -> String {
+    match values {
+        Values::Percent => value(amount, total, values),
+        Values::Absolute => human_bytes(amount),
+        Values::Both => format!("{} ({:.1}%)", human_bytes(amount), amount as f64 / total.max(1) as f64 * 100.0),
+    }
+}
+
+// 1.5MiB/s
+pub fn throughput(bytes_per_second: f64) -> String {
+    format!("{}/s", human_bytes(bytes_per_second.round() as usize))
+}
+
+// How long ago, in the largest unit that fits: 12s, 5m, 3h, 2d
+pub fn age(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match secs {
+        0..60 => format!("{}s ago", secs),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
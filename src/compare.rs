@@ -0,0 +1,253 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, Utc};
+
+use crate::Request;
+use crate::memory;
+use crate::quantiles::Quantiles;
+
+// Paths each period tells apart; those past it count only in its totals
+const MAX_PATHS: usize = 10_000;
+
+#[derive(Debug, Clone, Default)]
+pub struct PathPeriod {
+    pub requests: usize,
+    pub response_times: Quantiles,
+}
+
+// Requests, status codes and response times over a stretch of logged time
+#[derive(Debug, Clone, Default)]
+pub struct Period {
+    // Logged times of the first and latest request, or with a --compare-window
+    // where the period begins and, once over, ends
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub requests: usize,
+    pub server_errors: usize,
+    pub status_codes: BTreeMap<u16, usize>,
+    pub response_times: Quantiles,
+    pub paths: HashMap<String, PathPeriod>,
+}
+
+impl Period {
+    fn starting(start: DateTime<Utc>) -> Self {
+        Period { start: Some(start), end: Some(start), ..Period::default() }
+    }
+
+    pub fn record(&mut self, request: &Request, path: &str) {
+        let weight = request.weight;
+        self.start = Some(self.start.map_or(request.timestamp, |start| start.min(request.timestamp)));
+        self.end = Some(self.end.map_or(request.timestamp, |end| end.max(request.timestamp)));
+        self.requests += weight;
+        if request.status_code >= 500 {
+            self.server_errors += weight;
+        }
+        *self.status_codes.entry(request.status_code).or_insert(0) += weight;
+        if request.response_time > 0.0 {
+            self.response_times.record(request.response_time);
+        }
+        if self.paths.len() < MAX_PATHS || self.paths.contains_key(path) {
+            let entry = self.paths.entry(path.to_string()).or_default();
+            entry.requests += weight;
+            if request.response_time > 0.0 {
+                entry.response_times.record(request.response_time);
+            }
+        }
+    }
+
+    // Of `requests` over the logged time the period spans, a second at least
+    pub fn per_minute(&self, requests: usize) -> f64 {
+        let seconds = match (self.start, self.end) {
+            (Some(start), Some(end)) => (end - start).num_milliseconds() as f64 / 1000.0,
+            _ => 0.0,
+        };
+        requests as f64 / seconds.max(1.0) * 60.0
+    }
+
+    // E.g. "2026-05-01 13:00:00 to 2026-05-01 14:00:00", in local time
+    pub fn span(&self) -> String {
+        let time = |time: DateTime<Utc>| time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string();
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => format!("{} to {}", time(start), time(end)),
+            _ => "nothing logged".to_string(),
+        }
+    }
+
+    fn memory(&self) -> usize {
+        memory::keyed(&self.paths) + self.paths.values().map(|path| path.response_times.memory()).sum::<usize>()
+    }
+
+    pub fn error_percent(&self) -> f64 {
+        self.server_errors as f64 / self.requests.max(1) as f64 * 100.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Against {
+    // The requests of a --compare log
+    File(String),
+    // Those of the --compare-window before the current one
+    Window(Duration),
+}
+
+// Two periods, the current one counted as requests come
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub against: Against,
+    pub baseline: Period,
+    pub current: Period,
+}
+
+// One path of either period, in requests per minute
+pub struct PathChange<'a> {
+    pub path: &'a str,
+    pub before: f64,
+    pub now: f64,
+    pub p95_before: Option<f64>,
+    pub p95_now: Option<f64>,
+}
+
+impl Comparison {
+    pub fn file(name: String, baseline: Period) -> Self {
+        Comparison { against: Against::File(name), baseline, current: Period::default() }
+    }
+
+    pub fn window(window: Duration) -> Self {
+        Comparison { against: Against::Window(window), baseline: Period::default(), current: Period::default() }
+    }
+
+    // Nothing counted since, but the same baseline log
+    pub fn cleared(&self) -> Self {
+        match &self.against {
+            Against::File(_) => Comparison { current: Period::default(), ..self.clone() },
+            Against::Window(window) => Comparison::window(*window),
+        }
+    }
+
+    pub fn record(&mut self, request: &Request, path: &str) {
+        if let Against::Window(window) = self.against {
+            // Periods begin on multiples of the window, so 1h ones on the hour
+            let seconds = window.as_secs().max(1) as i64;
+            let timestamp = request.timestamp.timestamp();
+            let Some(start) = DateTime::from_timestamp(timestamp - timestamp.rem_euclid(seconds), 0) else {
+                return;
+            };
+            let window = chrono::Duration::seconds(seconds);
+            match self.current.start {
+                Some(current) if start < current => {
+                    // Lines logged late count in the period they belong to
+                    if start + window == current {
+                        self.baseline.record(request, path);
+                    }
+                    return;
+                }
+                Some(current) if start > current => {
+                    let ended = std::mem::replace(&mut self.current, Period::starting(start));
+                    self.baseline = if current + window == start {
+                        Period { end: Some(start), ..ended }
+                    } else {
+                        // Nothing was logged in the period just before
+                        Period { start: Some(start - window), end: Some(start), ..Period::default() }
+                    };
+                }
+                Some(_) => {}
+                None => {
+                    self.current = Period::starting(start);
+                    self.baseline = Period { start: Some(start - window), end: Some(start), ..Period::default() };
+                }
+            }
+        }
+        self.current.record(request, path);
+    }
+
+    pub fn memory(&self) -> usize {
+        self.baseline.memory() + self.current.memory()
+    }
+
+    // Every path of either period, the busiest now first, then those gone
+    // by how busy they were
+    pub fn paths(&self) -> Vec<PathChange<'_>> {
+        let mut changes: Vec<PathChange> = self.current.paths.iter()
+            .map(|(path, now)| {
+                let before = self.baseline.paths.get(path);
+                PathChange {
+                    path,
+                    before: self.baseline.per_minute(before.map_or(0, |before| before.requests)),
+                    now: self.current.per_minute(now.requests),
+                    p95_before: before.and_then(|before| before.response_times.quantile(0.95)),
+                    p95_now: now.response_times.quantile(0.95),
+                }
+            })
+            .chain(self.baseline.paths.iter()
+                .filter(|(path, _)| !self.current.paths.contains_key(*path))
+                .map(|(path, before)| PathChange {
+                    path,
+                    before: self.baseline.per_minute(before.requests),
+                    now: 0.0,
+                    p95_before: before.response_times.quantile(0.95),
+                    p95_now: None,
+                }))
+            .collect();
+        changes.sort_by(|a, b| b.now.total_cmp(&a.now)
+            .then(b.before.total_cmp(&a.before))
+            .then(a.path.cmp(b.path)));
+        changes
+    }
+
+    // Status codes of either period, with their requests per minute
+    pub fn statuses(&self) -> Vec<(u16, f64, f64)> {
+        let mut codes: Vec<u16> = self.baseline.status_codes.keys().chain(self.current.status_codes.keys()).copied().collect();
+        codes.sort_unstable();
+        codes.dedup();
+        codes.into_iter()
+            .map(|code| {
+                let count = |period: &Period| period.per_minute(period.status_codes.get(&code).copied().unwrap_or(0));
+                (code, count(&self.baseline), count(&self.current))
+            })
+            .collect()
+    }
+
+    // E.g. "the 1h before" or "old.log"
+    pub fn describe(&self) -> String {
+        match &self.against {
+            Against::File(name) => name.clone(),
+            Against::Window(window) => format!("the {} before", window_name(*window)),
+        }
+    }
+}
+
+// E.g. "+35%", "new" for what was not there before and "gone" for what is
+// not there now
+pub fn percent_change(before: f64, now: f64) -> String {
+    match (before > 0.0, now > 0.0) {
+        (false, false) => "-".to_string(),
+        (false, true) => "new".to_string(),
+        (true, false) => "gone".to_string(),
+        (true, true) => format!("{:+.0}%", (now - before) / before * 100.0),
+    }
+}
+
+// E.g. "+120ms" or "-1.5s"
+pub fn time_change(before: Option<f64>, now: Option<f64>) -> String {
+    let (Some(before), Some(now)) = (before, now) else {
+        return "-".to_string();
+    };
+    let change = now - before;
+    if change.abs() < 1.0 {
+        format!("{:+.0}ms", change * 1000.0)
+    } else {
+        format!("{:+.1}s", change)
+    }
+}
+
+fn window_name(window: Duration) -> String {
+    let seconds = window.as_secs();
+    if seconds.is_multiple_of(3600) {
+        format!("{}h", seconds / 3600)
+    } else if seconds.is_multiple_of(60) {
+        format!("{}m", seconds / 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
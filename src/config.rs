@@ -0,0 +1,1216 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+
+use crate::alerts::Rule;
+use crate::anomaly;
+use crate::apdex;
+use crate::bans::{self, BanFormat, Thresholds};
+use crate::chart::ChartStyle;
+use crate::columns::{self, Column};
+use crate::content::StaticAssets;
+use crate::filters::Filters;
+use crate::follow;
+use crate::formats::LogFormat;
+use crate::forwarded::RealIp;
+use crate::geoip;
+use crate::highlight;
+use crate::kafka::Kafka;
+use crate::keymap::KeyMap;
+use crate::normalize;
+use crate::paging::{self, Notifier};
+use crate::pattern::Pattern;
+use crate::rate::ClassTimeline;
+use crate::settings::{self, Settings};
+use crate::slo::Slo;
+use crate::statsd;
+use crate::export::{self, Output};
+use crate::sample;
+use crate::tenants;
+use crate::theme::Theme;
+use crate::trace;
+use crate::history;
+use crate::visits;
+
+const ABOUT: &str = "A real-time HTTP log monitor similar to ApacheTop";
+
+const INPUTS: &str = "\
+Log files given as arguments are read from the start and then followed like
+tail -F, across logrotate renames and truncation, and merged into one view
+(patterns such as 'sites/*.log' are expanded). Files ending in .gz or .zst
+are decompressed with gzip or zstd and read once. Without any files, logs are
+read from stdin. With --batch, files are read once up to their end.";
+
+// The command line as clap reads it. Each value is checked on its own
+// here; Config::from_args checks how they go together
+#[derive(Debug, Parser)]
+#[command(name = "httop", version, about = ABOUT, after_help = INPUTS, next_line_help = true,
+    args_override_self = true, args_conflicts_with_subcommands = true, disable_help_subcommand = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    options: Options,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    #[command(about = "Run without a display, exposing /healthz and /readyz", next_line_help = true,
+        args_override_self = true)]
+    Serve {
+        #[command(flatten)]
+        options: Options,
+    },
+    #[command(about = "Re-drive the display from a session saved with --record", next_line_help = true,
+        args_override_self = true)]
+    Replay {
+        #[arg(value_name = "FILE", help = "Recording made with --record")]
+        recording: PathBuf,
+        #[command(flatten)]
+        options: Options,
+    },
+}
+
+#[derive(Debug, Args)]
+struct Options {
+    #[arg(value_name = "FILE", help = "Log files or patterns to read and follow (default: stdin)")]
+    files: Vec<String>,
+
+    #[arg(long, value_name = "NAME", overrides_with = "log_format",
+        help = "Access log format: nginx (default), apache-common, apache-combined, traefik, envoy, heroku \
+            (router), json, caddy, gcp (Google Cloud load balancer) or w3c (CloudFront, IIS)")]
+    format: Option<LogFormat>,
+    #[arg(long, value_name = "TEMPLATE", value_parser = log_format, overrides_with = "format",
+        help = "Custom log layout, in goaccess tokens or nginx variables, e.g. \
+            '%h %^ %^ [%d:%t %^] \"%r\" %s %b \"%R\" \"%u\" %T' or '$remote_addr [$time_local] \"$request\" $status'")]
+    log_format: Option<LogFormat>,
+    #[arg(long, value_name = "REGEX",
+        help = "Custom log layout as a regex whose named groups, e.g. (?P<ip>\\S+), fill in the request: ip, \
+            time, method, path, status, bytes, ua, rt (seconds) and also request (method, path and protocol), \
+            query, protocol, referrer and rt_ms; other groups become custom fields")]
+    regex: Option<String>,
+    #[arg(long, value_name = "FORMAT",
+        help = "strftime layout of the --regex time group, e.g. '%Y-%m-%d %H:%M:%S%.3f' (default: as \
+            --log-format reads times, or seconds since the epoch)")]
+    time_format: Option<String>,
+    #[arg(long, visible_alias = "json-field", value_name = "FIELD=KEY",
+        help = "JSON or logfmt key holding a request field (repeatable), e.g. 'status=code' or \
+            'ip=client.address' (JSON nesting); fields: ip, time, request, method, path, status, bytes, \
+            request_time, referrer, user_agent")]
+    map: Vec<String>,
+    #[arg(long, value_name = "FILE", help = "Save the parsed request stream to FILE for later replay")]
+    record: Option<PathBuf>,
+    #[arg(long, value_name = "FILE",
+        help = "Keep the requests the tables were counted from in FILE, as they come, so a restart can \
+            --resume from it; with --window only those within it")]
+    checkpoint: Option<PathBuf>,
+    #[arg(long,
+        help = "Count the requests of the --checkpoint file again on start, at the times they were first \
+            counted, then carry on adding to it")]
+    resume: bool,
+    #[arg(long, value_name = "FILE",
+        help = "Write every request as parsed, after filters, to FILE as a JSON line (- for stdout with \
+            --batch, instead of the report)")]
+    emit_parsed: Option<PathBuf>,
+
+    #[arg(long, value_name = "ADDR",
+        help = "Address for serve mode to listen on (default: 127.0.0.1:9180); syslog://ADDR receives logs \
+            as syslog messages over UDP and TCP, e.g. from nginx's access_log syslog:server=HOST:1514, and \
+            unix:PATH syslog datagrams on a unix socket (both repeatable, any mode)")]
+    listen: Vec<String>,
+    #[arg(long, value_name = "CONTAINER",
+        help = "Read the log output of a container (docker logs --follow; repeatable)")]
+    docker: Vec<String>,
+    #[arg(long, value_name = "TARGET",
+        help = "Read the logs of every pod of a Kubernetes workload (e.g. deployment/ingress-nginx), label \
+            selector (app=web) or pod, with kubectl (repeatable)")]
+    kube: Vec<String>,
+    #[arg(short, long, value_name = "NS", help = "Namespace for --kube")]
+    namespace: Option<String>,
+    #[arg(long, value_name = "[USER@]HOST:/PATH", value_parser = ssh_target,
+        help = "Follow a log file on another server over ssh (tail -F, repeatable; needs key-based login)")]
+    ssh: Vec<String>,
+    #[arg(long, value_name = "s3://BUCKET/PREFIX", value_parser = s3_location,
+        help = "Read the log objects under an S3 location, such as ALB or CloudFront access logs, in key \
+            order with the aws CLI; .gz objects are decompressed (repeatable)")]
+    s3: Vec<String>,
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..),
+        help = "List --s3 locations again every SECONDS for new objects instead of stopping after the last one")]
+    s3_poll: Option<u64>,
+    #[arg(long, value_name = "brokers=HOST:PORT,... topic=NAME [group=NAME]",
+        help = "Consume log lines from a Kafka topic with kcat, in a consumer group (default: httop) whose \
+            committed offsets let a restart resume where it left off (repeatable)")]
+    kafka: Vec<String>,
+    #[arg(long, value_name = "SPEED", num_args = 0..=1, require_equals = true, default_missing_value = "1",
+        value_parser = speed,
+        help = "Read log files at the pace of their timestamps instead of all at once, optionally sped up \
+            (e.g. 10 or 10x)")]
+    replay: Option<f64>,
+    #[arg(long, help = "Read logs from the systemd journal (journalctl --follow)")]
+    journald: bool,
+    #[arg(long, value_name = "UNIT",
+        help = "Only journal entries of UNIT, e.g. nginx.service (repeatable, implies --journald)")]
+    unit: Vec<String>,
+    #[arg(long,
+        help = "Serve mode for running under systemd: sd_notify READY and WATCHDOG, reload on SIGHUP, clean \
+            shutdown on SIGTERM")]
+    daemon: bool,
+
+    #[arg(long,
+        help = "Read the whole input without a display, then print a summary report (top paths and IPs, \
+            status codes, response time percentiles, bandwidth) to stdout")]
+    batch: bool,
+    #[arg(long, value_name = "FORMAT",
+        help = "Format of the --batch report: text (default), json with every table in full, or csv files \
+            of the paths, IPs, status codes and user agents tables in --out-dir")]
+    output: Option<Output>,
+    #[arg(long, value_name = "DIR", help = "Directory for --output csv, made if missing")]
+    out_dir: Option<PathBuf>,
+    #[arg(long, help = "Quit the display without printing the --batch report of the session to stdout")]
+    no_summary: bool,
+    #[arg(long, value_name = "FILE",
+        help = "Load a per-path baseline from FILE if it exists, and save baselines recorded with the B key \
+            to it")]
+    baseline: Option<PathBuf>,
+    #[arg(long, value_name = "FILE",
+        help = "Compare the traffic with that of the log FILE, read first with the same format and filters: \
+            requests per minute, p95 and status codes per path (P, and in the report)")]
+    compare: Option<PathBuf>,
+    #[arg(long, value_name = "TIME", value_parser = window,
+        help = "Compare the current TIME of logged traffic, e.g. 1h, with the TIME before it, periods \
+            starting on the hour for 1h")]
+    compare_window: Option<Duration>,
+    #[arg(long, value_name = "FILE", help = "Additional referrer spam domains, one per line")]
+    referrer_spam_list: Option<PathBuf>,
+    #[arg(long,
+        help = "Group the Referrers tab by domain rather than by full referrer URL (toggle with D)")]
+    referrer_domains: bool,
+    #[arg(long, help = "Start with bots and crawlers left out of every view (toggle with x)")]
+    hide_bots: bool,
+    #[arg(long, help = "Also count clients that fetched /robots.txt as bots, whatever their user agent")]
+    robots_txt_bots: bool,
+
+    #[arg(long, value_name = "NAME",
+        help = "Log field identifying the tenant or API key (repeatable, default: tenant, tenant_id, api_key)")]
+    tenant_field: Vec<String>,
+    #[arg(long, value_name = "ID", help = "Only count requests from this tenant (repeatable)")]
+    tenant: Vec<String>,
+    #[arg(long, value_name = "HOST",
+        help = "Only count requests for this virtual host, from the log's host, http_host or server_name \
+            field (repeatable)")]
+    vhost: Vec<String>,
+    #[arg(long, value_name = "CODES",
+        help = "Only count requests with these status codes, e.g. 5xx, 404 or 400-499, or a list such as \
+            404,5xx (repeatable, as are all of --include-* and --exclude-*: a request has to match one \
+            include of each field given, and no exclude)")]
+    include_status: Vec<String>,
+    #[arg(long, value_name = "CODES", help = "Drop requests with these status codes")]
+    exclude_status: Vec<String>,
+    #[arg(long, value_name = "METHODS", help = "Only count requests with these methods, e.g. GET,HEAD")]
+    include_method: Vec<String>,
+    #[arg(long, value_name = "METHODS", help = "Drop requests with these methods, e.g. OPTIONS")]
+    exclude_method: Vec<String>,
+    #[arg(long, value_name = "REGEX", help = "Only count requests whose path (without the query string) matches")]
+    include_path: Vec<String>,
+    #[arg(long, value_name = "REGEX", help = "Drop requests whose path matches, e.g. '\\.(css|js|png)$'")]
+    exclude_path: Vec<String>,
+    #[arg(long, value_name = "ADDRS",
+        help = "Only count requests from these client addresses or CIDR blocks, e.g. 10.0.0.0/8,192.168.0.0/16")]
+    include_ip: Vec<String>,
+    #[arg(long, value_name = "ADDRS", help = "Drop requests from these client addresses or CIDR blocks")]
+    exclude_ip: Vec<String>,
+    #[arg(long, value_name = "REGEX", help = "Only count requests whose user agent matches")]
+    include_ua: Vec<String>,
+    #[arg(long, value_name = "REGEX", help = "Drop requests whose user agent matches, e.g. 'Pingdom|kube-probe'")]
+    exclude_ua: Vec<String>,
+    #[arg(long,
+        help = "Count each request under the client address in its X-Forwarded-For (or X-Real-IP, \
+            True-Client-IP, CF-Connecting-IP) field rather than the proxy's or CDN edge's that connected")]
+    real_ip: bool,
+    #[arg(long, value_name = "NAME",
+        help = "Log field holding the client address or chain, e.g. http_x_forwarded_for (implies --real-ip)")]
+    real_ip_field: Option<String>,
+    #[arg(long, value_name = "ADDRS",
+        help = "Addresses or CIDR blocks of your proxies and CDN edges, e.g. 10.0.0.0/8 (repeatable, implies \
+            --real-ip): the client is then the last address of the chain not among them, and the field is \
+            ignored for requests that did not come through one")]
+    trusted_proxy: Vec<String>,
+    #[arg(long,
+        help = "Zero the last octet of IPv4 and the last 80 bits of IPv6 client addresses as they are read, \
+            so no table, export or recording holds a whole address")]
+    anonymize_ips: bool,
+    #[arg(long, value_name = "FILE",
+        help = "Country database in DB-IP lite CSV format (first_ip,last_ip,country_code), enables the \
+            Countries view")]
+    geoip: Option<PathBuf>,
+    #[arg(long, value_name = "CC", value_parser = country_code,
+        help = "Only count requests from this country (repeatable, requires --geoip)")]
+    country: Vec<String>,
+    #[arg(long, value_name = "CC", value_parser = country_code,
+        help = "Drop requests from this country (repeatable)")]
+    exclude_country: Vec<String>,
+    #[arg(long, value_name = "FILE",
+        help = "Network owner database in DB-IP lite ASN CSV format (first_ip,last_ip,asn,organisation), \
+            enables the Networks view")]
+    asn: Option<PathBuf>,
+
+    #[arg(long, value_name = "RULE",
+        help = "Show an alert banner while RULE holds (repeatable), e.g. 'error_rate>5%', '4xx_rate>20%', \
+            'avg_rt>800ms', 'rps<1', 'in_flight>50', 'p99>800ms', 'rps_from_single_ip>100', or with a time it \
+            must hold first: 'error_rate>5% for 2m'")]
+    alert: Vec<Rule>,
+    #[arg(long, value_name = "KEY", env = "HTTOP_PAGERDUTY_KEY", hide_env_values = true,
+        help = "Trigger and resolve PagerDuty incidents as alerts fire and clear (Events API v2 routing key)")]
+    pagerduty_key: Option<String>,
+    #[arg(long, value_name = "KEY", env = "HTTOP_OPSGENIE_KEY", hide_env_values = true,
+        help = "Open and close Opsgenie alerts likewise (API integration key); needs curl")]
+    opsgenie_key: Option<String>,
+    #[arg(long, value_name = "URL", help = "Opsgenie API base URL (default: https://api.opsgenie.com)")]
+    opsgenie_url: Option<String>,
+    #[arg(long, value_name = "URL",
+        help = "POST a JSON description of each alert to URL as it fires and resolves (repeatable); needs curl")]
+    alert_webhook: Vec<String>,
+    #[arg(long, value_name = "URL",
+        help = "Post each alert as it fires and resolves to a Slack (or Mattermost) incoming webhook \
+            (repeatable); needs curl")]
+    slack_webhook: Vec<String>,
+    #[arg(long, value_name = "TIME", value_parser = cooldown,
+        help = "Don't notify of a rule firing again within TIME of its last notification, e.g. 15m \
+            (default: 5m, 0 for never)")]
+    alert_cooldown: Option<Duration>,
+    #[arg(long, value_name = "CMD",
+        help = "Run CMD with sh as each alert fires and resolves, with HTTOP_ALERT_STATUS (firing or \
+            resolved), HTTOP_ALERT_RULE, HTTOP_ALERT_VALUE and HTTOP_ALERT_SUMMARY set (repeatable)")]
+    alert_command: Vec<String>,
+    #[arg(long, value_name = "FILE",
+        help = "Append client IPs that look abusive to FILE as they are flagged, one line each (see --ban-format)")]
+    ban_file: Option<PathBuf>,
+    #[arg(long, value_name = "FORMAT",
+        help = "fail2ban (default: dated 'httop ban IP (reason)' lines) or ipset[:SET] ('add SET IP -exist' \
+            lines for ipset restore; default set: httop)")]
+    ban_format: Option<BanFormat>,
+    #[arg(long, value_name = "CMD",
+        help = "Run CMD with sh for each flagged IP, with HTTOP_BAN_IP and HTTOP_BAN_REASON set")]
+    ban_command: Option<String>,
+    #[arg(long, value_name = "N", value_parser = positive,
+        help = "Flag clients making N requests per second or more over the last minute (default: 20)")]
+    ban_rps: Option<f64>,
+    #[arg(long = "ban-4xx", value_name = "PERCENT", value_parser = percent,
+        help = "Flag clients with PERCENT 4xx responses or more, after 20 requests (default: 50)")]
+    ban_4xx: Option<f64>,
+    #[arg(long, value_name = "N", value_parser = count,
+        help = "Flag clients answered 4xx for N paths scanners probe for, such as /.env or /wp-login.php \
+            (default: 5)")]
+    ban_probes: Option<usize>,
+    #[arg(long, value_name = "DIR",
+        help = "Write the stats as JSON to DIR/httop-TIME.json every --snapshot-interval, as --output json \
+            has them")]
+    snapshot_dir: Option<PathBuf>,
+    #[arg(long, value_name = "TIME", value_parser = window,
+        help = "How often to write a snapshot, e.g. 5m (default: 60s)")]
+    snapshot_interval: Option<Duration>,
+    #[arg(long, value_name = "HOST:PORT",
+        help = "Send request counts by status class, bytes and response time percentiles to a StatsD or \
+            DogStatsD agent over UDP")]
+    statsd: Option<String>,
+    #[arg(long, value_name = "PREFIX", value_parser = statsd_prefix,
+        help = "Prefix of the metric names (default: httop)")]
+    statsd_prefix: Option<String>,
+    #[arg(long, value_name = "KEY:VALUE", value_parser = statsd_tag,
+        help = "DogStatsD tag added to every metric (repeatable)")]
+    statsd_tag: Vec<String>,
+    #[arg(long, value_name = "TIME", value_parser = window,
+        help = "How often metrics are sent, e.g. 30s (default: 10s)")]
+    statsd_interval: Option<Duration>,
+    #[arg(long, help = "Ring the terminal bell and flash the header when an alert fires")]
+    bell: bool,
+    #[arg(long, value_name = "N", value_parser = not_negative,
+        help = "Flag RPS or 5xx rate more than N standard deviations off their last ten minutes (default: 3, \
+            0 to turn off)")]
+    anomaly_sigmas: Option<f64>,
+    #[arg(long, value_name = "FILE", help = "Append a line to FILE as each anomaly starts and ends")]
+    anomaly_log: Option<PathBuf>,
+
+    #[arg(long, value_name = "REGEX=COLOR",
+        help = "Color live tail lines matching REGEX (repeatable, first match wins), e.g. ' 5[0-9]{2} =red' \
+            or '/checkout=yellow'; colors: black, red, green, yellow, blue, magenta, cyan, white, 0-255, \
+            on-COLOR (background), bold, reverse")]
+    highlight: Vec<highlight::Rule>,
+    #[arg(long, value_name = "TIME", value_parser = response_time,
+        help = "Show the Apdex score for a target response time, e.g. 300ms, overall and in an apdex column")]
+    apdex_t: Option<f64>,
+    #[arg(long, value_name = "SPEC",
+        help = "Track a service level objective for an endpoint (repeatable), e.g. 'path=/api/checkout \
+            availability=99.9 latency_p99=500ms', optionally with method=POST and window=6h (default: 1d of \
+            logged time); a path ending in * takes every path it begins. Compliance, budget left and burn \
+            rates in the SLOs view (L) and the report")]
+    slo: Vec<Slo>,
+    #[arg(long, value_name = "SECONDS", value_parser = positive,
+        help = "Show response times from SECONDS on in yellow (default: 0.5)")]
+    slow: Option<f64>,
+    #[arg(long, value_name = "TIME", value_parser = response_time,
+        help = "Keep every request taking TIME or longer, e.g. 1s or 800ms, for the Slow Requests view (the \
+            latest 1000)")]
+    slow_threshold: Option<f64>,
+    #[arg(long, value_name = "SECONDS", value_parser = positive,
+        help = "Show response times from SECONDS on in red (default: 2)")]
+    very_slow: Option<f64>,
+    #[arg(long, help = "No colors, for dumb terminals (also with NO_COLOR set)")]
+    no_color: bool,
+    #[arg(long, value_name = "THEME", value_parser = Theme::from_arg,
+        help = "Colors for dark or light terminal backgrounds, or a theme file of key = \"color\" lines \
+            (default: dark)")]
+    theme: Option<Theme>,
+    #[arg(long, value_name = "LIST",
+        help = "Columns of the Paths, IPs and other per-key tables, from count, rps, 4xx, 5xx, latency, p95, \
+            max, bytes, bw (bytes per second), uniques, last-seen, apdex, hit, upstream and overhead \
+            (default: count,rps,4xx,5xx,latency,p95,bytes; C to change)")]
+    columns: Option<String>,
+    #[arg(long, value_name = "NAME",
+        help = "Log field holding the request or trace id (repeatable, default: trace_id, request_id, \
+            x_request_id, traceparent)")]
+    trace_field: Vec<String>,
+    #[arg(long, value_name = "TEMPLATE", value_parser = trace_url,
+        help = "Link opened with o N on the search screen, {id} replaced by the trace id, e.g. \
+            'https://jaeger.example.com/trace/{id}'")]
+    trace_url: Option<String>,
+    #[arg(long,
+        help = "Skip exact repeats of a recent request instead of counting them again (they are always reported)")]
+    drop_duplicates: bool,
+    #[arg(long, help = "Count GET /x and POST /x separately in the Requests table (toggle with m)")]
+    by_endpoint: bool,
+    #[arg(long,
+        help = "Count numeric ids, UUIDs and hashes in paths as :id, so that /users/12345/orders/67890 is \
+            /users/:id/orders/:id")]
+    normalize_paths: bool,
+    #[arg(long, value_name = "REGEX=REPLACEMENT",
+        help = "Rewrite paths matching REGEX before they are counted (repeatable, applied in order before \
+            --normalize-paths), e.g. '^/blog/[^/]+=/blog/:slug'; $1 refers to a group")]
+    path_rule: Vec<normalize::Rule>,
+    #[arg(long, value_name = "all|type", num_args = 0..=1, require_equals = true, default_missing_value = "all",
+        value_parser = ["all", "type"],
+        help = "Count requests for images, CSS, JS, fonts and media in the Paths tables as one [static assets] \
+            row, or one row per type with 'type', rather than path by path")]
+    group_static: Option<String>,
+    #[arg(long, value_name = "PREFIX",
+        help = "Also count paths under PREFIX as static assets, e.g. /assets/ (repeatable, implies --group-static)")]
+    static_prefix: Vec<String>,
+    #[arg(long, overrides_with = "group_by_query",
+        help = "Count /search?q=a and /search?q=b as different paths rather than both as /search")]
+    keep_query: bool,
+    #[arg(long, value_name = "NAME", overrides_with = "keep_query",
+        help = "Keep only query parameter NAME in paths, e.g. page for /search?page=2 (repeatable)")]
+    group_by_query: Vec<String>,
+
+    #[arg(long, value_name = "N", help = "Individual requests kept for the / search screen (default: 100000)")]
+    history: Option<usize>,
+    #[arg(long, value_name = "TIME", value_parser = interval,
+        help = "How often the display is redrawn, from 100ms to 10s, e.g. 250ms or 2s (default: 500ms; < and \
+            > to change)")]
+    interval: Option<Duration>,
+    #[arg(long, value_name = "NEW=OLD",
+        help = "Press NEW for what OLD does, leaving OLD free (repeatable), e.g. J=j; keys are single \
+            characters, Space or Tab, and arrows, Enter, Backspace and Esc stay as they are")]
+    bind: Vec<String>,
+    #[arg(long, value_name = "N", value_parser = display_limit,
+        help = "Rows shown per table, 5 or more (default: 20; + and - to change)")]
+    limit: Option<usize>,
+    #[arg(long, value_name = "RATE",
+        help = "Parse only a fraction of the lines, e.g. 1/10, 10% or 0.1, counting each as the lines it \
+            stands for; auto reads all lines while httop keeps up and fewer as it falls behind")]
+    sample: Option<sample::Rate>,
+    #[arg(long, value_name = "TIME", value_parser = time,
+        help = "Count only requests logged from TIME on, e.g. '2026-05-01 14:30' (local time), 2026-05-01, \
+            2026-05-01T14:30:00Z, or 2h for two hours ago")]
+    since: Option<DateTime<Utc>>,
+    #[arg(long, value_name = "TIME", value_parser = time, help = "Count only requests logged before TIME")]
+    until: Option<DateTime<Utc>>,
+    #[arg(long, value_name = "TIME", value_parser = window,
+        help = "Count only the last TIME of traffic, e.g. 10m or 1h, with older requests aging out of every \
+            table (default: all)")]
+    window: Option<Duration>,
+    #[arg(long, value_name = "N",
+        help = "Paths, clients, user agents and other keys each table keeps, evicting the least requested \
+            past N; their counts may then be short, and 0 keeps them all (default: 10000)")]
+    max_keys: Option<usize>,
+    #[arg(long, value_name = "SIZE", value_parser = size,
+        help = "Memory the tables and the requests kept for search may take, e.g. 256M or 1G: past it the \
+            least requested keys of any table go, and the oldest requests once they take a quarter of it \
+            (default: none)")]
+    max_memory: Option<usize>,
+    #[arg(long, value_name = "TIME", value_parser = status_span,
+        help = "How far back the status panel (T) reaches, in 60 columns, e.g. 30m (default: 10m)")]
+    status_span: Option<Duration>,
+    #[arg(long, value_name = "TIME", value_parser = window,
+        help = "How long without a page view ends a visit in the Visits view, e.g. 30m (default: 30m)")]
+    visit_timeout: Option<Duration>,
+    #[arg(long, value_name = "STYLE",
+        help = "Characters used for charts: braille, block, ascii or auto (braille with a UTF-8 locale, ascii \
+            otherwise; default)")]
+    chart_style: Option<ChartStyle>,
+    #[arg(long, value_name = "FILE",
+        help = "Read default options from FILE, a TOML file of long options such as format = \
+            \"apache-combined\" and [colors], [keys] and [alerts] sections (default: \
+            ~/.config/httop/config.toml if there is one)")]
+    // Read ahead of the other options, by with_config_file
+    #[allow(dead_code)]
+    config: Option<PathBuf>,
+}
+
+const DEFAULT_TENANT_FIELDS: &[&str] = &["tenant", "tenant_id", "api_key"];
+const DEFAULT_LISTEN: &str = "127.0.0.1:9180";
+const DEFAULT_SLOW: f64 = 0.5;
+const DEFAULT_VERY_SLOW: f64 = 2.0;
+
+#[derive(Debug, Clone, Default)]
+pub enum Mode {
+    #[default]
+    Interactive,
+    Serve { listen: String, daemon: bool },
+    // Read the input to its end and print a report
+    Batch,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub mode: Mode,
+    pub baseline: Option<PathBuf>,
+    pub compare: Option<PathBuf>,
+    pub compare_window: Option<Duration>,
+    pub referrer_spam_list: Option<PathBuf>,
+    pub tenant_fields: Vec<String>,
+    pub tenant_filter: Vec<String>,
+    // Lowercased host names, without ports
+    pub vhost_filter: Vec<String>,
+    pub filters: Filters,
+    pub alerts: Vec<Rule>,
+    pub slos: Vec<Slo>,
+    pub anomaly_sigmas: f64,
+    pub anomaly_log: Option<PathBuf>,
+    pub bell: bool,
+    // Where flagged clients go
+    pub ban_file: Option<PathBuf>,
+    pub ban_format: BanFormat,
+    pub ban_command: Option<String>,
+    pub ban_thresholds: Thresholds,
+    pub snapshot_dir: Option<PathBuf>,
+    pub snapshot_interval: Duration,
+    // StatsD agent to send metrics to
+    pub statsd: Option<String>,
+    pub statsd_prefix: String,
+    pub statsd_tags: Vec<String>,
+    pub statsd_interval: Duration,
+    pub chart_style: ChartStyle,
+    // Format of the --batch report
+    pub output: Output,
+    pub out_dir: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub checkpoint: Option<PathBuf>,
+    pub resume: bool,
+    // Parsed requests as JSON lines, or "-" for stdout
+    pub emit_parsed: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub history: usize,
+    // Between redraws of the display
+    pub interval: Duration,
+    // Rows shown per table
+    pub display_limit: usize,
+    pub sample: Option<sample::Rate>,
+    pub keymap: KeyMap,
+    // How far back the tables reach, when not to the start
+    pub window: Option<Duration>,
+    // Keys each table keeps before evicting the rarest, 0 for all
+    pub max_keys: usize,
+    // Bytes the tables may take before evicting the rarest, 0 for no limit
+    pub max_memory: usize,
+    pub static_assets: Option<StaticAssets>,
+    // Of the logged timestamps, from since on and before until
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    // Of the status panel, split into ClassTimeline::PERIODS columns
+    pub status_span: Duration,
+    // A client's visit ends after this long without a page view
+    pub visit_timeout: Duration,
+    pub highlights: Vec<highlight::Rule>,
+    // Response times shown in yellow and red from these on
+    pub slow: f64,
+    pub very_slow: f64,
+    // Requests from this many seconds on go to the slow request log
+    pub slow_threshold: Option<f64>,
+    // Apdex target response time, in seconds
+    pub apdex_t: Option<f64>,
+    pub no_color: bool,
+    // No report on leaving the display
+    pub no_summary: bool,
+    pub theme: Theme,
+    pub columns: Vec<Column>,
+    pub geoip: Option<PathBuf>,
+    pub asn: Option<PathBuf>,
+    pub country_filter: Vec<String>,
+    pub country_exclude: Vec<String>,
+    pub notifiers: Vec<Notifier>,
+    // Before a rule's firing is sent again
+    pub alert_cooldown: Duration,
+    pub by_endpoint: bool,
+    pub normalize_paths: bool,
+    pub real_ip: Option<RealIp>,
+    pub anonymize_ips: bool,
+    pub path_rules: Vec<normalize::Rule>,
+    pub query_strings: normalize::QueryStrings,
+    pub referrer_domains: bool,
+    pub hide_bots: bool,
+    pub robots_txt_bots: bool,
+    pub drop_duplicates: bool,
+    pub trace_fields: Vec<String>,
+    pub trace_url: Option<String>,
+    pub format: LogFormat,
+    pub files: Vec<PathBuf>,
+    // Addresses receiving syslog, from --listen syslog://ADDR
+    pub syslog: Vec<String>,
+    // Unix sockets receiving syslog, from --listen unix:PATH
+    pub unix_sockets: Vec<PathBuf>,
+    pub journald: bool,
+    pub units: Vec<String>,
+    pub containers: Vec<String>,
+    pub kube: Vec<String>,
+    // user@host:/path
+    pub ssh: Vec<String>,
+    pub namespace: Option<String>,
+    // s3://BUCKET/PREFIX locations, listed again every s3_poll seconds
+    pub s3: Vec<String>,
+    pub s3_poll: Option<u64>,
+    pub kafka: Vec<Kafka>,
+    // --replay: feed files at the pace of their timestamps, times this
+    pub replay_speed: Option<f64>,
+}
+
+impl Config {
+    pub fn from_args() -> Result<Self, clap::Error> {
+        let (args, colors) = with_config_file(env::args().skip(1).collect())
+            .map_err(|e| Cli::command().error(ErrorKind::Io, e))?;
+        let cli = Cli::try_parse_from(["httop".to_string()].into_iter().chain(join_values(args)))?;
+        Config::from_cli(cli, colors).map_err(|e| Cli::command().error(ErrorKind::ValueValidation, e))
+    }
+
+    // A --theme wins over the config file's [colors], which can't go with
+    // a theme key in the file itself
+    fn from_cli(cli: Cli, colors: Option<Theme>) -> Result<Self, String> {
+        let (mode, replay, options) = match cli.command {
+            None => (Mode::Interactive, None, cli.options),
+            Some(Command::Serve { options }) => (Mode::Serve { listen: DEFAULT_LISTEN.to_string(), daemon: false }, None, options),
+            Some(Command::Replay { recording, options }) => (Mode::Interactive, Some(recording), options),
+        };
+        let mut config = Config {
+            mode,
+            replay,
+            baseline: options.baseline,
+            compare: options.compare,
+            compare_window: options.compare_window,
+            referrer_spam_list: options.referrer_spam_list,
+            tenant_fields: options.tenant_field,
+            tenant_filter: options.tenant,
+            vhost_filter: options.vhost.iter().map(|host| host.to_ascii_lowercase()).collect(),
+            alerts: options.alert,
+            slos: options.slo,
+            anomaly_sigmas: options.anomaly_sigmas.unwrap_or(anomaly::DEFAULT_SIGMAS),
+            anomaly_log: options.anomaly_log,
+            bell: options.bell,
+            ban_file: options.ban_file,
+            ban_format: options.ban_format.unwrap_or_default(),
+            ban_command: options.ban_command,
+            ban_thresholds: Thresholds {
+                rps: options.ban_rps.unwrap_or(bans::DEFAULT_RPS),
+                client_error_percent: options.ban_4xx.unwrap_or(bans::DEFAULT_CLIENT_ERROR_PERCENT),
+                probes: options.ban_probes.unwrap_or(bans::DEFAULT_PROBES),
+            },
+            snapshot_dir: options.snapshot_dir,
+            snapshot_interval: options.snapshot_interval.unwrap_or(export::DEFAULT_SNAPSHOT_INTERVAL),
+            statsd: options.statsd,
+            statsd_prefix: options.statsd_prefix.unwrap_or_else(|| statsd::DEFAULT_PREFIX.to_string()),
+            statsd_tags: options.statsd_tag,
+            statsd_interval: options.statsd_interval.unwrap_or(statsd::DEFAULT_INTERVAL),
+            chart_style: options.chart_style.unwrap_or_default(),
+            output: options.output.unwrap_or_default(),
+            out_dir: options.out_dir,
+            record: options.record,
+            checkpoint: options.checkpoint,
+            resume: options.resume,
+            emit_parsed: options.emit_parsed,
+            history: options.history.unwrap_or(history::DEFAULT_CAPACITY),
+            interval: options.interval.unwrap_or(crate::app::REFRESH_INTERVAL),
+            display_limit: options.limit.unwrap_or(crate::app::DISPLAY_LIMIT),
+            sample: options.sample,
+            keymap: KeyMap::new(&options.bind)?,
+            window: options.window,
+            max_keys: options.max_keys.unwrap_or(tenants::DEFAULT_MAX_KEYS),
+            max_memory: options.max_memory.unwrap_or(0),
+            static_assets: (options.group_static.is_some() || !options.static_prefix.is_empty()).then(|| StaticAssets {
+                by_type: options.group_static.as_deref() == Some("type"),
+                prefixes: options.static_prefix,
+            }),
+            since: options.since,
+            until: options.until,
+            status_span: options.status_span.unwrap_or(ClassTimeline::DEFAULT_SPAN),
+            visit_timeout: options.visit_timeout.unwrap_or(visits::DEFAULT_TIMEOUT),
+            highlights: options.highlight,
+            slow: options.slow.unwrap_or(DEFAULT_SLOW),
+            very_slow: options.very_slow.unwrap_or(DEFAULT_VERY_SLOW),
+            slow_threshold: options.slow_threshold,
+            apdex_t: options.apdex_t,
+            // https://no-color.org
+            no_color: options.no_color || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+            no_summary: options.no_summary,
+            theme: options.theme.or(colors).unwrap_or_default(),
+            columns: match &options.columns {
+                Some(list) => columns::parse(list).map_err(|e| format!("--columns: {}", e))?,
+                None => columns::DEFAULT.to_vec(),
+            },
+            geoip: options.geoip,
+            asn: options.asn,
+            country_filter: options.country,
+            country_exclude: options.exclude_country,
+            alert_cooldown: options.alert_cooldown.unwrap_or(paging::DEFAULT_COOLDOWN),
+            by_endpoint: options.by_endpoint,
+            normalize_paths: options.normalize_paths,
+            anonymize_ips: options.anonymize_ips,
+            path_rules: options.path_rule,
+            query_strings: if !options.group_by_query.is_empty() {
+                normalize::QueryStrings::Group(options.group_by_query)
+            } else if options.keep_query {
+                normalize::QueryStrings::Keep
+            } else {
+                normalize::QueryStrings::default()
+            },
+            referrer_domains: options.referrer_domains,
+            hide_bots: options.hide_bots,
+            robots_txt_bots: options.robots_txt_bots,
+            drop_duplicates: options.drop_duplicates,
+            trace_fields: options.trace_field,
+            trace_url: options.trace_url,
+            format: options.format.or(options.log_format).unwrap_or_default(),
+            journald: options.journald || !options.unit.is_empty(),
+            units: options.unit,
+            containers: options.docker,
+            kube: options.kube,
+            ssh: options.ssh,
+            namespace: options.namespace,
+            s3: options.s3,
+            s3_poll: options.s3_poll,
+            replay_speed: options.replay,
+            ..Config::default()
+        };
+
+        for file in &options.files {
+            config.files.extend(follow::expand(file)?);
+        }
+        let mut listen = None;
+        for addr in options.listen {
+            if let Some(addr) = addr.strip_prefix("syslog://") {
+                config.syslog.push(addr.to_string());
+            } else if let Some(path) = addr.strip_prefix("unix:") {
+                config.unix_sockets.push(PathBuf::from(path));
+            } else {
+                listen = Some(addr);
+            }
+        }
+        for settings in &options.kafka {
+            config.kafka.push(Kafka::from_settings(settings.split_whitespace())?);
+        }
+        for (option, values) in [
+            ("--include-status", &options.include_status),
+            ("--exclude-status", &options.exclude_status),
+            ("--include-method", &options.include_method),
+            ("--exclude-method", &options.exclude_method),
+            ("--include-path", &options.include_path),
+            ("--exclude-path", &options.exclude_path),
+            ("--include-ip", &options.include_ip),
+            ("--exclude-ip", &options.exclude_ip),
+            ("--include-ua", &options.include_ua),
+            ("--exclude-ua", &options.exclude_ua),
+        ] {
+            for value in values {
+                config.filters.add(option, value)?;
+            }
+        }
+        if options.real_ip || options.real_ip_field.is_some() || !options.trusted_proxy.is_empty() {
+            let real_ip = config.real_ip.insert(RealIp::default());
+            real_ip.field = options.real_ip_field;
+            for addresses in &options.trusted_proxy {
+                real_ip.trust(addresses)?;
+            }
+        }
+        config.notifiers.extend(options.alert_webhook.into_iter().map(|url| Notifier::Webhook { url }));
+        config.notifiers.extend(options.slack_webhook.into_iter().map(|url| Notifier::Slack { url }));
+        config.notifiers.extend(options.alert_command.into_iter().map(|command| Notifier::Command { command }));
+
+        if options.batch {
+            if !matches!(config.mode, Mode::Interactive) || options.daemon {
+                return Err("--batch prints a report, it does not go with serve or --daemon".to_string());
+            }
+            if !config.syslog.is_empty() || !config.unix_sockets.is_empty() || config.journald
+                || !config.containers.is_empty() || !config.kube.is_empty() || !config.ssh.is_empty()
+                || !config.kafka.is_empty() || config.s3_poll.is_some() {
+                return Err("--batch reads log files, stdin, --s3 objects or a recording, not live inputs".to_string());
+            }
+            config.mode = Mode::Batch;
+        } else if options.output.is_some() {
+            return Err("--output sets the format of the --batch report".to_string());
+        }
+        match (config.output, &config.out_dir) {
+            (Output::Csv, None) => return Err("--output csv requires --out-dir DIR for its files".to_string()),
+            (Output::Text | Output::Json, Some(_)) => return Err("--out-dir is only for --output csv".to_string()),
+            _ => {}
+        }
+        // --daemon implies serve mode
+        if options.daemon && let Mode::Interactive = config.mode {
+            config.mode = Mode::Serve { listen: DEFAULT_LISTEN.to_string(), daemon: false };
+        }
+        if let Mode::Serve { listen: addr, daemon } = &mut config.mode {
+            *daemon = options.daemon;
+            if let Some(listen) = listen {
+                *addr = listen;
+            }
+        } else if listen.is_some() {
+            return Err("--listen ADDR is only valid with serve or --daemon (use syslog://ADDR or unix:PATH to receive logs)".to_string());
+        }
+
+        if let Some(routing_key) = options.pagerduty_key.filter(|key| !key.is_empty()) {
+            config.notifiers.push(Notifier::PagerDuty { routing_key });
+        }
+        if let Some(api_key) = options.opsgenie_key.filter(|key| !key.is_empty()) {
+            let api_url = options.opsgenie_url.unwrap_or_else(|| paging::OPSGENIE_URL.to_string());
+            config.notifiers.push(Notifier::Opsgenie { api_key, api_url });
+        }
+        if !config.notifiers.is_empty() && config.alerts.is_empty() {
+            return Err("paging, webhooks and alert commands need at least one --alert rule".to_string());
+        }
+
+        if config.statsd.is_none() && (!config.statsd_tags.is_empty()
+            || config.statsd_prefix != statsd::DEFAULT_PREFIX || config.statsd_interval != statsd::DEFAULT_INTERVAL) {
+            return Err("--statsd-prefix, --statsd-tag and --statsd-interval require --statsd HOST:PORT".to_string());
+        }
+        if config.statsd.is_some() && matches!(config.mode, Mode::Batch) {
+            return Err("--statsd sends metrics as logs arrive, not from a --batch report".to_string());
+        }
+        if config.emit_parsed.as_deref() == Some(Path::new("-")) && !matches!(config.mode, Mode::Batch) {
+            return Err("--emit-parsed - writes to stdout, which only --batch leaves free".to_string());
+        }
+        let thresholds = &config.ban_thresholds;
+        if config.ban_file.is_none() && config.ban_command.is_none() && (config.ban_format != BanFormat::default()
+            || thresholds.rps != bans::DEFAULT_RPS || thresholds.client_error_percent != bans::DEFAULT_CLIENT_ERROR_PERCENT
+            || thresholds.probes != bans::DEFAULT_PROBES) {
+            return Err("--ban-format and the --ban thresholds require --ban-file FILE or --ban-command CMD".to_string());
+        }
+        if (config.ban_file.is_some() || config.ban_command.is_some()) && config.anonymize_ips {
+            return Err("--ban-file and --ban-command need whole client addresses, which --anonymize-ips masks".to_string());
+        }
+        if (config.ban_file.is_some() || config.ban_command.is_some()) && matches!(config.mode, Mode::Batch) {
+            return Err("--ban-file and --ban-command flag clients as logs arrive, not from a --batch report".to_string());
+        }
+        if config.snapshot_dir.is_none() && config.snapshot_interval != export::DEFAULT_SNAPSHOT_INTERVAL {
+            return Err("--snapshot-interval requires --snapshot-dir DIR".to_string());
+        }
+        if config.snapshot_dir.is_some() && matches!(config.mode, Mode::Batch) {
+            return Err("--snapshot-dir writes snapshots as logs arrive, --batch --output json writes one at the end".to_string());
+        }
+
+        if config.resume && config.checkpoint.is_none() {
+            return Err("--resume requires --checkpoint FILE".to_string());
+        }
+
+        if config.s3_poll.is_some() && config.s3.is_empty() {
+            return Err("--s3-poll requires an --s3 location".to_string());
+        }
+        if config.replay_speed.is_some() && (config.replay.is_some() || !config.syslog.is_empty()
+            || !config.unix_sockets.is_empty() || config.journald
+            || !config.containers.is_empty() || !config.kube.is_empty() || !config.ssh.is_empty() || !config.kafka.is_empty())
+        {
+            return Err("--replay paces log files, stdin or --s3 objects, not recordings or live inputs".to_string());
+        }
+        if config.replay.is_some() && !config.reads_stdin() {
+            return Err("replay reads a recording, not other inputs".to_string());
+        }
+
+        if config.compare.is_some() && config.compare_window.is_some() {
+            return Err("--compare compares with a log, --compare-window with the traffic before: pick one".to_string());
+        }
+
+        if config.geoip.is_none() && !(config.country_filter.is_empty() && config.country_exclude.is_empty()) {
+            return Err("--country and --exclude-country require --geoip".to_string());
+        }
+
+        if let (Some(since), Some(until)) = (config.since, config.until)
+            && since >= until {
+            return Err("--since must be before --until".to_string());
+        }
+        match options.regex {
+            Some(regex) => config.format = LogFormat::Regex(Pattern::new(&regex, options.time_format)?),
+            None if options.time_format.is_some() => return Err("--time-format requires --regex".to_string()),
+            None => {}
+        }
+        if !options.map.is_empty() {
+            let Some(map) = config.format.field_map() else {
+                return Err("--map requires --format json, caddy, gcp or logfmt".to_string());
+            };
+            for mapping in &options.map {
+                map.set(mapping)?;
+            }
+        }
+
+        if config.tenant_fields.is_empty() {
+            config.tenant_fields = DEFAULT_TENANT_FIELDS.iter().map(|f| f.to_string()).collect();
+        }
+        if config.trace_fields.is_empty() {
+            config.trace_fields = trace::DEFAULT_FIELDS.iter().map(|f| f.to_string()).collect();
+        }
+
+        Ok(config)
+    }
+
+    // Without other inputs, logs are piped in
+    pub fn reads_stdin(&self) -> bool {
+        self.files.is_empty() && self.syslog.is_empty() && self.unix_sockets.is_empty() && !self.journald && self.containers.is_empty()
+            && self.kube.is_empty() && self.ssh.is_empty() && self.s3.is_empty()
+            && self.kafka.is_empty()
+    }
+
+    // Where requests come from, as the help screen lists them
+    pub fn inputs(&self) -> Vec<String> {
+        if let Some(path) = &self.replay {
+            return vec![format!("recording {}", path.display())];
+        }
+        if self.reads_stdin() {
+            return vec!["stdin".to_string()];
+        }
+        let mut inputs: Vec<String> = self.files.iter().map(|path| path.display().to_string()).collect();
+        if let Some(speed) = self.replay_speed {
+            inputs = inputs.into_iter().map(|file| format!("{} (replayed at {}x)", file, speed)).collect();
+        }
+        if self.journald {
+            inputs.push(if self.units.is_empty() { "journald".to_string() } else { format!("journald {}", self.units.join(",")) });
+        }
+        inputs.extend(self.containers.iter().map(|container| format!("docker {}", container)));
+        inputs.extend(self.kube.iter().map(|target| format!("kube {}", target)));
+        inputs.extend(self.ssh.iter().map(|target| format!("ssh {}", target)));
+        inputs.extend(self.s3.iter().cloned());
+        inputs.extend(self.kafka.iter().map(|kafka| format!("kafka {} ({}, group {})", kafka.topic, kafka.brokers, kafka.group)));
+        inputs.extend(self.unix_sockets.iter().map(|path| format!("syslog unix {}", path.display())));
+        inputs.extend(self.syslog.iter().map(|addr| format!("syslog {}", addr)));
+        inputs
+    }
+
+    pub fn tenant_of<'a>(&self, fields: &'a [(String, String)]) -> Option<&'a str> {
+        self.tenant_fields.iter()
+            .find_map(|name| fields.iter().find(|(key, _)| key == name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn country_code(code: &str) -> Result<String, String> {
+    geoip::country(code)
+        .map(|country| country.code.to_string())
+        .ok_or_else(|| format!("unknown country code '{}'", code))
+}
+
+// Settings that follow --kafka as arguments of their own go into its value,
+// as do a --replay speed and a --group-static kind, which clap would
+// otherwise take for log files
+fn join_values(args: Vec<String>) -> Vec<String> {
+    let mut joined = Vec::with_capacity(args.len());
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--kafka" => {
+                joined.push(arg);
+                if let Some(mut settings) = args.next() {
+                    while let Some(setting) = args.next_if(|arg| Kafka::is_setting(arg)) {
+                        settings.push(' ');
+                        settings.push_str(&setting);
+                    }
+                    joined.push(settings);
+                }
+            }
+            "--replay" => match args.next_if(|arg| parse_speed(arg).is_some()) {
+                Some(speed) => joined.push(format!("--replay={}", speed)),
+                None => joined.push(arg),
+            },
+            "--group-static" => match args.next_if(|arg| arg == "all" || arg == "type") {
+                Some(kind) => joined.push(format!("--group-static={}", kind)),
+                None => joined.push(arg),
+            },
+            _ => joined.push(arg),
+        }
+    }
+    joined
+}
+
+fn log_format(template: &str) -> Result<LogFormat, String> {
+    Ok(LogFormat::Custom(template.parse()?))
+}
+
+fn ssh_target(target: &str) -> Result<String, String> {
+    match target.split_once(':') {
+        Some((host, path)) if !host.is_empty() && !path.is_empty() => Ok(target.to_string()),
+        _ => Err("expected [user@]host:/path".to_string()),
+    }
+}
+
+fn s3_location(location: &str) -> Result<String, String> {
+    match location.strip_prefix("s3://") {
+        Some(rest) if !rest.is_empty() && !rest.starts_with('/') => Ok(location.to_string()),
+        _ => Err("expected s3://bucket/prefix".to_string()),
+    }
+}
+
+fn speed(speed: &str) -> Result<f64, String> {
+    parse_speed(speed).ok_or_else(|| "a speed above 0, e.g. 2, 10x or 0.5x".to_string())
+}
+
+fn interval(value: &str) -> Result<Duration, String> {
+    parse_interval(value).ok_or_else(|| "100ms to 10s, e.g. 250ms or 2s".to_string())
+}
+
+fn window(value: &str) -> Result<Duration, String> {
+    parse_window(value).ok_or_else(|| "10s or more, e.g. 90s, 10m or 2h".to_string())
+}
+
+fn status_span(value: &str) -> Result<Duration, String> {
+    parse_window(value)
+        .filter(|span| *span >= MIN_STATUS_SPAN)
+        .ok_or_else(|| "1m or more, e.g. 10m or 1h".to_string())
+}
+
+fn cooldown(value: &str) -> Result<Duration, String> {
+    match value {
+        "0" => Ok(Duration::ZERO),
+        _ => parse_window(value).ok_or_else(|| "0, or 10s or more, e.g. 15m".to_string()),
+    }
+}
+
+fn time(value: &str) -> Result<DateTime<Utc>, String> {
+    parse_time(value, Utc::now()).ok_or_else(|| {
+        "e.g. '2026-05-01 14:30', 2026-05-01, 2026-05-01T14:30:00Z or 2h for 2 hours ago".to_string()
+    })
+}
+
+fn size(value: &str) -> Result<usize, String> {
+    parse_size(value).ok_or_else(|| "e.g. 512K, 256M or 1G, 0 for no limit".to_string())
+}
+
+fn response_time(value: &str) -> Result<f64, String> {
+    apdex::parse_t(value).ok_or_else(|| "a response time such as 300ms or 1s".to_string())
+}
+
+fn display_limit(value: &str) -> Result<usize, String> {
+    value.parse().ok()
+        .filter(|limit| *limit >= crate::app::MIN_DISPLAY_LIMIT)
+        .ok_or_else(|| format!("{} rows or more", crate::app::MIN_DISPLAY_LIMIT))
+}
+
+fn positive(value: &str) -> Result<f64, String> {
+    value.parse().ok().filter(|value: &f64| *value > 0.0).ok_or_else(|| "a number above 0".to_string())
+}
+
+fn not_negative(value: &str) -> Result<f64, String> {
+    value.parse().ok().filter(|value: &f64| *value >= 0.0).ok_or_else(|| "a number, 0 or more".to_string())
+}
+
+fn count(value: &str) -> Result<usize, String> {
+    value.parse().ok().filter(|count| *count > 0).ok_or_else(|| "a whole number above 0".to_string())
+}
+
+// 50 or 50%
+fn percent(value: &str) -> Result<f64, String> {
+    value.trim_end_matches('%').parse().ok()
+        .filter(|percent: &f64| *percent > 0.0 && *percent <= 100.0)
+        .ok_or_else(|| "a percentage above 0, up to 100".to_string())
+}
+
+fn statsd_prefix(prefix: &str) -> Result<String, String> {
+    if prefix.contains([':', '|', '@', '#']) {
+        return Err("no :, |, @ or # in a metric name".to_string());
+    }
+    Ok(prefix.to_string())
+}
+
+fn statsd_tag(tag: &str) -> Result<String, String> {
+    if tag.is_empty() || tag.contains([',', '|', '#']) {
+        return Err("a tag such as env:prod, without , | or #".to_string());
+    }
+    Ok(tag.to_string())
+}
+
+fn trace_url(template: &str) -> Result<String, String> {
+    if !template.contains("{id}") {
+        return Err("the template has no {id} placeholder".to_string());
+    }
+    Ok(template.to_string())
+}
+
+// Options from --config FILE or the default config file, if any, go after
+// the serve or replay command and before the other arguments, which so
+// override them; with the file's [colors], if it has any
+fn with_config_file(mut args: Vec<String>) -> Result<(Vec<String>, Option<Theme>), String> {
+    let settings = match args.iter().position(|arg| arg == "--config") {
+        Some(i) => {
+            let path = args.get(i + 1).ok_or("--config requires a file path")?.clone();
+            args.drain(i..i + 2);
+            settings::load(Path::new(&path))?
+        }
+        None => match settings::default_path().filter(|path| path.is_file()) {
+            Some(path) => settings::load(&path)?,
+            None => Settings::default(),
+        },
+    };
+    let start = match args.first().map(String::as_str) {
+        Some("serve") => 1,
+        Some("replay") => args.len().min(2),
+        _ => 0,
+    };
+    args.splice(start..start, settings.args);
+    Ok((args, settings.colors))
+}
+
+const MIN_WINDOW: Duration = Duration::from_secs(10);
+// A second per column of the status panel
+const MIN_STATUS_SPAN: Duration = Duration::from_secs(60);
+
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_INTERVAL: Duration = Duration::from_secs(10);
+
+// 250ms, 2s, or seconds without a unit
+fn parse_interval(value: &str) -> Option<Duration> {
+    let seconds: f64 = match value.strip_suffix("ms") {
+        Some(millis) => millis.parse::<f64>().ok()? / 1000.0,
+        None => value.strip_suffix('s').unwrap_or(value).parse().ok()?,
+    };
+    let interval = Duration::try_from_secs_f64(seconds).ok()?;
+    (MIN_INTERVAL..=MAX_INTERVAL).contains(&interval).then_some(interval)
+}
+
+// 90s, 10m, 2h, or seconds without a unit
+fn parse_window(value: &str) -> Option<Duration> {
+    let (number, unit) = match value.char_indices().last()? {
+        (at, 's') => (&value[..at], 1.0),
+        (at, 'm') => (&value[..at], 60.0),
+        (at, 'h') => (&value[..at], 3600.0),
+        _ => (value, 1.0),
+    };
+    let window = Duration::try_from_secs_f64(number.parse::<f64>().ok()? * unit).ok()?;
+    (window >= MIN_WINDOW).then_some(window)
+}
+
+// 512K, 256M, 1.5G, or bytes without a unit, in KiB, MiB and GiB as
+// human_bytes shows them; a trailing B or iB is fine
+fn parse_size(value: &str) -> Option<usize> {
+    let value = value.strip_suffix("iB").or_else(|| value.strip_suffix('B')).unwrap_or(value);
+    let (number, unit) = match value.char_indices().last()? {
+        (at, 'K' | 'k') => (&value[..at], 1u64 << 10),
+        (at, 'M' | 'm') => (&value[..at], 1 << 20),
+        (at, 'G' | 'g') => (&value[..at], 1 << 30),
+        _ => (value, 1),
+    };
+    let bytes = number.parse::<f64>().ok()? * unit as f64;
+    (bytes.is_finite() && bytes >= 0.0).then_some(bytes as usize)
+}
+
+// 2026-05-01 14:30, 2026-05-01, RFC 3339, or a time ago such as 2h or 3d;
+// times without a zone are local
+fn parse_time(value: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let local = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"].iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0));
+    if let Some(local) = local {
+        return Some(Local.from_local_datetime(&local).earliest()?.with_timezone(&Utc));
+    }
+    let (number, unit) = match value.char_indices().last()? {
+        (at, 's') => (&value[..at], 1.0),
+        (at, 'm') => (&value[..at], 60.0),
+        (at, 'h') => (&value[..at], 3600.0),
+        (at, 'd') => (&value[..at], 86400.0),
+        _ => return None,
+    };
+    let ago = Duration::try_from_secs_f64(number.parse::<f64>().ok()? * unit).ok()?;
+    Some(now - chrono::Duration::from_std(ago).ok()?)
+}
+
+// A --replay speed such as 2, 10x or 0.5x
+fn parse_speed(speed: &str) -> Option<f64> {
+    let speed: f64 = speed.strip_suffix('x').unwrap_or(speed).parse().ok()?;
+    (speed.is_finite() && speed > 0.0).then_some(speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config, String> {
+        let args = join_values(args.iter().map(|arg| arg.to_string()).collect());
+        let cli = Cli::try_parse_from(["httop".to_string()].into_iter().chain(args)).map_err(|e| e.to_string())?;
+        Config::from_cli(cli, None)
+    }
+
+    #[test]
+    fn command() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn values() {
+        let config = parse(&["--interval", "250ms", "--window", "10m", "--max-memory", "256M", "--limit", "30"]).unwrap();
+        assert_eq!(config.interval, Duration::from_millis(250));
+        assert_eq!(config.window, Some(Duration::from_secs(600)));
+        assert_eq!(config.max_memory, 256 << 20);
+        assert_eq!(config.display_limit, 30);
+        assert!(parse(&["--interval", "5ms"]).is_err());
+        assert!(parse(&["--limit", "1"]).is_err());
+        let config = parse(&["--bind", "X=b", "--bind", "b=x"]).unwrap();
+        assert_eq!(config.keymap.translate(b'X'), b'b');
+        assert!(parse(&["--bind", "P=Space"]).is_err());
+
+        // The last of a repeated option wins, as one from the config file
+        // gives way to the command line
+        let config = parse(&["--format", "json", "--log-format", "$remote_addr \"$request\" $status", "--format", "caddy"]).unwrap();
+        assert!(matches!(config.format, LogFormat::Json(_)));
+        let config = parse(&["--group-by-query", "page", "--keep-query"]).unwrap();
+        assert!(matches!(config.query_strings, normalize::QueryStrings::Keep));
+    }
+
+    #[test]
+    fn joined() {
+        let config = parse(&["--kafka", "brokers=k:9092", "topic=logs", "access.log", "--group-static", "type"]).unwrap();
+        assert_eq!(config.kafka.len(), 1);
+        assert_eq!(config.files, [PathBuf::from("access.log")]);
+        assert!(config.static_assets.is_some_and(|assets| assets.by_type));
+
+        let config = parse(&["--replay", "10x", "access.log"]).unwrap();
+        assert_eq!(config.replay_speed, Some(10.0));
+        let config = parse(&["--replay", "access.log"]).unwrap();
+        assert_eq!(config.replay_speed, Some(1.0));
+        assert_eq!(config.files, [PathBuf::from("access.log")]);
+    }
+
+    #[test]
+    fn modes() {
+        assert!(matches!(parse(&["serve", "--listen", "0.0.0.0:9000"]).unwrap().mode,
+            Mode::Serve { ref listen, daemon: false } if listen == "0.0.0.0:9000"));
+        assert!(matches!(parse(&["--daemon"]).unwrap().mode, Mode::Serve { daemon: true, .. }));
+        assert!(matches!(parse(&["--batch"]).unwrap().mode, Mode::Batch));
+        assert_eq!(parse(&["replay", "session.rec"]).unwrap().replay, Some(PathBuf::from("session.rec")));
+
+        assert!(parse(&["--listen", "0.0.0.0:9000"]).is_err());
+        assert!(parse(&["--output", "json"]).is_err());
+        assert!(parse(&["serve", "--batch"]).is_err());
+        assert!(parse(&["--resume"]).is_err());
+        assert!(parse(&["--country", "DE"]).is_err());
+        assert!(parse(&["--alert-webhook", "https://example.com/hook"]).is_err());
+        assert!(parse(&["--since", "1h", "--until", "2h"]).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentClass {
+    Html,
+    Json,
+    Js,
+    Css,
+    Image,
+    Font,
+    Media,
+    Other,
+}
+
+impl ContentClass {
+    pub const ALL: [ContentClass; 8] = [
+        ContentClass::Html,
+        ContentClass::Json,
+        ContentClass::Js,
+        ContentClass::Css,
+        ContentClass::Image,
+        ContentClass::Font,
+        ContentClass::Media,
+        ContentClass::Other,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ContentClass::Html => "html",
+            ContentClass::Json => "json",
+            ContentClass::Js => "js",
+            ContentClass::Css => "css",
+            ContentClass::Image => "images",
+            ContentClass::Font => "fonts",
+            ContentClass::Media => "media",
+            ContentClass::Other => "other",
+        }
+    }
+
+    // Subresources a browser loads while rendering a page
+    pub fn is_page_asset(self) -> bool {
+        matches!(self, ContentClass::Js | ContentClass::Css | ContentClass::Image | ContentClass::Font)
+    }
+}
+
+// Infers the content class from the file extension; extensionless paths are
+// pages, or JSON when they look like an API route
+pub fn classify(path: &str) -> ContentClass {
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    let file = path.rsplit('/').next().unwrap_or(path);
+
+    let Some((_, ext)) = file.rsplit_once('.') else {
+        if path.starts_with("/api/") || path == "/api" || path.starts_with("/graphql") {
+            return ContentClass::Json;
+        }
+        return ContentClass::Html;
+    };
+
+    match ext.to_ascii_lowercase().as_str() {
+        "html" | "htm" | "xhtml" | "php" | "asp" | "aspx" | "jsp" => ContentClass::Html,
+        "json" | "jsonld" | "geojson" => ContentClass::Json,
+        "js" | "mjs" | "cjs" | "map" => ContentClass::Js,
+        "css" => ContentClass::Css,
+        "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "avif" | "ico" | "bmp" | "tif" | "tiff" => ContentClass::Image,
+        "woff" | "woff2" | "ttf" | "otf" | "eot" => ContentClass::Font,
+        "mp4" | "webm" | "mkv" | "mov" | "avi" | "m3u8" | "ts" | "mp3" | "ogg" | "wav" | "flac" | "m4a" | "aac" => ContentClass::Media,
+        _ => ContentClass::Other,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ContentEntry {
+    pub count: usize,
+    pub bytes: usize,
+    pub total_response_time: f64,
+}
+
+impl ContentEntry {
+    pub fn avg_response_time(&self) -> f64 {
+        if self.count > 0 {
+            self.total_response_time / self.count as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ContentStats {
+    classes: [ContentEntry; ContentClass::ALL.len()],
+}
+
+impl ContentStats {
+    pub fn record(&mut self, class: ContentClass, bytes: usize, response_time: f64, weight: usize) {
+        let entry = &mut self.classes[class as usize];
+        entry.count += weight;
+        entry.bytes += bytes * weight;
+        entry.total_response_time += response_time * weight as f64;
+    }
+
+    pub fn get(&self, class: ContentClass) -> &ContentEntry {
+        &self.classes[class as usize]
+    }
+}
+
+// --group-static: requests for static assets counted under one bucket, or
+// one per type, in the Paths tables instead of under their own paths
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StaticAssets {
+    pub by_type: bool,
+    // Paths under these count as static whatever their extension
+    pub prefixes: Vec<String>,
+}
+
+impl StaticAssets {
+    pub fn bucket(&self, path: &str) -> Option<&'static str> {
+        let class = classify(path);
+        let prefixed = self.prefixes.iter().any(|prefix| path.starts_with(prefix.as_str()));
+        if !(class.is_page_asset() || class == ContentClass::Media || prefixed) {
+            return None;
+        }
+        if !self.by_type {
+            return Some("[static assets]");
+        }
+        Some(match class {
+            ContentClass::Js => "[static: js]",
+            ContentClass::Css => "[static: css]",
+            ContentClass::Image => "[static: images]",
+            ContentClass::Font => "[static: fonts]",
+            ContentClass::Media => "[static: media]",
+            _ => "[static: other]",
+        })
+    }
+}
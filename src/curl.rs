@@ -0,0 +1,38 @@
+use crate::Request;
+use crate::formats::HOST_FIELDS;
+
+// Log fields that may carry the requested scheme
+const SCHEME_FIELDS: &[&str] = &["scheme", "proto"];
+
+// An equivalent curl invocation. Without a host field in the log the URL
+// points at localhost, to be edited before running
+pub fn command(request: &Request) -> String {
+    let field = |names: &[&str]| names.iter()
+        .find_map(|name| request.fields.iter().find(|(key, _)| key == name))
+        .map(|(_, value)| value.clone());
+
+    let host = field(HOST_FIELDS).unwrap_or_else(|| "localhost".to_string());
+    let scheme = field(SCHEME_FIELDS).unwrap_or_else(|| {
+        if host == "localhost" { "http".to_string() } else { "https".to_string() }
+    });
+
+    let mut command = String::from("curl");
+    match request.method.as_str() {
+        "GET" => {}
+        "HEAD" => command.push_str(" -I"),
+        method => command.push_str(&format!(" -X {}", quote(method))),
+    }
+    if !request.user_agent.is_empty() && request.user_agent != "-" {
+        command.push_str(&format!(" -A {}", quote(&request.user_agent)));
+    }
+    if !request.referrer.is_empty() && request.referrer != "-" {
+        command.push_str(&format!(" -e {}", quote(&request.referrer)));
+    }
+    command.push_str(&format!(" {}", quote(&format!("{}://{}{}", scheme, host, request.url()))));
+    command
+}
+
+// POSIX shell single quoting
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
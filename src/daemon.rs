@@ -0,0 +1,79 @@
+use std::env;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+#[cfg(unix)]
+use signal_hook::consts::SIGHUP;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::flag;
+
+pub struct Signals {
+    terminate: Arc<AtomicBool>,
+    reload: Arc<AtomicBool>,
+}
+
+impl Signals {
+    pub fn register() -> io::Result<Self> {
+        let terminate = Arc::new(AtomicBool::new(false));
+        let reload = Arc::new(AtomicBool::new(false));
+        flag::register(SIGTERM, Arc::clone(&terminate))?;
+        flag::register(SIGINT, Arc::clone(&terminate))?;
+        #[cfg(unix)]
+        flag::register(SIGHUP, Arc::clone(&reload))?;
+        Ok(Signals { terminate, reload })
+    }
+
+    pub fn terminate_requested(&self) -> bool {
+        self.terminate.load(Ordering::Relaxed)
+    }
+
+    pub fn take_reload(&self) -> bool {
+        self.reload.swap(false, Ordering::Relaxed)
+    }
+}
+
+// sd_notify(3) without libsystemd; a no-op when not started by systemd
+#[cfg(unix)]
+pub fn notify(state: &str) -> io::Result<()> {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return Ok(());
+    };
+
+    let socket = UnixDatagram::unbound()?;
+    let path = path.to_string_lossy();
+    if let Some(name) = path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = name;
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "abstract notify socket"));
+        }
+    } else {
+        socket.send_to(state.as_bytes(), path.as_ref())?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) -> io::Result<()> {
+    Ok(())
+}
+
+// How often systemd expects WATCHDOG=1, if the unit sets WatchdogSec=
+pub fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = env::var("WATCHDOG_PID")
+        && pid.parse() != Ok(std::process::id()) {
+        return None;
+    }
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    Some(Duration::from_micros(usec))
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use crate::Request;
+use crate::cache::CacheStats;
+use crate::agents::Grouping;
+use crate::content::StaticAssets;
+
+// Recent requests shown for the selected row
+const RECENT: usize = 10;
+
+// What a drill-down is about: a row of the Paths, IPs, User Agents, Virtual
+// Hosts, Bots, Networks or Hosts tab
+#[derive(Debug, Clone, PartialEq)]
+pub enum Key {
+    Path(String),
+    // "METHOD path", as in the Paths tab split by method
+    Endpoint(String),
+    Ip(String),
+    UserAgent(String),
+    // A browser, OS or device class the User Agents tab grouped by
+    UserAgentGroup(Grouping, String),
+    // A --group-static bucket, "[static: js]" or "GET [static: js]"
+    StaticAssets(StaticAssets, String),
+    // "AS16509 Amazon.com", as in the Networks view
+    Network(String),
+    Vhost(String),
+    Bot(String),
+    // As in the Hosts view, see Request::host
+    Host(String),
+}
+
+impl Key {
+    pub fn describe(&self) -> String {
+        match self {
+            Key::Path(path) => format!("path {}", path),
+            Key::Endpoint(endpoint) => format!("endpoint {}", endpoint),
+            Key::Ip(ip) => format!("client {}", ip),
+            Key::UserAgent(user_agent) => format!("user agent {}", user_agent),
+            Key::UserAgentGroup(grouping, name) => format!("{} {}", grouping.name(), name),
+            Key::StaticAssets(_, bucket) => format!("static assets {}", bucket),
+            Key::Network(network) => format!("network {}", network),
+            Key::Vhost(host) => format!("virtual host {}", host),
+            Key::Bot(name) => format!("bot {}", name),
+            Key::Host(host) => format!("host {}", host),
+        }
+    }
+
+    pub fn matches(&self, request: &Request) -> bool {
+        match self {
+            Key::Path(path) => request.path == *path,
+            Key::Endpoint(endpoint) => endpoint.split_once(' ') == Some((request.method.as_str(), request.path.as_str())),
+            Key::Ip(ip) => request.ip == *ip,
+            Key::UserAgent(user_agent) => request.user_agent == *user_agent,
+            Key::UserAgentGroup(grouping, name) => grouping.group(&request.user_agent) == name,
+            Key::StaticAssets(assets, bucket) => {
+                let (method, bucket) = match bucket.split_once(" [") {
+                    Some((method, _)) => (Some(method), &bucket[method.len() + 1..]),
+                    None => (None, bucket.as_str()),
+                };
+                method.is_none_or(|method| method == request.method) && assets.bucket(&request.path) == Some(bucket)
+            }
+            Key::Network(network) => request.network.as_deref() == Some(network.as_str()),
+            Key::Vhost(host) => request.vhost.as_ref() == Some(host),
+            Key::Bot(name) => request.bot == Some(name.as_str()),
+            Key::Host(host) => request.host() == Some(host.as_str()),
+        }
+    }
+}
+
+// Everything the retained history says about one key
+#[derive(Debug, Default)]
+pub struct Detail {
+    pub count: usize,
+    pub bytes: usize,
+    pub statuses: Vec<(u16, usize)>,
+    pub methods: Vec<(String, usize)>,
+    pub cache: CacheStats,
+    // Response times, sorted
+    pub response_times: Vec<f64>,
+    // Of the requests with an upstream time, that time and their whole
+    // response time, added up
+    pub upstream_requests: usize,
+    pub upstream_time: f64,
+    pub proxied_time: f64,
+    pub user_agents: Vec<(String, usize)>,
+    pub ips: Vec<(String, usize)>,
+    // Newest first
+    pub recent: Vec<Request>,
+}
+
+impl Detail {
+    // From matching requests, newest first
+    pub fn collect<'a>(requests: impl Iterator<Item = &'a Request>) -> Self {
+        let mut detail = Detail::default();
+        let mut statuses = HashMap::new();
+        let mut methods = HashMap::new();
+        let mut user_agents = HashMap::new();
+        let mut ips = HashMap::new();
+
+        for request in requests {
+            detail.count += 1;
+            detail.bytes += request.bytes_sent;
+            *statuses.entry(request.status_code).or_insert(0) += 1;
+            *methods.entry(request.method.as_str()).or_insert(0) += 1;
+            *user_agents.entry(request.user_agent.as_str()).or_insert(0) += 1;
+            *ips.entry(request.ip.as_str()).or_insert(0) += 1;
+            if let Some(status) = &request.cache_status {
+                detail.cache.record(status, 1);
+            }
+            if request.response_time > 0.0 {
+                detail.response_times.push(request.response_time);
+            }
+            if let Some(upstream_time) = request.upstream_time {
+                detail.upstream_requests += 1;
+                detail.upstream_time += upstream_time;
+                detail.proxied_time += request.response_time;
+            }
+            if detail.recent.len() < RECENT {
+                detail.recent.push(request.clone());
+            }
+        }
+
+        detail.statuses = statuses.into_iter().collect();
+        detail.statuses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        detail.methods = top(methods);
+        detail.user_agents = top(user_agents);
+        detail.ips = top(ips);
+        detail.response_times.sort_by(f64::total_cmp);
+        detail
+    }
+
+    // The response time `fraction` of the timed requests stay under
+    pub fn percentile(&self, fraction: f64) -> Option<f64> {
+        let last = self.response_times.len().checked_sub(1)?;
+        Some(self.response_times[(last as f64 * fraction).round() as usize])
+    }
+
+    pub fn avg_response_time(&self) -> Option<f64> {
+        (!self.response_times.is_empty())
+            .then(|| self.response_times.iter().sum::<f64>() / self.response_times.len() as f64)
+    }
+}
+
+fn top(counts: HashMap<&str, usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = counts.into_iter().map(|(key, count)| (key.to_string(), count)).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
@@ -0,0 +1,84 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+
+use crate::rate::MinuteRate;
+use crate::Request;
+
+// How many recent requests a new one is compared against. Shippers that loop
+// or double-send repeat lines within seconds, well inside this window
+const WINDOW: usize = 4096;
+
+// Spots exact repeats of a request (same timestamp, client, request, status,
+// size and response time), the signature of a log loop or double shipping
+#[derive(Debug, Clone)]
+pub struct DuplicateStats {
+    seen: HashSet<u64>,
+    order: VecDeque<u64>,
+    pub drop: bool,
+    pub total: usize,
+    pub checked: usize,
+    arrivals: MinuteRate,
+    duplicates: MinuteRate,
+}
+
+impl DuplicateStats {
+    pub fn new(now: Instant, drop: bool) -> Self {
+        DuplicateStats {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            drop,
+            total: 0,
+            checked: 0,
+            arrivals: MinuteRate::new(now),
+            duplicates: MinuteRate::new(now),
+        }
+    }
+
+    // Remembers the request and tells whether it was seen just before
+    pub fn check(&mut self, now: Instant, request: &Request) -> bool {
+        let mut hasher = DefaultHasher::new();
+        request.timestamp.hash(&mut hasher);
+        request.ip.hash(&mut hasher);
+        request.method.hash(&mut hasher);
+        request.url().hash(&mut hasher);
+        request.status_code.hash(&mut hasher);
+        request.bytes_sent.hash(&mut hasher);
+        request.response_time.to_bits().hash(&mut hasher);
+        request.referrer.hash(&mut hasher);
+        request.user_agent.hash(&mut hasher);
+        let key = hasher.finish();
+
+        self.checked += 1;
+        self.arrivals.record(now, 1.0);
+        if self.seen.contains(&key) {
+            self.total += 1;
+            self.duplicates.record(now, 1.0);
+            return true;
+        }
+
+        if self.order.len() == WINDOW
+            && let Some(oldest) = self.order.pop_front() {
+            self.seen.remove(&oldest);
+        }
+        self.seen.insert(key);
+        self.order.push_back(key);
+        false
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        self.arrivals.tick(now);
+        self.duplicates.tick(now);
+    }
+
+    // Share of the last minute's arrivals that were duplicates
+    pub fn recent_percent(&self) -> f64 {
+        let arrivals = self.arrivals.rate();
+        if arrivals > 0.0 {
+            self.duplicates.rate() / arrivals * 100.0
+        } else {
+            0.0
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use chrono::SecondsFormat;
+
+use serde::Serialize;
+
+use crate::Request;
+use crate::export::ordered;
+
+// Every request as parsed and filtered, one JSON object per line, for tools
+// further down a pipeline
+pub struct Emitter {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    last_flush: Instant,
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    timestamp: String,
+    ip: &'a str,
+    method: &'a str,
+    path: &'a str,
+    query: Option<&'a str>,
+    protocol: Option<&'a str>,
+    status: u16,
+    bytes_sent: usize,
+    bytes_received: Option<usize>,
+    uncompressed_bytes: Option<usize>,
+    response_time: Option<f64>,
+    upstream_time: Option<f64>,
+    referrer: &'a str,
+    user_agent: &'a str,
+    vhost: Option<&'a str>,
+    tenant: Option<&'a str>,
+    cache_status: Option<&'a str>,
+    country: Option<&'a str>,
+    network: Option<&'a str>,
+    bot: Option<&'a str>,
+    source: Option<&'a str>,
+    #[serde(serialize_with = "ordered")]
+    fields: &'a [(String, String)],
+}
+
+impl Emitter {
+    // "-" for stdout
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let output: Box<dyn Write + Send> = if path == Path::new("-") {
+            Box::new(io::stdout())
+        } else {
+            Box::new(File::create(path)?)
+        };
+        Ok(Emitter { writer: BufWriter::new(output), last_flush: Instant::now() })
+    }
+
+    pub fn write(&mut self, request: &Request) -> io::Result<()> {
+        // JSON has no NaN, which a log's "nan" would parse to
+        let seconds = |value: Option<f64>| value.filter(|seconds| seconds.is_finite());
+        let line = Line {
+            timestamp: request.timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            ip: &request.ip,
+            method: &request.method,
+            path: &request.path,
+            query: request.query.as_deref(),
+            protocol: request.protocol.as_deref(),
+            status: request.status_code,
+            bytes_sent: request.bytes_sent,
+            bytes_received: request.bytes_received,
+            uncompressed_bytes: request.uncompressed_bytes,
+            response_time: seconds(Some(request.response_time)),
+            upstream_time: seconds(request.upstream_time),
+            referrer: &request.referrer,
+            user_agent: &request.user_agent,
+            vhost: request.vhost.as_deref(),
+            tenant: request.tenant.as_deref(),
+            cache_status: request.cache_status.as_deref(),
+            country: request.country.map(|country| country.code),
+            network: request.network.as_deref(),
+            bot: request.bot,
+            source: request.source.as_deref(),
+            fields: &request.fields,
+        };
+        serde_json::to_writer(&mut self.writer, &line)?;
+        writeln!(self.writer)?;
+
+        // Readers such as `tail -f | jq` see lines at least every second
+        if self.last_flush.elapsed() >= Duration::from_secs(1) {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.writer.flush()
+    }
+}
+
+impl Drop for Emitter {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
@@ -0,0 +1,464 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use serde::{Serialize, Serializer};
+
+use crate::Stats;
+use crate::attacks::{self, Signature};
+use crate::compare::{self, Comparison};
+use crate::slo::{self, Slos};
+use crate::histogram::human_bytes;
+use crate::tenants::{TenantEntry, TenantStats};
+
+// Rows of each table in the text report
+const TOP: usize = 10;
+pub const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+const QUANTILES: &[(&str, f64)] = &[("p50", 0.5), ("p90", 0.9), ("p95", 0.95), ("p99", 0.99)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Output {
+    #[default]
+    Text,
+    Json,
+    // A file per table, in --out-dir
+    Csv,
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(Output::Text),
+            "json" => Ok(Output::Json),
+            "csv" => Ok(Output::Csv),
+            _ => Err(format!("unknown output '{}' (text, json, csv)", value)),
+        }
+    }
+}
+
+// The summary --batch prints once the input is read: plain text without
+// colors, for cron mail and CI logs
+pub fn write_text(out: &mut impl Write, stats: &Stats, inputs: &[String], by_endpoint: bool) -> io::Result<()> {
+    writeln!(out, "httop report: {}", inputs.join(", "))?;
+    if let Some((first, last)) = logged_period(stats) {
+        writeln!(out, "Period: {} to {}",
+            first.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            last.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"))?;
+    }
+    writeln!(out, "Requests: {} | Unique IPs: ~{} | Bots: {:.1}%",
+        stats.total_requests, stats.unique_ips.estimate(), stats.bot_percent())?;
+    writeln!(out, "Bandwidth: {} sent, {} per request",
+        human_bytes(stats.bytes_sent), human_bytes(stats.bytes_sent / stats.total_requests.max(1)))?;
+
+    let quantiles: Vec<String> = QUANTILES.iter()
+        .filter_map(|(name, fraction)| Some((*name, stats.response_times.quantile(*fraction)?)))
+        .chain(stats.response_times.max().map(|max| ("max", max)))
+        .map(|(name, seconds)| format!("{} {:.3}s", name, seconds))
+        .collect();
+    if quantiles.is_empty() {
+        writeln!(out, "Response Time: n/a (log format has no request time)")?;
+    } else {
+        writeln!(out, "Response Time: {}", quantiles.join(" "))?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "Status Codes:")?;
+    let total = stats.total_requests.max(1) as f64;
+    for class in 1..=5 {
+        let mut codes: Vec<_> = stats.status_codes.iter().filter(|(code, _)| **code / 100 == class).collect();
+        if codes.is_empty() {
+            continue;
+        }
+        let count: usize = codes.iter().map(|(_, count)| **count).sum();
+        writeln!(out, "  {}xx   {:<9} {:>6.1}%", class, count, count as f64 / total * 100.0)?;
+        codes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (code, count) in codes {
+            writeln!(out, "    {} {:<9} {:>6.1}%", code, count, *count as f64 / total * 100.0)?;
+        }
+    }
+
+    let paths = if by_endpoint { &stats.endpoint_totals } else { &stats.path_totals };
+    write_table(out, if by_endpoint { "Top Endpoints" } else { "Top Paths" }, "PATH", paths, stats.total_requests)?;
+    write_table(out, "Top IPs", "IP", &stats.client_ips, stats.total_requests)?;
+    write_slos(out, &stats.slos)?;
+    write_attacks(out, stats)?;
+    if let Some(comparison) = &stats.comparison {
+        write_comparison(out, comparison)?;
+    }
+    out.flush()
+}
+
+// How each objective stands, the violated and burning first
+fn write_slos(out: &mut impl Write, slos: &Slos) -> io::Result<()> {
+    let standings = slos.standings();
+    if standings.is_empty() {
+        return Ok(());
+    }
+    writeln!(out)?;
+    writeln!(out, "SLOs:")?;
+    writeln!(out, "  {:<10} {:<22} {:<8} {:<10} {:<8} {:<10} {:<9} {:<9} {:<9} ENDPOINT",
+        "STATE", "OBJECTIVE", "WINDOW", "REQUESTS", "BAD", "GOOD", "BUDGET", "BURN 5M", "BURN 1H")?;
+    for (slo, compliance) in standings {
+        writeln!(out, "  {:<10} {:<22} {:<8} {:<10} {:<8} {:<10} {:<9} {:<9} {:<9} {}",
+            compliance.state.name(),
+            compliance.objective,
+            slo::window_name(slo.window),
+            compliance.requests,
+            compliance.bad,
+            compliance.percent().map_or("-".to_string(), |percent| format!("{:.2}%", percent)),
+            format!("{:.0}%", compliance.budget_left * 100.0),
+            slo::burn_text(compliance.burn_rates[0]),
+            slo::burn_text(compliance.burn_rates[1]),
+            slo)?;
+    }
+    Ok(())
+}
+
+// Requests matching attack signatures by kind, and the clients sending
+// the most of them
+fn write_attacks(out: &mut impl Write, stats: &Stats) -> io::Result<()> {
+    let mut guessing: Vec<_> = stats.client_ips.tenants.iter()
+        .filter(|(_, entry)| entry.denied >= attacks::AUTH_FAILURES)
+        .collect();
+    if stats.attacks.total() == 0 && guessing.is_empty() {
+        return Ok(());
+    }
+    let mut clients: Vec<_> = stats.client_ips.tenants.iter().filter(|(_, entry)| entry.attacks > 0).collect();
+    clients.sort_by(|a, b| b.1.attacks.cmp(&a.1.attacks).then(a.0.cmp(b.0)));
+    clients.truncate(TOP);
+    writeln!(out)?;
+    writeln!(out, "Attacks: {} requests from {} clients ({})", stats.attacks.total(), clients.len(), stats.attacks.describe())?;
+    if !clients.is_empty() {
+        let ip_width = clients.iter().map(|(ip, _)| ip.len()).max().unwrap_or(0).max(2);
+        writeln!(out, "  {:<9} {:<9} {:<ip_width$} KINDS", "ATTACKS", "COUNT", "IP")?;
+        for (ip, entry) in clients {
+            let kinds: Vec<&str> = Signature::all_in(entry.attack_kinds).map(Signature::name).collect();
+            writeln!(out, "  {:<9} {:<9} {:<ip_width$} {}", entry.attacks, entry.count, ip, kinds.join(", "))?;
+        }
+    }
+    if !guessing.is_empty() {
+        guessing.sort_by(|a, b| b.1.denied.cmp(&a.1.denied).then(a.0.cmp(b.0)));
+        let clients: Vec<String> = guessing.iter().take(TOP).map(|(ip, entry)| format!("{} ({})", ip, entry.denied)).collect();
+        writeln!(out, "  Credential guessing, {} or more 401/403 responses: {}", attacks::AUTH_FAILURES, clients.join(", "))?;
+    }
+    Ok(())
+}
+
+// Requests per minute and p95 of now and the --compare baseline, overall
+// and for the busiest paths now, with those new and gone among the top
+fn write_comparison(out: &mut impl Write, comparison: &Comparison) -> io::Result<()> {
+    let (before, now) = (&comparison.baseline, &comparison.current);
+    let seconds = |p95: Option<f64>| p95.map_or("n/a".to_string(), |p95| format!("{:.3}s", p95));
+    writeln!(out)?;
+    writeln!(out, "Compared With {} ({}):", comparison.describe(), before.span())?;
+    writeln!(out, "  Now: {}", now.span())?;
+    let (rate_before, rate_now) = (before.per_minute(before.requests), now.per_minute(now.requests));
+    writeln!(out, "  Requests: {:.1}/min, {:.1}/min before ({})",
+        rate_now, rate_before, compare::percent_change(rate_before, rate_now))?;
+    let (p95_before, p95_now) = (before.response_times.quantile(0.95), now.response_times.quantile(0.95));
+    writeln!(out, "  p95: {}, {} before ({})", seconds(p95_now), seconds(p95_before), compare::time_change(p95_before, p95_now))?;
+    writeln!(out, "  5xx: {:.1}%, {:.1}% before", now.error_percent(), before.error_percent())?;
+    let statuses: Vec<String> = comparison.statuses().into_iter()
+        .map(|(code, rate_before, rate_now)| format!("{} {}", code, compare::percent_change(rate_before, rate_now)))
+        .collect();
+    writeln!(out, "  Status Codes: {}", statuses.join(", "))?;
+
+    let paths = comparison.paths();
+    let new: Vec<&str> = paths.iter().take(TOP).filter(|change| change.before == 0.0).map(|change| change.path).collect();
+    if !new.is_empty() {
+        writeln!(out, "  New Top Paths: {}", new.join(", "))?;
+    }
+    // Of the busiest paths before, those with no requests now
+    let mut busiest: Vec<f64> = paths.iter().map(|change| change.before).collect();
+    busiest.sort_by(|a, b| b.total_cmp(a));
+    let least = busiest.get(TOP - 1).or(busiest.last()).copied().unwrap_or(0.0).max(f64::MIN_POSITIVE);
+    let gone: Vec<&str> = paths.iter().filter(|change| change.now == 0.0 && change.before >= least).map(|change| change.path).collect();
+    if !gone.is_empty() {
+        writeln!(out, "  Gone Top Paths: {}", gone.join(", "))?;
+    }
+    writeln!(out, "  {:<11} {:<11} {:<8} {:<11} {:<11} PATH", "BEFORE/MIN", "NOW/MIN", "CHANGE", "P95", "P95 CHANGE")?;
+    for change in paths.iter().take(TOP) {
+        writeln!(out, "  {:<11} {:<11} {:<8} {:<11} {:<11} {}",
+            format!("{:.1}", change.before),
+            format!("{:.1}", change.now),
+            compare::percent_change(change.before, change.now),
+            seconds(change.p95_now),
+            compare::time_change(change.p95_before, change.p95_now),
+            change.path)?;
+    }
+    Ok(())
+}
+
+// Every table in full, for jq or archiving
+pub fn write_json(out: &mut impl Write, stats: &Stats, inputs: &[String]) -> io::Result<()> {
+    let mut status_codes: Vec<(u16, usize)> = stats.status_codes.iter().map(|(code, count)| (*code, *count)).collect();
+    status_codes.sort();
+    let mut methods: Vec<(&str, usize)> = stats.methods.iter().map(|(method, count)| (method.as_str(), *count)).collect();
+    methods.sort();
+    let tables = [
+        ("paths", &stats.path_totals),
+        ("endpoints", &stats.endpoint_totals),
+        ("ips", &stats.client_ips),
+        ("user_agents", &stats.user_agents),
+        ("statuses", &stats.statuses),
+        ("not_found", &stats.not_found),
+        ("server_errors", &stats.server_error_paths),
+        ("vhosts", &stats.vhosts),
+        ("tenants", &stats.tenants),
+        ("services", &stats.services),
+        ("upstreams", &stats.upstreams),
+        ("sources", &stats.sources),
+        ("hosts", &stats.hosts),
+        ("networks", &stats.networks),
+        ("protocols", &stats.protocols),
+        ("bots", &stats.bots),
+    ].into_iter()
+        .map(|(name, groups)| (name, rows(groups)))
+        .collect();
+
+    let snapshot = Snapshot {
+        generated: time(Utc::now()),
+        inputs,
+        period: logged_period(stats).map(|(first, last)| Period { first: time(first), last: time(last) }),
+        requests: stats.total_requests,
+        bytes_sent: stats.bytes_sent,
+        unique_ips: stats.unique_ips.estimate(),
+        bot_percent: number(Some(stats.bot_percent())),
+        response_time: QUANTILES.iter()
+            .map(|(name, fraction)| (*name, number(stats.response_times.quantile(*fraction))))
+            .chain([("max", number(stats.response_times.max()))])
+            .collect(),
+        status_codes,
+        methods,
+        attacks: Signature::ALL.iter()
+            .map(|signature| (signature.name(), stats.attacks.counts[*signature as usize]))
+            .collect(),
+        slos: stats.slos.standings().into_iter()
+            .map(|(slo, compliance)| SloStanding {
+                endpoint: slo.to_string(),
+                window_seconds: slo.window.as_secs(),
+                target: number(Some(compliance.target)),
+                requests: compliance.requests,
+                bad: compliance.bad,
+                compliance: number(compliance.percent()),
+                budget_left: number(Some(compliance.budget_left)),
+                burn_rate_5m: number(compliance.burn_rates[0]),
+                burn_rate_1h: number(compliance.burn_rates[1]),
+                state: compliance.state.name().to_lowercase(),
+                objective: compliance.objective,
+            })
+            .collect(),
+        tables,
+    };
+    serde_json::to_writer(&mut *out, &snapshot)?;
+    writeln!(out)?;
+    out.flush()
+}
+
+// What write_json writes. Stats itself is not serialized: its sketches
+// and eviction state are no use outside httop, and a row's averages and
+// percentiles are worked out from them
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    generated: String,
+    inputs: &'a [String],
+    period: Option<Period>,
+    requests: usize,
+    bytes_sent: usize,
+    unique_ips: usize,
+    bot_percent: Option<f64>,
+    #[serde(serialize_with = "ordered")]
+    response_time: Vec<(&'static str, Option<f64>)>,
+    #[serde(serialize_with = "ordered")]
+    status_codes: Vec<(u16, usize)>,
+    #[serde(serialize_with = "ordered")]
+    methods: Vec<(&'a str, usize)>,
+    #[serde(serialize_with = "ordered")]
+    attacks: Vec<(&'static str, usize)>,
+    slos: Vec<SloStanding>,
+    #[serde(serialize_with = "ordered")]
+    tables: Vec<(&'static str, Vec<Row<'a>>)>,
+}
+
+// Logged times of the first and latest request
+#[derive(Serialize)]
+struct Period {
+    first: String,
+    last: String,
+}
+
+#[derive(Serialize)]
+struct SloStanding {
+    endpoint: String,
+    objective: String,
+    target: Option<f64>,
+    window_seconds: u64,
+    requests: usize,
+    bad: usize,
+    compliance: Option<f64>,
+    budget_left: Option<f64>,
+    burn_rate_5m: Option<f64>,
+    burn_rate_1h: Option<f64>,
+    state: String,
+}
+
+#[derive(Serialize)]
+struct Row<'a> {
+    key: &'a str,
+    count: usize,
+    bytes: usize,
+    client_errors: usize,
+    server_errors: usize,
+    avg_response_time: Option<f64>,
+    p95_response_time: Option<f64>,
+    max_response_time: Option<f64>,
+    unique_ips: usize,
+    cache_hit_ratio: Option<f64>,
+    first_logged: String,
+    last_logged: String,
+}
+
+// A JSON object with its keys in the order given rather than sorted
+pub fn ordered<S: Serializer, K: Serialize, V: Serialize>(pairs: &[(K, V)], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(pairs.iter().map(|(key, value)| (key, value)))
+}
+
+// httop-TIME.json in `dir`, written under another name first so that
+// nothing reads half a snapshot
+pub fn write_snapshot(dir: &Path, stats: &Stats, inputs: &[String]) -> io::Result<()> {
+    let name = format!("httop-{}.json", Local::now().format("%Y%m%d-%H%M%S"));
+    let partial = dir.join(format!(".{}.tmp", name));
+    let mut out = BufWriter::new(File::create(&partial)?);
+    write_json(&mut out, stats, inputs)?;
+    drop(out);
+    fs::rename(&partial, dir.join(name))
+}
+
+// paths.csv (endpoints.csv with --by-endpoint), ips.csv, status_codes.csv
+// and user_agents.csv in `dir`, made if missing; the names of the files
+pub fn write_csv(dir: &Path, stats: &Stats, by_endpoint: bool) -> io::Result<Vec<String>> {
+    fs::create_dir_all(dir)?;
+    let paths = if by_endpoint {
+        ("endpoints.csv", "endpoint", &stats.endpoint_totals)
+    } else {
+        ("paths.csv", "path", &stats.path_totals)
+    };
+    let tables = [
+        paths,
+        ("ips.csv", "ip", &stats.client_ips),
+        ("status_codes.csv", "status", &stats.statuses),
+        ("user_agents.csv", "user_agent", &stats.user_agents),
+    ];
+    let mut files = Vec::new();
+    for (file, key, groups) in tables {
+        let mut out = BufWriter::new(File::create(dir.join(file))?);
+        write_csv_table(&mut out, key, groups, stats.total_requests)?;
+        out.flush()?;
+        files.push(file.to_string());
+    }
+    Ok(files)
+}
+
+fn write_csv_table(out: &mut impl Write, key: &str, groups: &TenantStats, total: usize) -> io::Result<()> {
+    let mut rows: Vec<(&String, &TenantEntry)> = groups.tenants.iter().collect();
+    rows.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+
+    writeln!(out, "{},count,percent,bytes,client_errors,server_errors,avg_response_time,p95_response_time,max_response_time,unique_ips,cache_hit_ratio,first_logged,last_logged", key)?;
+    for (name, entry) in rows {
+        writeln!(out, "{},{},{:.2},{},{},{},{:.6},{:.6},{:.6},{},{},{},{}",
+            csv_field(name),
+            entry.count,
+            entry.count as f64 / total.max(1) as f64 * 100.0,
+            entry.bytes,
+            entry.client_errors,
+            entry.server_errors,
+            entry.avg_response_time(),
+            entry.p95_response_time(),
+            entry.max_response_time,
+            entry.visitors.estimate(),
+            entry.cache_hit_ratio().map_or(String::new(), |ratio| format!("{:.4}", ratio)),
+            entry.first_logged.to_rfc3339_opts(SecondsFormat::Secs, true),
+            entry.last_logged.to_rfc3339_opts(SecondsFormat::Secs, true))?;
+    }
+    Ok(())
+}
+
+// Quoted when it holds a comma, quote or line break, as RFC 4180 has it.
+// A leading = + - or @ would make spreadsheets run it as a formula, but
+// nginx's "-" for an empty user agent is left as it is
+fn csv_field(value: &str) -> String {
+    let formula = value.starts_with(['=', '+', '@']) || (value.starts_with('-') && value != "-");
+    let value = if formula { format!("'{}", value) } else { value.to_string() };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+// Rows most requests first
+fn rows(groups: &TenantStats) -> Vec<Row<'_>> {
+    let mut rows: Vec<(&String, &TenantEntry)> = groups.tenants.iter().collect();
+    rows.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+    rows.into_iter()
+        .map(|(key, entry)| Row {
+            key,
+            count: entry.count,
+            bytes: entry.bytes,
+            client_errors: entry.client_errors,
+            server_errors: entry.server_errors,
+            avg_response_time: number(Some(entry.avg_response_time())),
+            p95_response_time: number(Some(entry.p95_response_time())),
+            max_response_time: number(Some(entry.max_response_time)),
+            unique_ips: entry.visitors.estimate(),
+            cache_hit_ratio: number(entry.cache_hit_ratio()),
+            first_logged: time(entry.first_logged),
+            last_logged: time(entry.last_logged),
+        })
+        .collect()
+}
+
+// To the microsecond; JSON has no NaN or infinity
+fn number(value: Option<f64>) -> Option<f64> {
+    value.filter(|value| value.is_finite()).map(|value| (value * 1e6).round() / 1e6)
+}
+
+fn time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// Logged times of the first and latest request
+fn logged_period(stats: &Stats) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    stats.client_ips.tenants.values()
+        .map(|entry| (entry.first_logged, entry.last_logged))
+        .reduce(|(first, last), (entry_first, entry_last)| (first.min(entry_first), last.max(entry_last)))
+}
+
+fn write_table(out: &mut impl Write, title: &str, key: &str, groups: &TenantStats, total: usize) -> io::Result<()> {
+    let mut rows: Vec<(&String, &TenantEntry)> = groups.tenants.iter().collect();
+    rows.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+
+    writeln!(out)?;
+    if groups.evicted > 0 {
+        writeln!(out, "{} ({} of {}, and {} rarer ones with {} requests evicted over --max-keys or --max-memory):",
+            title, rows.len().min(TOP), rows.len(), groups.evicted, groups.evicted_requests)?;
+    } else {
+        writeln!(out, "{} ({} of {}):", title, rows.len().min(TOP), rows.len())?;
+    }
+    writeln!(out, "  {:<9} {:<7} {:<10} {:<9} {:<9} {}", "COUNT", "%", "BYTES", "ERR%", "P95 RT", key)?;
+    for (name, entry) in rows.into_iter().take(TOP) {
+        writeln!(out, "  {:<9} {:<7} {:<10} {:<9} {:<9} {}",
+            entry.count,
+            format!("{:.1}%", entry.count as f64 / total.max(1) as f64 * 100.0),
+            human_bytes(entry.bytes),
+            format!("{:.1}%", entry.error_percent(entry.client_errors + entry.server_errors)),
+            format!("{:.3}s", entry.p95_response_time()),
+            name)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use std::mem;
+
+use regex::Regex;
+
+use crate::Request;
+use crate::geoip;
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    // First and last code, e.g. 500 to 599 for 5xx
+    Status(u16, u16),
+    // Uppercased
+    Method(String),
+    Path(Regex),
+    // First and last address, as geoip::ip_number has them
+    Ip(u128, u128),
+    UserAgent(Regex),
+}
+
+impl Matcher {
+    fn parse(field: &str, value: &str) -> Result<Vec<Self>, String> {
+        // Status codes, methods and addresses may come as comma lists;
+        // commas in regexes are the regex's own
+        let items = || value.split(',').map(str::trim).filter(|item| !item.is_empty());
+        match field {
+            "status" => items()
+                .map(|item| status_range(item).map(|(first, last)| Matcher::Status(first, last))
+                    .ok_or_else(|| format!("invalid status '{}' (e.g. 404, 5xx or 400-499)", item)))
+                .collect(),
+            "method" => Ok(items().map(|method| Matcher::Method(method.to_ascii_uppercase())).collect()),
+            "ip" => items()
+                .map(|item| ip_range(item).map(|(first, last)| Matcher::Ip(first, last))
+                    .ok_or_else(|| format!("invalid address '{}' (e.g. 10.1.2.3, 10.0.0.0/8 or 2001:db8::/32)", item)))
+                .collect(),
+            "path" | "ua" => {
+                let regex = Regex::new(value).map_err(|e| format!("invalid {} regex '{}': {}", field, value, e))?;
+                Ok(vec![if field == "path" { Matcher::Path(regex) } else { Matcher::UserAgent(regex) }])
+            }
+            _ => Err(format!("unknown filter field '{}'", field)),
+        }
+    }
+
+    fn matches(&self, request: &Request) -> bool {
+        match self {
+            Matcher::Status(first, last) => (*first..=*last).contains(&request.status_code),
+            Matcher::Method(method) => request.method.eq_ignore_ascii_case(method),
+            Matcher::Path(regex) => regex.is_match(&request.path),
+            Matcher::Ip(first, last) => geoip::ip_number(&request.ip).is_some_and(|ip| (*first..=*last).contains(&ip)),
+            Matcher::UserAgent(regex) => regex.is_match(&request.user_agent),
+        }
+    }
+}
+
+// 404, 5xx or 400-499
+fn status_range(value: &str) -> Option<(u16, u16)> {
+    if let Some(class) = value.strip_suffix("xx") {
+        let class: u16 = class.parse().ok().filter(|class| (1..=5).contains(class))?;
+        return Some((class * 100, class * 100 + 99));
+    }
+    let (first, last) = value.split_once('-').unwrap_or((value, value));
+    let (first, last): (u16, u16) = (first.parse().ok()?, last.parse().ok()?);
+    (100..=599).contains(&first).then_some(())?;
+    (first..=599).contains(&last).then_some((first, last))
+}
+
+// An address or a CIDR block
+pub fn ip_range(value: &str) -> Option<(u128, u128)> {
+    let (ip, bits) = match value.split_once('/') {
+        Some((ip, bits)) => (ip, Some(bits.parse::<u32>().ok()?)),
+        None => (value, None),
+    };
+    let width = if ip.contains(':') { 128 } else { 32 };
+    let bits = bits.unwrap_or(width);
+    if bits > width {
+        return None;
+    }
+    let ip = geoip::ip_number(ip)?;
+    // IPv4 addresses are mapped into IPv6, whose prefix the mask keeps
+    let mask = u128::MAX.checked_shl(width - bits).unwrap_or(0);
+    Some((ip & mask, ip | !mask))
+}
+
+#[derive(Debug, Clone)]
+struct Filter {
+    include: bool,
+    field: &'static str,
+    value: String,
+    matchers: Vec<Matcher>,
+}
+
+// --include-FIELD and --exclude-FIELD, applied as requests are read
+#[derive(Debug, Clone, Default)]
+pub struct Filters {
+    filters: Vec<Filter>,
+}
+
+impl Filters {
+    pub fn add(&mut self, option: &str, value: &str) -> Result<(), String> {
+        let (action, field) = option.trim_start_matches("--").split_once('-')
+            .ok_or_else(|| format!("unknown filter {}", option))?;
+        let matchers = Matcher::parse(field, value)?;
+        if matchers.is_empty() {
+            return Err(format!("{} requires a value", option));
+        }
+        let field = match field {
+            "status" => "status",
+            "method" => "methods",
+            "path" => "paths",
+            "ip" => "addresses",
+            _ => "user agents",
+        };
+        self.filters.push(Filter { include: action == "include", field, value: value.to_string(), matchers });
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    // Includes of the same field are alternatives, and a request has to
+    // match one of them for every field that has any
+    pub fn allows(&self, request: &Request) -> bool {
+        let matched = |filter: &Filter| filter.matchers.iter().any(|matcher| matcher.matches(request));
+        let included = self.filters.iter().filter(|filter| filter.include).all(|filter| {
+            let kind = mem::discriminant(&filter.matchers[0]);
+            self.filters.iter()
+                .filter(|other| other.include && mem::discriminant(&other.matchers[0]) == kind)
+                .any(matched)
+        });
+        included && !self.filters.iter().any(|filter| !filter.include && matched(filter))
+    }
+
+    // E.g. "status 5xx" or "paths except \.css$", for the help screen
+    pub fn describe(&self) -> Vec<String> {
+        self.filters.iter()
+            .map(|filter| format!("{}{} {}", filter.field, if filter.include { "" } else { " except" }, filter.value))
+            .collect()
+    }
+}
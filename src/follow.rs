@@ -0,0 +1,136 @@
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Reads a file from the start and then keeps following it like `tail -F`:
+// when logrotate renames it away a new file at the same path is picked up,
+// and when it is truncated in place reading restarts from the top. Reads
+// block until there is more data, so the stream never ends
+pub struct Follow {
+    path: PathBuf,
+    file: File,
+    inode: u64,
+    position: u64,
+}
+
+impl Follow {
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        let file = File::open(&path)?;
+        let inode = inode(&file.metadata()?);
+        Ok(Follow { path, file, inode, position: 0 })
+    }
+
+    // Switches to a new file at the path or rewinds a truncated one; false
+    // while nothing changed
+    fn check_rotation(&mut self) -> io::Result<bool> {
+        // Between the rename and the new file being created the path is missing
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return Ok(false);
+        };
+
+        if inode(&metadata) != self.inode {
+            // Lines written to the old file just before the rename were
+            // already read, since we only get here at its end
+            self.file = File::open(&self.path)?;
+            self.inode = inode(&self.file.metadata()?);
+            self.position = 0;
+            return Ok(true);
+        }
+        if metadata.len() < self.position {
+            self.file.seek(SeekFrom::Start(0))?;
+            self.position = 0;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> u64 {
+    metadata.ino()
+}
+
+// Without inodes a rotation is only noticed as the file being shorter
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> u64 {
+    0
+}
+
+impl Read for Follow {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let read = self.file.read(buf)?;
+            if read > 0 {
+                self.position += read as u64;
+                return Ok(read);
+            }
+            if !self.check_rotation()? {
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+// Expands `*` and `?` in the file name part of a path, for patterns the shell
+// did not expand (quoted, or from a unit file)
+pub fn expand(pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let path = PathBuf::from(pattern);
+    let Some(name) = path.file_name().and_then(|name| name.to_str()).filter(|name| name.contains(['*', '?'])) else {
+        return Ok(vec![path]);
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    if dir.to_string_lossy().contains(['*', '?']) {
+        return Err(format!("wildcards are only supported in the file name: {}", pattern));
+    }
+
+    let entries = fs::read_dir(&dir).map_err(|e| format!("could not read {}: {}", dir.display(), e))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_str().is_some_and(|file| wildcard_match(name, file)))
+        .map(|entry| dir.join(entry.file_name()))
+        .collect();
+    if paths.is_empty() {
+        return Err(format!("no files match {}", pattern));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // Position after the last `*` and the name position it is matched up to
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, matched)) => {
+                    p = after;
+                    n = matched + 1;
+                    star = Some((after, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
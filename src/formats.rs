@@ -0,0 +1,717 @@
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use chrono::{DateTime, TimeZone, Utc};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::logfmt;
+use crate::pattern::Pattern;
+use crate::scan::{self, Scanner};
+use crate::template::Template;
+use crate::w3c::W3c;
+use crate::Request;
+use crate::parser::{parse_fields, parse_log_line};
+
+// %h %l %u %t "%r" %>s %b
+static APACHE_COMMON: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(\S+) \S+ \S+ \[([^\]]+)\] "((?:[^"\\]|\\.)*)" (\d{3}) (\d+|-)(.*)$"#).unwrap()
+});
+// ... "%{Referer}i" "%{User-agent}i"
+static APACHE_COMBINED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(\S+) \S+ \S+ \[([^\]]+)\] "((?:[^"\\]|\\.)*)" (\d{3}) (\d+|-) "((?:[^"\\]|\\.)*)" "((?:[^"\\]|\\.)*)"(.*)$"#).unwrap()
+});
+
+// Combined Log Format followed by Traefik's request count, "router"
+// "server URL" and duration, e.g. ... 42 "web@docker" "http://10.0.0.3:80" 3ms
+static TRAEFIK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(\S+) \S+ \S+ \[([^\]]+)\] "((?:[^"\\]|\\.)*)" (\d{3}) (\d+|-) "((?:[^"\\]|\\.)*)" "((?:[^"\\]|\\.)*)" \d+ "([^"]*)" "([^"]*)" (\d+)ms(.*)$"#).unwrap()
+});
+
+// Envoy's default format; versions since 1.18 add the response code details,
+// connection termination details and "upstream transport failure reason"
+static ENVOY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^\[([^\]]+)\] "(\S+) (\S+) ([^"]*)" (\d{3}) (\S+)(?: \S+ \S+ "[^"]*")? (\d+) (\d+) (\d+) (\S+) "([^"]*)" "([^"]*)" "([^"]*)" "([^"]*)" "([^"]*)"(.*)$"#).unwrap()
+});
+
+// Header added by syslog relays: "Jan 12 10:00:01 host nginx: " (BSD,
+// journalctl), "2026-01-12T10:00:01.123+00:00 host nginx[42]: " (rsyslog with
+// RFC 3339 dates), or "<190>1 2026-01-12T10:00:01Z host nginx 42 - - " (RFC 5424).
+// The host is captured as group 1 or 2
+static SYSLOG_PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^(?:<\d{1,3}>)?",
+        r"(?:(?:[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}|\d{4}-\d{2}-\d{2}T\S+) (\S+) [^\s:\[]+(?:\[\d+\])?: ",
+        r"|1 \S+ (\S+) \S+ \S+ \S+ (?:-|(?:\[(?:[^\]\\]|\\.)*\])+) ?)",
+    )).unwrap()
+});
+
+// The field naming the machine that logged a request, taken from the
+// syslog header when the line has none of its own
+pub const HOSTNAME_FIELD: &str = "hostname";
+
+// Keys tried for each request field in a JSON log line, covering nginx
+// `escape=json` formats and Envoy's default JSON access log
+const JSON_KEYS: &[(&str, &[&str])] = &[
+    ("ip", &["remote_addr", "client_ip", "downstream_remote_address", "ip"]),
+    ("time", &["time_local", "time_iso8601", "start_time", "timestamp", "time", "@timestamp"]),
+    ("request", &["request"]),
+    ("method", &["request_method", "method"]),
+    ("path", &["request_uri", "uri", "path"]),
+    ("status", &["status", "response_code", "status_code"]),
+    ("bytes", &["body_bytes_sent", "bytes_sent", "bytes"]),
+    ("request_time", &["request_time", "response_time"]),
+    ("referrer", &["http_referer", "referer", "referrer"]),
+    ("user_agent", &["http_user_agent", "user_agent"]),
+    ("protocol", &["server_protocol", "protocol"]),
+];
+
+// Caddy v2's http.log.access entries
+const CADDY_KEYS: &[(&str, &[&str])] = &[
+    ("ip", &["request.client_ip", "request.remote_ip"]),
+    ("time", &["ts"]),
+    ("method", &["request.method"]),
+    ("path", &["request.uri"]),
+    ("status", &["status"]),
+    ("bytes", &["size"]),
+    ("request_time", &["duration"]),
+    ("referrer", &["request.headers.Referer"]),
+    ("user_agent", &["request.headers.User-Agent"]),
+    ("protocol", &["request.proto"]),
+];
+
+// Cloud Logging entries from Google Cloud HTTP(S) load balancers
+const GCP_KEYS: &[(&str, &[&str])] = &[
+    ("ip", &["httpRequest.remoteIp"]),
+    ("time", &["httpRequest.requestTimestamp", "timestamp"]),
+    ("method", &["httpRequest.requestMethod"]),
+    ("path", &["httpRequest.requestUrl"]),
+    ("status", &["httpRequest.status"]),
+    ("bytes", &["httpRequest.responseSize"]),
+    ("request_time", &["httpRequest.latency"]),
+    ("referrer", &["httpRequest.referer"]),
+    ("user_agent", &["httpRequest.userAgent"]),
+    ("protocol", &["httpRequest.protocol"]),
+];
+
+// The request fields a JSON or logfmt line can be mapped onto
+const MAPPED_FIELDS: &[&str] = &[
+    "ip", "time", "request", "method", "path", "status", "bytes", "request_time", "referrer", "user_agent",
+    "protocol",
+];
+
+// Which keys hold which request field; the first key present wins. FIELD=KEY
+// mappings (--map) replace the defaults for that field
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldMap {
+    defaults: &'static [(&'static str, &'static [&'static str])],
+    overrides: Vec<(String, String)>,
+}
+
+impl FieldMap {
+    pub fn new(defaults: &'static [(&'static str, &'static [&'static str])]) -> Self {
+        FieldMap { defaults, overrides: Vec::new() }
+    }
+
+    pub fn set(&mut self, mapping: &str) -> Result<(), String> {
+        let (field, key) = mapping.split_once('=')
+            .filter(|(_, key)| !key.is_empty())
+            .ok_or_else(|| format!("invalid field mapping '{}' (expected FIELD=KEY)", mapping))?;
+        if !MAPPED_FIELDS.contains(&field) {
+            return Err(format!("unknown field '{}' (expected one of {})", field, MAPPED_FIELDS.join(", ")));
+        }
+        self.overrides.retain(|(name, _)| name != field);
+        self.overrides.push((field.to_string(), key.to_string()));
+        Ok(())
+    }
+
+    fn keys(&self, field: &str) -> Vec<&str> {
+        if let Some((_, key)) = self.overrides.iter().find(|(name, _)| name == field) {
+            return vec![key.as_str()];
+        }
+        self.defaults.iter()
+            .find(|(name, _)| *name == field)
+            .map_or_else(Vec::new, |(_, keys)| keys.to_vec())
+    }
+
+    // Builds a request from `text`, which gives the value of a key (if set)
+    pub fn request(&self, text: impl Fn(&str) -> Option<String>) -> Option<Request> {
+        let value = |field: &str| self.keys(field).into_iter().find_map(&text);
+
+        let (method, path, protocol) = match value("request") {
+            Some(request) => {
+                let mut parts = request.split(' ');
+                (parts.next()?.to_string(), parts.next()?.to_string(), parts.next().and_then(http_version))
+            }
+            None => (value("method")?, url_path(&value("path")?).to_string(), None),
+        };
+        let timestamp = match value("time") {
+            Some(time) => parse_timestamp(&time)?,
+            None => Utc::now(),
+        };
+        let status_code = value("status")?.parse().ok()?;
+        let bytes_sent = value("bytes").map_or(Some(0), |bytes| bytes.parse().ok())?;
+        let mut request = Request::new(
+            timestamp,
+            &value("ip").unwrap_or_else(|| "-".to_string()),
+            &method,
+            &path,
+            status_code,
+            bytes_sent,
+        );
+        request.protocol = protocol.or_else(|| value("protocol").and_then(|protocol| http_version(&protocol)));
+        request.response_time = value("request_time")
+            .and_then(|time| duration_seconds(&time))
+            .unwrap_or(0.0);
+        if let Some(referrer) = value("referrer") {
+            request.referrer = referrer;
+        }
+        if let Some(user_agent) = value("user_agent") {
+            request.user_agent = user_agent;
+        }
+        Some(request)
+    }
+}
+
+// JSON keys, which may name a nested member as a.b
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonFields {
+    pub map: FieldMap,
+    // An object pretty-printed over several lines, as gcloud does
+    pending: String,
+}
+
+impl Default for JsonFields {
+    fn default() -> Self {
+        JsonFields::new(JSON_KEYS)
+    }
+}
+
+impl JsonFields {
+    pub fn caddy() -> Self {
+        JsonFields::new(CADDY_KEYS)
+    }
+
+    pub fn gcp() -> Self {
+        JsonFields::new(GCP_KEYS)
+    }
+
+    fn new(keys: &'static [(&'static str, &'static [&'static str])]) -> Self {
+        JsonFields { map: FieldMap::new(keys), pending: String::new() }
+    }
+
+    fn text(object: &Value, key: &str) -> Option<String> {
+        let mut value = key.split('.').try_fold(object, |value, name| value.get(name))?;
+        // Headers are logged as lists of values, as Caddy does
+        if let Value::Array(items) = value {
+            value = items.first()?;
+        }
+        // nginx writes "" or "-" for variables that are not set
+        match value {
+            Value::String(text) if !text.is_empty() && text != "-" => Some(text.clone()),
+            Value::Number(number) => Some(number.to_string()),
+            _ => None,
+        }
+    }
+
+    // Joins lines until the object is complete, skipping the brackets and
+    // commas of a pretty-printed list of objects
+    fn complete(&mut self, line: &str) -> Option<String> {
+        let trimmed = line.trim();
+        if self.pending.is_empty() {
+            if matches!(trimmed, "[" | "]" | "[]") {
+                return None;
+            }
+            if !trimmed.starts_with('{') || depth(trimmed) == 0 {
+                return Some(trimmed.trim_end_matches(',').to_string());
+            }
+        }
+
+        self.pending.push_str(trimmed);
+        self.pending.push('\n');
+        if depth(&self.pending) > 0 {
+            // A stray '{' must not swallow the rest of the stream
+            if self.pending.len() > MAX_PENDING {
+                self.pending.clear();
+            }
+            return None;
+        }
+        let object = std::mem::take(&mut self.pending);
+        Some(object.trim_end().trim_end_matches(',').to_string())
+    }
+
+    fn parse(&mut self, line: &str) -> Option<Request> {
+        let line = self.complete(line)?;
+        let object: Value = serde_json::from_str(&line).ok()?;
+        let Value::Object(members) = &object else {
+            return None;
+        };
+
+        let request = self.map.request(|key| JsonFields::text(&object, key))?;
+
+        // Every other top-level string or number is a custom field (tenant,
+        // request_length, ssl_protocol, ...)
+        let fields = members.iter()
+            .filter_map(|(key, value)| match value {
+                Value::String(text) => Some((key.clone(), text.clone())),
+                Value::Number(number) => Some((key.clone(), number.to_string())),
+                _ => None,
+            })
+            .collect();
+        Some(request.with_fields(fields))
+    }
+}
+
+const MAX_PENDING: usize = 1 << 20;
+
+// Unclosed braces and brackets, outside of strings
+fn depth(json: &str) -> i32 {
+    let (mut depth, mut in_string, mut escaped) = (0, false, false);
+    for c in json.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '{' | '[' if !in_string => depth += 1,
+            '}' | ']' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+// Some logs carry the full URL (GCP's requestUrl); only the path is kept
+fn url_path(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |start| &rest[start..]),
+        None => url,
+    }
+}
+
+// nginx $server_protocol as a custom field
+pub const PROTOCOL_FIELDS: &[&str] = &["server_protocol", "protocol"];
+
+// HTTP/1.1 as it is, HTTP/2.0 and HTTP/3.0 (CloudFront, Caddy) as HTTP/2 and
+// HTTP/3; None for anything else, such as Heroku's protocol=https
+pub fn http_version(text: &str) -> Option<String> {
+    let version = text.trim().to_ascii_uppercase();
+    let version = version.strip_prefix("HTTP/")?;
+    match version.strip_suffix(".0") {
+        Some(major @ ("2" | "3")) => Some(format!("HTTP/{}", major)),
+        _ if !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit() || b == b'.') => Some(format!("HTTP/{}", version)),
+        _ => None,
+    }
+}
+
+// Seconds, or a duration string such as "0.023s" (GCP), "12ms" or Go's
+// "350µs"
+pub fn duration_seconds(text: &str) -> Option<f64> {
+    for (unit, scale) in [("ms", 1e-3), ("µs", 1e-6), ("us", 1e-6), ("ns", 1e-9)] {
+        if let Some(value) = text.strip_suffix(unit) {
+            return value.parse::<f64>().ok().map(|value| value * scale);
+        }
+    }
+    text.strip_suffix('s').unwrap_or(text).parse().ok()
+}
+
+// $time_local, ISO 8601 ($time_iso8601, Envoy's START_TIME) or epoch seconds
+fn parse_timestamp(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(seconds) = text.parse::<f64>() {
+        return Utc.timestamp_millis_opt((seconds * 1000.0) as i64).single();
+    }
+    DateTime::parse_from_str(text, "%d/%b/%Y:%H:%M:%S %z")
+        .or_else(|_| DateTime::parse_from_rfc3339(text))
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// Fields naming the service or router that handled a request
+pub const SERVICE_FIELDS: &[&str] = &["service", "router"];
+// Fields naming the backend a proxy sent the request to
+pub const UPSTREAM_FIELDS: &[&str] = &["upstream", "upstream_host", "upstream_addr", "dyno"];
+// Fields with the time the backend took, in seconds as nginx logs
+// $upstream_response_time
+pub const UPSTREAM_TIME_FIELDS: &[&str] = &["upstream_response_time", "upstream_time", "urt"];
+
+// The backend's part of the response time. nginx logs a time for each
+// upstream tried, "0.012, 0.250", and for each internal redirect,
+// "0.012 : 0.250", which add up; Envoy's upstream_service_time is in
+// milliseconds
+pub fn upstream_time(fields: &[(String, String)]) -> Option<f64> {
+    let field = |name: &str| fields.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+    if let Some(millis) = field("upstream_service_time").and_then(|millis| millis.parse::<f64>().ok()) {
+        return Some(millis / 1000.0);
+    }
+    let times: Vec<f64> = UPSTREAM_TIME_FIELDS.iter()
+        .find_map(|name| field(name))?
+        .split([',', ':'])
+        .filter_map(|time| time.trim().parse().ok())
+        .collect();
+    (!times.is_empty()).then(|| times.iter().sum())
+}
+
+// Fields carrying the requested host: nginx's $host, Apache's %v, the Host
+// header CloudFront was sent (x-host-header) rather than its own domain
+pub const HOST_FIELDS: &[&str] = &["host", "http_host", "server_name", "vhost", "x_host_header", "cs_host"];
+
+// The virtual host a request was for, lowercased and without a port, so that
+// Example.com:443 and example.com count together
+pub fn vhost(fields: &[(String, String)]) -> Option<String> {
+    let host = HOST_FIELDS.iter()
+        .find_map(|name| fields.iter().find(|(key, _)| key == name))
+        .map(|(_, value)| value.as_str())
+        .filter(|host| !host.is_empty() && *host != "-")?;
+    let host = match host.rsplit_once(':') {
+        // Not the colons of a bare IPv6 address
+        Some((name, port)) if !name.contains(':') && port.bytes().all(|byte| byte.is_ascii_digit()) => name,
+        _ => host,
+    };
+    Some(host.to_ascii_lowercase())
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum LogFormat {
+    #[default]
+    Nginx,
+    ApacheCommon,
+    ApacheCombined,
+    Traefik,
+    Envoy,
+    Heroku,
+    Logfmt(FieldMap),
+    Json(JsonFields),
+    // --log-format
+    Custom(Template),
+    // --regex
+    Regex(Pattern),
+    // Field positions change with each #Fields: header
+    W3c(W3c),
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "nginx" => Ok(LogFormat::Nginx),
+            "apache-common" | "common" => Ok(LogFormat::ApacheCommon),
+            "apache-combined" | "combined" => Ok(LogFormat::ApacheCombined),
+            "traefik" => Ok(LogFormat::Traefik),
+            "envoy" => Ok(LogFormat::Envoy),
+            "heroku" => Ok(LogFormat::Heroku),
+            "logfmt" => Ok(LogFormat::Logfmt(FieldMap::new(logfmt::DEFAULT_KEYS))),
+            "json" => Ok(LogFormat::Json(JsonFields::default())),
+            "caddy" => Ok(LogFormat::Json(JsonFields::caddy())),
+            "gcp" => Ok(LogFormat::Json(JsonFields::gcp())),
+            "w3c" | "cloudfront" | "iis" => Ok(LogFormat::W3c(W3c::default())),
+            _ => Err(format!("unknown log format '{}' (expected nginx, apache-common, apache-combined, traefik, envoy, heroku, logfmt, json, caddy, gcp or w3c)", name)),
+        }
+    }
+}
+
+impl LogFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogFormat::Nginx => "nginx",
+            LogFormat::ApacheCommon => "apache-common",
+            LogFormat::ApacheCombined => "apache-combined",
+            LogFormat::Traefik => "traefik",
+            LogFormat::Envoy => "envoy",
+            LogFormat::Heroku => "heroku",
+            LogFormat::Logfmt(_) => "logfmt",
+            LogFormat::Json(_) => "json",
+            LogFormat::Custom(_) => "custom (--log-format)",
+            LogFormat::Regex(_) => "custom (--regex)",
+            LogFormat::W3c(_) => "w3c",
+        }
+    }
+
+    // Whether a line `parse` just made no request of is one the format has
+    // between requests: a blank line, a W3C directive, or a bracket or part
+    // of a JSON object pretty-printed over several lines
+    pub fn skipped(&self, line: &str) -> bool {
+        let line = line.trim();
+        line.is_empty() || match self {
+            LogFormat::W3c(_) => line.starts_with('#'),
+            LogFormat::Json(fields) => !fields.pending.is_empty() || matches!(line, "[" | "]" | "[]"),
+            _ => false,
+        }
+    }
+
+    // The key mapping --map adjusts, for formats that have one
+    pub fn field_map(&mut self) -> Option<&mut FieldMap> {
+        match self {
+            LogFormat::Logfmt(map) => Some(map),
+            LogFormat::Json(fields) => Some(&mut fields.map),
+            _ => None,
+        }
+    }
+
+    pub fn parse(&mut self, line: &str) -> Option<Request> {
+        let unwrapped = docker_log(line);
+        let line = unwrapped.as_deref().unwrap_or(line);
+        // No access log format starts like a syslog header, so it is always safe to drop
+        let (line, hostname) = match SYSLOG_PREFIX.find(line) {
+            Some(prefix) => (&line[prefix.end()..], syslog_host(prefix.as_str())),
+            None => (line, None),
+        };
+
+        let mut request = match self {
+            LogFormat::Nginx => parse_log_line(line),
+            LogFormat::ApacheCommon => {
+                let line = split_apache(line, false)
+                    .or_else(|| Some(ApacheLine::from_captures(&APACHE_COMMON.captures(line)?, false)))?;
+                let request = apache_request(line.ip, line.time, line.request, line.status, line.bytes)?;
+                Some(request.with_fields(parse_fields(line.rest)))
+            }
+            LogFormat::ApacheCombined => {
+                let line = split_apache(line, true)
+                    .or_else(|| Some(ApacheLine::from_captures(&APACHE_COMBINED.captures(line)?, true)))?;
+                let mut request = apache_request(line.ip, line.time, line.request, line.status, line.bytes)?;
+                request.referrer = unescape(line.referrer);
+                request.user_agent = unescape(line.user_agent);
+                Some(request.with_fields(parse_fields(line.rest)))
+            }
+            LogFormat::Traefik => {
+                let caps = TRAEFIK.captures(line)?;
+                let mut request = apache_request(&caps[1], &caps[2], &caps[3], &caps[4], &caps[5])?;
+                request.referrer = unescape(&caps[6]);
+                request.user_agent = unescape(&caps[7]);
+                request.response_time = caps[10].parse::<f64>().ok()? / 1_000.0;
+                let mut fields = parse_fields(&caps[11]);
+                for (name, value) in [("router", &caps[8]), ("upstream", &caps[9])] {
+                    if !value.is_empty() && value != "-" {
+                        fields.push((name.to_string(), value.to_string()));
+                    }
+                }
+                Some(request.with_fields(fields))
+            }
+            LogFormat::Envoy => parse_envoy(line),
+            LogFormat::Heroku => logfmt::parse_heroku(line),
+            LogFormat::Logfmt(map) => logfmt::parse(map, line),
+            LogFormat::Json(fields) => fields.parse(line),
+            LogFormat::Custom(template) => template.parse(line),
+            LogFormat::Regex(pattern) => pattern.parse(line),
+            LogFormat::W3c(w3c) => w3c.parse(line),
+        }?;
+        if let Some(hostname) = hostname
+            && !request.fields.iter().any(|(key, _)| key == HOSTNAME_FIELD) {
+            request.fields.push((HOSTNAME_FIELD.to_string(), hostname.to_string()));
+        }
+        Some(request)
+    }
+}
+
+// The host a syslog header names, unless it is RFC 5424's "-"
+fn syslog_host(prefix: &str) -> Option<&str> {
+    let captures = SYSLOG_PREFIX.captures(prefix)?;
+    let host = captures.get(1).or_else(|| captures.get(2))?.as_str();
+    (host != "-").then_some(host)
+}
+
+// Docker's json-file driver wraps each line as
+// {"log":"...\n","stream":"stdout","time":"..."}
+fn docker_log(line: &str) -> Option<String> {
+    if !line.starts_with("{\"log\":") {
+        return None;
+    }
+    let envelope: DockerLine = serde_json::from_str(line).ok()?;
+    Some(envelope.log.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[derive(Deserialize)]
+struct DockerLine {
+    log: String,
+}
+
+fn parse_envoy(line: &str) -> Option<Request> {
+    let caps = ENVOY.captures(line)?;
+    let timestamp = DateTime::parse_from_rfc3339(&caps[1]).ok()?.with_timezone(&Utc);
+
+    // The client is the first X-Forwarded-For hop
+    let ip = caps[11].split(',').next().map(str::trim).filter(|ip| !ip.is_empty()).unwrap_or("-");
+    let mut request = Request::new(timestamp, ip, &caps[2], &caps[3], caps[5].parse().ok()?, caps[8].parse().ok()?);
+    request.protocol = http_version(&caps[4]);
+    request.response_time = caps[9].parse::<f64>().ok()? / 1_000.0;
+    request.user_agent = caps[12].to_string();
+
+    let mut fields = parse_fields(&caps[16]);
+    for (name, value) in [
+        ("response_flags", &caps[6]),
+        ("bytes_received", &caps[7]),
+        ("upstream_service_time", &caps[10]),
+        ("request_id", &caps[13]),
+        ("authority", &caps[14]),
+        ("upstream", &caps[15]),
+    ] {
+        if !value.is_empty() && value != "-" {
+            fields.push((name.to_string(), value.to_string()));
+        }
+    }
+    Some(request.with_fields(fields))
+}
+
+// The parts of an Apache common or combined line, as their regexes
+// capture them; the referrer and user agent stay empty for common
+#[derive(Debug, PartialEq)]
+struct ApacheLine<'a> {
+    ip: &'a str,
+    time: &'a str,
+    request: &'a str,
+    status: &'a str,
+    bytes: &'a str,
+    referrer: &'a str,
+    user_agent: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> ApacheLine<'a> {
+    fn from_captures(caps: &regex::Captures<'a>, combined: bool) -> Self {
+        let group = |i: usize| caps.get(i).map_or("", |m| m.as_str());
+        ApacheLine {
+            ip: group(1),
+            time: group(2),
+            request: group(3),
+            status: group(4),
+            bytes: group(5),
+            referrer: if combined { group(6) } else { "" },
+            user_agent: if combined { group(7) } else { "" },
+            rest: group(if combined { 8 } else { 6 }),
+        }
+    }
+}
+
+// The same parts without the regex, for lines just as Apache writes them,
+// escaping what is beyond ASCII; others are left to the regex
+fn split_apache(line: &str, combined: bool) -> Option<ApacheLine<'_>> {
+    if !line.is_ascii() {
+        return None;
+    }
+    let mut scanner = Scanner::new(line);
+    let ip = scanner.token()?;
+    scanner.expect(" ")?;
+    scanner.token()?;
+    scanner.expect(" ")?;
+    scanner.token()?;
+    scanner.expect(" [")?;
+    let time = scanner.until(']').filter(|time| !time.is_empty())?;
+    scanner.expect(" ")?;
+    let request = scanner.quoted()?;
+    scanner.expect(" ")?;
+    let status = scanner.digits().filter(|status| status.len() == 3)?;
+    scanner.expect(" ")?;
+    let bytes = match scanner.digits() {
+        Some(bytes) => bytes,
+        None => scanner.expect("-").map(|_| "-")?,
+    };
+    let (mut referrer, mut user_agent) = ("", "");
+    if combined {
+        scanner.expect(" ")?;
+        referrer = scanner.quoted()?;
+        scanner.expect(" ")?;
+        user_agent = scanner.quoted()?;
+    }
+    // (.*)$ fails on a line break
+    let rest = scanner.rest();
+    if rest.contains('\n') {
+        return None;
+    }
+    Some(ApacheLine { ip, time, request, status, bytes, referrer, user_agent, rest })
+}
+
+fn apache_request(ip: &str, time: &str, request_line: &str, status: &str, bytes: &str) -> Option<Request> {
+    let timestamp = match scan::clf_time(time) {
+        Some(timestamp) => timestamp,
+        None => DateTime::parse_from_str(time, "%d/%b/%Y:%H:%M:%S %z").ok()?.with_timezone(&Utc),
+    };
+
+    // "-" when the client closed before sending a request; nothing to count
+    let request_line = unescape(request_line);
+    let mut parts = request_line.split(' ');
+    let method = parts.next().filter(|method| !method.is_empty() && *method != "-")?;
+    let path = parts.next()?;
+
+    // %b logs "-" rather than 0 for an empty body
+    let bytes_sent = if bytes == "-" { 0 } else { bytes.parse().ok()? };
+    let mut request = Request::new(timestamp, ip, method, path, status.parse().ok()?, bytes_sent);
+    request.protocol = parts.next().and_then(http_version);
+    Some(request)
+}
+
+// Apache escapes quotes, backslashes and control characters inside quoted fields
+fn unescape(value: &str) -> String {
+    if !value.contains('\\') {
+        return value.to_string();
+    }
+
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whatever the splitter takes has to come out as the regex has it
+    #[test]
+    fn apache_split() {
+        let lines = [
+            r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326 "http://www.example.com/start.html" "Mozilla/4.08 [en] (Win98; I ;Nav)""#,
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET /a HTTP/1.1" 304 - "-" "say \"hi\" \\o/" 1234"#,
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "-" 408 - "-" "-""#,
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.1" 200 12"#,
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.1" 2000 12 "-" "-""#,
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.1" 200 12 "-" "Mözilla""#,
+        ];
+        for line in lines {
+            for (combined, regex) in [(false, &APACHE_COMMON), (true, &APACHE_COMBINED)] {
+                if let Some(split) = split_apache(line, combined) {
+                    assert_eq!(Some(split), regex.captures(line).map(|caps| ApacheLine::from_captures(&caps, combined)), "{}", line);
+                }
+            }
+        }
+        assert!(lines[..4].iter().all(|line| split_apache(line, false).is_some()));
+        assert!(lines[..3].iter().all(|line| split_apache(line, true).is_some()));
+        assert!(split_apache(lines[5], true).is_none());
+
+        let mut format = LogFormat::ApacheCombined;
+        assert_eq!(format.parse(lines[1]).unwrap().user_agent, r#"say "hi" \o/"#);
+        assert_eq!(format.parse(lines[5]).unwrap().user_agent, "Mözilla");
+    }
+
+    // Lines between requests are not taken for lines the format missed
+    #[test]
+    fn skipped() {
+        let mut format: LogFormat = "json".parse().unwrap();
+        for line in ["[", "{", r#"  "remote_addr": "10.1.1.1","#, ""] {
+            assert!(format.parse(line).is_none());
+            assert!(format.skipped(line), "{}", line);
+        }
+        for line in [r#"  "status": "200", "request": "GET / HTTP/1.1""#, "},"] {
+            format.parse(line);
+        }
+        assert!(format.parse("]").is_none());
+        assert!(format.skipped("]"));
+        assert!(format.parse("remote_addr=10.1.1.1").is_none());
+        assert!(!format.skipped("remote_addr=10.1.1.1"));
+
+        let mut format: LogFormat = "w3c".parse().unwrap();
+        assert!(format.parse("#Version: 1.0").is_none());
+        assert!(format.skipped("#Version: 1.0"));
+        assert!(!LogFormat::Nginx.skipped("#Version: 1.0"));
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::filters;
+use crate::geoip;
+use crate::parser;
+
+// Fields that may carry the address a proxy or CDN received the request
+// from, as the log formats name them; the first one a request has is used
+const FIELDS: &[&str] = &[
+    "x_forwarded_for", "http_x_forwarded_for", "forwarded_for", "xff", "x_real_ip", "http_x_real_ip", "real_ip",
+    "true_client_ip", "http_true_client_ip", "cf_connecting_ip", "http_cf_connecting_ip",
+];
+
+// --real-ip: the client address taken from X-Forwarded-For or a real-IP
+// field rather than the address of the proxy that connected
+#[derive(Debug, Clone, Default)]
+pub struct RealIp {
+    // The field with the address or chain, or None for the first of FIELDS
+    pub field: Option<String>,
+    // The proxies' and CDN edges' addresses, first and last of each block
+    trusted: Vec<(u128, u128)>,
+}
+
+impl RealIp {
+    // Addresses or CIDR blocks, alone or in a comma list
+    pub fn trust(&mut self, value: &str) -> Result<(), String> {
+        for item in value.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let range = filters::ip_range(item)
+                .ok_or_else(|| format!("invalid --trusted-proxy '{}' (e.g. 10.0.0.0/8 or 2400:cb00::/32)", item))?;
+            self.trusted.push(range);
+        }
+        Ok(())
+    }
+
+    // Without trusted proxies, the first address of the chain, which the
+    // client added itself. With them, the chain is read back from the
+    // connecting address to the first one not of a trusted proxy, so a
+    // client cannot pass itself off as another by sending the header
+    pub fn client(&self, ip: &str, fields: &[(String, String)]) -> Option<String> {
+        let value = match &self.field {
+            Some(name) => fields.iter().find(|(key, _)| key == name),
+            None => FIELDS.iter().find_map(|name| fields.iter().find(|(key, _)| key == name)),
+        }.map(|(_, value)| value.as_str())?;
+        let chain: Vec<IpAddr> = value.split(',').filter_map(parser::client_ip).collect();
+        if self.trusted.is_empty() {
+            return chain.first().map(IpAddr::to_string);
+        }
+        let trusted = |ip: &IpAddr| geoip::ip_number(&ip.to_string())
+            .is_some_and(|number| self.trusted.iter().any(|(first, last)| (*first..=*last).contains(&number)));
+        if !parser::client_ip(ip).is_some_and(|ip| trusted(&ip)) {
+            return None;
+        }
+        chain.iter().rev().find(|ip| !trusted(ip)).or(chain.first()).map(IpAddr::to_string)
+    }
+}
+
+impl fmt::Display for RealIp {
+    // For the help screen
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "from {}", self.field.as_deref().unwrap_or("X-Forwarded-For or a real-IP field"))?;
+        match self.trusted.len() {
+            0 => write!(f, ", first address of the chain"),
+            ranges => write!(f, ", last address not of a trusted proxy ({} range{})", ranges, if ranges == 1 { "" } else { "s" }),
+        }
+    }
+}
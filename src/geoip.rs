@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::Path;
+use std::sync::LazyLock;
+
+const COUNTRY_LIST: &str = include_str!("countries.txt");
+
+#[derive(Debug)]
+pub struct Country {
+    pub code: &'static str,
+    pub continent: &'static str,
+    pub name: &'static str,
+}
+
+static COUNTRIES: LazyLock<Vec<Country>> = LazyLock::new(|| {
+    COUNTRY_LIST.lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            Some(Country { code: parts.next()?, continent: parts.next()?, name: parts.next()? })
+        })
+        .collect()
+});
+
+static BY_CODE: LazyLock<HashMap<&'static str, usize>> = LazyLock::new(|| {
+    COUNTRIES.iter().enumerate().map(|(index, country)| (country.code, index)).collect()
+});
+
+pub fn country(code: &str) -> Option<&'static Country> {
+    BY_CODE.get(code.to_ascii_uppercase().as_str()).map(|&index| &COUNTRIES[index])
+}
+
+pub fn continent_name(code: &str) -> &'static str {
+    match code {
+        "AF" => "Africa",
+        "AN" => "Antarctica",
+        "AS" => "Asia",
+        "EU" => "Europe",
+        "NA" => "North America",
+        "OC" => "Oceania",
+        "SA" => "South America",
+        _ => "Unknown",
+    }
+}
+
+// IP ranges from a DB-IP style country CSV (`first_ip,last_ip,country_code`,
+// e.g. the free dbip-country-lite), IPv4 stored as IPv4-mapped IPv6
+#[derive(Debug)]
+pub struct GeoIp {
+    ranges: Vec<(u128, u128, usize)>,
+}
+
+impl GeoIp {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut ranges = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let mut columns = line.split(',').map(|column| column.trim().trim_matches('"'));
+            let (Some(first), Some(last), Some(code)) = (columns.next(), columns.next(), columns.next()) else {
+                continue;
+            };
+            // Skips headers, comments and codes such as ZZ for unassigned space
+            let (Some(first), Some(last), Some(&index)) = (ip_number(first), ip_number(last), BY_CODE.get(code)) else {
+                continue;
+            };
+            ranges.push((first, last, index));
+        }
+
+        if ranges.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "no IP ranges found"));
+        }
+        ranges.sort_unstable_by_key(|&(first, _, _)| first);
+        Ok(GeoIp { ranges })
+    }
+
+    pub fn lookup(&self, ip: &str) -> Option<&'static Country> {
+        let ip = ip_number(ip)?;
+        let after = self.ranges.partition_point(|&(first, _, _)| first <= ip);
+        let &(_, last, index) = self.ranges[..after].last()?;
+        (ip <= last).then(|| &COUNTRIES[index])
+    }
+}
+
+pub fn ip_number(ip: &str) -> Option<u128> {
+    let ip: IpAddr = ip.parse().ok()?;
+    let ip: Ipv6Addr = match ip {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+        IpAddr::V6(v6) => v6,
+    };
+    Some(u128::from(ip))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CountryEntry {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CountryStats {
+    pub countries: HashMap<&'static str, CountryEntry>,
+    // Requests from addresses not in the database, e.g. private networks
+    pub unknown: usize,
+}
+
+impl CountryStats {
+    pub fn record(&mut self, country: Option<&'static Country>, bytes: usize, weight: usize) {
+        let Some(country) = country else {
+            self.unknown += weight;
+            return;
+        };
+        let entry = self.countries.entry(country.code).or_default();
+        entry.count += weight;
+        entry.bytes += bytes * weight;
+    }
+
+    // Totals per continent code, busiest first
+    pub fn continents(&self) -> Vec<(&'static str, CountryEntry)> {
+        let mut continents: HashMap<&'static str, CountryEntry> = HashMap::new();
+        for (code, entry) in &self.countries {
+            let Some(country) = country(code) else {
+                continue;
+            };
+            let total = continents.entry(country.continent).or_default();
+            total.count += entry.count;
+            total.bytes += entry.bytes;
+        }
+        let mut continents: Vec<_> = continents.into_iter().collect();
+        continents.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.count));
+        continents
+    }
+}
@@ -0,0 +1,97 @@
+use std::str::FromStr;
+
+use ratatui::style::{self, Modifier, Style};
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    // One of the 256 colors of the extended palette
+    Fixed(u8),
+    // Behind the text rather than the text itself, from the same palette
+    Background(u8),
+    Bold,
+    Reverse,
+}
+
+const NAMES: &[&str] = &["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let index = |value: &str| NAMES.iter().position(|name| *name == value)
+            .map(|index| index as u8)
+            .or_else(|| value.parse().ok());
+        match value {
+            "black" => Ok(Color::Black),
+            "red" => Ok(Color::Red),
+            "green" => Ok(Color::Green),
+            "yellow" => Ok(Color::Yellow),
+            "blue" => Ok(Color::Blue),
+            "magenta" => Ok(Color::Magenta),
+            "cyan" => Ok(Color::Cyan),
+            "white" => Ok(Color::White),
+            "bold" => Ok(Color::Bold),
+            "reverse" => Ok(Color::Reverse),
+            _ => match value.strip_prefix("on-") {
+                Some(background) => index(background).map(Color::Background),
+                None => value.parse().ok().map(Color::Fixed),
+            }
+            .ok_or_else(|| format!(
+                "unknown color '{}' (black, red, green, yellow, blue, magenta, cyan, white, 0-255, \
+                 on-COLOR for the background, bold, reverse)", value)),
+        }
+    }
+}
+
+impl Color {
+    pub fn style(self) -> Style {
+        match self {
+            Color::Black => Style::new().fg(style::Color::Black),
+            Color::Red => Style::new().fg(style::Color::Red),
+            Color::Green => Style::new().fg(style::Color::Green),
+            Color::Yellow => Style::new().fg(style::Color::Yellow),
+            Color::Blue => Style::new().fg(style::Color::Blue),
+            Color::Magenta => Style::new().fg(style::Color::Magenta),
+            Color::Cyan => Style::new().fg(style::Color::Cyan),
+            // ratatui's White is the bright one
+            Color::White => Style::new().fg(style::Color::Gray),
+            Color::Fixed(index) => Style::new().fg(style::Color::Indexed(index)),
+            Color::Background(index) => Style::new().bg(style::Color::Indexed(index)),
+            Color::Bold => Style::new().add_modifier(Modifier::BOLD),
+            Color::Reverse => Style::new().add_modifier(Modifier::REVERSED),
+        }
+    }
+}
+
+// `REGEX=COLOR`, split at the last `=` so the regex may contain one
+#[derive(Debug, Clone)]
+pub struct Rule {
+    regex: Regex,
+    color: Color,
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, color) = value.rsplit_once('=')
+            .ok_or_else(|| format!("highlight rule '{}' should be REGEX=COLOR", value))?;
+        let regex = Regex::new(pattern)
+            .map_err(|e| format!("invalid highlight regex '{}': {}", pattern, e))?;
+        Ok(Rule { regex, color: color.parse()? })
+    }
+}
+
+// The color of the first rule whose regex matches a line
+pub fn rule_color(rules: &[Rule], line: &str) -> Option<Color> {
+    rules.iter().find(|rule| rule.regex.is_match(line)).map(|rule| rule.color)
+}
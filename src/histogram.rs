@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::Request;
+use crate::quantiles::Quantiles;
+
+// Bucket upper bounds grow by 4x: <128B, <512B, <2K ... <32M, then 32M+
+const BUCKETS: usize = 11;
+const FIRST_BOUND: usize = 128;
+// Responses kept for the Largest Responses view
+const LARGEST: usize = 100;
+
+#[derive(Debug, Clone, Default)]
+pub struct SizeHistogram {
+    pub buckets: [usize; BUCKETS],
+    pub count: usize,
+    pub max: usize,
+    // The same sizes, finely enough for percentiles
+    sizes: Quantiles,
+}
+
+impl SizeHistogram {
+    // Sampled responses stand for `weight` of them, the percentiles being
+    // the same either way
+    pub fn record(&mut self, bytes: usize, weight: usize) {
+        self.buckets[bucket_of(bytes)] += weight;
+        self.count += weight;
+        self.max = self.max.max(bytes);
+        self.sizes.record(bytes as f64);
+    }
+
+    pub fn memory(&self) -> usize {
+        self.sizes.memory()
+    }
+
+    // The size `fraction` of the responses stay under, within 1%
+    pub fn percentile(&self, fraction: f64) -> usize {
+        self.sizes.quantile(fraction).map_or(0, |bytes| bytes.round() as usize)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LargeResponse {
+    pub bytes: usize,
+    pub timestamp: DateTime<Utc>,
+    pub ip: String,
+    pub method: String,
+    pub path: String,
+    pub status_code: u16,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SizeStats {
+    pub global: SizeHistogram,
+    pub paths: HashMap<String, SizeHistogram>,
+    // Largest first
+    pub largest: Vec<LargeResponse>,
+}
+
+impl SizeStats {
+    // Counted under `path`, the request's own or its --group-static bucket
+    pub fn record(&mut self, path: &str, request: &Request) {
+        let bytes = request.bytes_sent;
+        self.global.record(bytes, request.weight);
+        self.paths.entry(path.to_string()).or_default().record(bytes, request.weight);
+
+        if self.largest.len() == LARGEST && self.largest.last().is_none_or(|smallest| bytes <= smallest.bytes) {
+            return;
+        }
+        // After those of the same size, so the earliest of equals stays
+        let at = self.largest.partition_point(|response| response.bytes >= bytes);
+        self.largest.insert(at, LargeResponse {
+            bytes,
+            timestamp: request.timestamp,
+            ip: request.ip.clone(),
+            method: request.method.clone(),
+            path: request.path.clone(),
+            status_code: request.status_code,
+        });
+        self.largest.truncate(LARGEST);
+    }
+}
+
+fn bucket_of(bytes: usize) -> usize {
+    let mut bound = FIRST_BOUND;
+    for bucket in 0..BUCKETS - 1 {
+        if bytes < bound {
+            return bucket;
+        }
+        bound *= 4;
+    }
+    BUCKETS - 1
+}
+
+pub fn bucket_label(bucket: usize) -> String {
+    let bound = FIRST_BOUND << (2 * bucket.min(BUCKETS - 2));
+    if bucket == BUCKETS - 1 {
+        format!(">= {}", human_bytes(bound))
+    } else {
+        format!("< {}", human_bytes(bound))
+    }
+}
+
+// 512B, 1.5KiB, 3MiB
+pub fn human_bytes(bytes: usize) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 || value.fract() == 0.0 {
+        format!("{:.0}{}", value, UNITS[unit])
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
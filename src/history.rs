@@ -0,0 +1,244 @@
+use std::collections::VecDeque;
+
+use regex::Regex;
+
+use crate::Request;
+use crate::slow::SlowLog;
+use crate::{filters, geoip, memory};
+
+pub const DEFAULT_CAPACITY: usize = 100_000;
+// With --max-memory, the requests kept take at most a quarter of it and
+// the tables the rest
+pub const MEMORY_SHARE: usize = 4;
+
+// The most recent individual requests, oldest first. Each gets a sequence
+// number that stays valid until it is evicted
+#[derive(Debug)]
+pub struct History {
+    requests: VecDeque<Request>,
+    capacity: usize,
+    // Bytes the requests take, and the most they may, 0 for no limit
+    memory: usize,
+    max_memory: usize,
+    first_id: usize,
+    pub slow: SlowLog,
+}
+
+impl History {
+    pub fn new(capacity: usize, slow_threshold: Option<f64>) -> Self {
+        History {
+            requests: VecDeque::new(),
+            capacity,
+            memory: 0,
+            max_memory: 0,
+            first_id: 1,
+            slow: SlowLog::new(slow_threshold),
+        }
+    }
+
+    // Past `max_memory` bytes the oldest requests go, however few are kept
+    pub fn with_max_memory(self, max_memory: usize) -> Self {
+        History { max_memory, ..self }
+    }
+
+    pub fn push(&mut self, request: Request) {
+        self.slow.record(&request);
+        if self.capacity == 0 {
+            return;
+        }
+        self.memory += memory::request(&request);
+        self.requests.push_back(request);
+        while self.requests.len() > self.capacity
+            || self.max_memory > 0 && self.memory > self.max_memory && self.requests.len() > 1 {
+            let Some(oldest) = self.requests.pop_front() else {
+                break;
+            };
+            self.memory -= memory::request(&oldest);
+            self.first_id += 1;
+        }
+    }
+
+    pub fn extend(&mut self, requests: impl IntoIterator<Item = Request>) {
+        for request in requests {
+            self.push(request);
+        }
+    }
+
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    // The last `count` requests, oldest first
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &Request> {
+        self.requests.iter().skip(self.requests.len().saturating_sub(count))
+    }
+
+    pub fn newest_first(&self) -> impl Iterator<Item = &Request> {
+        self.requests.iter().rev()
+    }
+
+    // Requests from sequence number `id` on, oldest first
+    pub fn since(&self, id: usize) -> impl Iterator<Item = &Request> {
+        self.requests.iter().skip(id.saturating_sub(self.first_id))
+    }
+
+    // The sequence number the next request will get
+    pub fn next_id(&self) -> usize {
+        self.first_id + self.requests.len()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Request> {
+        self.requests.get(id.checked_sub(self.first_id)?)
+    }
+
+    // Matching requests with their sequence numbers, newest first
+    pub fn search<'a>(&'a self, query: &'a Query) -> impl Iterator<Item = (usize, &'a Request)> + 'a {
+        self.requests.iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, request)| query.matches(request))
+            .map(|(index, request)| (self.first_id + index, request))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Any,
+    Path,
+    Ip,
+    Status,
+    UserAgent,
+    Country,
+    File,
+    Host,
+    Protocol,
+    Bot,
+    Network,
+    Vhost,
+}
+
+#[derive(Debug)]
+enum Pattern {
+    Substring(String),
+    Regex(Regex),
+    // First and last address of a CIDR block, as geoip::ip_number has them
+    Block(u128, u128),
+}
+
+impl Pattern {
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Pattern::Substring(needle) => value.contains(needle.as_str()),
+            Pattern::Regex(regex) => regex.is_match(value),
+            Pattern::Block(first, last) => geoip::ip_number(value).is_some_and(|ip| (*first..=*last).contains(&ip)),
+        }
+    }
+}
+
+// Whitespace-separated terms that must all match, each `[field:]pattern`
+// where field is path, ip, status, ua, vhost, country, asn, file, host,
+// proto or bot and a pattern starting with `~` is a regex, e.g. `status:~^5
+// path:/api/`. Statuses also take a class such as 5xx, and addresses a
+// CIDR block such as 2001:db8::/32
+#[derive(Debug)]
+pub struct Query {
+    text: String,
+    terms: Vec<(Field, Pattern)>,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut terms = Vec::new();
+        for term in text.split_whitespace() {
+            let (field, pattern) = match term.split_once(':') {
+                Some(("path", pattern)) => (Field::Path, pattern),
+                Some(("ip", pattern)) => (Field::Ip, pattern),
+                Some(("status", pattern)) => (Field::Status, pattern),
+                Some(("ua", pattern)) => (Field::UserAgent, pattern),
+                Some(("vhost", pattern)) => (Field::Vhost, pattern),
+                Some(("country", pattern)) => (Field::Country, pattern),
+                Some(("asn", pattern)) => (Field::Network, pattern),
+                Some(("file", pattern)) => (Field::File, pattern),
+                Some(("host", pattern)) => (Field::Host, pattern),
+                Some(("proto", pattern)) => (Field::Protocol, pattern),
+                Some(("bot", pattern)) => (Field::Bot, pattern),
+                _ => (Field::Any, term),
+            };
+
+            let pattern = match pattern.strip_prefix('~') {
+                Some(regex) => Pattern::Regex(Regex::new(regex)
+                    .map_err(|e| format!("invalid regex '{}': {}", regex, e))?),
+                None if field == Field::Status && is_status_class(pattern) => {
+                    Pattern::Regex(Regex::new(&format!("^{}\\d\\d$", &pattern[..1])).expect("valid regex"))
+                }
+                None if field == Field::Ip && pattern.contains('/') => {
+                    let (first, last) = filters::ip_range(pattern)
+                        .ok_or_else(|| format!("invalid address block '{}' (e.g. 10.0.0.0/8 or 2001:db8::/32)", pattern))?;
+                    Pattern::Block(first, last)
+                }
+                None => Pattern::Substring(pattern.to_string()),
+            };
+            terms.push((field, pattern));
+        }
+
+        if terms.is_empty() {
+            return Err("empty search".to_string());
+        }
+        Ok(Query { text: text.trim().to_string(), terms })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn matches(&self, request: &Request) -> bool {
+        let status = request.status_code.to_string();
+        self.terms.iter().all(|(field, pattern)| match field {
+            Field::Path => pattern.matches(&request.url()),
+            Field::Ip => pattern.matches(&request.ip),
+            Field::Status => pattern.matches(&status),
+            Field::UserAgent => pattern.matches(&request.user_agent),
+            Field::Country => request.country.is_some_and(|country| pattern.matches(country.code)),
+            Field::File => request.source.as_deref().is_some_and(|source| pattern.matches(source)),
+            Field::Host => request.host().is_some_and(|host| pattern.matches(host)),
+            Field::Protocol => request.protocol.as_deref().is_some_and(|protocol| pattern.matches(protocol)),
+            Field::Bot => request.bot.is_some_and(|bot| pattern.matches(bot)),
+            Field::Network => request.network.as_deref().is_some_and(|network| pattern.matches(network)),
+            Field::Vhost => request.vhost.as_deref().is_some_and(|host| pattern.matches(host)),
+            Field::Any => [&request.url(), request.ip.as_str(), &status, &request.user_agent]
+                .iter()
+                .any(|value| pattern.matches(value)),
+        })
+    }
+}
+
+// 2xx, 4XX, ...
+fn is_status_class(pattern: &str) -> bool {
+    let bytes = pattern.as_bytes();
+    bytes.len() == 3 && (b'1'..=b'5').contains(&bytes[0]) && bytes[1..].eq_ignore_ascii_case(b"xx")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_log_line;
+
+    #[test]
+    fn max_memory() {
+        let line = r#"192.0.2.1 - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 200 100 "-" "-" 0.002"#;
+        let request = parse_log_line(line).unwrap();
+        let size = memory::request(&request);
+        let mut history = History::new(DEFAULT_CAPACITY, None).with_max_memory(size * 10);
+        for _ in 0..100 {
+            history.push(request.clone());
+        }
+        assert_eq!(history.len(), 10);
+        assert_eq!(history.memory(), size * 10);
+        assert_eq!(history.next_id(), 101);
+        assert!(history.get(90).is_none() && history.get(91).is_some());
+    }
+}
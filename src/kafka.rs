@@ -0,0 +1,51 @@
+// A Kafka topic consumed as a member of a consumer group through kcat
+// (kafkacat), which commits offsets as it goes so that a restarted httop
+// resumes near where it left off
+#[derive(Debug, Clone)]
+pub struct Kafka {
+    pub brokers: String,
+    pub topic: String,
+    pub group: String,
+}
+
+pub const DEFAULT_GROUP: &str = "httop";
+
+impl Kafka {
+    // From brokers=HOST:PORT,... topic=NAME [group=NAME] settings
+    pub fn from_settings<'a>(settings: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let (mut brokers, mut topic, mut group) = (None, None, None);
+        for setting in settings {
+            match setting.split_once('=') {
+                Some(("brokers", value)) => brokers = Some(value),
+                Some(("topic", value)) => topic = Some(value),
+                Some(("group", value)) => group = Some(value),
+                _ => return Err(format!("unknown --kafka setting '{}' (expected brokers=, topic= or group=)", setting)),
+            }
+        }
+        let present = |value: Option<&str>| value.filter(|value| !value.is_empty()).map(String::from);
+        Ok(Kafka {
+            brokers: present(brokers).ok_or("--kafka requires brokers=HOST:PORT")?,
+            topic: present(topic).ok_or("--kafka requires topic=NAME")?,
+            group: present(group).unwrap_or_else(|| DEFAULT_GROUP.to_string()),
+        })
+    }
+
+    pub fn is_setting(arg: &str) -> bool {
+        ["brokers=", "topic=", "group="].iter().any(|prefix| arg.starts_with(prefix))
+    }
+
+    // kcat arguments printing one message value per line. A group seen for
+    // the first time starts at the end of the topic, like tail -f
+    pub fn consume_args(&self) -> Vec<String> {
+        [
+            "-b", &self.brokers,
+            "-G", &self.group,
+            "-X", "auto.offset.reset=latest",
+            "-f", "%s\\n",
+            "-u", "-q",
+            &self.topic,
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+}
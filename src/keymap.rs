@@ -0,0 +1,158 @@
+// Keys with an action of their own, which --bind can move to another key
+const BOUND: &[u8] = b" \t%+-/123456<>?ABCDEFHLOPSTUabcdefghjklmnoqrstvwxy";
+
+// --bind NEW=OLD moves the action of OLD to NEW, freeing OLD. Arrows,
+// Enter, Backspace, Esc and Ctrl-C stay as they are
+#[derive(Debug, Clone, Copy)]
+pub struct KeyMap {
+    // The key each pressed key stands for, 0 for none
+    keys: [u8; 128],
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap { keys: std::array::from_fn(|key| key as u8) }
+    }
+}
+
+impl KeyMap {
+    pub fn new(bindings: &[String]) -> Result<Self, String> {
+        let bindings = bindings.iter()
+            .map(|binding| parse_binding(binding).ok_or_else(|| format!(
+                "invalid --bind '{}' (NEW=OLD, with single keys, Space or Tab, e.g. J=j)", binding)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut map = KeyMap::default();
+        for &(_, old) in &bindings {
+            if !BOUND.contains(&old) {
+                return Err(format!("--bind: '{}' has no action to move", name(old)));
+            }
+            if map.keys[old as usize] == 0 {
+                return Err(format!("--bind: '{}' is moved twice", name(old)));
+            }
+            map.keys[old as usize] = 0;
+        }
+        for &(new, old) in &bindings {
+            match map.keys[new as usize] {
+                0 => {}
+                current if current != new => return Err(format!("--bind: '{}' is bound twice", name(new))),
+                _ if BOUND.contains(&new) => return Err(format!(
+                    "--bind: '{}' has an action of its own (move it to another key with --bind too)", name(new))),
+                _ => {}
+            }
+            map.keys[new as usize] = old;
+        }
+        Ok(map)
+    }
+
+    // Into the key with the action, for read_keys to match on
+    pub fn translate(&self, key: u8) -> u8 {
+        match key {
+            b'\t' | b' '..=b'~' => self.keys[key as usize],
+            _ => key,
+        }
+    }
+
+    // What to press for the action of a default key
+    pub fn key_for(&self, key: u8) -> u8 {
+        (0..128u8).find(|&pressed| self.keys[pressed as usize] == key).unwrap_or(key)
+    }
+
+    // A KEYS entry such as "c / b / l / n" or "Up/Down, k/j", with the keys
+    // as bound
+    pub fn label(&self, label: &str) -> String {
+        label.split(' ').enumerate()
+            .map(|(i, word)| match word.strip_suffix(',') {
+                Some(word) => format!("{},", self.word(word, i == 0)),
+                None => self.word(word, i == 0),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn word(&self, word: &str, first: bool) -> String {
+        let bound = |key: &str| key.len() == 1 && BOUND.contains(&key.as_bytes()[0]);
+        match word {
+            "Space" => name(self.key_for(b' ')),
+            "Tab" => name(self.key_for(b'\t')),
+            // Between keys, as in "c / b", rather than the search key
+            "/" if !first => word.to_string(),
+            _ if bound(word) => name(self.key_for(word.as_bytes()[0])),
+            // k/j and 1-6
+            _ => match word.find(['/', '-']) {
+                Some(i) if bound(&word[..i]) && bound(&word[i + 1..]) => format!("{}{}{}",
+                    name(self.key_for(word.as_bytes()[0])), &word[i..i + 1], name(self.key_for(word.as_bytes()[i + 1]))),
+                _ => word.to_string(),
+            },
+        }
+    }
+}
+
+fn name(key: u8) -> String {
+    match key {
+        b' ' => "Space".to_string(),
+        b'\t' => "Tab".to_string(),
+        _ => (key as char).to_string(),
+    }
+}
+
+fn parse_key(key: &str) -> Option<u8> {
+    match key {
+        _ if key.eq_ignore_ascii_case("space") => Some(b' '),
+        _ if key.eq_ignore_ascii_case("tab") => Some(b'\t'),
+        _ if key.len() == 1 && key.as_bytes()[0].is_ascii_graphic() => Some(key.as_bytes()[0]),
+        _ => None,
+    }
+}
+
+// NEW=OLD; "==x" binds = itself
+fn parse_binding(binding: &str) -> Option<(u8, u8)> {
+    let (new, old) = match binding.strip_prefix("==") {
+        Some(old) => ("=", old),
+        None => binding.split_once('=')?,
+    };
+    Some((parse_key(new)?, parse_key(old)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bind(bindings: &[&str]) -> Result<KeyMap, String> {
+        KeyMap::new(&bindings.iter().map(|binding| binding.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn moved() {
+        let keymap = bind(&["X=b", "b=x", "Z=Space", "==r"]).unwrap();
+        assert_eq!(keymap.translate(b'X'), b'b');
+        assert_eq!(keymap.translate(b'b'), b'x');
+        assert_eq!(keymap.translate(b'Z'), b' ');
+        assert_eq!(keymap.translate(b'='), b'r');
+        // Freed keys do nothing, the rest keep their action
+        assert_eq!(keymap.translate(b'x'), 0);
+        assert_eq!(keymap.translate(b' '), 0);
+        assert_eq!(keymap.translate(b'c'), b'c');
+        assert_eq!(keymap.translate(b'\r'), b'\r');
+
+        assert_eq!(keymap.key_for(b'b'), b'X');
+        assert_eq!(keymap.label("c / b / l / n"), "c / X / l / n");
+        assert_eq!(keymap.label("Space"), "Z");
+        assert_eq!(keymap.label("x"), "b");
+    }
+
+    #[test]
+    fn rejected() {
+        for bindings in [
+            &["P=Space"][..],
+            &["Z=z"],
+            &["J=j", "K=j"],
+            &["J=j", "J=k"],
+            &["JJ=j"],
+            &["J"],
+            &["Enter=j"],
+        ] {
+            assert!(bind(bindings).is_err(), "{:?}", bindings);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::process::Command;
+
+use serde_json::Value;
+
+// Concurrent log streams kubectl may open, i.e. replicas followed at once
+const MAX_PODS: usize = 100;
+
+// kubectl logs arguments following every pod of TARGET: a workload such as
+// deployment/ingress-nginx (resolved to its label selector, since kubectl
+// would otherwise pick a single pod), a label selector, or pod/NAME. Lines
+// are prefixed with [pod/NAME/CONTAINER]
+pub fn logs_args(target: &str, namespace: Option<&str>) -> Result<Vec<String>, String> {
+    let mut args = vec!["logs".to_string(), "--follow".to_string(), "--prefix".to_string()];
+    if let Some(namespace) = namespace {
+        args.push(format!("--namespace={}", namespace));
+    }
+
+    if target.contains('=') {
+        args.push(format!("--selector={}", target));
+    } else if target.starts_with("pod/") || !target.contains('/') {
+        args.push(target.to_string());
+        return Ok(args);
+    } else {
+        args.push(format!("--selector={}", selector(target, namespace)?));
+    }
+    args.push(format!("--max-log-requests={}", MAX_PODS));
+    Ok(args)
+}
+
+fn selector(target: &str, namespace: Option<&str>) -> Result<String, String> {
+    let mut command = Command::new("kubectl");
+    command.args(["get", target, "--output=json"]);
+    if let Some(namespace) = namespace {
+        command.arg(format!("--namespace={}", namespace));
+    }
+    let output = command.output().map_err(|e| format!("could not run kubectl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("kubectl get {}: {}", target, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    let object: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("invalid kubectl output: {}", e))?;
+    // Workloads have spec.selector.matchLabels, services spec.selector
+    let selector = object.get("spec").and_then(|spec| spec.get("selector"));
+    let labels = selector.and_then(|selector| selector.get("matchLabels")).or(selector);
+    let Some(Value::Object(labels)) = labels else {
+        return Err(format!("{} has no label selector", target));
+    };
+
+    let labels: Vec<String> = labels.iter()
+        .filter_map(|(key, value)| Some(format!("{}={}", key, value.as_str()?)))
+        .collect();
+    if labels.is_empty() {
+        return Err(format!("{} has no label selector", target));
+    }
+    Ok(labels.join(","))
+}
+
+// Splits "[pod/NAME/CONTAINER] line" into the pod name and the line
+pub fn split_prefix(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix("[pod/")?;
+    let (prefix, line) = rest.split_once("] ")?;
+    let pod = prefix.split('/').next()?;
+    Some((pod, line))
+}
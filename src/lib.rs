@@ -0,0 +1,85 @@
+// The access log parser (parser), the streaming aggregation (stats), the
+// inputs feeding it (source) and the reports written from it (export), for
+// other programs to embed; the httop binary itself just calls run()
+
+mod agents;
+mod alerts;
+mod anomaly;
+mod apdex;
+mod app;
+mod asn;
+mod attacks;
+mod bans;
+mod baseline;
+mod bots;
+mod cache;
+mod campaigns;
+mod changes;
+mod chart;
+mod checkpoint;
+mod clipboard;
+mod columns;
+mod compare;
+mod config;
+mod content;
+mod curl;
+mod daemon;
+mod detail;
+mod duplicates;
+mod emit;
+pub mod export;
+mod filters;
+mod follow;
+mod formats;
+mod forwarded;
+mod geoip;
+mod highlight;
+mod histogram;
+mod history;
+mod kafka;
+mod keymap;
+mod kube;
+mod logfmt;
+mod memory;
+mod normalize;
+mod notices;
+mod outliers;
+mod paging;
+pub mod parser;
+mod pattern;
+mod piped;
+mod preflight;
+mod quantiles;
+mod rate;
+mod rdap;
+mod record;
+mod redirects;
+mod referrers;
+mod s3;
+mod sample;
+mod scan;
+mod serve;
+mod settings;
+mod slo;
+mod slow;
+pub mod source;
+pub mod stats;
+mod statsd;
+mod syslog;
+mod template;
+mod tenants;
+mod terminal;
+mod theme;
+mod tls;
+mod toggles;
+mod trace;
+mod transfer;
+mod uniques;
+mod unparsed;
+mod visits;
+mod w3c;
+
+pub use app::run;
+pub use config::Config;
+pub use parser::Request;
+pub use stats::Stats;
@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+
+use crate::formats::{duration_seconds, FieldMap};
+use crate::Request;
+
+// key=value pairs, values optionally quoted with backslash escapes:
+// at=info method=GET path="/x y" status=200
+pub fn pairs(line: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+            key.push(c);
+        }
+        if key.is_empty() && chars.peek().is_none() {
+            return pairs;
+        }
+
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            if chars.next_if_eq(&'"').is_some() {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+            }
+        }
+        if !key.is_empty() {
+            pairs.push((key, value));
+        }
+    }
+}
+
+// Heroku router lines: "<time> heroku[router]: at=info method=GET path="/x"
+// host=... request_id=... fwd="1.2.3.4" dyno=web.1 connect=1ms service=12ms
+// status=200 bytes=1234 protocol=https"
+pub fn parse_heroku(line: &str) -> Option<Request> {
+    let (timestamp, body) = match line.split_once(" heroku[router]: ") {
+        Some((time, body)) => (DateTime::parse_from_rfc3339(time.trim()).ok().map(|time| time.with_timezone(&Utc)), body),
+        None => (None, line),
+    };
+    let pairs = pairs(body);
+    let value = |key: &str| pairs.iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+        .filter(|value| !value.is_empty());
+
+    // fwd is the X-Forwarded-For chain, the client first
+    let ip = value("fwd").and_then(|fwd| fwd.split(',').next()).map_or("-", str::trim);
+    let mut request = Request::new(
+        timestamp.unwrap_or_else(Utc::now),
+        ip,
+        value("method")?,
+        value("path")?,
+        value("status")?.parse().ok()?,
+        value("bytes").and_then(|bytes| bytes.parse().ok()).unwrap_or(0),
+    );
+    request.response_time = value("service").and_then(duration_seconds).unwrap_or(0.0);
+
+    // service is a duration here, not the service name the Services view groups by
+    let fields = pairs.iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(key, value)| match key.as_str() {
+            "service" => ("service_time".to_string(), value.clone()),
+            _ => (key.clone(), value.clone()),
+        })
+        .collect();
+    Some(request.with_fields(fields))
+}
+
+// Keys tried for each request field in generic logfmt access logs, such as
+// those of Go services
+pub const DEFAULT_KEYS: &[(&str, &[&str])] = &[
+    ("ip", &["remote_addr", "client_ip", "remote_ip", "ip"]),
+    ("time", &["time", "ts", "timestamp"]),
+    ("method", &["method", "request_method"]),
+    ("path", &["path", "uri", "url", "request_uri"]),
+    ("status", &["status", "status_code", "code"]),
+    ("bytes", &["bytes", "size", "bytes_sent", "response_size"]),
+    ("request_time", &["duration", "latency", "elapsed", "took", "request_time"]),
+    ("referrer", &["referer", "referrer"]),
+    ("user_agent", &["user_agent", "ua"]),
+];
+
+pub fn parse(map: &FieldMap, line: &str) -> Option<Request> {
+    let pairs = pairs(line);
+    let request = map.request(|key| {
+        pairs.iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.clone())
+            .filter(|value| !value.is_empty() && value != "-")
+    })?;
+    Some(request.with_fields(pairs))
+}
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::Request;
+
+// What the allocator adds to each block, roughly
+const OVERHEAD: usize = 16;
+
+// A key of a table: its slot, with the byte of hash the map keeps beside
+// it, and its text, before whatever the value holds on the heap
+pub fn entry<T>(key: &str) -> usize {
+    size_of::<(String, T)>() + 1 + key.len() + OVERHEAD
+}
+
+// A string the key's value holds on the heap
+pub fn text(value: &str) -> usize {
+    value.len() + OVERHEAD
+}
+
+pub fn set(set: &HashSet<String>) -> usize {
+    set.iter().map(|key| entry::<()>(key)).sum::<usize>() + (set.capacity() - set.len()) * (size_of::<String>() + 1)
+}
+
+// The slots a map has room for beyond its keys
+pub fn spare<K, T>(map: &HashMap<K, T>) -> usize {
+    (map.capacity() - map.len()) * (size_of::<(K, T)>() + 1)
+}
+
+pub fn keyed<T>(map: &HashMap<String, T>) -> usize {
+    map.keys().map(|key| entry::<T>(key)).sum::<usize>() + spare(map)
+}
+
+// A request kept for search, with its text
+pub fn request(request: &Request) -> usize {
+    let texts = [&request.ip, &request.method, &request.path, &request.referrer, &request.user_agent];
+    let optional = [&request.query, &request.protocol, &request.tenant, &request.vhost, &request.cache_status];
+    size_of::<Request>() + OVERHEAD
+        + texts.into_iter().map(|value| text(value)).sum::<usize>()
+        + optional.into_iter().flatten().map(|value| text(value)).sum::<usize>()
+        + request.fields.iter().map(|(key, value)| size_of::<(String, String)>() + text(key) + text(value)).sum::<usize>()
+}
+
+// Given the requests each key is thought to have had and the bytes it
+// takes, the count below which keys go to free `excess` bytes, taking
+// the rarest first, and how many on it go too
+pub fn threshold(mut keys: Vec<(usize, usize)>, excess: usize) -> Option<(usize, usize)> {
+    keys.sort_unstable_by_key(|&(estimate, _)| estimate);
+    let mut freed = 0;
+    let last = keys.iter().position(|&(_, bytes)| {
+        freed += bytes;
+        freed >= excess
+    }).unwrap_or(keys.len().checked_sub(1)?);
+    let threshold = keys[last].0;
+    Some((threshold, last + 1 - keys.partition_point(|&(estimate, _)| estimate < threshold)))
+}
+
+// Space-Saving, a quarter of the keys at a time: past `max_keys`, the count
+// below which keys go so that three quarters are left, and how many on it
+// go too
+pub fn limit<K: Eq + Hash, T>(map: &HashMap<K, T>, estimate: impl Fn(&T) -> usize, max_keys: usize) -> Option<(usize, usize)> {
+    if max_keys == 0 || map.len() <= max_keys {
+        return None;
+    }
+    let mut estimates: Vec<usize> = map.values().map(estimate).collect();
+    let evicting = map.len() - (max_keys - max_keys / 4).max(1);
+    let (_, &mut threshold, _) = estimates.select_nth_unstable(evicting - 1);
+    // Keys on the threshold go only as far as needed
+    let ties = evicting - estimates.iter().filter(|&&estimate| estimate < threshold).count();
+    Some((threshold, ties))
+}
+
+// Drops the entries `estimate` puts below `threshold`, and `ties` of those
+// on it, handing each to `evicted` on the way out
+pub fn evict<K: Eq + Hash, T>(
+    map: &mut HashMap<K, T>,
+    estimate: impl Fn(&T) -> usize,
+    threshold: usize,
+    ties: &mut usize,
+    mut evicted: impl FnMut(&T),
+) {
+    map.retain(|_, value| {
+        let estimate = estimate(value);
+        let evict = estimate < threshold || estimate == threshold && *ties > 0;
+        if evict && estimate == threshold {
+            *ties -= 1;
+        }
+        if evict {
+            evicted(value);
+        }
+        !evict
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_frees() {
+        assert_eq!(threshold(Vec::new(), 100), None);
+        // The rarest go first, until what they take covers the excess
+        let keys = vec![(5, 100), (1, 100), (3, 100), (3, 100)];
+        assert_eq!(threshold(keys.clone(), 150), Some((3, 1)));
+        assert_eq!(threshold(keys.clone(), 300), Some((3, 2)));
+        // More than all of them take: every key goes
+        assert_eq!(threshold(keys, 10_000), Some((5, 1)));
+    }
+
+    #[test]
+    fn limit_and_evict() {
+        let mut map: HashMap<u32, usize> = (1..=8).map(|key| (key, key as usize)).collect();
+        assert_eq!(limit(&map, |count| *count, 8), None);
+        assert_eq!(limit(&map, |count| *count, 0), None);
+
+        // Three quarters of 4 stay: the five rarest go
+        let (threshold, mut ties) = limit(&map, |count| *count, 4).unwrap();
+        assert_eq!((threshold, ties), (5, 1));
+        let mut evicted = Vec::new();
+        evict(&mut map, |count| *count, threshold, &mut ties, |count| evicted.push(*count));
+        evicted.sort();
+        assert_eq!(evicted, [1, 2, 3, 4, 5]);
+        assert_eq!(ties, 0);
+        assert_eq!(map.len(), 3);
+
+        // Of keys with as many requests, only as many as needed go
+        let mut map: HashMap<u32, usize> = [(1, 1), (2, 2), (3, 2), (4, 2), (5, 3)].into();
+        let (threshold, mut ties) = limit(&map, |count| *count, 4).unwrap();
+        assert_eq!((threshold, ties), (2, 1));
+        evict(&mut map, |count| *count, threshold, &mut ties, |_| {});
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.values().filter(|&&count| count == 2).count(), 2);
+    }
+}
@@ -0,0 +1,105 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use regex::Regex;
+
+// Hex strings this long or longer are hashes or object ids rather than words
+const MIN_HASH_LEN: usize = 16;
+
+// `REGEX=REPLACEMENT`, split at the last `=` so the regex may contain one.
+// The replacement may use the regex's groups as $1 or ${name}
+#[derive(Debug, Clone)]
+pub struct Rule {
+    regex: Regex,
+    replacement: String,
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, replacement) = value.rsplit_once('=')
+            .ok_or_else(|| format!("path rule '{}' should be REGEX=REPLACEMENT", value))?;
+        let regex = Regex::new(pattern)
+            .map_err(|e| format!("invalid path rule regex '{}': {}", pattern, e))?;
+        Ok(Rule { regex, replacement: replacement.to_string() })
+    }
+}
+
+// The path with the rules applied in order, then, with `ids`, every numeric
+// id, UUID or hash segment replaced by :id. The query string is left as is
+pub fn path(path: &str, rules: &[Rule], ids: bool) -> String {
+    let (path, query) = path.split_at(path.find('?').unwrap_or(path.len()));
+    let mut rewritten = path.to_string();
+    for rule in rules {
+        rewritten = rule.regex.replace_all(&rewritten, rule.replacement.as_str()).into_owned();
+    }
+    if ids {
+        rewritten = rewritten.split('/')
+            .map(|segment| if is_id(segment) { ":id" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/");
+    }
+    rewritten + query
+}
+
+fn is_id(segment: &str) -> bool {
+    let bytes = segment.as_bytes();
+    if !bytes.is_empty() && bytes.iter().all(u8::is_ascii_digit) {
+        return true;
+    }
+    // 8-4-4-4-12 hex digits
+    let uuid = bytes.len() == 36 && bytes.iter().enumerate().all(|(index, byte)| match index {
+        8 | 13 | 18 | 23 => *byte == b'-',
+        _ => byte.is_ascii_hexdigit(),
+    });
+    let hash = bytes.len() >= MIN_HASH_LEN
+        && bytes.iter().all(u8::is_ascii_hexdigit)
+        && bytes.iter().any(u8::is_ascii_digit);
+    uuid || hash
+}
+
+// What happens to query strings before requests are counted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum QueryStrings {
+    // /search?q=a counts as /search
+    #[default]
+    Strip,
+    Keep,
+    // Only these parameters are kept: /search?page=2 for /search?q=a&page=2
+    Group(Vec<String>),
+}
+
+// The path to count a request under and the query string taken off it,
+// unless the path stays as it is
+pub fn split_query(path: &str, mode: &QueryStrings) -> Option<(String, String)> {
+    let (base, query) = path.split_once('?')?;
+    let counted = match mode {
+        QueryStrings::Keep => return None,
+        QueryStrings::Strip => base.to_string(),
+        QueryStrings::Group(names) => {
+            let kept: Vec<&str> = query.split('&')
+                .filter(|pair| names.iter().any(|name| pair.split('=').next() == Some(name.as_str())))
+                .collect();
+            if kept.is_empty() {
+                base.to_string()
+            } else {
+                format!("{}?{}", base, kept.join("&"))
+            }
+        }
+    };
+    Some((counted, query.to_string()))
+}
+
+// --anonymize-ips: an address, or a comma list of them as in
+// X-Forwarded-For, with the last octet of IPv4 or the last 80 bits of IPv6
+// zeroed. None for anything else
+pub fn anonymize_ips(value: &str) -> Option<String> {
+    value.split(',')
+        .map(|item| Some(match item.trim().parse::<IpAddr>().ok()? {
+            IpAddr::V4(ip) => Ipv4Addr::from(u32::from(ip) & !0xff).to_string(),
+            IpAddr::V6(ip) => Ipv6Addr::from(u128::from(ip) & !((1 << 80) - 1)).to_string(),
+        }))
+        .collect::<Option<Vec<_>>>()
+        .map(|ips| ips.join(", "))
+}
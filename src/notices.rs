@@ -0,0 +1,69 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// The most messages kept while the display has the terminal; older ones go
+const MAX_HELD: usize = 32;
+
+// Errors and warnings of background threads, such as a recording that
+// stopped or an alert command that failed. While the display owns the
+// terminal they wait for it to show them in its status line, as writing
+// to stderr would tear its frame; otherwise they go straight to stderr
+#[derive(Debug, Default)]
+pub struct Notices {
+    held: AtomicBool,
+    pending: Mutex<Vec<String>>,
+}
+
+impl Notices {
+    pub fn post(&self, message: String) {
+        let mut pending = self.pending.lock().unwrap();
+        if !self.held.load(Ordering::SeqCst) {
+            eprintln!("httop: {}", message);
+            return;
+        }
+        if pending.len() == MAX_HELD {
+            pending.remove(0);
+        }
+        pending.push(message);
+    }
+
+    // From now on messages wait for take
+    pub fn hold(&self) {
+        self.held.store(true, Ordering::SeqCst);
+    }
+
+    // Back to stderr, with whatever was not taken yet
+    pub fn release(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        self.held.store(false, Ordering::SeqCst);
+        for message in pending.drain(..) {
+            eprintln!("httop: {}", message);
+        }
+    }
+
+    // Messages posted since the last take, oldest first
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn held() {
+        let notices = Notices::default();
+        notices.hold();
+        for i in 0..MAX_HELD + 2 {
+            notices.post(format!("notice {}", i));
+        }
+        let taken = notices.take();
+        assert_eq!(taken.len(), MAX_HELD);
+        assert_eq!(taken[0], "notice 2");
+        assert!(notices.take().is_empty());
+        notices.release();
+        notices.post("to stderr".to_string());
+        assert!(notices.take().is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+
+// Weight of each new response time in a path's running mean and variance
+const ALPHA: f64 = 0.05;
+// Requests a path needs before its baseline is trusted
+const MIN_SAMPLES: usize = 30;
+// Standard deviations above the mean that make a request an outlier
+pub const Z_THRESHOLD: f64 = 4.0;
+// Ignore deviations too small to matter, e.g. 3ms on a 1ms endpoint
+const MIN_EXCESS: f64 = 0.05;
+const KEEP: usize = 200;
+
+#[derive(Debug, Clone, Default)]
+pub struct LatencyBaseline {
+    pub samples: usize,
+    pub mean: f64,
+    variance: f64,
+    pub outliers: usize,
+}
+
+impl LatencyBaseline {
+    pub fn stddev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    // z-score against the baseline before this sample is folded in
+    fn observe(&mut self, response_time: f64) -> Option<f64> {
+        let z = if self.samples >= MIN_SAMPLES && self.variance > 0.0 {
+            Some((response_time - self.mean) / self.stddev())
+        } else {
+            None
+        };
+
+        if self.samples == 0 {
+            self.mean = response_time;
+        } else {
+            // Exponentially weighted, so the baseline follows slow drift
+            let diff = response_time - self.mean;
+            let increment = ALPHA * diff;
+            self.mean += increment;
+            self.variance = (1.0 - ALPHA) * (self.variance + diff * increment);
+        }
+        self.samples += 1;
+        z
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Outlier {
+    pub timestamp: DateTime<Utc>,
+    pub ip: String,
+    pub path: String,
+    pub status_code: u16,
+    pub response_time: f64,
+    pub expected: f64,
+    pub z: f64,
+}
+
+// Requests that were far slower than usual for their path
+#[derive(Debug, Clone, Default)]
+pub struct OutlierStats {
+    pub paths: HashMap<String, LatencyBaseline>,
+    // Newest last
+    pub recent: VecDeque<Outlier>,
+    pub total: usize,
+}
+
+impl OutlierStats {
+    pub fn record(&mut self, timestamp: DateTime<Utc>, ip: &str, path: &str, status_code: u16, response_time: f64) {
+        // Without a response time in the log there is nothing to compare
+        if response_time <= 0.0 {
+            return;
+        }
+
+        let baseline = self.paths.entry(path.to_string()).or_default();
+        let expected = baseline.mean;
+        let Some(z) = baseline.observe(response_time) else {
+            return;
+        };
+        if z < Z_THRESHOLD || response_time - expected < MIN_EXCESS {
+            return;
+        }
+
+        baseline.outliers += 1;
+        self.total += 1;
+        if self.recent.len() == KEEP {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(Outlier {
+            timestamp,
+            ip: ip.to_string(),
+            path: path.to_string(),
+            status_code,
+            response_time,
+            expected,
+            z,
+        });
+    }
+}
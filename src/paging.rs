@@ -0,0 +1,243 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use crate::alerts::{Alert, Transition};
+use crate::notices::Notices;
+
+const PAGERDUTY_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+pub const OPSGENIE_URL: &str = "https://api.opsgenie.com";
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone)]
+pub enum Notifier {
+    // Events API v2 integration (routing) key
+    PagerDuty { routing_key: String },
+    // API integration key; use https://api.eu.opsgenie.com for EU accounts
+    Opsgenie { api_key: String, api_url: String },
+    // Any URL taking a JSON POST, such as a chat bot
+    Webhook { url: String },
+    // A Slack incoming webhook, or Mattermost's, which takes the same text
+    Slack { url: String },
+    // Run with sh -c, the alert in HTTOP_ALERT_* environment variables
+    Command { command: String },
+}
+
+// Triggers and resolves incidents as alert rules start and stop firing.
+// Requests go through curl(1), and commands run, on a background thread so
+// a slow or unreachable endpoint never stalls the display.
+//
+// A rule flapping around its threshold would notify on every swing, so once
+// a rule's firing went out, it firing again within the cooldown is held
+// back: dropped along with its resolve if that comes first, sent once the
+// cooldown is over if the rule still fires
+#[derive(Debug, Clone)]
+pub struct Paging {
+    notifiers: Vec<Notifier>,
+    host: String,
+    cooldown: Duration,
+    // When each rule's firing was last sent
+    last_sent: HashMap<String, Instant>,
+    // Rules firing without having notified
+    held_back: HashSet<String>,
+    notices: Arc<Notices>,
+}
+
+impl Paging {
+    pub fn new(notifiers: Vec<Notifier>, cooldown: Duration, notices: Arc<Notices>) -> Self {
+        let host = fs::read_to_string("/proc/sys/kernel/hostname")
+            .map(|name| name.trim().to_string())
+            .unwrap_or_else(|_| "httop".to_string());
+        Paging { notifiers, host, cooldown, last_sent: HashMap::new(), held_back: HashSet::new(), notices }
+    }
+
+    pub fn send(&mut self, now: Instant, transition: &Transition) {
+        if self.notifiers.is_empty() {
+            return;
+        }
+        match transition {
+            Transition::Fired(alert) => {
+                let rule = alert.rule.to_string();
+                if self.last_sent.get(&rule).is_some_and(|sent| now.saturating_duration_since(*sent) < self.cooldown) {
+                    self.held_back.insert(rule);
+                    return;
+                }
+                self.last_sent.insert(rule, now);
+            }
+            Transition::Resolved(alert) => {
+                if self.held_back.remove(&alert.rule.to_string()) {
+                    return;
+                }
+            }
+        }
+        self.notify(transition);
+    }
+
+    // Sends the held back firings of rules still firing past their cooldown
+    pub fn release(&mut self, now: Instant, active: &[Alert]) {
+        for alert in active {
+            let rule = alert.rule.to_string();
+            if self.held_back.contains(&rule)
+                && self.last_sent.get(&rule).is_none_or(|sent| now.saturating_duration_since(*sent) >= self.cooldown) {
+                self.held_back.remove(&rule);
+                self.last_sent.insert(rule, now);
+                self.notify(&Transition::Fired(alert.clone()));
+            }
+        }
+    }
+
+    fn notify(&self, transition: &Transition) {
+        for notifier in &self.notifiers {
+            if let Notifier::Command { command } = notifier {
+                let mut command = self.command(command, transition);
+                let notices = Arc::clone(&self.notices);
+                thread::spawn(move || match command.status() {
+                    Ok(status) if !status.success() => notices.post(format!("alert command failed: {}", status)),
+                    Ok(_) => {}
+                    Err(e) => notices.post(format!("could not run alert command: {}", e)),
+                });
+            } else if let Some(request) = self.request(notifier, transition) {
+                let notices = Arc::clone(&self.notices);
+                thread::spawn(move || {
+                    if let Err(e) = curl(&request) {
+                        notices.post(format!("could not notify {}: {}", request.url, e));
+                    }
+                });
+            }
+        }
+    }
+
+    fn command(&self, command: &str, transition: &Transition) -> Command {
+        let (alert, status) = match transition {
+            Transition::Fired(alert) => (alert, "firing"),
+            Transition::Resolved(alert) => (alert, "resolved"),
+        };
+        let mut child = Command::new("sh");
+        child.args(["-c", command])
+            .env("HTTOP_ALERT_STATUS", status)
+            .env("HTTOP_ALERT_RULE", alert.rule.to_string())
+            .env("HTTOP_ALERT_VALUE", alert.rule.metric.format(alert.value))
+            .env("HTTOP_ALERT_HOST", &self.host)
+            .env("HTTOP_ALERT_SUMMARY", self.summary(alert))
+            // Output would land in the middle of the display
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        child
+    }
+
+    // The same rule on the same host always maps to one incident
+    fn dedup_key(&self, alert: &Alert) -> String {
+        format!("httop/{}/{}", self.host, alert.rule)
+    }
+
+    fn summary(&self, alert: &Alert) -> String {
+        format!("httop on {}: {} (now {})", self.host, alert.rule, alert.rule.metric.format(alert.value))
+    }
+
+    // None for commands, which are run rather than posted
+    fn request(&self, notifier: &Notifier, transition: &Transition) -> Option<PageRequest> {
+        let (alert, firing) = match transition {
+            Transition::Fired(alert) => (alert, true),
+            Transition::Resolved(alert) => (alert, false),
+        };
+        let key = self.dedup_key(alert);
+
+        Some(match notifier {
+            Notifier::PagerDuty { routing_key } => {
+                let body = if firing {
+                    json!({
+                        "routing_key": routing_key,
+                        "event_action": "trigger",
+                        "dedup_key": key,
+                        "payload": {"summary": self.summary(alert), "source": self.host, "severity": "error", "component": "httop"},
+                    })
+                } else {
+                    json!({"routing_key": routing_key, "event_action": "resolve", "dedup_key": key})
+                };
+                PageRequest { url: PAGERDUTY_URL.to_string(), auth: None, body: body.to_string() }
+            }
+            Notifier::Opsgenie { api_key, api_url } => {
+                let api_url = api_url.trim_end_matches('/');
+                let (url, body) = if firing {
+                    (format!("{}/v2/alerts", api_url),
+                        json!({"message": self.summary(alert), "alias": key, "source": "httop", "priority": "P2", "tags": ["httop"]}))
+                } else {
+                    (format!("{}/v2/alerts/{}/close?identifierType=alias", api_url, percent_encode(&key)),
+                        json!({"source": "httop", "note": "Alert rule no longer holds"}))
+                };
+                PageRequest { url, auth: Some(format!("GenieKey {}", api_key)), body: body.to_string() }
+            }
+            Notifier::Webhook { url } => {
+                let body = json!({
+                    "status": if firing { "firing" } else { "resolved" },
+                    "rule": alert.rule.to_string(),
+                    "value": alert.rule.metric.format(alert.value),
+                    "host": self.host,
+                    "summary": self.summary(alert),
+                });
+                PageRequest { url: url.clone(), auth: None, body: body.to_string() }
+            }
+            Notifier::Slack { url } => {
+                let text = if firing {
+                    format!(":rotating_light: *{}* on {}, now {}", alert.rule, self.host, alert.rule.metric.format(alert.value))
+                } else {
+                    format!(":white_check_mark: Resolved: *{}* on {}, now {}", alert.rule, self.host, alert.rule.metric.format(alert.value))
+                };
+                PageRequest { url: url.clone(), auth: None, body: json!({"text": text}).to_string() }
+            }
+            Notifier::Command { .. } => return None,
+        })
+    }
+}
+
+struct PageRequest {
+    url: String,
+    auth: Option<String>,
+    body: String,
+}
+
+// Options go in through stdin (`--config -`) to keep keys out of the
+// process list
+fn curl(request: &PageRequest) -> io::Result<()> {
+    let mut options = format!(
+        "url = {}\nrequest = \"POST\"\nheader = \"Content-Type: application/json\"\ndata-binary = {}\n",
+        config_string(&request.url), config_string(&request.body));
+    if let Some(auth) = &request.auth {
+        options.push_str(&format!("header = {}\n", config_string(&format!("Authorization: {}", auth))));
+    }
+
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time", "10", "--output", "/dev/null", "--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(options.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(message));
+    }
+    Ok(())
+}
+
+fn config_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn percent_encode(value: &str) -> String {
+    value.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,607 @@
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, LazyLock};
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+pub use crate::formats::LogFormat;
+use crate::formats;
+use crate::geoip;
+use crate::scan::{self, Scanner};
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub timestamp: DateTime<Utc>,
+    pub ip: String,
+    pub method: String,
+    // Without its query string, unless --keep-query or --group-by-query
+    // left it or part of it
+    pub path: String,
+    // The query string taken off the path, without the ?
+    pub query: Option<String>,
+    // HTTP/1.0, HTTP/1.1, HTTP/2 or HTTP/3, when the log has it
+    pub protocol: Option<String>,
+    pub status_code: u16,
+    pub response_time: f64,
+    pub referrer: String,
+    pub user_agent: String,
+    pub bytes_sent: usize,
+    pub bytes_received: Option<usize>,
+    // Response body size before gzip/brotli compression
+    pub uncompressed_bytes: Option<usize>,
+    // Of the response time, what the backend took
+    pub upstream_time: Option<f64>,
+    // Trailing key=value pairs appended to the log format
+    pub fields: Vec<(String, String)>,
+    pub tenant: Option<String>,
+    // The virtual host the request was for, lowercased and without a port
+    pub vhost: Option<String>,
+    // HIT, MISS, BYPASS and so on, uppercased
+    pub cache_status: Option<String>,
+    pub country: Option<&'static geoip::Country>,
+    // The network the client address belongs to, e.g. "AS15169 Google LLC"
+    pub network: Option<Arc<str>>,
+    // The crawler or other bot the request came from, if any
+    pub bot: Option<&'static str>,
+    // The file (or other input) the line was read from
+    pub source: Option<Arc<str>>,
+    // How many lines this one stands for, with --sample
+    pub weight: usize,
+}
+
+// Common Nginx log format regex
+// Example: 192.168.1.1 - - [29/Nov/2021:12:34:56 +0000] "GET /page.html HTTP/1.1" 200 2326 "http://referrer.com" "Mozilla/5.0 ..." 0.002
+// optionally followed by custom key=value fields, e.g. tenant=acme
+static NGINX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(\S+) (?:\S+) (?:\S+) \[([^\]]+)\] "(\S+) (\S+)([^"]*)" (\d+) (\d+) "([^"]*)" "([^"]*)" (?:(\d+\.\d+))?(.*)"#).unwrap()
+});
+
+// The parts of a line in the default format, as NGINX captures them
+#[derive(Debug, PartialEq)]
+struct NginxLine<'a> {
+    ip: &'a str,
+    time: &'a str,
+    method: &'a str,
+    path: &'a str,
+    protocol: &'a str,
+    status: &'a str,
+    bytes: &'a str,
+    referrer: &'a str,
+    user_agent: &'a str,
+    response_time: Option<&'a str>,
+    rest: &'a str,
+}
+
+impl<'a> NginxLine<'a> {
+    fn from_captures(caps: &regex::Captures<'a>) -> Self {
+        let group = |i: usize| caps.get(i).map_or("", |m| m.as_str());
+        NginxLine {
+            ip: group(1),
+            time: group(2),
+            method: group(3),
+            path: group(4),
+            protocol: group(5),
+            status: group(6),
+            bytes: group(7),
+            referrer: group(8),
+            user_agent: group(9),
+            response_time: caps.get(10).map(|m| m.as_str()),
+            rest: group(11),
+        }
+    }
+}
+
+pub fn parse_log_line(line: &str) -> Option<Request> {
+    let line = split_nginx(line).or_else(|| Some(NginxLine::from_captures(&NGINX.captures(line)?)))?;
+
+    let timestamp = match scan::clf_time(line.time) {
+        Some(timestamp) => timestamp,
+        None => DateTime::parse_from_str(line.time, "%d/%b/%Y:%H:%M:%S %z").ok()?.with_timezone(&Utc),
+    };
+    let mut request = Request::new(timestamp, line.ip, line.method, line.path, line.status.parse().ok()?, line.bytes.parse().ok()?);
+    request.protocol = formats::http_version(line.protocol);
+    request.referrer = line.referrer.to_string();
+    request.user_agent = line.user_agent.to_string();
+    request.response_time = line.response_time.map_or(0.0, |time| time.parse::<f64>().unwrap_or(0.0));
+    Some(request.with_fields(parse_fields(line.rest)))
+}
+
+// The same parts as NGINX, without the regex, for lines laid out just as
+// nginx writes them; anything else, e.g. a line with a prefix or with
+// characters beyond ASCII, which nginx escapes, is left to the regex
+fn split_nginx(line: &str) -> Option<NginxLine<'_>> {
+    if !line.is_ascii() {
+        return None;
+    }
+    let mut scanner = Scanner::new(line);
+    let ip = scanner.token()?;
+    scanner.expect(" ")?;
+    scanner.token()?;
+    scanner.expect(" ")?;
+    scanner.token()?;
+    scanner.expect(" [")?;
+    let time = scanner.until(']').filter(|time| !time.is_empty())?;
+    scanner.expect(" \"")?;
+    let method = scanner.token()?;
+    scanner.expect(" ")?;
+    let path = scanner.token()?;
+    let protocol = scanner.until('"').filter(|protocol| !protocol.is_empty())?;
+    scanner.expect(" ")?;
+    let status = scanner.digits()?;
+    scanner.expect(" ")?;
+    let bytes = scanner.digits()?;
+    scanner.expect(" \"")?;
+    let referrer = scanner.until('"')?;
+    scanner.expect(" \"")?;
+    let user_agent = scanner.until('"')?;
+    scanner.expect(" ")?;
+    let response_time = scanner.decimal();
+    let rest = scanner.rest();
+    Some(NginxLine {
+        ip, time, method, path, protocol, status, bytes, referrer, user_agent, response_time,
+        // (.*) stops at a line break
+        rest: rest.split('\n').next().unwrap_or(""),
+    })
+}
+
+impl Request {
+    // Referrer and user agent default to "-", as nginx logs them when absent
+    pub fn new(timestamp: DateTime<Utc>, ip: &str, method: &str, path: &str, status_code: u16, bytes_sent: usize) -> Self {
+        Request {
+            timestamp,
+            ip: address(ip),
+            method: method.to_string(),
+            path: path.to_string(),
+            query: None,
+            protocol: None,
+            status_code,
+            response_time: 0.0,
+            referrer: "-".to_string(),
+            user_agent: "-".to_string(),
+            bytes_sent,
+            bytes_received: None,
+            uncompressed_bytes: None,
+            upstream_time: None,
+            fields: Vec::new(),
+            tenant: None,
+            vhost: None,
+            cache_status: None,
+            country: None,
+            network: None,
+            bot: None,
+            source: None,
+            weight: 1,
+        }
+    }
+
+    // The machine that logged it: as its syslog header or hostname field
+    // has it, else the input it was read from
+    pub fn host(&self) -> Option<&str> {
+        self.fields.iter()
+            .find(|(key, _)| key == formats::HOSTNAME_FIELD)
+            .map(|(_, value)| value.as_str())
+            .or(self.source.as_deref())
+    }
+
+    // The path as requested, with its whole query string
+    pub fn url(&self) -> Cow<'_, str> {
+        match &self.query {
+            Some(query) => Cow::Owned(format!("{}?{}", self.path.split('?').next().unwrap_or(""), query)),
+            None => Cow::Borrowed(&self.path),
+        }
+    }
+
+    // Attaches custom fields along with the sizes, upstream time (and
+    // protocol, if the request line had none) derived from them
+    pub fn with_fields(mut self, fields: Vec<(String, String)>) -> Self {
+        if self.protocol.is_none() {
+            self.protocol = field_value::<String>(&fields, formats::PROTOCOL_FIELDS)
+                .and_then(|protocol| formats::http_version(&protocol));
+        }
+        self.bytes_received = field_value(&fields, &["request_length", "bytes_received"]);
+        self.uncompressed_bytes = field_value(&fields, &["uncompressed_bytes", "upstream_response_length"])
+            .or_else(|| {
+                field_value::<f64>(&fields, &["gzip_ratio"])
+                    .map(|ratio| (self.bytes_sent as f64 * ratio).round() as usize)
+            });
+        self.upstream_time = formats::upstream_time(&fields);
+        self.fields = fields;
+        self
+    }
+}
+
+// A client address as logs write it: 203.0.113.7 or 2001:db8::7, in
+// brackets ([2001:db8::7]), with a port (203.0.113.7:4711,
+// [2001:db8::7]:443) or with a zone (fe80::7%eth0). IPv4 mapped into IPv6
+// comes back as IPv4
+pub fn client_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    let ip = match value.strip_prefix('[') {
+        Some(bracketed) => {
+            let (ip, port) = bracketed.split_once(']')?;
+            (port.is_empty() || port.strip_prefix(':').is_some_and(is_port)).then_some(ip)?
+        }
+        // One colon is a port, more are IPv6
+        None => match value.split_once(':') {
+            Some((ip, port)) if is_port(port) => ip,
+            _ => value,
+        },
+    };
+    let ip = ip.split_once('%').map_or(ip, |(ip, _)| ip);
+    ip.parse::<IpAddr>().ok().map(|ip| ip.to_canonical())
+}
+
+fn is_port(text: &str) -> bool {
+    !text.is_empty() && text.len() <= 5 && text.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+// The address the way Display writes it, so one client is one key however
+// the log wrote it, or the text as it was if it is no address
+fn address(ip: &str) -> String {
+    if ip.parse::<Ipv4Addr>().is_ok() {
+        return ip.to_string();
+    }
+    client_ip(ip).map_or_else(|| ip.to_string(), |ip| ip.to_string())
+}
+
+// First of the named fields that is present and parses
+pub fn field_value<T: std::str::FromStr>(fields: &[(String, String)], names: &[&str]) -> Option<T> {
+    names.iter()
+        .find_map(|name| fields.iter().find(|(key, _)| key == name))
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+// Parses `key=value key="quoted value"` pairs, skipping nginx's "-" placeholders
+pub fn parse_fields(input: &str) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut rest = input.trim_start();
+
+    while !rest.is_empty() {
+        let key_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = &rest[key_end..];
+
+        let Some(after_eq) = rest.strip_prefix('=') else {
+            rest = rest.trim_start();
+            continue;
+        };
+
+        let value;
+        if let Some(quoted) = after_eq.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            value = &quoted[..end];
+            rest = quoted.get(end + 1..).unwrap_or("");
+        } else {
+            let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+            value = &after_eq[..end];
+            rest = &after_eq[end..];
+        }
+
+        if !key.is_empty() && !value.is_empty() && value != "-" {
+            fields.push((key.to_string(), value.to_string()));
+        }
+        rest = rest.trim_start();
+    }
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(format: &str, line: &str) -> Request {
+        let mut format: LogFormat = format.parse().unwrap();
+        format.parse(line).unwrap_or_else(|| panic!("{} did not parse: {}", format.name(), line))
+    }
+
+    fn time(request: &Request) -> String {
+        request.timestamp.to_rfc3339()
+    }
+
+    #[test]
+    fn nginx() {
+        let request = parse("nginx", r#"192.168.1.1 - - [29/Nov/2021:12:34:56 +0000] "GET /page.html?a=1 HTTP/1.1" 200 2326 "http://referrer.com/" "Mozilla/5.0 (X11)" 0.002"#);
+        assert_eq!(request.ip, "192.168.1.1");
+        assert_eq!(time(&request), "2021-11-29T12:34:56+00:00");
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/page.html?a=1");
+        assert_eq!(request.protocol.as_deref(), Some("HTTP/1.1"));
+        assert_eq!(request.status_code, 200);
+        assert_eq!(request.bytes_sent, 2326);
+        assert_eq!(request.referrer, "http://referrer.com/");
+        assert_eq!(request.user_agent, "Mozilla/5.0 (X11)");
+        assert_eq!(request.response_time, 0.002);
+        assert!(request.fields.is_empty());
+    }
+
+    #[test]
+    fn client_addresses() {
+        let line = |ip: &str| format!(r#"{} - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 200 5 "-" "-" 0.001"#, ip);
+        for (logged, ip) in [
+            ("2001:DB8:0:0::7", "2001:db8::7"),
+            ("[2001:db8::7]", "2001:db8::7"),
+            ("[2001:db8::7]:443", "2001:db8::7"),
+            ("fe80::7%eth0", "fe80::7"),
+            ("::ffff:192.0.2.1", "192.0.2.1"),
+            ("192.0.2.1:4711", "192.0.2.1"),
+            ("unix:", "unix:"),
+            ("-", "-"),
+        ] {
+            assert_eq!(parse("nginx", &line(logged)).ip, ip, "{}", logged);
+        }
+        assert_eq!(client_ip("[2001:db8::7]:http"), None);
+        assert_eq!(client_ip("2001:db8::7:443"), Some("2001:db8::7:443".parse().unwrap()));
+    }
+
+    #[test]
+    fn nginx_fields() {
+        let request = parse("nginx", r#"10.0.0.1 - - [29/Nov/2021:12:34:56 +0100] "POST /api HTTP/2.0" 201 12 "-" "curl/8" 0.150 tenant=acme host="Example.com:443" request_length=420 upstream_response_time=0.120 x=-"#);
+        assert_eq!(time(&request), "2021-11-29T11:34:56+00:00");
+        assert_eq!(request.protocol.as_deref(), Some("HTTP/2"));
+        assert_eq!(request.referrer, "-");
+        assert_eq!(request.fields, vec![
+            ("tenant".to_string(), "acme".to_string()),
+            ("host".to_string(), "Example.com:443".to_string()),
+            ("request_length".to_string(), "420".to_string()),
+            ("upstream_response_time".to_string(), "0.120".to_string()),
+        ]);
+        assert_eq!(request.bytes_received, Some(420));
+        assert_eq!(request.upstream_time, Some(0.12));
+    }
+
+    #[test]
+    fn nginx_rejects_other_lines() {
+        let mut format = LogFormat::Nginx;
+        assert!(format.parse("").is_none());
+        assert!(format.parse("not an access log line").is_none());
+        assert!(format.parse(r#"10.0.0.1 - - [yesterday] "GET / HTTP/1.1" 200 1 "-" "-" 0.1"#).is_none());
+    }
+
+    #[test]
+    fn syslog_prefix() {
+        let request = parse("nginx", r#"Jan 12 10:00:01 web1 nginx: 10.0.0.1 - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 404 0 "-" "-" 0.001"#);
+        assert_eq!(request.ip, "10.0.0.1");
+        assert_eq!(request.status_code, 404);
+        assert_eq!(request.host(), Some("web1"));
+
+        let request = parse("nginx", r#"<190>1 2026-01-12T10:00:01Z web2 nginx 42 - - 10.0.0.1 - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 200 0 "-" "-" 0.001"#);
+        assert_eq!(request.host(), Some("web2"));
+
+        let mut request = parse("nginx", r#"10.0.0.1 - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 200 0 "-" "-" 0.001"#);
+        assert_eq!(request.host(), None);
+        request.source = Some(Arc::from("access.log"));
+        assert_eq!(request.host(), Some("access.log"));
+    }
+
+    #[test]
+    fn apache_common() {
+        let request = parse("apache-common", r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 -"#);
+        assert_eq!(request.ip, "127.0.0.1");
+        assert_eq!(time(&request), "2000-10-10T20:55:36+00:00");
+        assert_eq!(request.path, "/apache_pb.gif");
+        assert_eq!(request.status_code, 200);
+        assert_eq!(request.bytes_sent, 0);
+        assert_eq!(request.response_time, 0.0);
+    }
+
+    #[test]
+    fn apache_combined() {
+        let request = parse("combined", r#"127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /q?x=\"y\" HTTP/1.1" 302 2326 "http://example.com/start.html" "Mozilla/4.08 [en] \"quoted\"""#);
+        assert_eq!(request.path, r#"/q?x="y""#);
+        assert_eq!(request.status_code, 302);
+        assert_eq!(request.referrer, "http://example.com/start.html");
+        assert_eq!(request.user_agent, r#"Mozilla/4.08 [en] "quoted""#);
+    }
+
+    #[test]
+    fn traefik() {
+        let request = parse("traefik", r#"192.0.2.1 - - [10/Oct/2026:13:55:36 +0000] "GET /x HTTP/1.1" 200 123 "-" "curl/8" 42 "web@docker" "http://172.17.0.3:80" 3ms"#);
+        assert_eq!(request.ip, "192.0.2.1");
+        assert_eq!(request.path, "/x");
+        assert_eq!(request.user_agent, "curl/8");
+        assert_eq!(request.response_time, 0.003);
+        assert!(request.fields.contains(&("router".to_string(), "web@docker".to_string())));
+        assert!(request.fields.contains(&("upstream".to_string(), "http://172.17.0.3:80".to_string())));
+    }
+
+    #[test]
+    fn envoy() {
+        let request = parse("envoy", r#"[2026-10-10T20:17:00.310Z] "POST /api/v1/locations HTTP/2" 204 - 154 0 226 100 "10.0.35.28" "nsq2http" "cc21d9b0-cf5c-432b-8c7e-98aeb7988cd2" "locations" "tcp://10.0.2.1:80""#);
+        assert_eq!(request.ip, "10.0.35.28");
+        assert_eq!(time(&request), "2026-10-10T20:17:00.310+00:00");
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.status_code, 204);
+        assert_eq!(request.bytes_received, Some(154));
+        assert_eq!(request.response_time, 0.226);
+        assert_eq!(request.user_agent, "nsq2http");
+        assert!(request.fields.contains(&("request_id".to_string(), "cc21d9b0-cf5c-432b-8c7e-98aeb7988cd2".to_string())));
+        assert!(request.fields.contains(&("upstream".to_string(), "tcp://10.0.2.1:80".to_string())));
+    }
+
+    #[test]
+    fn heroku() {
+        let request = parse("heroku", r#"2026-10-10T13:55:36.123456+00:00 heroku[router]: at=info method=GET path="/x" host=app.herokuapp.com request_id=8601b555 fwd="204.204.204.204" dyno=web.1 connect=1ms service=18ms status=200 bytes=975 protocol=https"#);
+        assert_eq!(request.ip, "204.204.204.204");
+        assert_eq!(request.path, "/x");
+        assert_eq!(request.status_code, 200);
+        assert_eq!(request.bytes_sent, 975);
+        assert!((request.response_time - 0.018).abs() < 1e-9);
+        assert!(request.fields.contains(&("dyno".to_string(), "web.1".to_string())));
+
+        let mut format: LogFormat = "heroku".parse().unwrap();
+        assert!(format.parse("2026-10-10T13:55:36+00:00 app[web.1]: Started GET \"/x\"").is_none());
+    }
+
+    #[test]
+    fn json() {
+        let request = parse("json", r#"{"remote_addr":"10.1.1.1","time_iso8601":"2026-10-10T13:55:36+00:00","request":"GET /api/users HTTP/1.1","status":"200","body_bytes_sent":"512","request_time":"0.012","http_referer":"","http_user_agent":"curl/8","tenant":"acme"}"#);
+        assert_eq!(request.ip, "10.1.1.1");
+        assert_eq!(time(&request), "2026-10-10T13:55:36+00:00");
+        assert_eq!(request.path, "/api/users");
+        assert_eq!(request.status_code, 200);
+        assert_eq!(request.bytes_sent, 512);
+        assert_eq!(request.response_time, 0.012);
+        assert_eq!(request.referrer, "-");
+        assert!(request.fields.contains(&("tenant".to_string(), "acme".to_string())));
+    }
+
+    #[test]
+    fn caddy() {
+        let request = parse("caddy", r#"{"level":"info","ts":1791640536.5,"msg":"handled request","request":{"remote_ip":"10.0.0.2","client_ip":"203.0.113.7","proto":"HTTP/2.0","method":"GET","uri":"/index.html","headers":{"User-Agent":["Mozilla/5.0"],"Referer":["https://example.com/"]}},"duration":0.0042,"size":1024,"status":200}"#);
+        assert_eq!(request.ip, "203.0.113.7");
+        assert_eq!(request.timestamp.timestamp(), 1791640536);
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/index.html");
+        assert_eq!(request.protocol.as_deref(), Some("HTTP/2"));
+        assert_eq!(request.bytes_sent, 1024);
+        assert_eq!(request.response_time, 0.0042);
+        assert_eq!(request.user_agent, "Mozilla/5.0");
+        assert_eq!(request.referrer, "https://example.com/");
+    }
+
+    #[test]
+    fn gcp() {
+        let mut format: LogFormat = "gcp".parse().unwrap();
+        let lines = [
+            "[",
+            "{",
+            r#"  "httpRequest": {"remoteIp": "198.51.100.4", "requestMethod": "GET", "requestUrl": "https://example.com/shop?id=1", "status": 503, "responseSize": "200", "latency": "0.023s", "userAgent": "curl/8"},"#,
+            r#"  "timestamp": "2026-10-10T13:55:36.5Z""#,
+            "}",
+            "]",
+        ];
+        let requests: Vec<Request> = lines.iter().filter_map(|line| format.parse(line)).collect();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.ip, "198.51.100.4");
+        assert_eq!(request.status_code, 503);
+        assert_eq!(request.bytes_sent, 200);
+        assert_eq!(request.response_time, 0.023);
+        assert_eq!(request.path, "/shop?id=1");
+    }
+
+    #[test]
+    fn logfmt() {
+        let request = parse("logfmt", r#"time=2026-10-10T13:55:36Z level=info method=GET path=/api/users status=200 bytes=512 duration=12.5ms remote_ip=10.1.1.1 ua="Go-http-client/1.1""#);
+        assert_eq!(request.ip, "10.1.1.1");
+        assert_eq!(time(&request), "2026-10-10T13:55:36+00:00");
+        assert_eq!(request.path, "/api/users");
+        assert_eq!(request.status_code, 200);
+        assert_eq!(request.bytes_sent, 512);
+        assert_eq!(request.response_time, 0.0125);
+        assert_eq!(request.user_agent, "Go-http-client/1.1");
+    }
+
+    #[test]
+    fn cloudfront() {
+        let line = [
+            "2026-10-10", "13:55:36", "FRA56-C1", "2390", "192.0.2.10", "GET", "d111111abcdef8.cloudfront.net",
+            "/index.html", "200", "-", "Mozilla/5.0%20(Windows)", "a=1", "-", "Hit", "abc==", "www.example.com",
+            "https", "25", "0.001", "-", "TLSv1.3", "TLS_AES_128_GCM_SHA256", "Hit", "HTTP/2.0",
+        ].join("\t");
+        let request = parse("cloudfront", &line);
+        assert_eq!(request.ip, "192.0.2.10");
+        assert_eq!(time(&request), "2026-10-10T13:55:36+00:00");
+        assert_eq!(request.path, "/index.html?a=1");
+        assert_eq!(request.status_code, 200);
+        assert_eq!(request.bytes_sent, 2390);
+        assert_eq!(request.response_time, 0.001);
+        assert_eq!(request.protocol.as_deref(), Some("HTTP/2"));
+        assert!(request.fields.contains(&("ssl_protocol".to_string(), "TLSv1.3".to_string())));
+    }
+
+    #[test]
+    fn iis() {
+        let mut format: LogFormat = "iis".parse().unwrap();
+        assert!(format.parse("#Software: Microsoft Internet Information Services 10.0").is_none());
+        assert!(format.parse("#Fields: date time s-ip cs-method cs-uri-stem cs-uri-query s-port cs-username c-ip cs(User-Agent) cs(Referer) sc-status sc-substatus sc-win32-status time-taken").is_none());
+        let request = format.parse("2026-10-10 13:55:36 10.0.0.5 POST /login.aspx - 443 - 203.0.113.8 Mozilla/5.0+(Windows+NT+10.0) - 401 1 0 250").unwrap();
+        assert_eq!(request.ip, "203.0.113.8");
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/login.aspx");
+        assert_eq!(request.status_code, 401);
+        assert_eq!(request.response_time, 0.25);
+    }
+
+    #[test]
+    fn log_format_template() {
+        let template = r#"$remote_addr [$time_local] "$request" $status $body_bytes_sent $request_time $http_x_tenant_id"#;
+        let mut format = LogFormat::Custom(template.parse().unwrap());
+        let request = format.parse(r#"10.2.2.2 [10/Oct/2026:13:55:36 +0000] "DELETE /items/7 HTTP/1.1" 204 0 0.050 acme"#).unwrap();
+        assert_eq!(request.ip, "10.2.2.2");
+        assert_eq!(request.method, "DELETE");
+        assert_eq!(request.path, "/items/7");
+        assert_eq!(request.status_code, 204);
+        assert_eq!(request.response_time, 0.05);
+        assert_eq!(request.fields, vec![("http_x_tenant_id".to_string(), "acme".to_string())]);
+    }
+
+    #[test]
+    fn regex() {
+        let pattern = crate::pattern::Pattern::new(
+            r#"^(?P<time>\S+ \S+) \| (?P<ip>\S+) \| (?P<method>\S+) (?P<path>\S+) (?P<status>\d{3}) (?P<bytes>\d+)b (?P<rt_ms>[\d.]+)ms "(?P<ua>[^"]*)" (?P<host>\S+)"#,
+            Some("%Y-%m-%d %H:%M:%S%.3f".to_string()),
+        ).unwrap();
+        let mut format = LogFormat::Regex(pattern);
+        let request = format.parse(r#"2026-10-10 13:55:36.250 | 10.3.3.3 | GET /health 200 2b 1.5ms "kube-probe/1.29" api.internal"#).unwrap();
+        assert_eq!(time(&request), "2026-10-10T13:55:36.250+00:00");
+        assert_eq!(request.ip, "10.3.3.3");
+        assert_eq!(request.path, "/health");
+        assert_eq!(request.bytes_sent, 2);
+        assert_eq!(request.response_time, 0.0015);
+        assert_eq!(request.user_agent, "kube-probe/1.29");
+        assert_eq!(request.fields, vec![("host".to_string(), "api.internal".to_string())]);
+        assert!(format.parse("something else").is_none());
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(parse_fields(r#" a=1 b="two words" c=- d= e f=3"#), vec![
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "two words".to_string()),
+            ("f".to_string(), "3".to_string()),
+        ]);
+    }
+
+    // Whatever the splitter takes has to come out as the regex has it
+    #[test]
+    fn nginx_split() {
+        let lines = [
+            r#"192.168.1.1 - - [29/Nov/2021:12:34:56 +0000] "GET /page.html?a=1 HTTP/1.1" 200 2326 "http://referrer.com/" "Mozilla/5.0 (X11)" 0.002"#,
+            r#"10.0.0.1 - alice [29/Nov/2021:12:34:56 -0700] "POST /api HTTP/2.0" 201 0 "" "" 12.5 tenant=acme upstream_response_time=0.010"#,
+            r#"10.0.0.1 - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 200 5 "-" "curl/8.0" tenant=acme"#,
+            r#"10.0.0.1 - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 200 5 "-" "curl/8.0" 1.2.3"#,
+            r#"10.0.0.1 - - [29/Nov/2021:12:34:56 +0000] "GET /a"b HTTP/1.1" 200 5 "-" "curl/8.0" 0.1"#,
+            "10.0.0.1 - - [29/Nov/2021:12:34:56 +0000] \"GET /\tHTTP/1.1\" 200 5 \"-\" \"curl\" 0.1",
+            r#"10.0.0.1 - - [29/Nov/2021:12:34:56 +0000] "GET /ab" 200 5 "-" "curl/8.0" 0.1"#,
+            r#"10.0.0.1  - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 200 5 "-" "curl/8.0" 0.1"#,
+            r#"web1 nginx: 10.0.0.1 - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 200 5 "-" "curl/8.0" 0.1"#,
+            r#"10.0.0.1 - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 200 5 "-" "Mözilla" 0.1"#,
+        ];
+        for line in lines {
+            if let Some(split) = split_nginx(line) {
+                assert_eq!(Some(split), NGINX.captures(line).map(|caps| NginxLine::from_captures(&caps)), "{}", line);
+            }
+        }
+        assert!(split_nginx(lines[0]).is_some());
+        // No protocol, a doubled space, a prefix and beyond ASCII
+        assert!(lines[6..].iter().all(|line| split_nginx(line).is_none()));
+        let request = parse_log_line(lines[6]).unwrap();
+        assert_eq!((request.path.as_str(), request.protocol), ("/ab", None));
+        assert_eq!(parse_log_line(lines[8]).unwrap().ip, "10.0.0.1");
+        assert_eq!(parse_log_line(lines[9]).unwrap().user_agent, "Mözilla");
+    }
+
+    #[test]
+    fn clf_time() {
+        for time in ["29/Nov/2021:12:34:56 +0000", "01/Jan/2026:00:00:00 -0700", "31/Dec/1999:23:59:59 +0530"] {
+            let parsed = DateTime::parse_from_str(time, "%d/%b/%Y:%H:%M:%S %z").unwrap().with_timezone(&Utc);
+            assert_eq!(scan::clf_time(time), Some(parsed), "{}", time);
+        }
+        for time in ["31/Feb/2021:12:34:56 +0000", "29/nov/2021:12:34:56 +0000", "29/Nov/2021:12:34:60 +0000", "29/Nov/2021:12:34:56 +0099", "9/Nov/2021:12:34:56 +0000"] {
+            assert_eq!(scan::clf_time(time), None, "{}", time);
+        }
+    }
+}
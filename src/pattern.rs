@@ -0,0 +1,91 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+
+use crate::template;
+use crate::{formats, Request};
+
+// Named groups with a meaning of their own; any other becomes a custom field
+const GROUPS: &[&str] = &[
+    "ip", "time", "request", "method", "path", "query", "protocol", "status", "bytes", "referrer", "ua", "rt", "rt_ms",
+];
+
+// A --regex log layout, its named groups mapped onto the request
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    regex: Regex,
+    // strftime layout of the time group, e.g. "%Y-%m-%d %H:%M:%S%.3f"
+    time_format: Option<String>,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str, time_format: Option<String>) -> Result<Self, String> {
+        let regex = Regex::new(pattern).map_err(|e| format!("invalid --regex: {}", e))?;
+        let has = |name: &str| regex.capture_names().flatten().any(|group| group == name);
+        if !(has("request") || has("method") && has("path")) {
+            return Err("--regex needs a request group, (?P<request>...), or method and path groups".to_string());
+        }
+        if !has("status") {
+            return Err("--regex needs a status group, (?P<status>\\d{3})".to_string());
+        }
+        if let Some(format) = &time_format {
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(format!("invalid --time-format '{}'", format));
+            }
+            if !has("time") {
+                return Err("--time-format needs a time group in --regex, (?P<time>...)".to_string());
+            }
+        }
+        Ok(Pattern { regex, time_format })
+    }
+
+    pub fn parse(&self, line: &str) -> Option<Request> {
+        let caps = self.regex.captures(line)?;
+        let group = |name: &str| caps.name(name).map(|value| value.as_str()).filter(|value| !value.is_empty() && *value != "-");
+        let number = |name: &str| group(name).and_then(|value| value.parse::<f64>().ok());
+
+        let (mut method, mut path, mut protocol) = (group("method"), group("path"), group("protocol"));
+        if let Some(request) = group("request") {
+            let mut parts = request.split(' ');
+            method = method.or(parts.next());
+            path = path.or(parts.next());
+            protocol = protocol.or(parts.next());
+        }
+        let mut path = path?.to_string();
+        if let Some(query) = group("query") {
+            path.push_str(if query.starts_with('?') { "" } else { "?" });
+            path.push_str(query);
+        }
+
+        let timestamp = group("time").and_then(|time| self.timestamp(time)).unwrap_or_else(Utc::now);
+        let bytes_sent = group("bytes").and_then(|bytes| bytes.parse().ok()).unwrap_or(0);
+        let mut request = Request::new(timestamp, group("ip").unwrap_or("-"), method?, &path, group("status")?.parse().ok()?, bytes_sent);
+        request.protocol = protocol.and_then(formats::http_version);
+        request.response_time = number("rt").or_else(|| number("rt_ms").map(|millis| millis / 1_000.0)).unwrap_or(0.0);
+        if let Some(referrer) = group("referrer") {
+            request.referrer = referrer.to_string();
+        }
+        if let Some(user_agent) = group("ua") {
+            request.user_agent = user_agent.to_string();
+        }
+
+        let fields = self.regex.capture_names().flatten()
+            .filter(|name| !GROUPS.contains(name))
+            .filter_map(|name| Some((name.to_string(), group(name)?.to_string())))
+            .collect();
+        Some(request.with_fields(fields))
+    }
+
+    // With --time-format, or else as --log-format reads times, or seconds
+    // since the epoch; times without a zone are UTC
+    fn timestamp(&self, value: &str) -> Option<DateTime<Utc>> {
+        match &self.time_format {
+            Some(format) => DateTime::parse_from_str(value, format).map(|time| time.with_timezone(&Utc)).ok()
+                .or_else(|| NaiveDateTime::parse_from_str(value, format).ok().map(|time| time.and_utc())),
+            None => template::parse_datetime(value).or_else(|| {
+                let seconds = value.parse::<f64>().ok()?;
+                Utc.timestamp_millis_opt((seconds * 1000.0) as i64).single()
+            }),
+        }
+    }
+}
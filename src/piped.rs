@@ -0,0 +1,89 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+// The output of a helper program (gzip, journalctl, ...) read as a log
+// stream. Its stderr is discarded so it cannot scribble over the display
+pub struct Piped {
+    child: Child,
+    stdout: ChildStdout,
+    // The download feeding a decompressor
+    upstream: Option<Child>,
+}
+
+impl Piped {
+    pub fn spawn(program: &str, args: &[String]) -> io::Result<Self> {
+        let (child, stdout) = run(program, args, Stdio::null())?;
+        Ok(Piped { child, stdout, upstream: None })
+    }
+
+    // The output of a program that writes a file called `name`, such as a
+    // download, decompressed when the name says it is compressed
+    pub fn spawn_file(program: &str, args: &[String], name: &str) -> io::Result<Self> {
+        let Some(decompressor) = decompressor(name) else {
+            return Piped::spawn(program, args);
+        };
+        let (mut upstream, input) = run(program, args, Stdio::null())?;
+        let (child, stdout) = match run(decompressor, &["--decompress", "--stdout", "--quiet"].map(String::from), input.into()) {
+            Ok(decompressed) => decompressed,
+            Err(e) => {
+                let _ = upstream.kill();
+                let _ = upstream.wait();
+                return Err(e);
+            }
+        };
+        Ok(Piped { child, stdout, upstream: Some(upstream) })
+    }
+
+    // A rotated, compressed log decompressed with the system's gzip or zstd;
+    // None when the file is not compressed. These are read once to the end
+    // rather than followed
+    pub fn decompress(path: &Path) -> io::Result<Option<Self>> {
+        let Some(program) = decompressor(&path.to_string_lossy()) else {
+            return Ok(None);
+        };
+        // Report a missing file like any other, since the helper's stderr is discarded
+        fs::metadata(path)?;
+
+        let args = ["--decompress", "--stdout", "--quiet", &path.display().to_string()].map(String::from);
+        Piped::spawn(program, &args).map(Some)
+    }
+}
+
+impl Read for Piped {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stdout.read(buf)
+    }
+}
+
+impl Drop for Piped {
+    fn drop(&mut self) {
+        for child in [Some(&mut self.child), self.upstream.as_mut()].into_iter().flatten() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+fn run(program: &str, args: &[String], stdin: Stdio) -> io::Result<(Child, ChildStdout)> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("could not run {}: {}", program, e)))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    Ok((child, stdout))
+}
+
+fn decompressor(name: &str) -> Option<&'static str> {
+    if name.ends_with(".gz") {
+        Some("gzip")
+    } else if name.ends_with(".zst") {
+        Some("zstd")
+    } else {
+        None
+    }
+}
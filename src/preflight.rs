@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use crate::memory;
+
+#[derive(Debug, Clone, Default)]
+pub struct PreflightEntry {
+    pub count: usize,
+    pub total_response_time: f64,
+}
+
+impl PreflightEntry {
+    fn record(&mut self, response_time: f64, weight: usize) {
+        self.count += weight;
+        self.total_response_time += response_time * weight as f64;
+    }
+
+    pub fn avg_response_time(&self) -> f64 {
+        if self.count > 0 {
+            self.total_response_time / self.count as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+// CORS preflight (OPTIONS) requests, kept apart so their cost is visible
+#[derive(Debug, Clone, Default)]
+pub struct PreflightStats {
+    pub total: PreflightEntry,
+    pub paths: HashMap<String, PreflightEntry>,
+    pub origins: HashMap<String, usize>,
+}
+
+impl PreflightStats {
+    pub fn record(&mut self, method: &str, path: &str, origin: Option<&str>, response_time: f64, weight: usize) {
+        if method != "OPTIONS" {
+            return;
+        }
+
+        self.total.record(response_time, weight);
+        self.paths.entry(path.to_string()).or_default().record(response_time, weight);
+        if let Some(origin) = origin {
+            *self.origins.entry(origin.to_string()).or_insert(0) += weight;
+        }
+    }
+
+    pub fn memory(&self) -> usize {
+        memory::keyed(&self.paths) + memory::keyed(&self.origins)
+    }
+
+    // The origins with the fewest preflights go past `max_keys`
+    pub fn limit(&mut self, max_keys: usize) {
+        if let Some((threshold, mut ties)) = memory::limit(&self.origins, |&count| count, max_keys) {
+            memory::evict(&mut self.origins, |&count| count, threshold, &mut ties, |_| {});
+        }
+    }
+}
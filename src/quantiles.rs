@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// Bucket bounds grow by 2%, so any quantile read back is within 1% of a
+// response time that was really logged, however many were recorded
+const GAMMA: f64 = 1.02;
+// Anything faster counts as a microsecond
+const SMALLEST: f64 = 1e-6;
+
+// Response times in logarithmic buckets, in the manner of DDSketch: a few
+// hundred counters at most, for any number of requests
+#[derive(Debug, Clone, Default)]
+pub struct Quantiles {
+    buckets: BTreeMap<i32, usize>,
+    count: usize,
+    max: f64,
+}
+
+impl Quantiles {
+    pub fn record(&mut self, seconds: f64) {
+        *self.buckets.entry(bucket_of(seconds)).or_insert(0) += 1;
+        self.count += 1;
+        self.max = self.max.max(seconds);
+    }
+
+    // The response time `fraction` of the recorded ones stay under
+    pub fn quantile(&self, fraction: f64) -> Option<f64> {
+        let last = self.count.checked_sub(1)?;
+        let rank = (last as f64 * fraction).round() as usize;
+        let mut seen = 0;
+        for (&bucket, &count) in &self.buckets {
+            seen += count;
+            if seen > rank {
+                // The middle of the bucket, never above what was seen
+                return Some((2.0 * GAMMA.powi(bucket) / (GAMMA + 1.0)).min(self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // Response times in the buckets up to the one holding `seconds`
+    pub fn count_at_most(&self, seconds: f64) -> usize {
+        self.buckets.range(..=bucket_of(seconds)).map(|(_, count)| count).sum()
+    }
+
+    // Bytes of buckets on the heap, in B-tree nodes of 11 that are about
+    // two thirds full
+    pub fn memory(&self) -> usize {
+        self.buckets.len().div_ceil(7) * (11 * size_of::<(i32, usize)>() + 32)
+    }
+
+    fn merge(&mut self, other: &Quantiles) {
+        for (&bucket, &count) in &other.buckets {
+            *self.buckets.entry(bucket).or_insert(0) += count;
+        }
+        self.count += other.count;
+        self.max = self.max.max(other.max);
+    }
+}
+
+// Response times of the last minute, in slices of 10 seconds that drop off
+// as a whole once they are older
+#[derive(Debug, Clone)]
+pub struct RecentQuantiles {
+    started: Instant,
+    slices: VecDeque<(u64, Quantiles)>,
+}
+
+impl RecentQuantiles {
+    const SLICE: Duration = Duration::from_secs(10);
+    const SLICES: u64 = 6;
+
+    pub fn new(now: Instant) -> Self {
+        RecentQuantiles { started: now, slices: VecDeque::new() }
+    }
+
+    fn slice(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started).as_secs() / Self::SLICE.as_secs()
+    }
+
+    pub fn record(&mut self, now: Instant, seconds: f64) {
+        let slice = self.slice(now);
+        if self.slices.back().is_none_or(|(at, _)| *at != slice) {
+            self.slices.push_back((slice, Quantiles::default()));
+        }
+        if let Some((_, quantiles)) = self.slices.back_mut() {
+            quantiles.record(seconds);
+        }
+        while self.slices.front().is_some_and(|(at, _)| at + Self::SLICES <= slice) {
+            self.slices.pop_front();
+        }
+    }
+
+    pub fn quantile(&self, now: Instant, fraction: f64) -> Option<f64> {
+        let slice = self.slice(now);
+        let mut merged = Quantiles::default();
+        for (_, quantiles) in self.slices.iter().filter(|(at, _)| at + Self::SLICES > slice) {
+            merged.merge(quantiles);
+        }
+        merged.quantile(fraction)
+    }
+}
+
+fn bucket_of(seconds: f64) -> i32 {
+    (seconds.max(SMALLEST).ln() / GAMMA.ln()).ceil() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(quantile: Option<f64>, seconds: f64) -> bool {
+        quantile.is_some_and(|quantile| (quantile - seconds).abs() <= seconds * 0.01)
+    }
+
+    #[test]
+    fn within_a_percent() {
+        let mut quantiles = Quantiles::default();
+        assert_eq!(quantiles.quantile(0.5), None);
+        assert_eq!(quantiles.max(), None);
+        for millis in 1..=1000 {
+            quantiles.record(millis as f64 / 1000.0);
+        }
+        for fraction in [0.5, 0.95, 0.99, 1.0] {
+            assert!(close(quantiles.quantile(fraction), fraction), "p{}", fraction * 100.0);
+        }
+        assert_eq!(quantiles.max(), Some(1.0));
+        assert_eq!(quantiles.count(), 1000);
+        let fast = quantiles.count_at_most(0.1);
+        assert!((99..=102).contains(&fast), "{}", fast);
+    }
+
+    // Slices older than a minute no longer count
+    #[test]
+    fn recent() {
+        let start = Instant::now();
+        let mut recent = RecentQuantiles::new(start);
+        recent.record(start, 2.0);
+        let later = start + Duration::from_secs(30);
+        recent.record(later, 0.1);
+        assert!(close(recent.quantile(later, 1.0), 2.0));
+        assert!(close(recent.quantile(start + Duration::from_secs(65), 1.0), 0.1));
+        assert_eq!(recent.quantile(start + Duration::from_secs(95), 1.0), None);
+    }
+}
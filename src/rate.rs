@@ -0,0 +1,359 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Same sampling period the kernel uses for the load average
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct Ewma {
+    window: f64,
+    rate: f64,
+}
+
+impl Ewma {
+    fn new(window: Duration) -> Self {
+        Ewma {
+            window: window.as_secs_f64(),
+            rate: 0.0,
+        }
+    }
+
+    fn fold(&mut self, sample: f64, elapsed: f64) {
+        let alpha = 1.0 - (-elapsed / self.window).exp();
+        self.rate += alpha * (sample - self.rate);
+    }
+
+    fn decay(&mut self, elapsed: f64) {
+        self.rate *= (-elapsed / self.window).exp();
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+// Accumulates a quantity and hands it out once per sample interval
+#[derive(Debug, Clone)]
+struct Sampler {
+    pending: f64,
+    last_sample: Instant,
+}
+
+impl Sampler {
+    fn new(now: Instant) -> Self {
+        Sampler {
+            pending: 0.0,
+            last_sample: now,
+        }
+    }
+
+    fn advance(&mut self, now: Instant, ewmas: &mut [&mut Ewma]) {
+        let elapsed = now.saturating_duration_since(self.last_sample);
+        let intervals = (elapsed.as_secs_f64() / SAMPLE_INTERVAL.as_secs_f64()) as u32;
+        if intervals == 0 {
+            return;
+        }
+
+        // Everything counted so far belongs to the first elapsed interval,
+        // the remaining ones were idle and only decay the averages
+        let step = SAMPLE_INTERVAL.as_secs_f64();
+        let sample = self.pending / step;
+        let idle = step * (intervals - 1) as f64;
+        for ewma in ewmas.iter_mut() {
+            ewma.fold(sample, step);
+            ewma.decay(idle);
+        }
+
+        self.pending = 0.0;
+        self.last_sample += SAMPLE_INTERVAL * intervals;
+    }
+}
+
+// Per-second rate of some quantity (requests, busy seconds) averaged over
+// 1, 5 and 15 minutes, load-average style
+#[derive(Debug, Clone)]
+pub struct LoadRates {
+    sampler: Sampler,
+    pub one: Ewma,
+    pub five: Ewma,
+    pub fifteen: Ewma,
+}
+
+impl LoadRates {
+    pub fn new(now: Instant) -> Self {
+        LoadRates {
+            sampler: Sampler::new(now),
+            one: Ewma::new(Duration::from_secs(60)),
+            five: Ewma::new(Duration::from_secs(5 * 60)),
+            fifteen: Ewma::new(Duration::from_secs(15 * 60)),
+        }
+    }
+
+    pub fn record(&mut self, now: Instant, amount: f64) {
+        self.tick(now);
+        self.sampler.pending += amount;
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        self.sampler.advance(now, &mut [&mut self.one, &mut self.five, &mut self.fifteen]);
+    }
+}
+
+// Cheaper single-window variant for per-key rates
+#[derive(Debug, Clone)]
+pub struct MinuteRate {
+    sampler: Sampler,
+    ewma: Ewma,
+}
+
+impl MinuteRate {
+    pub fn new(now: Instant) -> Self {
+        MinuteRate {
+            sampler: Sampler::new(now),
+            ewma: Ewma::new(Duration::from_secs(60)),
+        }
+    }
+
+    pub fn record(&mut self, now: Instant, amount: f64) {
+        self.tick(now);
+        self.sampler.pending += amount;
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        self.sampler.advance(now, &mut [&mut self.ewma]);
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.ewma.rate()
+    }
+}
+
+// Requests per second over exactly the last 1, 5 and 15 minutes, counted
+// in a ring of one-second buckets that old seconds are cleared out of
+#[derive(Debug, Clone)]
+pub struct WindowRates {
+    start: Instant,
+    buckets: Vec<usize>,
+    // The second since start that the newest bucket counts
+    current: u64,
+}
+
+impl WindowRates {
+    const SPAN_SECS: u64 = 15 * 60;
+
+    pub fn new(now: Instant) -> Self {
+        WindowRates { start: now, buckets: vec![0; Self::SPAN_SECS as usize], current: 0 }
+    }
+
+    pub fn record(&mut self, now: Instant, count: usize) {
+        self.tick(now);
+        let slot = self.slot(self.current);
+        self.buckets[slot] += count;
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        let second = now.saturating_duration_since(self.start).as_secs();
+        // Seconds without requests since the last one leave their buckets empty
+        for skipped in (self.current + 1..=second).take(Self::SPAN_SECS as usize) {
+            let slot = self.slot(skipped);
+            self.buckets[slot] = 0;
+        }
+        self.current = self.current.max(second);
+    }
+
+    // Over the last `minutes`, or since the start while that is shorter
+    pub fn rate(&self, minutes: u64) -> f64 {
+        let seconds = (minutes * 60).min(Self::SPAN_SECS).min(self.current + 1);
+        let total: usize = (self.current + 1 - seconds..=self.current).map(|second| self.buckets[self.slot(second)]).sum();
+        total as f64 / seconds as f64
+    }
+
+    fn slot(&self, second: u64) -> usize {
+        (second % Self::SPAN_SECS) as usize
+    }
+}
+
+// Requests per status class, 2xx to 5xx, over the last minute and the
+// one before it in one-second buckets
+#[derive(Debug, Clone)]
+pub struct ClassWindow {
+    start: Instant,
+    buckets: VecDeque<(u64, [usize; 4])>,
+}
+
+impl ClassWindow {
+    const MINUTE_SECS: u64 = 60;
+    const SPAN_SECS: u64 = 2 * Self::MINUTE_SECS;
+
+    pub fn new(now: Instant) -> Self {
+        ClassWindow { start: now, buckets: VecDeque::new() }
+    }
+
+    pub fn record(&mut self, now: Instant, status_code: u16, count: usize) {
+        let class = match status_code {
+            200..=599 => (status_code / 100 - 2) as usize,
+            _ => return,
+        };
+        let second = now.saturating_duration_since(self.start).as_secs();
+        while self.buckets.front().is_some_and(|(at, _)| at + Self::SPAN_SECS <= second) {
+            self.buckets.pop_front();
+        }
+        match self.buckets.back_mut() {
+            Some((at, counts)) if *at == second => counts[class] += count,
+            _ => {
+                let mut counts = [0; 4];
+                counts[class] = count;
+                self.buckets.push_back((second, counts));
+            }
+        }
+    }
+
+    // 2xx, 3xx, 4xx and 5xx requests of the last minute
+    pub fn counts(&self, now: Instant) -> [usize; 4] {
+        self.minute(now, 0)
+    }
+
+    // The same for the minute before that
+    pub fn previous_counts(&self, now: Instant) -> [usize; 4] {
+        self.minute(now, 1)
+    }
+
+    fn minute(&self, now: Instant, ago: u64) -> [usize; 4] {
+        let second = now.saturating_duration_since(self.start).as_secs();
+        let mut total = [0; 4];
+        // Nothing before the start
+        let Some(newest) = second.checked_sub(ago * Self::MINUTE_SECS) else {
+            return total;
+        };
+        for (_, counts) in self.buckets.iter().filter(|(at, _)| *at <= newest && at + Self::MINUTE_SECS > newest) {
+            for (total, count) in total.iter_mut().zip(counts) {
+                *total += count;
+            }
+        }
+        total
+    }
+}
+
+// Requests per status class, 2xx to 5xx, in each of the periods `span` is
+// split into, for the status panel
+#[derive(Debug, Clone)]
+pub struct ClassTimeline {
+    start: Instant,
+    span: Duration,
+    buckets: VecDeque<(u64, [usize; 4])>,
+}
+
+impl ClassTimeline {
+    pub const PERIODS: u64 = 60;
+    pub const DEFAULT_SPAN: Duration = Duration::from_secs(10 * 60);
+
+    pub fn new(now: Instant, span: Duration) -> Self {
+        ClassTimeline { start: now, span, buckets: VecDeque::new() }
+    }
+
+    // No requests yet, over the same span
+    pub fn cleared(&self, now: Instant) -> Self {
+        ClassTimeline::new(now, self.span)
+    }
+
+    pub fn span(&self) -> Duration {
+        self.span
+    }
+
+    pub fn period(&self) -> Duration {
+        self.span / Self::PERIODS as u32
+    }
+
+    fn index(&self, now: Instant) -> u64 {
+        (now.saturating_duration_since(self.start).as_secs_f64() / self.period().as_secs_f64()) as u64
+    }
+
+    pub fn record(&mut self, now: Instant, status_code: u16, count: usize) {
+        let class = match status_code {
+            200..=599 => (status_code / 100 - 2) as usize,
+            _ => return,
+        };
+        let index = self.index(now);
+        while self.buckets.front().is_some_and(|(at, _)| at + Self::PERIODS <= index) {
+            self.buckets.pop_front();
+        }
+        match self.buckets.back_mut() {
+            Some((at, counts)) if *at == index => counts[class] += count,
+            _ => {
+                let mut counts = [0; 4];
+                counts[class] = count;
+                self.buckets.push_back((index, counts));
+            }
+        }
+    }
+
+    // Every period of the span, oldest first and the current one last,
+    // empty for those without requests or from before the start
+    pub fn periods(&self, now: Instant) -> Vec<[usize; 4]> {
+        let newest = self.index(now);
+        let mut periods = vec![[0; 4]; Self::PERIODS as usize];
+        for (at, counts) in &self.buckets {
+            if let Some(age) = newest.checked_sub(*at).filter(|age| *age < Self::PERIODS) {
+                periods[(Self::PERIODS - 1 - age) as usize] = *counts;
+            }
+        }
+        periods
+    }
+}
+
+// Requests per second over successive periods, for the traffic sparkline
+#[derive(Debug, Clone, Default)]
+pub struct RateHistory {
+    samples: VecDeque<f64>,
+    last: Option<(Instant, usize)>,
+}
+
+impl RateHistory {
+    // A sample per refresh at the default interval, five minutes of them.
+    // Faster refreshes skip samples, slower ones take longer periods
+    const PERIOD: Duration = crate::app::REFRESH_INTERVAL;
+    const CAPACITY: usize = 600;
+
+    // Takes a sample from the running total once a period has passed
+    pub fn sample(&mut self, now: Instant, total: usize) {
+        if let Some((at, count)) = self.last {
+            let elapsed = now.saturating_duration_since(at);
+            if elapsed < Self::PERIOD {
+                return;
+            }
+            if self.samples.len() == Self::CAPACITY {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(total.saturating_sub(count) as f64 / elapsed.as_secs_f64());
+        }
+        self.last = Some((now, total));
+    }
+
+    // Up to `count` of the newest samples, oldest first
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = f64> + '_ {
+        self.samples.iter().copied().skip(self.samples.len().saturating_sub(count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minute_rate() {
+        let start = Instant::now();
+        let mut rate = MinuteRate::new(start);
+        // A steady 100 a second for ten minutes, counted as it comes
+        for second in 0..600 {
+            rate.record(start + Duration::from_secs(second), 100.0);
+        }
+        rate.tick(start + Duration::from_secs(600));
+        assert!((rate.rate() - 100.0).abs() < 1.0, "{}", rate.rate());
+
+        // Idle minutes only decay it, by e each minute
+        rate.tick(start + Duration::from_secs(660));
+        assert!((rate.rate() - 100.0 / 1f64.exp()).abs() < 1.0, "{}", rate.rate());
+        rate.tick(start + Duration::from_secs(1200));
+        assert!(rate.rate() < 0.01, "{}", rate.rate());
+    }
+}
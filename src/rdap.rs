@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde_json::Value;
+
+// Redirects to the registry (ARIN, RIPE, APNIC, ...) responsible for the IP
+const RDAP_URL: &str = "https://rdap.org/ip/";
+
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    pub netblock: String,
+    pub name: Option<String>,
+    pub org: Option<String>,
+    pub country: Option<String>,
+    pub abuse: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Lookup {
+    Pending,
+    Found(Network),
+    Failed(String),
+}
+
+// RDAP lookups run on their own thread and are kept for the session
+#[derive(Debug, Clone, Default)]
+pub struct Rdap {
+    cache: Arc<Mutex<HashMap<IpAddr, Lookup>>>,
+}
+
+impl Rdap {
+    pub fn lookup(&self, ip: IpAddr) {
+        let mut cache = self.cache.lock().unwrap();
+        if matches!(cache.get(&ip), Some(Lookup::Pending | Lookup::Found(_))) {
+            return;
+        }
+        cache.insert(ip, Lookup::Pending);
+
+        let cache = Arc::clone(&self.cache);
+        thread::spawn(move || {
+            let result = match fetch(ip) {
+                Ok(network) => Lookup::Found(network),
+                Err(e) => Lookup::Failed(e),
+            };
+            cache.lock().unwrap().insert(ip, result);
+        });
+    }
+
+    pub fn get(&self, ip: IpAddr) -> Option<Lookup> {
+        self.cache.lock().unwrap().get(&ip).cloned()
+    }
+}
+
+fn fetch(ip: IpAddr) -> Result<Network, String> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location", "--max-time", "15"])
+        .args(["--header", "Accept: application/rdap+json"])
+        .arg(format!("{}{}", RDAP_URL, ip))
+        .output()
+        .map_err(|e| format!("could not run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let body = String::from_utf8_lossy(&output.stdout);
+    let response: Value = serde_json::from_str(&body).map_err(|e| format!("invalid RDAP response: {}", e))?;
+    Ok(network(&response))
+}
+
+fn network(response: &Value) -> Network {
+    let text = |key: &str| response[key].as_str().map(str::to_string);
+
+    let cidrs: Vec<String> = items(&response["cidr0_cidrs"]).iter()
+        .filter_map(|cidr| {
+            let prefix = cidr.get("v4prefix").or_else(|| cidr.get("v6prefix"))?.as_str()?;
+            let length = cidr["length"].as_u64()?;
+            Some(format!("{}/{}", prefix, length))
+        })
+        .collect();
+    let netblock = if cidrs.is_empty() {
+        format!("{} - {}", text("startAddress").unwrap_or_default(), text("endAddress").unwrap_or_default())
+    } else {
+        cidrs.join(", ")
+    };
+
+    let entities = items(&response["entities"]);
+    Network {
+        netblock,
+        name: text("name"),
+        org: find_entity(entities, "registrant").and_then(|entity| vcard(entity, "fn")),
+        country: text("country"),
+        abuse: find_entity(entities, "abuse").and_then(|entity| vcard(entity, "email")),
+    }
+}
+
+// Entities nest (the abuse contact usually hangs off the registrant)
+fn find_entity<'a>(entities: &'a [Value], role: &str) -> Option<&'a Value> {
+    entities.iter().find_map(|entity| {
+        let has_role = items(&entity["roles"]).iter().any(|r| r.as_str() == Some(role));
+        if has_role {
+            Some(entity)
+        } else {
+            find_entity(items(&entity["entities"]), role)
+        }
+    })
+}
+
+// jCard: ["vcard", [[name, params, type, value], ...]]
+fn vcard(entity: &Value, property: &str) -> Option<String> {
+    items(&entity["vcardArray"][1]).iter()
+        .find(|item| item[0].as_str() == Some(property))
+        .and_then(|item| item[3].as_str())
+        .map(str::to_string)
+}
+
+// Empty for anything but an array, which keeps lookups chainable
+fn items(value: &Value) -> &[Value] {
+    value.as_array().map_or(&[], Vec::as_slice)
+}
@@ -0,0 +1,253 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::Split;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::parser::field_value;
+use crate::{formats, Request};
+
+// One event per line: milliseconds since the recording started, so
+// replays keep the original pacing, a tab and the request as JSON
+const MAGIC: &str = "httop-record 2";
+// Tab-separated columns in place of the JSON, still read
+const MAGIC_COLUMNS: &str = "httop-record 1";
+
+pub struct Recorder {
+    writer: BufWriter<File>,
+    started: Instant,
+    last_flush: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", MAGIC)?;
+        let now = Instant::now();
+        Ok(Recorder { writer, started: now, last_flush: now })
+    }
+
+    pub fn write(&mut self, request: &Request) -> io::Result<()> {
+        write!(self.writer, "{}\t", self.started.elapsed().as_millis())?;
+        // Replays are counted under the recording rather than these sources
+        write_request(&mut self.writer, request, None)?;
+
+        // Keep the file usable if httop is killed mid-session
+        if self.last_flush.elapsed() >= Duration::from_secs(1) {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.writer.flush()
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+// A request as recordings and checkpoints keep it
+#[derive(Serialize, Deserialize)]
+struct Line<'a> {
+    timestamp: String,
+    ip: Cow<'a, str>,
+    method: Cow<'a, str>,
+    url: Cow<'a, str>,
+    protocol: Option<Cow<'a, str>>,
+    status: u16,
+    bytes_sent: usize,
+    referrer: Cow<'a, str>,
+    user_agent: Cow<'a, str>,
+    // None for NaN, which JSON has no number for
+    response_time: Option<f64>,
+    bytes_received: Option<usize>,
+    uncompressed_bytes: Option<usize>,
+    fields: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<Cow<'a, str>>,
+}
+
+// A request as one line of JSON, which checkpoints also write after
+// columns of their own, with its source for those
+pub fn write_request(writer: &mut impl Write, request: &Request, source: Option<&str>) -> io::Result<()> {
+    let line = Line {
+        timestamp: request.timestamp.to_rfc3339(),
+        ip: Cow::Borrowed(&request.ip),
+        method: Cow::Borrowed(&request.method),
+        url: request.url(),
+        protocol: request.protocol.as_deref().map(Cow::Borrowed),
+        status: request.status_code,
+        bytes_sent: request.bytes_sent,
+        referrer: Cow::Borrowed(&request.referrer),
+        user_agent: Cow::Borrowed(&request.user_agent),
+        response_time: Some(request.response_time).filter(|seconds| !seconds.is_nan()),
+        bytes_received: request.bytes_received,
+        uncompressed_bytes: request.uncompressed_bytes,
+        fields: request.fields.iter().map(|(key, value)| (Cow::Borrowed(key.as_str()), Cow::Borrowed(value.as_str()))).collect(),
+        source: source.map(Cow::Borrowed),
+    };
+    serde_json::to_writer(&mut *writer, &line)?;
+    writeln!(writer)
+}
+
+// Recorded events with their offset from the start of the recording
+pub fn read_events(path: &Path) -> io::Result<impl Iterator<Item = io::Result<(Duration, Request)>> + use<>> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let columns = match lines.next() {
+        Some(Ok(header)) if header == MAGIC => false,
+        Some(Ok(header)) if header == MAGIC_COLUMNS => true,
+        _ => return Err(invalid(format!("{} is not an httop recording", path.display()))),
+    };
+
+    Ok(lines.map(move |line| {
+        let line = line?;
+        parse_event(&line, columns).ok_or_else(|| invalid(format!("malformed recorded event: {}", line)))
+    }))
+}
+
+fn parse_event(line: &str, columns: bool) -> Option<(Duration, Request)> {
+    let (offset, request) = line.split_once('\t')?;
+    let offset = Duration::from_millis(offset.parse().ok()?);
+    let request = if columns { parse_columns(request.split('\t'))? } else { parse_request(request)?.0 };
+    Some((offset, request))
+}
+
+// The request write_request wrote, and the source with it
+pub fn parse_request(json: &str) -> Option<(Request, Option<String>)> {
+    let line: Line = serde_json::from_str(json).ok()?;
+    let fields: Vec<(String, String)> = line.fields.into_iter()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    let upstream_time = formats::upstream_time(&fields);
+    let request = Request {
+        timestamp: DateTime::parse_from_rfc3339(&line.timestamp).ok()?.with_timezone(&Utc),
+        ip: line.ip.into_owned(),
+        method: line.method.into_owned(),
+        path: line.url.into_owned(),
+        protocol: line.protocol.map(Cow::into_owned),
+        status_code: line.status,
+        response_time: line.response_time.unwrap_or(f64::NAN),
+        referrer: line.referrer.into_owned(),
+        user_agent: line.user_agent.into_owned(),
+        bytes_sent: line.bytes_sent,
+        bytes_received: line.bytes_received,
+        uncompressed_bytes: line.uncompressed_bytes,
+        upstream_time,
+        fields,
+        tenant: None,
+        vhost: None,
+        cache_status: None,
+        query: None,
+        country: None,
+        network: None,
+        bot: None,
+        source: None,
+        weight: 1,
+    };
+    Some((request, line.source.map(Cow::into_owned)))
+}
+
+// A request as the tab-separated columns of earlier recordings and
+// checkpoints
+pub fn parse_columns(mut columns: Split<'_, char>) -> Option<Request> {
+    let mut next = || columns.next();
+    let timestamp = DateTime::parse_from_rfc3339(next()?).ok()?.with_timezone(&Utc);
+    let ip = unescape(next()?);
+    let method = unescape(next()?);
+    let path = unescape(next()?);
+    let status_code = next()?.parse().ok()?;
+    let bytes_sent = next()?.parse().ok()?;
+    let referrer = unescape(next()?);
+    let user_agent = unescape(next()?);
+    let response_time = next()?.parse().ok()?;
+    let optional = |value: &str| if value.is_empty() { None } else { value.parse().ok() };
+    let bytes_received = optional(next()?);
+    let uncompressed_bytes = optional(next()?);
+
+    let fields: Vec<(String, String)> = columns
+        .filter_map(|field| field.split_once('='))
+        .map(|(key, value)| (unescape(key), unescape(value)))
+        .collect();
+    let protocol = field_value::<String>(&fields, formats::PROTOCOL_FIELDS).and_then(|protocol| formats::http_version(&protocol));
+    let upstream_time = formats::upstream_time(&fields);
+
+    Some(Request {
+        timestamp,
+        ip,
+        method,
+        path,
+        protocol,
+        status_code,
+        response_time,
+        referrer,
+        user_agent,
+        bytes_sent,
+        bytes_received,
+        uncompressed_bytes,
+        upstream_time,
+        fields,
+        tenant: None,
+        vhost: None,
+        cache_status: None,
+        query: None,
+        country: None,
+        network: None,
+        bot: None,
+        source: None,
+        weight: 1,
+    })
+}
+
+pub fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+pub fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tabs and line breaks in a request survive the trip, as does a
+    // response time JSON has no number for
+    #[test]
+    fn round_trip() {
+        let mut request = Request::new(Utc::now(), "10.0.0.1", "GET", "/a\tb?c=d", 200, 512);
+        request.user_agent = "curl\n8".to_string();
+        request.response_time = f64::NAN;
+        request.fields = vec![("upstream_response_time".to_string(), "0.250".to_string())];
+        let mut line = Vec::new();
+        write_request(&mut line, &request, Some("web 1")).unwrap();
+        assert_eq!(line.iter().filter(|&&byte| byte == b'\n').count(), 1);
+
+        let (read, source) = parse_request(std::str::from_utf8(&line).unwrap().trim_end()).unwrap();
+        assert_eq!((read.path.as_str(), read.user_agent.as_str()), ("/a\tb?c=d", "curl\n8"));
+        assert!(read.response_time.is_nan());
+        assert_eq!((read.upstream_time, source.as_deref()), (Some(0.25), Some("web 1")));
+    }
+}
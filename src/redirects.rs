@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::memory;
+
+// A request from the same client this soon after a redirect is taken to be
+// the client following it
+const FOLLOW_WINDOW_SECS: i64 = 2;
+const MAX_PENDING: usize = 10_000;
+
+#[derive(Debug, Clone, Default)]
+pub struct RedirectEntry {
+    pub count: usize,
+    // Indexed 301, 302, 307, 308
+    pub by_code: [usize; 4],
+}
+
+#[derive(Debug, Clone)]
+struct PendingRedirect {
+    path: String,
+    timestamp: DateTime<Utc>,
+    hops: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RedirectStats {
+    pub total: usize,
+    pub paths: HashMap<String, RedirectEntry>,
+    // (from, to) -> number of clients seen following the redirect
+    pub edges: HashMap<(String, String), usize>,
+    // Followed redirects that landed on yet another redirect
+    pub chained: usize,
+    pub longest_chain: usize,
+    pending: HashMap<String, PendingRedirect>,
+}
+
+impl RedirectStats {
+    pub fn record(&mut self, ip: &str, path: &str, status_code: u16, timestamp: DateTime<Utc>, weight: usize) {
+        let code_index = match status_code {
+            301 => Some(0),
+            302 => Some(1),
+            307 => Some(2),
+            308 => Some(3),
+            _ => None,
+        };
+
+        let mut hops = 0;
+        if let Some(previous) = self.pending.remove(ip) {
+            let gap = (timestamp - previous.timestamp).num_seconds();
+            if (0..=FOLLOW_WINDOW_SECS).contains(&gap) && previous.path != path {
+                *self.edges.entry((previous.path, path.to_string())).or_insert(0) += weight;
+                hops = previous.hops;
+            }
+        }
+
+        let Some(code_index) = code_index else {
+            return;
+        };
+
+        self.total += weight;
+        let entry = self.paths.entry(path.to_string()).or_default();
+        entry.count += weight;
+        entry.by_code[code_index] += weight;
+
+        if hops > 0 {
+            self.chained += weight;
+        }
+        self.longest_chain = self.longest_chain.max(hops + 1);
+
+        if self.pending.len() >= MAX_PENDING {
+            self.pending.retain(|_, p| (timestamp - p.timestamp).num_seconds() <= FOLLOW_WINDOW_SECS);
+        }
+        self.pending.insert(ip.to_string(), PendingRedirect {
+            path: path.to_string(),
+            timestamp,
+            hops: hops + 1,
+        });
+    }
+
+    pub fn is_redirect(&self, path: &str) -> bool {
+        self.paths.contains_key(path)
+    }
+
+    // The pending redirects are few and short-lived, MAX_PENDING at most
+    pub fn memory(&self) -> usize {
+        memory::keyed(&self.paths)
+            + self.edges.keys().map(|(from, to)| memory::entry::<(String, usize)>(from) + memory::text(to)).sum::<usize>()
+    }
+}
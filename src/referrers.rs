@@ -0,0 +1,214 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::content;
+use crate::memory;
+
+const BUNDLED_SPAM_LIST: &str = include_str!("referrer_spam.txt");
+
+// Referrers seen this often without any of their visitors loading a single
+// page asset are flagged as likely spam
+const SUSPECT_MIN_HITS: usize = 5;
+const MAX_TRACKED_VISITORS: usize = 32;
+
+#[derive(Debug, Clone, Default)]
+pub struct SpamList {
+    domains: HashSet<String>,
+}
+
+impl SpamList {
+    pub fn bundled() -> Self {
+        let mut list = SpamList::default();
+        list.add_lines(BUNDLED_SPAM_LIST);
+        list
+    }
+
+    pub fn extend_from_file(&mut self, path: &Path) -> io::Result<()> {
+        let contents = fs::read_to_string(path)?;
+        self.add_lines(&contents);
+        Ok(())
+    }
+
+    fn add_lines(&mut self, contents: &str) {
+        for line in contents.lines() {
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                self.domains.insert(line.to_lowercase());
+            }
+        }
+    }
+
+    // Matches the domain itself and any subdomain of a listed domain
+    pub fn contains(&self, domain: &str) -> bool {
+        let mut candidate = domain;
+        loop {
+            if self.domains.contains(candidate) {
+                return true;
+            }
+            match candidate.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => candidate = parent,
+                _ => return false,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpamVerdict {
+    Clean,
+    Listed,
+    Suspect,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReferrerEntry {
+    pub domain: String,
+    pub count: usize,
+    pub bytes: usize,
+    listed: bool,
+}
+
+#[derive(Debug, Clone)]
+struct DomainVisitors {
+    hits: usize,
+    ips: HashSet<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReferrerStats {
+    spam_list: Arc<SpamList>,
+    pub referrers: HashMap<String, ReferrerEntry>,
+    // Requests without a referrer: typed in, bookmarks, apps
+    pub direct: usize,
+    domains: HashMap<String, DomainVisitors>,
+    asset_ips: HashSet<String>,
+}
+
+impl ReferrerStats {
+    pub fn new(spam_list: SpamList) -> Self {
+        ReferrerStats {
+            spam_list: Arc::new(spam_list),
+            referrers: HashMap::new(),
+            direct: 0,
+            domains: HashMap::new(),
+            asset_ips: HashSet::new(),
+        }
+    }
+
+    // No referrers yet, but the same spam list
+    pub fn cleared(&self) -> Self {
+        ReferrerStats { spam_list: Arc::clone(&self.spam_list), ..ReferrerStats::new(SpamList::default()) }
+    }
+
+    pub fn set_spam_list(&mut self, spam_list: SpamList) {
+        for entry in self.referrers.values_mut() {
+            entry.listed = spam_list.contains(&entry.domain);
+        }
+        self.spam_list = Arc::new(spam_list);
+    }
+
+    pub fn record(&mut self, ip: &str, path: &str, referrer: &str, bytes: usize, weight: usize) {
+        if content::classify(path).is_page_asset() {
+            self.asset_ips.insert(ip.to_string());
+        }
+
+        let Some(domain) = referrer_domain(referrer) else {
+            self.direct += weight;
+            return;
+        };
+
+        let entry = self.referrers.entry(referrer.to_string()).or_insert_with(|| ReferrerEntry {
+            listed: self.spam_list.contains(&domain),
+            domain: domain.clone(),
+            count: 0,
+            bytes: 0,
+        });
+        entry.count += weight;
+        entry.bytes += bytes * weight;
+
+        let visitors = self.domains.entry(domain).or_insert_with(|| DomainVisitors {
+            hits: 0,
+            ips: HashSet::new(),
+        });
+        visitors.hits += weight;
+        if visitors.ips.len() < MAX_TRACKED_VISITORS {
+            visitors.ips.insert(ip.to_string());
+        }
+    }
+
+    // The referrers with the fewest requests go past `max_keys`, and the
+    // domains none of the rest are from
+    pub fn limit(&mut self, max_keys: usize) {
+        let Some((threshold, mut ties)) = memory::limit(&self.referrers, |entry| entry.count, max_keys) else {
+            return;
+        };
+        memory::evict(&mut self.referrers, |entry| entry.count, threshold, &mut ties, |_| {});
+        self.forget_domains();
+    }
+
+    pub fn memory(&self) -> usize {
+        let domains: usize = self.domains.iter()
+            .map(|(domain, visitors)| memory::entry::<DomainVisitors>(domain) + visitors.ips.iter().map(|ip| memory::entry::<()>(ip)).sum::<usize>())
+            .sum();
+        memory::keyed(&self.referrers) + domains + memory::set(&self.asset_ips)
+    }
+
+    // Past max_memory, the referrers with fewer requests than `threshold`,
+    // and `ties` of those with as many, go
+    pub fn evict(&mut self, threshold: usize, ties: &mut usize) {
+        memory::evict(&mut self.referrers, |entry| entry.count, threshold, ties, |_| {});
+        self.forget_domains();
+        self.referrers.shrink_to_fit();
+        self.domains.shrink_to_fit();
+    }
+
+    // Each referrer's requests and bytes, for memory::threshold
+    pub fn weigh(&self, keys: &mut Vec<(usize, usize)>) {
+        keys.extend(self.referrers.iter().map(|(referrer, entry)| (entry.count, memory::entry::<ReferrerEntry>(referrer))));
+    }
+
+    fn forget_domains(&mut self) {
+        let kept: HashSet<&str> = self.referrers.values().map(|entry| entry.domain.as_str()).collect();
+        self.domains.retain(|domain, _| kept.contains(domain.as_str()));
+    }
+
+    // Clients that loaded page assets are only remembered while `kept` has them
+    pub fn forget_clients<T>(&mut self, kept: &HashMap<String, T>) {
+        self.asset_ips.retain(|ip| kept.contains_key(ip));
+        self.asset_ips.shrink_to_fit();
+    }
+
+    pub fn verdict(&self, entry: &ReferrerEntry) -> SpamVerdict {
+        if entry.listed {
+            return SpamVerdict::Listed;
+        }
+
+        match self.domains.get(&entry.domain) {
+            Some(visitors) if visitors.hits >= SUSPECT_MIN_HITS
+                && !visitors.ips.iter().any(|ip| self.asset_ips.contains(ip)) => SpamVerdict::Suspect,
+            _ => SpamVerdict::Clean,
+        }
+    }
+}
+
+// Host part of the referrer URL, None for direct traffic
+pub fn referrer_domain(referrer: &str) -> Option<String> {
+    if referrer.is_empty() || referrer == "-" {
+        return None;
+    }
+
+    let rest = referrer.split_once("://").map_or(referrer, |(_, rest)| rest);
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = host.split(':').next()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
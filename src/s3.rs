@@ -0,0 +1,120 @@
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, Read};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::piped::Piped;
+
+// The log objects under an s3://BUCKET/PREFIX location, as delivered by ALB
+// and CloudFront access logging, read one after another through the aws
+// CLI. Keys are read in order, which for those is the order of time. With a
+// poll interval the location is listed again for new objects once all are
+// read; otherwise the stream ends with the last one
+pub struct Objects {
+    bucket: String,
+    prefix: String,
+    poll: Option<Duration>,
+    seen: HashSet<String>,
+    queue: VecDeque<String>,
+    current: Option<Piped>,
+}
+
+// What of `aws s3api list-objects-v2` output is read
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Listing {
+    #[serde(default)]
+    contents: Vec<Object>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Object {
+    key: String,
+}
+
+impl Objects {
+    // Lists the location right away, so that a wrong bucket or missing
+    // credentials are reported at startup
+    pub fn open(location: &str, poll: Option<Duration>) -> Result<Self, String> {
+        let (bucket, prefix) = split_location(location).ok_or_else(|| format!("invalid S3 location: {}", location))?;
+        let mut objects = Objects {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            poll,
+            seen: HashSet::new(),
+            queue: VecDeque::new(),
+            current: None,
+        };
+        objects.list()?;
+        Ok(objects)
+    }
+
+    fn list(&mut self) -> Result<(), String> {
+        let output = Command::new("aws")
+            .args(["s3api", "list-objects-v2", "--bucket", &self.bucket, "--prefix", &self.prefix, "--output", "json"])
+            .output()
+            .map_err(|e| format!("could not run aws: {}", e))?;
+        if !output.status.success() {
+            return Err(format!("listing s3://{}/{}: {}", self.bucket, self.prefix, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+
+        // Nothing at all is printed when there are no objects
+        let listing = String::from_utf8_lossy(&output.stdout);
+        if listing.trim().is_empty() {
+            return Ok(());
+        }
+        let listing: Listing = serde_json::from_str(&listing).map_err(|e| format!("invalid aws output: {}", e))?;
+        let mut keys: Vec<String> = listing.contents.into_iter()
+            .map(|object| object.key)
+            // "Folders" created in the console
+            .filter(|key| !key.ends_with('/') && !self.seen.contains(key))
+            .collect();
+        keys.sort();
+        for key in keys {
+            self.seen.insert(key.clone());
+            self.queue.push_back(key);
+        }
+        Ok(())
+    }
+
+    fn download(&self, key: &str) -> io::Result<Piped> {
+        let args = ["s3", "cp", "--quiet", &format!("s3://{}/{}", self.bucket, key), "-"].map(String::from);
+        Piped::spawn_file("aws", &args, key)
+    }
+}
+
+impl Read for Objects {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(object) = &mut self.current {
+                let read = object.read(buf)?;
+                if read > 0 {
+                    return Ok(read);
+                }
+                self.current = None;
+            }
+
+            match self.queue.pop_front() {
+                Some(key) => self.current = Some(self.download(&key)?),
+                None => {
+                    let Some(poll) = self.poll else {
+                        return Ok(0);
+                    };
+                    thread::sleep(poll);
+                    // A failed listing is retried at the next poll
+                    let _ = self.list();
+                }
+            }
+        }
+    }
+}
+
+fn split_location(location: &str) -> Option<(&str, &str)> {
+    let location = location.strip_prefix("s3://")?;
+    let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+    (!bucket.is_empty()).then_some((bucket, prefix))
+}
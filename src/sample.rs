@@ -0,0 +1,74 @@
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::source::QUEUE_CAPACITY;
+
+// The most lines one kept line stands for with --sample auto
+const MAX_EVERY: usize = 1024;
+// Between changes of the adaptive rate
+const ADJUST_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rate {
+    // One line in every N; 1 for all of them
+    Every(usize),
+    // All lines while the aggregator keeps up, and fewer as its queue fills
+    Adaptive,
+}
+
+impl FromStr for Rate {
+    type Err = String;
+
+    // 1/10, 10% or 0.1, or auto
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || format!("invalid --sample '{}' (e.g. 1/10, 10%, 0.1 or auto)", value);
+        if value == "auto" {
+            return Ok(Rate::Adaptive);
+        }
+        let fraction = match (value.strip_prefix("1/"), value.strip_suffix('%')) {
+            (Some(every), _) => 1.0 / every.parse::<f64>().map_err(|_| error())?,
+            (_, Some(percent)) => percent.parse::<f64>().map_err(|_| error())? / 100.0,
+            _ => value.parse::<f64>().map_err(|_| error())?,
+        };
+        if !(fraction > 0.0 && fraction <= 1.0) {
+            return Err(error());
+        }
+        Ok(Rate::Every((1.0 / fraction).round() as usize))
+    }
+}
+
+// Decides which lines a reader parses. Each kept line is counted as the
+// number of lines it stands for, so totals and rates come out as if all
+// of them had been read
+#[derive(Debug)]
+pub struct Sampler {
+    adaptive: bool,
+    every: usize,
+    seen: usize,
+    last_adjust: Instant,
+}
+
+impl Sampler {
+    pub fn new(rate: Option<Rate>) -> Self {
+        let (adaptive, every) = match rate {
+            None => (false, 1),
+            Some(Rate::Every(every)) => (false, every),
+            Some(Rate::Adaptive) => (true, 1),
+        };
+        Sampler { adaptive, every, seen: 0, last_adjust: Instant::now() }
+    }
+
+    // The weight of the next line, or None to skip it
+    pub fn keep(&mut self, queued: usize) -> Option<usize> {
+        if self.adaptive && self.last_adjust.elapsed() >= ADJUST_INTERVAL {
+            self.last_adjust = Instant::now();
+            if queued > QUEUE_CAPACITY * 3 / 4 {
+                self.every = (self.every * 2).min(MAX_EVERY);
+            } else if queued < QUEUE_CAPACITY / 10 {
+                self.every = (self.every / 2).max(1);
+            }
+        }
+        self.seen += 1;
+        self.seen.is_multiple_of(self.every).then_some(self.every)
+    }
+}
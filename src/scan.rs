@@ -0,0 +1,108 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// Walks a log line from the start, for the formats common enough to be
+// worth splitting by hand. Each step takes what the matching part of the
+// format's regex would, or gives up, leaving the line to the regex
+pub struct Scanner<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(line: &'a str) -> Self {
+        Scanner { rest: line }
+    }
+
+    // Whatever is left, e.g. trailing key=value fields
+    pub fn rest(self) -> &'a str {
+        self.rest
+    }
+
+    pub fn expect(&mut self, prefix: &str) -> Option<()> {
+        self.rest = self.rest.strip_prefix(prefix)?;
+        Some(())
+    }
+
+    // Up to the next whitespace, \S+
+    pub fn token(&mut self) -> Option<&'a str> {
+        let end = self.rest.find(char::is_whitespace).unwrap_or(self.rest.len());
+        self.take(end)
+    }
+
+    // Up to `end`, which is skipped: [^"]* and the like
+    pub fn until(&mut self, end: char) -> Option<&'a str> {
+        let (taken, rest) = self.rest.split_once(end)?;
+        self.rest = rest;
+        Some(taken)
+    }
+
+    // \d+
+    pub fn digits(&mut self) -> Option<&'a str> {
+        let end = self.rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(self.rest.len());
+        self.take(end)
+    }
+
+    // (?:\d+\.\d+)?: taken if the line goes on with one, or nothing is
+    pub fn decimal(&mut self) -> Option<&'a str> {
+        let start = self.rest;
+        let mut ahead = Scanner::new(start);
+        ahead.digits()?;
+        ahead.expect(".")?;
+        ahead.digits()?;
+        self.rest = ahead.rest;
+        Some(&start[..start.len() - self.rest.len()])
+    }
+
+    // A quoted field with Apache's backslash escapes, "((?:[^"\\]|\\.)*)",
+    // still escaped
+    pub fn quoted(&mut self) -> Option<&'a str> {
+        let inside = self.rest.strip_prefix('"')?;
+        let mut bytes = inside.bytes().enumerate();
+        while let Some((i, byte)) = bytes.next() {
+            match byte {
+                b'"' => {
+                    self.rest = &inside[i + 1..];
+                    return Some(&inside[..i]);
+                }
+                b'\\' if bytes.next().is_none_or(|(_, escaped)| escaped == b'\n') => return None,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn take(&mut self, end: usize) -> Option<&'a str> {
+        (end > 0).then(|| {
+            let (taken, rest) = self.rest.split_at(end);
+            self.rest = rest;
+            taken
+        })
+    }
+}
+
+// $time_local and Apache's %t, "29/Nov/2021:12:34:56 +0000", the way
+// DateTime::parse_from_str reads "%d/%b/%Y:%H:%M:%S %z" but without
+// going through the format string; None for anything less regular
+pub fn clf_time(text: &str) -> Option<DateTime<Utc>> {
+    let bytes = text.as_bytes();
+    let separators = [(2, b'/'), (6, b'/'), (11, b':'), (14, b':'), (17, b':'), (20, b' ')];
+    if bytes.len() != 26 || !text.is_ascii() || separators.iter().any(|&(i, separator)| bytes[i] != separator) {
+        return None;
+    }
+    // Without the sign parse() would take
+    let number = |from: usize, to: usize| -> Option<u32> {
+        let digits = &text[from..to];
+        if digits.bytes().all(|b| b.is_ascii_digit()) { digits.parse().ok() } else { None }
+    };
+    let month = MONTHS.iter().position(|month| *month == &text[3..6])? as u32 + 1;
+    let date = NaiveDate::from_ymd_opt(number(7, 11)? as i32, month, number(0, 2)?)?
+        .and_hms_opt(number(12, 14)?, number(15, 17)?, number(18, 20)?)?;
+    let offset = (number(22, 24)? * 3600 + number(24, 26).filter(|minutes| *minutes < 60)? * 60) as i32;
+    let offset = match bytes[21] {
+        b'+' => FixedOffset::east_opt(offset)?,
+        b'-' => FixedOffset::west_opt(offset)?,
+        _ => return None,
+    };
+    Some(offset.from_local_datetime(&date).single()?.with_timezone(&Utc))
+}
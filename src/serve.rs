@@ -0,0 +1,205 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::source::{Health, QUEUE_CAPACITY};
+
+// Not ready once the queue is this full: the aggregator can't keep up
+const QUEUE_READY_LIMIT: usize = QUEUE_CAPACITY * 9 / 10;
+// A client gets this long to send its request line, however slowly, and
+// that many bytes of it
+const REQUEST_DEADLINE: Duration = Duration::from_secs(2);
+const MAX_REQUEST_LINE: usize = 8192;
+// Connections answered at once; more are closed unanswered
+const MAX_CONNECTIONS: usize = 16;
+
+pub struct Server {
+    listener: TcpListener,
+    connections: Arc<AtomicUsize>,
+}
+
+impl Server {
+    pub fn bind(listen: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(listen)?;
+        listener.set_nonblocking(true)?;
+        Ok(Server { listener, connections: Arc::default() })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Accepts all pending connections without blocking, answering each on
+    // a thread of its own so a slow client can't hold up the caller's loop
+    pub fn poll(&self, health: &Arc<Health>) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if self.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                        self.connections.fetch_sub(1, Ordering::SeqCst);
+                        continue;
+                    }
+                    let (health, connections) = (Arc::clone(health), Arc::clone(&self.connections));
+                    thread::spawn(move || {
+                        if let Err(e) = stream.set_nonblocking(false).and_then(|()| handle(stream, &health)) {
+                            health.notices.post(format!("request failed: {}", e));
+                        }
+                        connections.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    health.notices.post(format!("accept failed: {}", e));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn handle(mut stream: TcpStream, health: &Health) -> io::Result<()> {
+    let request_line = read_request_line(&mut stream)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let path = path.split('?').next().unwrap_or(path);
+
+    let (status, body) = match path {
+        "/healthz" => {
+            let healthy = health.aggregator_running();
+            (if healthy { 200 } else { 503 }, report(health, healthy))
+        }
+        "/readyz" => {
+            let ready = is_ready(health);
+            (if ready { 200 } else { 503 }, report(health, ready))
+        }
+        _ => (404, "{\"error\":\"not found\"}".to_string()),
+    };
+
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        _ => "Service Unavailable",
+    };
+    stream.set_write_timeout(Some(REQUEST_DEADLINE))?;
+    write!(stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason, body.len(), body)?;
+    stream.flush()
+}
+
+// Up to the first newline, within REQUEST_DEADLINE and MAX_REQUEST_LINE
+// bytes. The rest of the request, headers and all, goes unread
+fn read_request_line(stream: &mut TcpStream) -> io::Result<String> {
+    let deadline = Instant::now() + REQUEST_DEADLINE;
+    let mut line = Vec::new();
+    let mut buffer = [0; 1024];
+    while !line.contains(&b'\n') {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "request line not sent in time"));
+        }
+        if line.len() >= MAX_REQUEST_LINE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "request line too long"));
+        }
+        stream.set_read_timeout(Some(remaining))?;
+        let read = stream.read(&mut buffer[..(MAX_REQUEST_LINE - line.len()).min(1024)])?;
+        if read == 0 {
+            break;
+        }
+        line.extend_from_slice(&buffer[..read]);
+    }
+    let end = line.iter().position(|&byte| byte == b'\n').unwrap_or(line.len());
+    Ok(String::from_utf8_lossy(&line[..end]).into_owned())
+}
+
+// Ready while the aggregator runs, at least one source is still open and
+// the queue isn't backing up
+fn is_ready(health: &Health) -> bool {
+    health.aggregator_running()
+        && health.sources().iter().any(|source| !source.finished)
+        && health.queued() < QUEUE_READY_LIMIT
+}
+
+#[derive(Serialize)]
+struct Report {
+    status: &'static str,
+    sources: Vec<SourceReport>,
+    queue: QueueReport,
+    aggregator_running: bool,
+}
+
+#[derive(Serialize)]
+struct SourceReport {
+    name: String,
+    events: usize,
+    seconds_since_last_event: Option<f64>,
+    finished: bool,
+}
+
+#[derive(Serialize)]
+struct QueueReport {
+    depth: usize,
+    capacity: usize,
+}
+
+fn report(health: &Health, ok: bool) -> String {
+    let now = Instant::now();
+    let sources = health.sources().into_iter()
+        .map(|source| SourceReport {
+            seconds_since_last_event: source.last_event
+                .map(|t| (now.duration_since(t).as_secs_f64() * 10.0).round() / 10.0),
+            name: source.name,
+            events: source.events,
+            finished: source.finished,
+        })
+        .collect();
+
+    let report = Report {
+        status: if ok { "ok" } else { "fail" },
+        sources,
+        queue: QueueReport { depth: health.queued(), capacity: QUEUE_CAPACITY },
+        aggregator_running: health.aggregator_running(),
+    };
+    serde_json::to_string(&report).expect("plain data serializes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A client that never sends its request holds up neither poll nor
+    // the clients after it
+    #[test]
+    fn slow_client() {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let health = Arc::new(Health::default());
+        let _silent = TcpStream::connect(addr).unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"GET /healthz HTTP/1.1\r\n\r\n").unwrap();
+
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_millis(200) {
+            server.poll(&health);
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(started.elapsed() < REQUEST_DEADLINE);
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503 "), "{}", response);
+    }
+
+    #[test]
+    fn long_request_line() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        client.write_all(&[b'a'; MAX_REQUEST_LINE + 1]).unwrap();
+        let error = read_request_line(&mut stream).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,165 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml::{Table, Value};
+
+use crate::theme::Theme;
+
+const SECTIONS: &str = "[colors], [keys] or [alerts]";
+// The keys of [alerts], and the options they stand for
+const ALERTS: &[(&str, &str)] = &[
+    ("rules", "alert"),
+    ("cooldown", "alert-cooldown"),
+    ("webhook", "alert-webhook"),
+    ("slack-webhook", "slack-webhook"),
+    ("command", "alert-command"),
+    ("pagerduty-key", "pagerduty-key"),
+    ("opsgenie-key", "opsgenie-key"),
+    ("opsgenie-url", "opsgenie-url"),
+    ("bell", "bell"),
+];
+
+// What a config file sets: defaults for long options, as arguments to go
+// before those on the command line, and the colors of the display
+#[derive(Debug, Default)]
+pub struct Settings {
+    pub args: Vec<String>,
+    pub colors: Option<Theme>,
+}
+
+// $XDG_CONFIG_HOME/httop/config.toml, or ~/.config/httop/config.toml
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(base.join("httop").join("config.toml"))
+}
+
+pub fn load(path: &Path) -> Result<Settings, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("could not read config {}: {}", path.display(), e))?;
+    parse(&text).map_err(|e| format!("config {}: {}", path.display(), e))
+}
+
+// Top-level keys are long options, `format = "apache-combined"` for
+// --format apache-combined. [keys] holds --bind NEW=OLD pairs as NEW =
+// "OLD", [alerts] the alert rules and where they are sent, and [colors]
+// the roles of a theme file
+fn parse(text: &str) -> Result<Settings, String> {
+    let table = text.parse::<Table>().map_err(|e| e.to_string())?;
+    let mut settings = Settings::default();
+    for (key, value) in &table {
+        match (key.as_str(), value) {
+            ("colors", Value::Table(colors)) => {
+                if table.contains_key("theme") {
+                    return Err("theme and [colors] both set the colors: pick one".to_string());
+                }
+                settings.colors = Some(Theme::from_table(colors).map_err(|e| format!("[colors] {}", e))?);
+            }
+            ("keys", Value::Table(keys)) => {
+                for (new, old) in keys {
+                    let old = old.as_str().ok_or_else(|| format!("[keys] {}: expected the \"key\" it stands for", new))?;
+                    settings.args.extend(["--bind".to_string(), format!("{}={}", new, old)]);
+                }
+            }
+            ("alerts", Value::Table(alerts)) => {
+                for (key, value) in alerts {
+                    let Some((_, option)) = ALERTS.iter().find(|(name, _)| *name == key.replace('_', "-")) else {
+                        let keys: Vec<&str> = ALERTS.iter().map(|(name, _)| *name).collect();
+                        return Err(format!("[alerts] unknown key '{}' ({})", key, keys.join(", ")));
+                    };
+                    push_option(&mut settings.args, option, value).map_err(|e| format!("[alerts] {}: {}", key, e))?;
+                }
+            }
+            (_, Value::Table(_)) => return Err(format!("unknown section [{}] ({})", key, SECTIONS)),
+            _ => {
+                let option = key.replace('_', "-");
+                if matches!(option.as_str(), "config" | "help" | "version") {
+                    return Err(format!("'{}' only works on the command line", key));
+                }
+                push_option(&mut settings.args, &option, value).map_err(|e| format!("{}: {}", key, e))?;
+            }
+        }
+    }
+    Ok(settings)
+}
+
+// A string or number for an option's argument, true for a flag and an
+// array for a repeatable option
+fn push_option(args: &mut Vec<String>, option: &str, value: &Value) -> Result<(), String> {
+    let values = match value {
+        Value::Array(values) => values.as_slice(),
+        value => std::slice::from_ref(value),
+    };
+    for value in values {
+        match value {
+            Value::Boolean(true) => args.push(format!("--{}", option)),
+            Value::Boolean(false) => {}
+            Value::String(text) => args.extend([format!("--{}", option), text.clone()]),
+            Value::Integer(number) => args.extend([format!("--{}", option), number.to_string()]),
+            Value::Float(number) => args.extend([format!("--{}", option), number.to_string()]),
+            _ => return Err(format!("expected a \"string\", number, true or false{}",
+                if matches!(value, Value::Array(_)) { " in the array" } else { "" })),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections() {
+        let settings = parse(r#"
+            format = "apache-combined"
+            log_format = '$remote_addr "$request" $status'
+            hide-bots = true
+            robots-txt-bots = false
+            limit = 30
+            exclude-country = [
+                "CN",
+                "RU",
+            ]
+
+            [keys]
+            J = "j"
+
+            [alerts]
+            rules = ["error_rate>5%", "p99>800ms for 2m"]
+            cooldown = "15m"
+            slack-webhook = "https://hooks.slack.com/services/T0/B0/x"
+
+            [colors]
+            base = "light"
+            status_5xx = "bold"
+        "#).unwrap();
+        assert_eq!(settings.args, [
+            "--format", "apache-combined",
+            "--log-format", "$remote_addr \"$request\" $status",
+            "--hide-bots",
+            "--limit", "30",
+            "--exclude-country", "CN", "--exclude-country", "RU",
+            "--bind", "J=j",
+            "--alert", "error_rate>5%", "--alert", "p99>800ms for 2m",
+            "--alert-cooldown", "15m",
+            "--slack-webhook", "https://hooks.slack.com/services/T0/B0/x",
+        ]);
+        let colors = settings.colors.unwrap();
+        assert_eq!(colors.status[3], Some(crate::highlight::Color::Bold));
+    }
+
+    #[test]
+    fn rejected() {
+        assert!(parse("[filters]\nvhost = \"example.com\"").is_err());
+        assert!(parse("[alerts]\nrule = \"rps<1\"").is_err());
+        assert!(parse("[keys]\nJ = 1").is_err());
+        assert!(parse("theme = \"light\"\n[colors]\nheader = \"bold\"").is_err());
+        assert!(parse("[colors]\nheader = \"plaid\"").is_err());
+        assert!(parse("config = \"other.toml\"").is_err());
+        assert!(parse("since = 2026-05-01").is_err());
+        assert!(parse("format = \"nginx").is_err());
+    }
+}
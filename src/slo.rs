@@ -0,0 +1,363 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::Request;
+
+// Logged time the compliance is over, unless the objective gives a window
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(24 * 3600);
+// The shorter spans burn rates are over, as multiwindow burn alerts have them
+pub const BURN_SPANS: [(&str, i64); 2] = [("5m", 5), ("1h", 60)];
+
+// An objective for one endpoint, such as
+// `path=/api/checkout availability=99.9 latency_p99=500ms`: 99.9% of its
+// requests answered without a 5xx, and 99% of them within 500ms. A path
+// ending in * takes every path it begins
+#[derive(Debug, Clone)]
+pub struct Slo {
+    pub path: String,
+    prefix: bool,
+    pub method: Option<String>,
+    // Percentages of requests
+    pub availability: Option<f64>,
+    // The percentage within the time, in seconds
+    pub latency: Option<(f64, f64)>,
+    pub window: Duration,
+}
+
+impl FromStr for Slo {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut slo = Slo { path: String::new(), prefix: false, method: None, availability: None, latency: None, window: DEFAULT_WINDOW };
+        for pair in spec.split_whitespace() {
+            let (key, value) = pair.split_once('=')
+                .ok_or_else(|| format!("'{}' in SLO '{}' is not KEY=VALUE", pair, spec))?;
+            let invalid = || format!("invalid {} '{}' in SLO '{}' (e.g. availability=99.9, latency_p99=500ms, window=6h)", key, value, spec);
+            match key {
+                "path" => {
+                    slo.prefix = value.ends_with('*');
+                    slo.path = value.trim_end_matches('*').to_string();
+                }
+                "method" => slo.method = Some(value.to_ascii_uppercase()),
+                "availability" => slo.availability = Some(parse_target(value).ok_or_else(invalid)?),
+                "window" => slo.window = parse_window(value).ok_or_else(invalid)?,
+                _ => match key.strip_prefix("latency_p").map(parse_target) {
+                    Some(Some(target)) => slo.latency = Some((target, parse_time(value).ok_or_else(invalid)?)),
+                    Some(None) => return Err(format!("invalid percentile in '{}' of SLO '{}' (e.g. latency_p99)", key, spec)),
+                    None => return Err(format!(
+                        "unknown key '{}' in SLO '{}' (path, method, availability, latency_pNN, window)", key, spec)),
+                },
+            }
+        }
+        if !slo.path.starts_with('/') {
+            return Err(format!("SLO '{}' needs a path=/... to apply to", spec));
+        }
+        if slo.availability.is_none() && slo.latency.is_none() {
+            return Err(format!("SLO '{}' needs an availability=PERCENT or latency_pNN=TIME objective", spec));
+        }
+        Ok(slo)
+    }
+}
+
+impl fmt::Display for Slo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(method) = &self.method {
+            write!(f, "{} ", method)?;
+        }
+        write!(f, "{}{}", self.path, if self.prefix { "*" } else { "" })
+    }
+}
+
+impl Slo {
+    fn applies(&self, method: &str, path: &str) -> bool {
+        let path_matches = if self.prefix { path.starts_with(&self.path) } else { path == self.path };
+        path_matches && self.method.as_ref().is_none_or(|wanted| wanted.eq_ignore_ascii_case(method))
+    }
+}
+
+// Below 100, as a 100% objective leaves no budget to burn
+fn parse_target(value: &str) -> Option<f64> {
+    let target: f64 = value.strip_suffix('%').unwrap_or(value).parse().ok()?;
+    (target > 0.0 && target < 100.0).then_some(target)
+}
+
+// 500ms, 1.5s, or seconds without a unit
+fn parse_time(value: &str) -> Option<f64> {
+    let seconds: f64 = match value.strip_suffix("ms") {
+        Some(millis) => millis.parse::<f64>().ok()? / 1000.0,
+        None => value.strip_suffix('s').unwrap_or(value).parse().ok()?,
+    };
+    (seconds.is_finite() && seconds > 0.0).then_some(seconds)
+}
+
+// 30m, 6h, 7d, a minute at least
+fn parse_window(value: &str) -> Option<Duration> {
+    let (number, unit) = match value.char_indices().last()? {
+        (at, 'm') => (&value[..at], 60.0),
+        (at, 'h') => (&value[..at], 3600.0),
+        (at, 'd') => (&value[..at], 86400.0),
+        _ => return None,
+    };
+    let window = Duration::try_from_secs_f64(number.parse::<f64>().ok()? * unit).ok()?;
+    (window >= Duration::from_secs(60)).then_some(window)
+}
+
+// E.g. "1d", "6h" or "30m"
+pub fn window_name(window: Duration) -> String {
+    let minutes = window.as_secs() / 60;
+    if minutes.is_multiple_of(24 * 60) {
+        format!("{}d", minutes / (24 * 60))
+    } else if minutes.is_multiple_of(60) {
+        format!("{}h", minutes / 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+// One minute of logged time of an endpoint
+#[derive(Debug, Clone, Copy, Default)]
+struct Minute {
+    minute: i64,
+    requests: usize,
+    server_errors: usize,
+    // Requests with a response time, and those of them over the objective's
+    timed: usize,
+    slow: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Met,
+    // Over the last 5 minutes and hour alike the budget goes faster than
+    // it would last the window
+    Burning,
+    // Less compliant than the target over the window: the budget is spent
+    Violated,
+}
+
+impl State {
+    pub fn name(self) -> &'static str {
+        match self {
+            State::Met => "met",
+            State::Burning => "burning",
+            State::Violated => "VIOLATED",
+        }
+    }
+}
+
+// How one objective stands over the window, of the requests it counts
+#[derive(Debug, Clone)]
+pub struct Compliance {
+    // E.g. "availability 99.9%" or "p99 500ms"
+    pub objective: String,
+    pub target: f64,
+    pub requests: usize,
+    pub bad: usize,
+    // The share of the window's budget left, below 0 once overspent
+    pub budget_left: f64,
+    // Bad requests as a multiple of what the budget allows, over
+    // BURN_SPANS, None for a span without requests
+    pub burn_rates: [Option<f64>; 2],
+    pub state: State,
+}
+
+impl Compliance {
+    // Percent of requests that were good, None without any
+    pub fn percent(&self) -> Option<f64> {
+        (self.requests > 0).then(|| (self.requests - self.bad) as f64 / self.requests as f64 * 100.0)
+    }
+}
+
+// One objective being tracked, by minute of logged time over its window
+#[derive(Debug, Clone)]
+pub struct Tracked {
+    pub slo: Slo,
+    // Oldest first
+    minutes: VecDeque<Minute>,
+}
+
+impl Tracked {
+    fn record(&mut self, minute: i64, latest: i64, request: &Request) {
+        let window = self.window_minutes();
+        if minute <= latest - window {
+            return;
+        }
+        let index = match self.minutes.iter().rposition(|bucket| bucket.minute <= minute) {
+            Some(index) if self.minutes[index].minute == minute => index,
+            found => {
+                // Lines logged late go in the minute they belong to
+                let at = found.map_or(0, |index| index + 1);
+                self.minutes.insert(at, Minute { minute, ..Minute::default() });
+                at
+            }
+        };
+        let bucket = &mut self.minutes[index];
+        bucket.requests += request.weight;
+        if request.status_code >= 500 {
+            bucket.server_errors += request.weight;
+        }
+        if let Some((_, limit)) = self.slo.latency
+            && request.response_time > 0.0 {
+            bucket.timed += request.weight;
+            if request.response_time > limit {
+                bucket.slow += request.weight;
+            }
+        }
+        while self.minutes.front().is_some_and(|bucket| bucket.minute <= latest - window) {
+            self.minutes.pop_front();
+        }
+    }
+
+    fn window_minutes(&self) -> i64 {
+        (self.slo.window.as_secs() / 60).max(1) as i64
+    }
+
+    // The minutes of the last `span`, up to `latest`, added up
+    fn sum(&self, latest: i64, span: i64) -> Minute {
+        self.minutes.iter().rev()
+            .take_while(|bucket| bucket.minute > latest - span)
+            .fold(Minute::default(), |sum, bucket| Minute {
+                minute: sum.minute,
+                requests: sum.requests + bucket.requests,
+                server_errors: sum.server_errors + bucket.server_errors,
+                timed: sum.timed + bucket.timed,
+                slow: sum.slow + bucket.slow,
+            })
+    }
+
+    // Availability first, then latency, for those the SLO has
+    pub fn compliance(&self, latest: i64) -> Vec<Compliance> {
+        let window = self.window_minutes();
+        let availability = self.slo.availability.map(|target| {
+            let count = |sum: Minute| (sum.requests, sum.server_errors);
+            (format!("availability {}%", target), target, count as fn(Minute) -> (usize, usize))
+        });
+        let latency = self.slo.latency.map(|(target, limit)| {
+            let count = |sum: Minute| (sum.timed, sum.slow);
+            (format!("p{} {:.0}ms", target, limit * 1000.0), target, count as fn(Minute) -> (usize, usize))
+        });
+        availability.into_iter().chain(latency)
+            .map(|(objective, target, count)| {
+                let budget = 1.0 - target / 100.0;
+                let (requests, bad) = count(self.sum(latest, window));
+                let burn_rates = BURN_SPANS.map(|(_, span)| {
+                    let (requests, bad) = count(self.sum(latest, span.min(window)));
+                    (requests > 0).then(|| bad as f64 / requests as f64 / budget)
+                });
+                let budget_left = if requests > 0 { 1.0 - bad as f64 / (requests as f64 * budget) } else { 1.0 };
+                let state = if budget_left < 0.0 {
+                    State::Violated
+                } else if burn_rates.iter().all(|rate| rate.is_some_and(|rate| rate > 1.0)) {
+                    State::Burning
+                } else {
+                    State::Met
+                };
+                Compliance { objective, target, requests, bad, budget_left, burn_rates, state }
+            })
+            .collect()
+    }
+}
+
+// The --slo objectives, counted as requests come
+#[derive(Debug, Clone, Default)]
+pub struct Slos {
+    pub tracked: Vec<Tracked>,
+    // Minute of the newest request logged, which the windows end with
+    pub latest: i64,
+}
+
+impl Slos {
+    pub fn new(slos: Vec<Slo>) -> Self {
+        Slos { tracked: slos.into_iter().map(|slo| Tracked { slo, minutes: VecDeque::new() }).collect(), latest: 0 }
+    }
+
+    // The same objectives, nothing counted
+    pub fn cleared(&self) -> Self {
+        Slos::new(self.tracked.iter().map(|tracked| tracked.slo.clone()).collect())
+    }
+
+    pub fn record(&mut self, request: &Request) {
+        if self.tracked.is_empty() {
+            return;
+        }
+        let minute = request.timestamp.timestamp().div_euclid(60);
+        self.latest = self.latest.max(minute);
+        for tracked in &mut self.tracked {
+            if tracked.slo.applies(&request.method, &request.path) {
+                tracked.record(minute, self.latest, request);
+            }
+        }
+    }
+
+    // Each objective with how it stands, the violated and burning first
+    pub fn standings(&self) -> Vec<(&Slo, Compliance)> {
+        let mut standings: Vec<(&Slo, Compliance)> = self.tracked.iter()
+            .flat_map(|tracked| tracked.compliance(self.latest).into_iter().map(move |compliance| (&tracked.slo, compliance)))
+            .collect();
+        standings.sort_by_key(|(_, compliance)| match compliance.state {
+            State::Violated => 0,
+            State::Burning => 1,
+            State::Met => 2,
+        });
+        standings
+    }
+}
+
+// E.g. "12.0x", or "-" without requests
+pub fn burn_text(rate: Option<f64>) -> String {
+    rate.map_or("-".to_string(), |rate| format!("{:.1}x", rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_log_line;
+
+    fn request(time: &str, method: &str, path: &str, status: u16, weight: usize) -> Request {
+        let line = format!(r#"192.0.2.1 - - [{} +0000] "{} {} HTTP/1.1" {} 100 "-" "-" 0.250"#, time, method, path, status);
+        Request { weight, ..parse_log_line(&line).unwrap() }
+    }
+
+    #[test]
+    fn parsed() {
+        let slo: Slo = "path=/api/* method=post availability=99.9% latency_p99=500ms window=6h".parse().unwrap();
+        assert_eq!(slo.to_string(), "POST /api/*");
+        assert_eq!((slo.availability, slo.latency, slo.window), (Some(99.9), Some((99.0, 0.5)), Duration::from_secs(6 * 3600)));
+        assert!(slo.applies("post", "/api/checkout") && !slo.applies("GET", "/api/checkout"));
+
+        for spec in ["path=/api availability=100", "path=api availability=99", "path=/api", "path=/api latency_pxx=1s",
+            "path=/api availability=99 window=30s", "path=/api availability=99 owner=me"] {
+            assert!(spec.parse::<Slo>().is_err(), "{}", spec);
+        }
+    }
+
+    // 1% of a day's requests may fail: five failures in the last minute
+    // spend half of it, at 50 times the rate that would last the day
+    #[test]
+    fn burn_rates() {
+        let mut slos = Slos::new(vec!["path=/api/* availability=99".parse().unwrap()]);
+        slos.record(&request("29/Nov/2021:06:00:00", "GET", "/api/cart", 200, 990));
+        slos.record(&request("29/Nov/2021:06:00:00", "GET", "/static/app.js", 500, 100));
+        slos.record(&request("29/Nov/2021:12:00:00", "GET", "/api/cart", 200, 5));
+        slos.record(&request("29/Nov/2021:12:00:30", "GET", "/api/cart", 500, 5));
+
+        let [(_, compliance)] = slos.standings().try_into().unwrap();
+        assert_eq!((compliance.requests, compliance.bad), (1000, 5));
+        assert!((compliance.budget_left - 0.5).abs() < 1e-9);
+        assert_eq!(compliance.burn_rates.map(|rate| rate.map(|rate| rate.round())), [Some(50.0); 2]);
+        assert_eq!(compliance.state, State::Burning);
+        assert_eq!(compliance.percent(), Some(99.5));
+
+        // Once more fail than the budget allows, it is violated
+        slos.record(&request("29/Nov/2021:12:01:00", "GET", "/api/cart", 500, 10));
+        assert_eq!(slos.standings()[0].1.state, State::Violated);
+
+        // A day on, the morning's requests are out of the window
+        slos.record(&request("30/Nov/2021:06:30:00", "GET", "/api/cart", 200, 1));
+        let compliance = &slos.standings()[0].1;
+        assert_eq!((compliance.requests, compliance.bad), (21, 15));
+        assert_eq!(compliance.burn_rates, [Some(0.0); 2]);
+    }
+}
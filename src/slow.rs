@@ -0,0 +1,40 @@
+use std::collections::VecDeque;
+
+use crate::Request;
+
+pub const CAPACITY: usize = 1_000;
+
+// Every request from --slow-threshold on, in full, kept apart from the
+// history so that a burst of fast traffic does not push them out
+#[derive(Debug)]
+pub struct SlowLog {
+    pub threshold: Option<f64>,
+    requests: VecDeque<Request>,
+    // All slow requests seen, including those no longer kept
+    pub total: usize,
+}
+
+impl SlowLog {
+    pub fn new(threshold: Option<f64>) -> Self {
+        SlowLog { threshold, requests: VecDeque::new(), total: 0 }
+    }
+
+    pub fn record(&mut self, request: &Request) {
+        if !self.threshold.is_some_and(|threshold| request.response_time >= threshold) {
+            return;
+        }
+        self.total += 1;
+        if self.requests.len() == CAPACITY {
+            self.requests.pop_front();
+        }
+        self.requests.push_back(request.clone());
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn newest_first(&self) -> impl ExactSizeIterator<Item = &Request> {
+        self.requests.iter().rev()
+    }
+}
@@ -0,0 +1,699 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::asn::AsnDb;
+use crate::bots;
+use crate::cache;
+use crate::checkpoint::Checkpoint;
+use crate::config::Config;
+use crate::emit::Emitter;
+use crate::formats;
+use crate::geoip::GeoIp;
+use crate::history::History;
+use crate::kube;
+use crate::normalize;
+use crate::notices::Notices;
+use crate::record::{self, Recorder};
+use crate::sample::Sampler;
+use crate::stats::Panel;
+use crate::unparsed::{Reason, Unparsed};
+use crate::{Request, Stats};
+
+// Parsed requests waiting for the aggregator; readers block when it is full
+pub const QUEUE_CAPACITY: usize = 10_000;
+// The longest the aggregator goes without looking for changes or a copy
+// asked of it, when no requests arrive
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Requests the aggregator holds back from the history, to take its lock
+// once per batch rather than once per request
+const HISTORY_BATCH: usize = 256;
+
+type Change = Box<dyn FnOnce(&mut Stats) + Send>;
+
+// The stats as the aggregator last published them. The aggregator thread
+// owns the live stats and publishes a copy every interval, so reading
+// them never holds up ingestion; changes to them are handed over and
+// applied between requests
+pub struct Published {
+    state: Mutex<Publication>,
+    published: Condvar,
+    // Set with a change or a copy asked for, so the aggregator only
+    // locks the state when there is something to do
+    asked: AtomicBool,
+    // Between copies, in milliseconds
+    interval: AtomicU64,
+}
+
+struct Publication {
+    latest: Arc<Stats>,
+    // The panel of the latest copy, unless it has them all
+    panel: Option<Panel>,
+    full: bool,
+    copies: u64,
+    // For the next copy: the panel of the view shown, all of them, and
+    // whether it is waited for
+    wanted_panel: Option<Panel>,
+    wanted_full: bool,
+    wanted_now: bool,
+    changes: Vec<Change>,
+    // The live stats while no aggregator runs, before ingestion starts and
+    // once it has read everything
+    idle: Option<Stats>,
+}
+
+impl Published {
+    pub fn new(mut stats: Stats, interval: Duration) -> Self {
+        let latest = Arc::new(stats.snapshot());
+        Published {
+            state: Mutex::new(Publication {
+                latest,
+                panel: None,
+                full: true,
+                copies: 0,
+                wanted_panel: None,
+                wanted_full: false,
+                wanted_now: false,
+                changes: Vec::new(),
+                idle: Some(stats),
+            }),
+            published: Condvar::new(),
+            asked: AtomicBool::new(false),
+            interval: AtomicU64::new(interval.as_millis() as u64),
+        }
+    }
+
+    pub fn set_interval(&self, interval: Duration) {
+        self.interval.store(interval.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    // The latest copy, however many panels it has
+    pub fn latest(&self) -> Arc<Stats> {
+        Arc::clone(&self.state.lock().unwrap().latest)
+    }
+
+    // The latest copy for drawing a view, or a new one if it lacks the
+    // view's panel; copies from then on have it
+    pub fn frame(&self, panel: Option<Panel>) -> Arc<Stats> {
+        {
+            let state = self.state.lock().unwrap();
+            if state.full || state.panel == panel {
+                return Arc::clone(&state.latest);
+            }
+        }
+        self.copy(panel, false)
+    }
+
+    // A new copy with every panel, for exports and reports
+    pub fn snapshot(&self) -> Arc<Stats> {
+        self.copy(None, true)
+    }
+
+    fn copy(&self, panel: Option<Panel>, full: bool) -> Arc<Stats> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(stats) = state.idle.as_mut() {
+            stats.tick(Instant::now());
+            return Arc::new(if full { stats.snapshot() } else { stats.frame(panel) });
+        }
+        state.wanted_panel = panel;
+        state.wanted_full |= full;
+        state.wanted_now = true;
+        self.asked.store(true, Ordering::Relaxed);
+        // A copy already on its way may have been made for another view
+        let copies = state.copies;
+        while state.copies == copies || !(state.full || !full && state.panel == panel) {
+            state = self.published.wait(state).unwrap();
+        }
+        Arc::clone(&state.latest)
+    }
+
+    // Applies `change` to the live stats, waiting for the aggregator to
+    // get to it
+    pub fn update<T: Send + 'static>(&self, change: impl FnOnce(&mut Stats) -> T + Send + 'static) -> T {
+        let mut state = self.state.lock().unwrap();
+        if let Some(stats) = state.idle.as_mut() {
+            return change(stats);
+        }
+        let (sender, receiver) = mpsc::channel();
+        state.changes.push(Box::new(move |stats| {
+            let _ = sender.send(change(stats));
+        }));
+        self.asked.store(true, Ordering::Relaxed);
+        drop(state);
+        receiver.recv().expect("the aggregator applies every change before it stops")
+    }
+
+    fn take(&self) -> Stats {
+        self.state.lock().unwrap().idle.take().expect("one aggregator at a time")
+    }
+
+    // Between requests: changes first, then a copy if one is due or asked
+    // for. The copy is made without the lock, so readers never wait on it.
+    // True if one was published
+    fn serve(&self, stats: &mut Stats, due: bool) -> bool {
+        let (changes, panel, full, now) = {
+            let mut state = self.state.lock().unwrap();
+            self.asked.store(false, Ordering::Relaxed);
+            let now = std::mem::take(&mut state.wanted_now);
+            (std::mem::take(&mut state.changes), state.wanted_panel, std::mem::take(&mut state.wanted_full), now)
+        };
+        for change in changes {
+            change(stats);
+        }
+        if !due && !now {
+            return false;
+        }
+        stats.tick(Instant::now());
+        let copy = Arc::new(if full { stats.snapshot() } else { stats.frame(panel) });
+        let mut state = self.state.lock().unwrap();
+        state.latest = copy;
+        state.panel = panel;
+        state.full = full;
+        state.copies += 1;
+        self.published.notify_all();
+        true
+    }
+
+    // Once ingestion is over: the last changes and a full copy, and the
+    // stats parked for whatever comes after
+    fn retire(&self, mut stats: Stats) {
+        let mut state = self.state.lock().unwrap();
+        for change in std::mem::take(&mut state.changes) {
+            change(&mut stats);
+        }
+        stats.tick(Instant::now());
+        state.latest = Arc::new(stats.snapshot());
+        state.full = true;
+        state.copies += 1;
+        state.idle = Some(stats);
+        self.published.notify_all();
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval.load(Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceStatus {
+    pub name: String,
+    pub last_event: Option<Instant>,
+    pub events: usize,
+    pub finished: bool,
+}
+
+// Liveness of the input sources and the queue feeding the aggregator
+#[derive(Debug, Default)]
+pub struct Health {
+    // Locked only to add a source or take a snapshot; readers count
+    // their lines in their own source's atomics
+    sources: Mutex<Vec<Arc<Source>>>,
+    queued: AtomicUsize,
+    aggregator_running: AtomicBool,
+    // Lines given to the format, and those it could not parse
+    lines: AtomicUsize,
+    unparsed: Mutex<Unparsed>,
+    // Errors of the reader and aggregator threads, and of the display's
+    // helpers, for the status line or stderr
+    pub notices: Arc<Notices>,
+}
+
+// The counts of one source, kept by its reader
+#[derive(Debug)]
+struct Source {
+    name: String,
+    registered: Instant,
+    events: AtomicUsize,
+    // Nanoseconds from `registered` to the last event, 0 before the first
+    last_event: AtomicU64,
+    finished: AtomicBool,
+}
+
+impl Source {
+    fn event(&self) {
+        let since = self.registered.elapsed().as_nanos().max(1) as u64;
+        self.last_event.store(since, Ordering::Relaxed);
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+
+    fn status(&self) -> SourceStatus {
+        let last_event = match self.last_event.load(Ordering::Relaxed) {
+            0 => None,
+            since => Some(self.registered + Duration::from_nanos(since)),
+        };
+        SourceStatus {
+            name: self.name.clone(),
+            last_event,
+            events: self.events.load(Ordering::Relaxed),
+            finished: self.finished.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Health {
+    fn register(&self, name: &str) -> Arc<Source> {
+        let source = Arc::new(Source {
+            name: name.to_string(),
+            registered: Instant::now(),
+            events: AtomicUsize::new(0),
+            last_event: AtomicU64::new(0),
+            finished: AtomicBool::new(false),
+        });
+        self.sources.lock().unwrap().push(Arc::clone(&source));
+        source
+    }
+
+    pub fn sources(&self) -> Vec<SourceStatus> {
+        self.sources.lock().unwrap().iter().map(|source| source.status()).collect()
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    pub fn aggregator_running(&self) -> bool {
+        self.aggregator_running.load(Ordering::Relaxed)
+    }
+
+    pub fn lines(&self) -> usize {
+        self.lines.load(Ordering::Relaxed)
+    }
+
+    pub fn unparsed(&self) -> Unparsed {
+        self.unparsed.lock().unwrap().clone()
+    }
+}
+
+// Paces --replay by the log's own timestamps. The clock is shared by all
+// readers so that several files are replayed in step
+#[derive(Debug, Default)]
+struct Pacer {
+    // The first timestamp seen and when it was
+    start: Mutex<Option<(DateTime<Utc>, Instant)>>,
+}
+
+impl Pacer {
+    fn wait(&self, timestamp: DateTime<Utc>, speed: f64) {
+        let (first, started) = *self.start.lock().unwrap().get_or_insert((timestamp, Instant::now()));
+        // Lines older than the first one are not held back
+        let Ok(offset) = (timestamp - first).to_std() else {
+            return;
+        };
+        let due = started + offset.div_f64(speed);
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+    }
+}
+
+// Files the aggregator writes requests to as it counts them
+pub struct Writers {
+    pub checkpoint: Option<Checkpoint>,
+    pub recorder: Option<Recorder>,
+    pub emitter: Option<Emitter>,
+}
+
+#[derive(Clone)]
+pub struct Ingest {
+    pub health: Arc<Health>,
+    sender: SyncSender<Request>,
+    geoip: Option<Arc<GeoIp>>,
+    asn: Option<Arc<AsnDb>>,
+    pacer: Arc<Pacer>,
+    // By source, the newest logged time a resumed checkpoint counted and
+    // how many requests it counted at it, for readers going over the same
+    // lines again
+    counted: Arc<HashMap<String, (DateTime<Utc>, usize)>>,
+}
+
+impl Ingest {
+    // Starts the aggregator thread that applies queued requests to the
+    // stats and history, optionally recording them as they are applied
+    pub fn start(
+        health: Arc<Health>,
+        stats: Arc<Published>,
+        history: Arc<Mutex<History>>,
+        writers: Writers,
+        geoip: Option<Arc<GeoIp>>,
+        asn: Option<Arc<AsnDb>>,
+        bots: bots::Detector,
+    ) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_CAPACITY);
+
+        health.aggregator_running.store(true, Ordering::Relaxed);
+        let aggregator_health = Arc::clone(&health);
+        thread::spawn(move || aggregate(receiver, stats, history, aggregator_health, writers, bots));
+
+        Ingest { health, sender, geoip, asn, pacer: Arc::default(), counted: Arc::default() }
+    }
+
+    // Has readers started from here on skip the lines of `requests`, as
+    // resumed from a checkpoint
+    pub fn skip_counted(&mut self, requests: &[(DateTime<Utc>, Request)]) {
+        let mut counted: HashMap<String, (DateTime<Utc>, usize)> = HashMap::new();
+        for (_, request) in requests {
+            let Some(source) = &request.source else {
+                continue;
+            };
+            let (newest, same) = counted.entry(source.to_string()).or_insert((request.timestamp, 0));
+            if request.timestamp > *newest {
+                (*newest, *same) = (request.timestamp, 0);
+            }
+            if request.timestamp == *newest {
+                *same += 1;
+            }
+        }
+        self.counted = Arc::new(counted);
+    }
+
+    // Reads log lines from `reader` on its own thread until EOF
+    pub fn spawn_reader<R: BufRead + Send + 'static>(&self, name: &str, reader: R, config: Config) {
+        self.spawn_lines(name, reader, config, false);
+    }
+
+    // Reads `kubectl logs --prefix` output, each pod a source of its own
+    pub fn spawn_kube_reader<R: BufRead + Send + 'static>(&self, name: &str, reader: R, config: Config) {
+        self.spawn_lines(name, reader, config, true);
+    }
+
+    fn spawn_lines<R: BufRead + Send + 'static>(&self, name: &str, mut reader: R, config: Config, prefixed: bool) {
+        let source = self.health.register(name);
+        let health = Arc::clone(&self.health);
+        let sender = self.sender.clone();
+        let geoip = self.geoip.clone();
+        let asn = self.asn.clone();
+        let pacer = Arc::clone(&self.pacer);
+        let mut counted = (*self.counted).clone();
+        let name: Arc<str> = Arc::from(name);
+
+        thread::spawn(move || {
+            let mut format = config.format.clone();
+            let mut pods: HashMap<String, Arc<str>> = HashMap::new();
+            let mut sampler = Sampler::new(config.sample);
+            let mut bytes = Vec::new();
+            loop {
+                bytes.clear();
+                match reader.read_until(b'\n', &mut bytes) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {}
+                }
+                // Bytes that are not UTF-8 become U+FFFD rather than ending
+                // the input, as lines() would have it
+                let line = String::from_utf8_lossy(&bytes);
+                let lossy = matches!(line, Cow::Owned(_));
+                let line = line.trim_end_matches(['\n', '\r']);
+                let pod = if prefixed { kube::split_prefix(line) } else { None };
+                let (origin, line) = match pod {
+                    Some((pod, line)) => {
+                        let origin = pods.entry(pod.to_string()).or_insert_with(|| Arc::from(pod));
+                        (Arc::clone(origin), line)
+                    }
+                    None => (Arc::clone(&name), line),
+                };
+                // Skipped before parsing, which is where the time goes; W3C
+                // #Fields headers are always read
+                let weight = match sampler.keep(health.queued()) {
+                    Some(weight) => weight,
+                    None if line.starts_with('#') => 1,
+                    None => continue,
+                };
+                let Some(request) = format.parse(line) else {
+                    if !format.skipped(line) {
+                        health.lines.fetch_add(1, Ordering::Relaxed);
+                        health.unparsed.lock().unwrap().record(Arc::clone(&origin), Reason::of(line, lossy), line);
+                    }
+                    continue;
+                };
+                health.lines.fetch_add(1, Ordering::Relaxed);
+                let Some(mut request) = prepare(request, &config, geoip.as_deref(), asn.as_deref()) else {
+                    continue;
+                };
+                // Up to where the checkpoint got, when read again from the start
+                if let Some((newest, same)) = counted.get_mut(&*origin) {
+                    if request.timestamp < *newest {
+                        continue;
+                    }
+                    if request.timestamp == *newest && *same > 0 {
+                        *same -= 1;
+                        continue;
+                    }
+                    counted.remove(&*origin);
+                }
+                request.source = Some(origin);
+                request.weight = weight;
+                if let Some(speed) = config.replay_speed {
+                    pacer.wait(request.timestamp, speed);
+                }
+
+                source.event();
+                health.queued.fetch_add(1, Ordering::Relaxed);
+                if sender.send(request).is_err() {
+                    break;
+                }
+            }
+            source.finish();
+        });
+    }
+
+    // Feeds a recording back with the pacing it was recorded at
+    pub fn spawn_replay(&self, path: PathBuf, config: Config) -> io::Result<()> {
+        let events = record::read_events(&path)?;
+        let source = self.health.register(&path.display().to_string());
+        let health = Arc::clone(&self.health);
+        let sender = self.sender.clone();
+        let geoip = self.geoip.clone();
+        let asn = self.asn.clone();
+
+        thread::spawn(move || {
+            let started = Instant::now();
+            for event in events {
+                let (offset, request) = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        health.notices.post(format!("replay of {} stopped: {}", path.display(), e));
+                        break;
+                    }
+                };
+                let Some(request) = prepare(request, &config, geoip.as_deref(), asn.as_deref()) else {
+                    continue;
+                };
+
+                let due = started + offset;
+                let now = Instant::now();
+                if due > now {
+                    thread::sleep(due - now);
+                }
+
+                source.event();
+                health.queued.fetch_add(1, Ordering::Relaxed);
+                if sender.send(request).is_err() {
+                    break;
+                }
+            }
+            source.finish();
+        });
+
+        Ok(())
+    }
+}
+
+// Counts the requests of a checkpoint again, each at the time it was first
+// counted, so rates and a --window carry on from where they were. Returns
+// those the filters still let through, for the new checkpoint
+pub fn resume(
+    stats: &mut Stats,
+    history: &mut History,
+    requests: Vec<(DateTime<Utc>, Request)>,
+    config: &Config,
+    geoip: Option<&GeoIp>,
+    asn: Option<&AsnDb>,
+    bots: &mut bots::Detector,
+) -> Vec<(DateTime<Utc>, Request)> {
+    let (now, wall_now) = (Instant::now(), Utc::now());
+    // Before the machine started, as after a reboot, they count as new
+    let instant = |counted: DateTime<Utc>| (wall_now - counted).to_std().ok()
+        .and_then(|age| now.checked_sub(age))
+        .unwrap_or(now);
+    let kept: Vec<_> = requests.into_iter()
+        .filter_map(|(counted, request)| Some((counted, prepare(request, config, geoip, asn)?)))
+        .collect();
+    if let Some((first, _)) = kept.first() {
+        *stats = stats.cleared_at(instant(*first));
+    }
+    let mut last_tick = None;
+    for (counted, request) in &kept {
+        let at = instant(*counted);
+        if last_tick.is_none_or(|last| at.saturating_duration_since(last) >= Duration::from_secs(1)) {
+            stats.tick(at);
+            last_tick = Some(at);
+        }
+        let mut request = request.clone();
+        request.bot = bots.classify(&request.ip, &request.path, &request.user_agent);
+        history.push(request.clone());
+        stats.update_at(at, request);
+    }
+    stats.tick(now);
+    kept
+}
+
+// Reads a log to its end on this thread, as --compare does, handing each
+// request the filters let through to `each`
+pub fn read_all(
+    mut reader: impl BufRead,
+    config: &Config,
+    geoip: Option<&GeoIp>,
+    asn: Option<&AsnDb>,
+    mut each: impl FnMut(Request),
+) -> io::Result<()> {
+    let mut format = config.format.clone();
+    let mut bytes = Vec::new();
+    loop {
+        bytes.clear();
+        if reader.read_until(b'\n', &mut bytes)? == 0 {
+            return Ok(());
+        }
+        let line = String::from_utf8_lossy(&bytes);
+        if let Some(request) = format.parse(line.trim_end_matches(['\n', '\r']))
+            && let Some(request) = prepare(request, config, geoip, asn) {
+            each(request);
+        }
+    }
+}
+
+// Resolves derived fields and applies the ingestion filters
+fn prepare(mut request: Request, config: &Config, geoip: Option<&GeoIp>, asn: Option<&AsnDb>) -> Option<Request> {
+    if config.since.is_some_and(|since| request.timestamp < since)
+        || config.until.is_some_and(|until| request.timestamp >= until) {
+        return None;
+    }
+    if let Some(client) = config.real_ip.as_ref().and_then(|real_ip| real_ip.client(&request.ip, &request.fields)) {
+        request.ip = client;
+    }
+    if config.normalize_paths || !config.path_rules.is_empty() {
+        request.path = normalize::path(&request.path, &config.path_rules, config.normalize_paths);
+    }
+    if let Some((path, query)) = normalize::split_query(&request.path, &config.query_strings) {
+        request.path = path;
+        request.query = Some(query);
+    }
+    if !config.filters.is_empty() && !config.filters.allows(&request) {
+        return None;
+    }
+    request.tenant = config.tenant_of(&request.fields).map(str::to_string);
+    if !config.tenant_filter.is_empty()
+        && !request.tenant.as_ref().is_some_and(|t| config.tenant_filter.contains(t)) {
+        return None;
+    }
+    request.vhost = formats::vhost(&request.fields);
+    request.cache_status = cache::status(&request.fields);
+    if !config.vhost_filter.is_empty()
+        && !request.vhost.as_ref().is_some_and(|host| config.vhost_filter.contains(host)) {
+        return None;
+    }
+
+    request.country = geoip.and_then(|geoip| geoip.lookup(&request.ip));
+    let code = request.country.map(|country| country.code.to_string());
+    if !config.country_filter.is_empty()
+        && !code.as_ref().is_some_and(|code| config.country_filter.contains(code)) {
+        return None;
+    }
+    if code.is_some_and(|code| config.country_exclude.contains(&code)) {
+        return None;
+    }
+    request.network = asn.and_then(|asn| asn.lookup(&request.ip));
+
+    // Last, so filters and lookups still see whole addresses
+    if config.anonymize_ips {
+        if let Some(ip) = normalize::anonymize_ips(&request.ip) {
+            request.ip = ip;
+        }
+        for (_, value) in &mut request.fields {
+            if let Some(ips) = normalize::anonymize_ips(value) {
+                *value = ips;
+            }
+        }
+    }
+    Some(request)
+}
+
+fn aggregate(
+    receiver: Receiver<Request>,
+    published: Arc<Published>,
+    history: Arc<Mutex<History>>,
+    health: Arc<Health>,
+    writers: Writers,
+    mut bots: bots::Detector,
+) {
+    let Writers { mut checkpoint, mut recorder, mut emitter } = writers;
+    let mut stats = published.take();
+    let mut next_copy = Instant::now() + published.interval();
+    let mut batch = Vec::with_capacity(HISTORY_BATCH);
+    loop {
+        let wait = next_copy.saturating_duration_since(Instant::now()).min(POLL_INTERVAL);
+        match receiver.recv_timeout(wait) {
+            Ok(mut request) => {
+                health.queued.fetch_sub(1, Ordering::Relaxed);
+                // Here rather than in the readers, as robots.txt fetches seen by
+                // one reader mark the client in all of them
+                request.bot = bots.classify(&request.ip, &request.path, &request.user_agent);
+
+                // Flush whenever the backlog drains so an idle session is on disk
+                if let Some(writer) = checkpoint.as_mut()
+                    && let Err(e) = writer.write(&request).and_then(|_| {
+                        if health.queued() == 0 { writer.flush() } else { Ok(()) }
+                    }) {
+                    health.notices.post(format!("checkpoint stopped: {}", e));
+                    checkpoint = None;
+                }
+                if let Some(writer) = recorder.as_mut()
+                    && let Err(e) = writer.write(&request).and_then(|_| {
+                        if health.queued() == 0 { writer.flush() } else { Ok(()) }
+                    }) {
+                    health.notices.post(format!("recording stopped: {}", e));
+                    recorder = None;
+                }
+                if let Some(writer) = emitter.as_mut()
+                    && let Err(e) = writer.write(&request).and_then(|_| {
+                        if health.queued() == 0 { writer.flush() } else { Ok(()) }
+                    }) {
+                    health.notices.post(format!("--emit-parsed stopped: {}", e));
+                    emitter = None;
+                }
+
+                batch.push(request.clone());
+                stats.update(request);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        // Pushed once the backlog drains, so the history is never behind an idle queue
+        if batch.len() >= HISTORY_BATCH || (!batch.is_empty() && health.queued() == 0) {
+            history.lock().unwrap().extend(batch.drain(..));
+        }
+        let due = Instant::now() >= next_copy;
+        if (due || published.asked.load(Ordering::Relaxed)) && published.serve(&mut stats, due) {
+            next_copy = Instant::now() + published.interval();
+        }
+    }
+    history.lock().unwrap().extend(batch);
+    published.retire(stats);
+
+    // Flushed before --batch, waiting for this, goes on to exit
+    drop(checkpoint);
+    drop(recorder);
+    drop(emitter);
+    health.aggregator_running.store(false, Ordering::Relaxed);
+}
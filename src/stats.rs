@@ -0,0 +1,832 @@
+use std::collections::HashMap;
+use std::iter;
+use std::time::{Duration, Instant};
+
+use crate::{bans, content, formats, memory, tls, visits};
+use crate::Request;
+use crate::agents::{self, Grouping};
+use crate::alerts::Metrics;
+use crate::attacks::{self, AttackStats};
+use crate::baseline::{BaselineRecorder, PathActivity};
+use crate::cache::CacheStats;
+use crate::campaigns::CampaignStats;
+use crate::compare::Comparison;
+use crate::slo::Slos;
+use crate::content::{ContentClass, ContentStats, StaticAssets};
+use crate::duplicates::DuplicateStats;
+use crate::geoip::CountryStats;
+use crate::histogram::{SizeHistogram, SizeStats};
+use crate::outliers::{LatencyBaseline, OutlierStats};
+use crate::parser::field_value;
+use crate::preflight::{PreflightEntry, PreflightStats};
+use crate::quantiles::{Quantiles, RecentQuantiles};
+use crate::rate::{ClassTimeline, ClassWindow, LoadRates, MinuteRate, WindowRates};
+use crate::redirects::{RedirectEntry, RedirectStats};
+use crate::referrers::{ReferrerStats, SpamList};
+use crate::tenants::{DEFAULT_MAX_KEYS, TenantStats};
+use crate::tls::TlsStats;
+use crate::transfer::{TransferEntry, TransferStats};
+use crate::uniques::Uniques;
+use crate::visits::VisitStats;
+
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub total_requests: usize,
+    // Requests per second over the last 1, 5 and 15 minutes
+    pub load: WindowRates,
+    // Response time logged per second; by Little's law this is the
+    // average number of requests in flight
+    pub concurrency: LoadRates,
+    pub timed_requests: usize,
+    pub response_times: Quantiles,
+    // Of the requests with an upstream time: that time and their whole
+    // response time, added up
+    pub upstream_requests: usize,
+    pub upstream_time: f64,
+    pub proxied_time: f64,
+    // Of the last minute, for alert rules
+    pub recent_response_times: RecentQuantiles,
+    pub client_errors: MinuteRate,
+    pub server_errors: MinuteRate,
+    pub status_classes: ClassWindow,
+    pub status_timeline: ClassTimeline,
+    pub bytes_sent: usize,
+    // Bytes sent per second
+    pub throughput: MinuteRate,
+    pub status_codes: HashMap<u16, usize>,
+    pub paths: HashMap<String, usize>,
+    // Keyed by "METHOD path", see endpoint_key
+    pub endpoints: HashMap<String, usize>,
+    pub unique_ips: Uniques,
+    pub methods: HashMap<String, usize>,
+    pub path_activity: HashMap<String, PathActivity>,
+    pub baseline_recorder: Option<BaselineRecorder>,
+    // With --compare or --compare-window
+    pub comparison: Option<Comparison>,
+    // With --slo, over logged time of their own
+    pub slos: Slos,
+    pub campaigns: CampaignStats,
+    pub referrers: ReferrerStats,
+    pub visits: VisitStats,
+    pub tenants: TenantStats,
+    pub vhosts: TenantStats,
+    pub services: TenantStats,
+    pub upstreams: TenantStats,
+    pub sources: TenantStats,
+    // By the machine that logged the request, see Request::host
+    pub hosts: TenantStats,
+    pub countries: CountryStats,
+    pub networks: TenantStats,
+    // Requests from addresses in no --asn range, e.g. private networks
+    pub without_network: usize,
+    pub transfer: TransferStats,
+    pub sizes: SizeStats,
+    pub content: ContentStats,
+    pub tls: TlsStats,
+    pub cache: CacheStats,
+    pub preflight: PreflightStats,
+    pub redirects: RedirectStats,
+    pub outliers: OutlierStats,
+    pub attacks: AttackStats,
+    pub duplicates: DuplicateStats,
+    // Counts, bytes, error rates and latency of each path, endpoint,
+    // client, user agent and status code, for the tabs
+    pub path_totals: TenantStats,
+    pub endpoint_totals: TenantStats,
+    // Only the 404 and only the 5xx responses of each path
+    pub not_found: TenantStats,
+    pub server_error_paths: TenantStats,
+    pub client_ips: TenantStats,
+    pub user_agents: TenantStats,
+    pub browsers: TenantStats,
+    pub operating_systems: TenantStats,
+    pub devices: TenantStats,
+    pub statuses: TenantStats,
+    pub protocols: TenantStats,
+    // By the bot's name
+    pub bots: TenantStats,
+    // Requests whose log line had no HTTP version
+    pub without_protocol: usize,
+    // Every request that arrived, including those aged out since
+    pub received: usize,
+    // The same by status class, 1xx to 5xx, and their bytes
+    pub received_classes: [usize; 5],
+    pub received_bytes: usize,
+    // Lines the latest request stood for, with --sample
+    pub sampled: usize,
+    // With --window, the tables count requests from `started` on. Younger
+    // copies, begun a quarter of the window apart, take over in turn as the
+    // oldest reaches the window's age
+    pub window: Option<Duration>,
+    pub started: Instant,
+    // Counted in the path tables under their bucket
+    pub static_assets: Option<StaticAssets>,
+    // Keys each table keeps, 0 for all of them, see TenantStats::limit
+    pub max_keys: usize,
+    // Bytes the tables take, estimated every few thousand requests, and
+    // the most they may, 0 for no limit, see Stats::trim
+    pub memory: usize,
+    pub max_memory: usize,
+    // Requests received as of the last estimate
+    pub measured: usize,
+    pub generations: Vec<Stats>,
+}
+
+// The panels only their own views draw, see Stats::frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Panel {
+    Sizes,
+    Visits,
+    Outliers,
+    Compare,
+}
+
+// Copies of the stats kept counting over a --window
+const GENERATIONS: u32 = 4;
+// Requests between estimates of the memory the tables take
+const MEASURE_EVERY: usize = 4096;
+
+// For embedding: no spam referrer list and duplicates counted
+impl Default for Stats {
+    fn default() -> Self {
+        Stats::new(SpamList::default(), false)
+    }
+}
+
+impl Stats {
+    // Nothing counted yet, but configured like these
+    pub fn cleared(&self) -> Self {
+        self.cleared_at(Instant::now())
+    }
+
+    // The same as if begun at `now`, to count requests of the past again
+    pub fn cleared_at(&self, now: Instant) -> Self {
+        Stats {
+            referrers: self.referrers.cleared(),
+            visits: self.visits.cleared(),
+            status_timeline: self.status_timeline.cleared(now),
+            window: self.window,
+            static_assets: self.static_assets.clone(),
+            comparison: self.comparison.as_ref().map(Comparison::cleared),
+            slos: self.slos.cleared(),
+            max_keys: self.max_keys,
+            max_memory: self.max_memory,
+            ..Stats::starting(SpamList::default(), self.duplicates.drop, now)
+        }
+    }
+
+    pub fn new(spam_list: SpamList, drop_duplicates: bool) -> Self {
+        Stats::starting(spam_list, drop_duplicates, Instant::now())
+    }
+
+    fn starting(spam_list: SpamList, drop_duplicates: bool, now: Instant) -> Self {
+        Stats {
+            total_requests: 0,
+            load: WindowRates::new(now),
+            concurrency: LoadRates::new(now),
+            timed_requests: 0,
+            upstream_requests: 0,
+            upstream_time: 0.0,
+            proxied_time: 0.0,
+            response_times: Quantiles::default(),
+            recent_response_times: RecentQuantiles::new(now),
+            client_errors: MinuteRate::new(now),
+            server_errors: MinuteRate::new(now),
+            status_classes: ClassWindow::new(now),
+            status_timeline: ClassTimeline::new(now, ClassTimeline::DEFAULT_SPAN),
+            bytes_sent: 0,
+            throughput: MinuteRate::new(now),
+            status_codes: HashMap::new(),
+            paths: HashMap::new(),
+            endpoints: HashMap::new(),
+            unique_ips: Uniques::default(),
+            methods: HashMap::new(),
+            path_activity: HashMap::new(),
+            baseline_recorder: None,
+            comparison: None,
+            slos: Slos::default(),
+            campaigns: CampaignStats::default(),
+            referrers: ReferrerStats::new(spam_list),
+            visits: VisitStats::new(visits::DEFAULT_TIMEOUT),
+            tenants: TenantStats::default(),
+            vhosts: TenantStats::default(),
+            services: TenantStats::default(),
+            upstreams: TenantStats::default(),
+            sources: TenantStats::default(),
+            hosts: TenantStats::default(),
+            countries: CountryStats::default(),
+            networks: TenantStats::default(),
+            without_network: 0,
+            transfer: TransferStats::default(),
+            sizes: SizeStats::default(),
+            content: ContentStats::default(),
+            tls: TlsStats::default(),
+            cache: CacheStats::default(),
+            preflight: PreflightStats::default(),
+            redirects: RedirectStats::default(),
+            outliers: OutlierStats::default(),
+            attacks: AttackStats::new(now),
+            duplicates: DuplicateStats::new(now, drop_duplicates),
+            path_totals: TenantStats::default(),
+            endpoint_totals: TenantStats::default(),
+            not_found: TenantStats::default(),
+            server_error_paths: TenantStats::default(),
+            client_ips: TenantStats::default(),
+            user_agents: TenantStats::default(),
+            browsers: TenantStats::default(),
+            operating_systems: TenantStats::default(),
+            devices: TenantStats::default(),
+            statuses: TenantStats::default(),
+            protocols: TenantStats::default(),
+            bots: TenantStats::default(),
+            without_protocol: 0,
+            received: 0,
+            received_classes: [0; 5],
+            received_bytes: 0,
+            sampled: 1,
+            window: None,
+            started: now,
+            static_assets: None,
+            max_keys: DEFAULT_MAX_KEYS,
+            memory: 0,
+            max_memory: 0,
+            measured: 0,
+            generations: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, request: Request) {
+        self.update_at(Instant::now(), request);
+    }
+
+    // As if `request` came in at `now`, which may be in the past but no
+    // earlier than the last
+    pub fn update_at(&mut self, now: Instant, request: Request) {
+        self.count(now, request);
+        if self.received >= self.measured + MEASURE_EVERY {
+            self.measured = self.received;
+            self.memory = self.measure();
+            if self.max_memory > 0 && self.memory > self.max_memory {
+                self.trim();
+                self.memory = self.measure();
+            }
+        }
+    }
+
+    fn count(&mut self, now: Instant, request: Request) {
+        let weight = request.weight;
+        self.sampled = weight;
+        self.received += weight;
+        if let Some(count) = self.received_classes.get_mut((request.status_code / 100).wrapping_sub(1) as usize) {
+            *count += weight;
+        }
+        self.received_bytes += request.bytes_sent * weight;
+        for generation in &mut self.generations {
+            generation.count(now, request.clone());
+        }
+        if self.duplicates.check(now, &request) && self.duplicates.drop {
+            return;
+        }
+
+        self.total_requests += weight;
+        self.bytes_sent += request.bytes_sent * weight;
+        self.throughput.record(now, (request.bytes_sent * weight) as f64);
+        self.load.record(now, weight);
+        self.concurrency.record(now, request.response_time * weight as f64);
+        if request.response_time > 0.0 {
+            self.timed_requests += weight;
+            self.response_times.record(request.response_time);
+            self.recent_response_times.record(now, request.response_time);
+        }
+        if let Some(upstream_time) = request.upstream_time {
+            self.upstream_requests += weight;
+            self.upstream_time += upstream_time * weight as f64;
+            self.proxied_time += request.response_time * weight as f64;
+        }
+        match request.status_code {
+            400..=499 => self.client_errors.record(now, weight as f64),
+            500..=599 => self.server_errors.record(now, weight as f64),
+            _ => {}
+        }
+        self.status_classes.record(now, request.status_code, weight);
+        self.status_timeline.record(now, request.status_code, weight);
+
+        let path = self.path_key(&request.path);
+        *self.status_codes.entry(request.status_code).or_insert(0) += weight;
+        *self.paths.entry(path.to_string()).or_insert(0) += weight;
+        *self.endpoints.entry(endpoint_key(&request.method, path)).or_insert(0) += weight;
+        self.unique_ips.insert(&request.ip);
+        *self.methods.entry(request.method.clone()).or_insert(0) += weight;
+
+        self.path_activity.entry(path.to_string())
+            .or_insert_with(|| PathActivity::new(now))
+            .record(now, request.response_time, weight);
+        if let Some(recorder) = self.baseline_recorder.as_mut() {
+            recorder.record(path, request.response_time, weight);
+        }
+        if let Some(comparison) = self.comparison.as_mut() {
+            comparison.record(&request, path);
+        }
+        self.slos.record(&request);
+        // Like the tables, the panels count a sampled request `weight` times
+        self.campaigns.record(now, &request.url(), request.bytes_sent, weight);
+        self.campaigns.limit(self.max_keys);
+        self.referrers.record(&request.ip, &request.path, &request.referrer, request.bytes_sent, weight);
+        self.referrers.limit(self.max_keys);
+        let max_keys = self.max_keys;
+        let record = |groups: &mut TenantStats, key: &str| {
+            groups.record(now, key, &request);
+            groups.limit(max_keys)
+        };
+        if let Some(tenant) = &request.tenant {
+            record(&mut self.tenants, tenant);
+        }
+        if let Some(vhost) = &request.vhost {
+            record(&mut self.vhosts, vhost);
+        }
+        if let Some(status) = &request.cache_status {
+            self.cache.record(status, weight);
+            self.cache.limit(max_keys);
+        }
+        if let Some(service) = field_value::<String>(&request.fields, formats::SERVICE_FIELDS) {
+            record(&mut self.services, &service);
+        }
+        if let Some(upstream) = field_value::<String>(&request.fields, formats::UPSTREAM_FIELDS) {
+            record(&mut self.upstreams, &upstream);
+        }
+        if let Some(source) = &request.source {
+            record(&mut self.sources, source);
+        }
+        if let Some(host) = request.host() {
+            record(&mut self.hosts, host);
+        }
+        self.countries.record(request.country, request.bytes_sent, weight);
+        self.transfer.record(path, request.bytes_sent, request.bytes_received, request.uncompressed_bytes, weight);
+        self.sizes.record(path, &request);
+        let class = content::classify(&request.path);
+        self.content.record(class, request.bytes_sent, request.response_time, weight);
+        // Pages people were shown, not errors or what bots fetched
+        if class == ContentClass::Html && request.status_code < 400 && request.bot.is_none() {
+            self.visits.record(&request.ip, &request.user_agent, path, request.timestamp);
+        }
+        let protocol: Option<String> = field_value(&request.fields, tls::PROTOCOL_FIELDS);
+        let cipher: Option<String> = field_value(&request.fields, tls::CIPHER_FIELDS);
+        self.tls.record(protocol.as_deref(), cipher.as_deref(), &request.ip, &request.user_agent, weight);
+        let origin = request.fields.iter()
+            .find(|(key, _)| key == "origin" || key == "http_origin")
+            .map(|(_, value)| value.as_str());
+        self.preflight.record(&request.method, path, origin, request.response_time, weight);
+        self.preflight.limit(max_keys);
+        self.redirects.record(&request.ip, path, request.status_code, request.timestamp, weight);
+        self.outliers.record(request.timestamp, &request.ip, path, request.status_code, request.response_time);
+
+        let paths_evicted = record(&mut self.path_totals, path);
+        let endpoints_evicted = record(&mut self.endpoint_totals, &endpoint_key(&request.method, path));
+        if paths_evicted || endpoints_evicted {
+            self.forget_evicted_paths();
+        }
+        match request.status_code {
+            404 => {
+                record(&mut self.not_found, path);
+            }
+            500..=599 => {
+                record(&mut self.server_error_paths, path);
+            }
+            _ => {}
+        }
+        if record(&mut self.client_ips, &request.ip) {
+            self.forget_evicted_clients();
+        }
+        if bans::is_probe(&request.path, request.status_code)
+            && let Some(entry) = self.client_ips.tenants.get_mut(&request.ip) {
+            entry.probes += weight;
+        }
+        let signatures = attacks::signatures(&request);
+        if signatures != 0 {
+            self.attacks.record(now, &request, signatures);
+            if let Some(entry) = self.client_ips.tenants.get_mut(&request.ip) {
+                entry.attacks += weight;
+                entry.attack_kinds |= signatures;
+            }
+        }
+        if record(&mut self.user_agents, &request.user_agent) {
+            self.tls.forget_user_agents(&self.user_agents.tenants);
+        }
+        record(&mut self.browsers, agents::browser(&request.user_agent));
+        record(&mut self.operating_systems, agents::os(&request.user_agent));
+        record(&mut self.devices, agents::device(&request.user_agent));
+        record(&mut self.statuses, &request.status_code.to_string());
+        match &request.protocol {
+            Some(protocol) => {
+                record(&mut self.protocols, protocol);
+            }
+            None => self.without_protocol += weight,
+        }
+        if let Some(bot) = request.bot {
+            record(&mut self.bots, bot);
+        }
+        match &request.network {
+            Some(network) => {
+                record(&mut self.networks, network);
+            }
+            None => self.without_network += weight,
+        }
+    }
+}
+
+impl Stats {
+    // Decays the rolling rates, so they also drop while no requests arrive
+    pub fn tick(&mut self, now: Instant) {
+        if let Some(window) = self.window {
+            self.age(now, window);
+        }
+        self.load.tick(now);
+        self.throughput.tick(now);
+        self.concurrency.tick(now);
+        self.client_errors.tick(now);
+        self.server_errors.tick(now);
+        for activity in self.path_activity.values_mut() {
+            activity.tick(now);
+        }
+        self.campaigns.tick(now);
+        self.visits.sweep();
+        self.tenants.tick(now);
+        self.vhosts.tick(now);
+        self.services.tick(now);
+        self.upstreams.tick(now);
+        self.sources.tick(now);
+        self.hosts.tick(now);
+        self.path_totals.tick(now);
+        self.endpoint_totals.tick(now);
+        self.not_found.tick(now);
+        self.server_error_paths.tick(now);
+        self.client_ips.tick(now);
+        self.user_agents.tick(now);
+        self.browsers.tick(now);
+        self.operating_systems.tick(now);
+        self.devices.tick(now);
+        self.statuses.tick(now);
+        self.protocols.tick(now);
+        self.bots.tick(now);
+        self.networks.tick(now);
+        self.duplicates.tick(now);
+        self.attacks.tick(now);
+    }
+
+    // Starts a younger generation every quarter window and hands over to
+    // the oldest one once these stats span the whole window. The rolling
+    // rates, a baseline being recorded, a comparison and the SLOs carry on as
+    // they are
+    fn age(&mut self, now: Instant, window: Duration) {
+        let youngest = self.generations.last().map_or(self.started, |generation| generation.started);
+        if now.saturating_duration_since(youngest) >= window / GENERATIONS {
+            self.generations.push(Stats { window: None, comparison: None, slos: Slos::default(), ..self.cleared_at(now) });
+        }
+        if now.saturating_duration_since(self.started) < window || self.generations.is_empty() {
+            return;
+        }
+
+        let mut next = self.generations.remove(0);
+        next.load = self.load.clone();
+        next.throughput = self.throughput.clone();
+        next.concurrency = self.concurrency.clone();
+        next.recent_response_times = self.recent_response_times.clone();
+        next.client_errors = self.client_errors.clone();
+        next.server_errors = self.server_errors.clone();
+        next.attacks.rate = self.attacks.rate.clone();
+        next.status_classes = self.status_classes.clone();
+        next.status_timeline = self.status_timeline.clone();
+        next.duplicates = self.duplicates.clone();
+        next.baseline_recorder = self.baseline_recorder.take();
+        next.comparison = self.comparison.take();
+        next.slos = std::mem::take(&mut self.slos);
+        next.received = self.received;
+        next.received_classes = self.received_classes;
+        next.received_bytes = self.received_bytes;
+        next.sampled = self.sampled;
+        next.window = self.window;
+        next.memory = self.memory;
+        next.max_memory = self.max_memory;
+        next.measured = self.measured;
+        next.generations = std::mem::take(&mut self.generations);
+        *self = next;
+    }
+
+    // A copy for drawing, without the generations counting along
+    pub fn snapshot(&mut self) -> Stats {
+        let generations = std::mem::take(&mut self.generations);
+        let snapshot = self.clone();
+        self.generations = generations;
+        snapshot
+    }
+
+    // The same for drawing one frame of a view, with the panels of every
+    // other view left empty: with a key per path or client they grow
+    // without bound, and copying them each frame held up ingestion
+    pub fn frame(&mut self, panel: Option<Panel>) -> Stats {
+        let skip = |other: Panel| panel != Some(other);
+        let sizes = skip(Panel::Sizes).then(|| std::mem::take(&mut self.sizes));
+        let visits = skip(Panel::Visits).then(|| {
+            let cleared = self.visits.cleared();
+            std::mem::replace(&mut self.visits, cleared)
+        });
+        let outliers = skip(Panel::Outliers).then(|| std::mem::take(&mut self.outliers));
+        let comparison = skip(Panel::Compare).then(|| self.comparison.take());
+        let frame = self.snapshot();
+        if let Some(sizes) = sizes {
+            self.sizes = sizes;
+        }
+        if let Some(visits) = visits {
+            self.visits = visits;
+        }
+        if let Some(outliers) = outliers {
+            self.outliers = outliers;
+        }
+        if let Some(comparison) = comparison {
+            self.comparison = comparison;
+        }
+        frame
+    }
+
+    pub fn set_spam_list(&mut self, spam_list: SpamList) {
+        for generation in &mut self.generations {
+            generation.referrers.set_spam_list(spam_list.clone());
+        }
+        self.referrers.set_spam_list(spam_list);
+    }
+
+    // What the path tables count `path` under: its --group-static bucket,
+    // if it has one
+    pub fn path_key<'a>(&self, path: &'a str) -> &'a str {
+        self.static_assets.as_ref().and_then(|assets| assets.bucket(path)).unwrap_or(path)
+    }
+
+    pub fn bot_percent(&self) -> f64 {
+        let bots = group_totals(&self.bots).0;
+        if self.total_requests > 0 { bots as f64 / self.total_requests as f64 * 100.0 } else { 0.0 }
+    }
+
+    // Keys evicted over max_keys or max_memory, of all the tables
+    pub fn evicted_keys(&self) -> usize {
+        self.tables().iter().map(|groups| groups.evicted).sum()
+    }
+
+    fn tables(&self) -> [&TenantStats; 19] {
+        [
+            &self.tenants, &self.vhosts, &self.services, &self.upstreams, &self.sources, &self.hosts, &self.networks,
+            &self.path_totals, &self.endpoint_totals, &self.not_found, &self.server_error_paths,
+            &self.client_ips, &self.user_agents, &self.browsers, &self.operating_systems, &self.devices,
+            &self.statuses, &self.protocols, &self.bots,
+        ]
+    }
+
+    fn tables_mut(&mut self) -> [&mut TenantStats; 19] {
+        [
+            &mut self.tenants, &mut self.vhosts, &mut self.services, &mut self.upstreams, &mut self.sources, &mut self.hosts,
+            &mut self.networks, &mut self.path_totals, &mut self.endpoint_totals, &mut self.not_found,
+            &mut self.server_error_paths, &mut self.client_ips, &mut self.user_agents, &mut self.browsers,
+            &mut self.operating_systems, &mut self.devices, &mut self.statuses, &mut self.protocols, &mut self.bots,
+        ]
+    }
+
+    // The counts by path go with the keys the tables keep
+    fn forget_evicted_paths(&mut self) {
+        let kept = &self.path_totals.tenants;
+        self.paths.retain(|path, _| kept.contains_key(path));
+        self.path_activity.retain(|path, _| kept.contains_key(path));
+        self.sizes.paths.retain(|path, _| kept.contains_key(path));
+        self.outliers.paths.retain(|path, _| kept.contains_key(path));
+        self.transfer.paths.retain(|path, _| kept.contains_key(path));
+        self.visits.forget_pages(kept);
+        if let Some(recorder) = self.baseline_recorder.as_mut() {
+            recorder.forget(kept);
+        }
+        self.preflight.paths.retain(|path, _| kept.contains_key(path));
+        self.redirects.paths.retain(|path, _| kept.contains_key(path));
+        self.redirects.edges.retain(|(from, to), _| kept.contains_key(from) && kept.contains_key(to));
+        let kept = &self.endpoint_totals.tenants;
+        self.endpoints.retain(|endpoint, _| kept.contains_key(endpoint));
+    }
+
+    // And those by client
+    fn forget_evicted_clients(&mut self) {
+        let kept = &self.client_ips.tenants;
+        self.referrers.forget_clients(kept);
+        self.visits.forget_clients(kept);
+        // Closing visits early may have added exit pages of evicted paths
+        self.visits.forget_pages(&self.path_totals.tenants);
+    }
+
+    // Bytes the tables and the panels keyed by path, client, user agent or
+    // anything else a log line makes up take, of these stats and their
+    // generations: keys, counters and sketches, without what the allocator
+    // adds. Everything counted here trim can evict, but for open visits,
+    // which close once idle, and a comparison, which keeps
+    // compare::MAX_PATHS paths at most. The requests kept for search are
+    // History's to count
+    fn measure(&self) -> usize {
+        let sizes: usize = self.sizes.paths.iter()
+            .map(|(path, histogram)| memory::entry::<SizeHistogram>(path) + histogram.memory())
+            .sum::<usize>() + memory::spare(&self.sizes.paths);
+        self.tables().iter().map(|groups| groups.memory()).sum::<usize>()
+            + memory::keyed(&self.paths) + memory::keyed(&self.endpoints) + memory::keyed(&self.path_activity)
+            + sizes + memory::keyed(&self.outliers.paths) + memory::keyed(&self.transfer.paths)
+            + self.redirects.memory() + self.preflight.memory() + self.referrers.memory()
+            + self.campaigns.memory() + self.tls.memory() + self.cache.memory()
+            + self.baseline_recorder.as_ref().map_or(0, BaselineRecorder::memory)
+            + self.visits.memory()
+            + self.comparison.as_ref().map_or(0, Comparison::memory)
+            + self.generations.iter().map(Stats::measure).sum::<usize>()
+    }
+
+    // Past max_memory, the keys with the fewest requests go, whichever table
+    // or generation they are in, until the rest take three quarters of it.
+    // The per-path panels go the same way
+    fn trim(&mut self) {
+        let excess = self.memory - (self.max_memory - self.max_memory / 4);
+        let mut generations = std::mem::take(&mut self.generations);
+        let mut all: Vec<&mut Stats> = iter::once(&mut *self).chain(&mut generations).collect();
+        let mut keys = Vec::new();
+        for stats in &all {
+            for groups in stats.tables() {
+                groups.weigh(&mut keys);
+            }
+            keys.extend(stats.sizes.paths.iter()
+                .map(|(path, histogram)| (histogram.count, memory::entry::<SizeHistogram>(path) + histogram.memory())));
+            keys.extend(stats.outliers.paths.iter()
+                .map(|(path, baseline)| (baseline.samples, memory::entry::<LatencyBaseline>(path))));
+            keys.extend(stats.transfer.paths.iter()
+                .map(|(path, entry)| (entry.count, memory::entry::<TransferEntry>(path))));
+            for pages in [&stats.visits.entry_pages, &stats.visits.exit_pages, &stats.preflight.origins] {
+                keys.extend(pages.iter().map(|(key, &count)| (count, memory::entry::<usize>(key))));
+            }
+            keys.extend(stats.preflight.paths.iter()
+                .map(|(path, entry)| (entry.count, memory::entry::<PreflightEntry>(path))));
+            keys.extend(stats.redirects.paths.iter()
+                .map(|(path, entry)| (entry.count, memory::entry::<RedirectEntry>(path))));
+            stats.referrers.weigh(&mut keys);
+            stats.campaigns.weigh(&mut keys);
+            stats.tls.weigh(&mut keys);
+            keys.extend(stats.cache.statuses.iter().map(|(status, &count)| (count, memory::entry::<usize>(status))));
+            if let Some(recorder) = &stats.baseline_recorder {
+                recorder.weigh(&mut keys);
+            }
+        }
+        if let Some((threshold, mut ties)) = memory::threshold(keys, excess) {
+            for stats in &mut all {
+                for groups in stats.tables_mut() {
+                    groups.evict(threshold, &mut ties);
+                    groups.tenants.shrink_to_fit();
+                }
+                memory::evict(&mut stats.sizes.paths, |histogram| histogram.count, threshold, &mut ties, |_| {});
+                memory::evict(&mut stats.outliers.paths, |baseline| baseline.samples, threshold, &mut ties, |_| {});
+                memory::evict(&mut stats.transfer.paths, |entry| entry.count, threshold, &mut ties, |_| {});
+                for pages in [&mut stats.visits.entry_pages, &mut stats.visits.exit_pages, &mut stats.preflight.origins] {
+                    memory::evict(pages, |&count| count, threshold, &mut ties, |_| {});
+                    pages.shrink_to_fit();
+                }
+                memory::evict(&mut stats.preflight.paths, |entry| entry.count, threshold, &mut ties, |_| {});
+                memory::evict(&mut stats.redirects.paths, |entry| entry.count, threshold, &mut ties, |_| {});
+                stats.referrers.evict(threshold, &mut ties);
+                stats.campaigns.evict(threshold, &mut ties);
+                stats.tls.evict(threshold, &mut ties);
+                memory::evict(&mut stats.cache.statuses, |&count| count, threshold, &mut ties, |_| {});
+                stats.cache.statuses.shrink_to_fit();
+                if let Some(recorder) = stats.baseline_recorder.as_mut() {
+                    recorder.evict(threshold, &mut ties);
+                }
+                stats.forget_evicted_paths();
+                stats.forget_evicted_clients();
+                stats.tls.forget_user_agents(&stats.user_agents.tenants);
+                // Room left by the keys would otherwise still count
+                stats.sizes.paths.shrink_to_fit();
+                stats.outliers.paths.shrink_to_fit();
+                stats.transfer.paths.shrink_to_fit();
+                stats.paths.shrink_to_fit();
+                stats.path_activity.shrink_to_fit();
+                stats.endpoints.shrink_to_fit();
+                stats.preflight.paths.shrink_to_fit();
+                stats.redirects.paths.shrink_to_fit();
+                stats.redirects.edges.shrink_to_fit();
+            }
+        }
+        self.generations = generations;
+    }
+
+    pub fn user_agent_groups(&self, grouping: Grouping) -> &TenantStats {
+        match grouping {
+            Grouping::Raw => &self.user_agents,
+            Grouping::Browser => &self.browsers,
+            Grouping::Os => &self.operating_systems,
+            Grouping::Device => &self.devices,
+        }
+    }
+
+    pub fn alert_metrics(&self) -> Metrics {
+        let rps = self.load.rate(1);
+        let percent_of_rps = |rate: f64| if rps > 0.0 { rate / rps * 100.0 } else { 0.0 };
+        Metrics {
+            rps,
+            error_rate: percent_of_rps(self.server_errors.rate()),
+            client_error_rate: percent_of_rps(self.client_errors.rate()),
+            in_flight: self.concurrency.one.rate(),
+            avg_response_time: if rps > 0.0 { self.concurrency.one.rate() / rps } else { 0.0 },
+            p95_response_time: self.recent_response_times.quantile(Instant::now(), 0.95).unwrap_or(0.0),
+            p99_response_time: self.recent_response_times.quantile(Instant::now(), 0.99).unwrap_or(0.0),
+            rps_from_single_ip: self.client_ips.tenants.values().map(|entry| entry.rate.rate()).fold(0.0, f64::max),
+        }
+    }
+}
+
+// GET /api/users and POST /api/users behave differently enough to be
+// counted apart
+pub fn endpoint_key(method: &str, path: &str) -> String {
+    format!("{} {}", method, path)
+}
+
+// Requests and bytes of all the rows of a table, and of those evicted
+pub fn group_totals(groups: &TenantStats) -> (usize, usize) {
+    groups.tenants.values()
+        .fold((groups.evicted_requests, groups.evicted_bytes), |(count, bytes), entry| (count + entry.count, bytes + entry.bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_log_line;
+
+    // A sampled request counts `weight` times in the panels as in the totals
+    #[test]
+    fn sampled_shares() {
+        let mut stats = Stats::default();
+        for (path, referrer) in [("/", "-"), ("/app.js", "http://example.com/"), ("/logo.png", "-"), ("/about", "http://example.com/")] {
+            let line = format!(r#"192.0.2.1 - - [29/Nov/2021:12:34:56 +0000] "GET {} HTTP/1.1" 200 100 "{}" "-" 0.002"#, path, referrer);
+            let mut request = parse_log_line(&line).unwrap();
+            request.weight = 10;
+            request.cache_status = Some("HIT".to_string());
+            stats.update(request);
+        }
+        assert_eq!(stats.total_requests, 40);
+        assert_eq!(stats.bytes_sent, 4000);
+
+        let classes = ContentClass::ALL.iter().map(|&class| stats.content.get(class));
+        assert_eq!(classes.clone().map(|entry| entry.count).sum::<usize>(), stats.total_requests);
+        assert_eq!(classes.map(|entry| entry.bytes).sum::<usize>(), stats.bytes_sent);
+        let countries = stats.countries.countries.values().map(|entry| entry.count).sum::<usize>();
+        assert_eq!(countries + stats.countries.unknown, stats.total_requests);
+        let referred = stats.referrers.referrers.values().map(|entry| entry.count).sum::<usize>();
+        assert_eq!(referred + stats.referrers.direct, stats.total_requests);
+        assert_eq!(stats.cache.lookups(), stats.total_requests);
+        assert_eq!(stats.sizes.global.count, stats.total_requests);
+        assert_eq!(group_totals(&stats.path_totals), (stats.total_requests, stats.bytes_sent));
+    }
+
+    // Under --max-keys, the panels keyed by path, client, user agent or
+    // campaign stay as small as the tables
+    #[test]
+    fn bounded_panels() {
+        let mut stats = Stats { max_keys: 8, ..Stats::default() };
+        for n in 0..200 {
+            let line = format!(
+                r#"192.0.2.{} - - [29/Nov/2021:12:34:56 +0000] "GET /page/{}?utm_source=s{} HTTP/1.1" 200 100 "http://example.com/{}" "agent {}" 0.002"#,
+                n % 250, n, n, n, n);
+            let mut request = parse_log_line(&line).unwrap();
+            request.bytes_received = Some(10);
+            request.cache_status = Some(format!("STATUS{}", n));
+            request.fields.push(("ssl_protocol".to_string(), "TLSv1".to_string()));
+            stats.update(request);
+        }
+        assert!(stats.path_totals.tenants.len() <= 8);
+        for (name, len) in [
+            ("sizes", stats.sizes.paths.len()),
+            ("transfer", stats.transfer.paths.len()),
+            ("outliers", stats.outliers.paths.len()),
+            ("entry pages", stats.visits.entry_pages.len()),
+            ("campaigns", stats.campaigns.campaigns.len()),
+            ("cache", stats.cache.statuses.len()),
+            ("legacy clients", stats.tls.legacy_clients.len()),
+            ("referrers", stats.referrers.referrers.len()),
+        ] {
+            assert!(len <= 8, "{} kept {} keys", name, len);
+        }
+        assert!(stats.visits.visits() - stats.visits.closed <= 8);
+    }
+
+    // Past --max-memory, keys go from every table and panel, including
+    // those keyed by campaign and user agent
+    #[test]
+    fn trimmed_to_max_memory() {
+        let mut stats = Stats { max_keys: 0, max_memory: 512 * 1024, ..Stats::default() };
+        for n in 0..MEASURE_EVERY * 4 {
+            let line = format!(
+                r#"192.0.2.1 - - [29/Nov/2021:12:34:56 +0000] "GET /?utm_source=s{} HTTP/1.1" 200 100 "-" "agent {}" 0.002"#, n, n);
+            let mut request = parse_log_line(&line).unwrap();
+            request.fields.push(("ssl_protocol".to_string(), "TLSv1".to_string()));
+            stats.update(request);
+        }
+        assert!(stats.memory > 0 && stats.memory <= stats.max_memory, "{} bytes", stats.memory);
+        assert!(stats.campaigns.campaigns.len() < MEASURE_EVERY * 4);
+        assert!(stats.tls.legacy_clients.len() < MEASURE_EVERY * 4);
+    }
+}
+
@@ -0,0 +1,108 @@
+use std::io;
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::notices::Notices;
+
+pub const DEFAULT_PREFIX: &str = "httop";
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+// Keeps a datagram within an Ethernet MTU
+const MAX_PACKET: usize = 1432;
+
+// Running totals since httop started; each flush sends the difference
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Totals {
+    // 1xx to 5xx
+    pub classes: [usize; 5],
+    pub bytes: usize,
+}
+
+// Sends request counts by status class, bytes and response time
+// percentiles to a StatsD or DogStatsD agent over UDP every interval, as
+// counters and gauges
+#[derive(Debug)]
+pub struct Statsd {
+    socket: UdpSocket,
+    prefix: String,
+    // DogStatsD's |#key:value,... suffix, empty without tags
+    tags: String,
+    interval: Duration,
+    last_flush: Instant,
+    sent: Totals,
+    // Only the first of a run of failed sends is reported
+    failing: bool,
+    notices: Arc<Notices>,
+}
+
+impl Statsd {
+    pub fn connect(
+        addr: &str,
+        prefix: &str,
+        tags: &[String],
+        interval: Duration,
+        notices: Arc<Notices>,
+    ) -> io::Result<Self> {
+        let socket = UdpSocket::bind(if addr.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" })?;
+        socket.connect(addr)?;
+        Ok(Statsd {
+            socket,
+            prefix: if prefix.is_empty() { String::new() } else { format!("{}.", prefix.trim_end_matches('.')) },
+            tags: if tags.is_empty() { String::new() } else { format!("|#{}", tags.join(",")) },
+            interval,
+            last_flush: Instant::now(),
+            sent: Totals::default(),
+            failing: false,
+            notices,
+        })
+    }
+
+    pub fn due(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_flush) >= self.interval
+    }
+
+    // Response times are gauges in milliseconds, of the last minute
+    pub fn flush(&mut self, now: Instant, totals: Totals, response_times: &[(&str, Option<f64>)]) {
+        let requests = totals.classes.iter().sum::<usize>() - self.sent.classes.iter().sum::<usize>();
+        let mut lines = vec![self.line("requests", &requests.to_string(), "c")];
+        for (class, (total, sent)) in totals.classes.iter().zip(self.sent.classes).enumerate() {
+            lines.push(self.line(&format!("requests.{}xx", class + 1), &(total - sent).to_string(), "c"));
+        }
+        lines.push(self.line("bytes", &(totals.bytes - self.sent.bytes).to_string(), "c"));
+        for (name, seconds) in response_times {
+            if let Some(seconds) = seconds {
+                lines.push(self.line(&format!("response_time.{}", name), &format!("{:.3}", seconds * 1000.0), "g"));
+            }
+        }
+        self.last_flush = now;
+        self.sent = totals;
+
+        let mut packet = String::new();
+        for line in lines {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET {
+                self.send(&packet);
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(&line);
+        }
+        self.send(&packet);
+    }
+
+    fn line(&self, name: &str, value: &str, kind: &str) -> String {
+        format!("{}{}:{}|{}{}", self.prefix, name, value, kind, self.tags)
+    }
+
+    fn send(&mut self, packet: &str) {
+        match self.socket.send(packet.as_bytes()) {
+            Ok(_) => self.failing = false,
+            Err(e) if !self.failing => {
+                self.notices.post(format!("could not send metrics to statsd: {}", e));
+                self.failing = true;
+            }
+            Err(_) => {}
+        }
+    }
+}
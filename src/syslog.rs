@@ -0,0 +1,106 @@
+#[cfg(unix)]
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::net::{TcpListener, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::thread;
+
+use crate::config::Config;
+use crate::source::Ingest;
+
+// Large enough for any datagram nginx sends (it truncates lines at 2048 bytes)
+const MAX_DATAGRAM: usize = 65_536;
+
+// Receives syslog messages on both UDP and TCP at `addr`, as sent by nginx's
+// `access_log syslog:server=...` or a relaying rsyslog. Over TCP, messages
+// are newline-delimited. The syslog header itself is dropped by the parser
+pub fn listen(ingest: &Ingest, addr: &str, config: &Config) -> io::Result<()> {
+    let socket = UdpSocket::bind(addr)?;
+    let listener = TcpListener::bind(addr)?;
+
+    ingest.spawn_reader(&format!("syslog udp {}", addr), BufReader::new(Datagrams::new(socket)), config.clone());
+
+    let ingest = ingest.clone();
+    let config = config.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().map_while(Result::ok) {
+            let name = match stream.peer_addr() {
+                Ok(peer) => format!("syslog tcp {}", peer.ip()),
+                Err(_) => "syslog tcp".to_string(),
+            };
+            ingest.spawn_reader(&name, BufReader::new(stream), config.clone());
+        }
+    });
+    Ok(())
+}
+
+// Receives syslog datagrams on a unix socket at `path`, as sent by nginx's
+// `access_log syslog:server=unix:PATH`, so lines never touch the disk. A
+// socket left behind by an earlier run is replaced
+#[cfg(unix)]
+pub fn listen_unix(ingest: &Ingest, path: &Path, config: &Config) -> io::Result<()> {
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        fs::remove_file(path)?;
+    }
+    let socket = UnixDatagram::bind(path)?;
+    ingest.spawn_reader(&format!("syslog unix {}", path.display()), BufReader::new(Datagrams::new(socket)), config.clone());
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen_unix(_ingest: &Ingest, _path: &Path, _config: &Config) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "unix sockets need a Unix system"))
+}
+
+trait Receive {
+    fn receive(&self, buf: &mut [u8]) -> io::Result<usize>;
+}
+
+impl Receive for UdpSocket {
+    fn receive(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv(buf)
+    }
+}
+
+#[cfg(unix)]
+impl Receive for UnixDatagram {
+    fn receive(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv(buf)
+    }
+}
+
+// Turns each datagram into a line of a stream
+struct Datagrams<S> {
+    socket: S,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<S: Receive> Datagrams<S> {
+    fn new(socket: S) -> Self {
+        Datagrams { socket, buffer: Vec::new(), position: 0 }
+    }
+}
+
+impl<S: Receive> Read for Datagrams<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            self.buffer.resize(MAX_DATAGRAM, 0);
+            let size = self.socket.receive(&mut self.buffer)?;
+            self.buffer.truncate(size);
+            if self.buffer.last() != Some(&b'\n') {
+                self.buffer.push(b'\n');
+            }
+            self.position = 0;
+        }
+
+        let size = buf.len().min(self.buffer.len() - self.position);
+        buf[..size].copy_from_slice(&self.buffer[self.position..self.position + size]);
+        self.position += size;
+        Ok(size)
+    }
+}
@@ -0,0 +1,245 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+
+use crate::parser::parse_fields;
+use crate::{formats, Request};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ip,
+    Date,
+    Time,
+    DateTime,
+    Epoch,
+    Request,
+    Method,
+    Path,
+    Query,
+    Protocol,
+    Status,
+    Bytes,
+    Referrer,
+    UserAgent,
+    Seconds,
+    Millis,
+    Micros,
+    Skip,
+    // Any other nginx variable, kept as a custom field under its name
+    Field(String),
+}
+
+impl Token {
+    fn goaccess(specifier: char) -> Option<Token> {
+        Some(match specifier {
+            'h' => Token::Ip,
+            'd' => Token::Date,
+            't' => Token::Time,
+            'x' => Token::DateTime,
+            'v' => Token::Field("host".to_string()),
+            'r' => Token::Request,
+            'm' => Token::Method,
+            'U' => Token::Path,
+            'q' => Token::Query,
+            'H' => Token::Protocol,
+            's' => Token::Status,
+            'b' => Token::Bytes,
+            'R' => Token::Referrer,
+            'u' => Token::UserAgent,
+            'T' => Token::Seconds,
+            'L' => Token::Millis,
+            'D' => Token::Micros,
+            'e' | 'K' | 'k' | 'M' | 'n' | '^' => Token::Skip,
+            _ => return None,
+        })
+    }
+
+    fn nginx(variable: &str) -> Token {
+        match variable {
+            "remote_addr" | "realip_remote_addr" => Token::Ip,
+            "time_local" | "time_iso8601" => Token::DateTime,
+            "msec" => Token::Epoch,
+            "request" => Token::Request,
+            "request_method" => Token::Method,
+            "request_uri" | "uri" | "document_uri" => Token::Path,
+            "server_protocol" => Token::Protocol,
+            "status" => Token::Status,
+            "body_bytes_sent" | "bytes_sent" => Token::Bytes,
+            "http_referer" => Token::Referrer,
+            "http_user_agent" => Token::UserAgent,
+            "request_time" => Token::Seconds,
+            _ => Token::Field(variable.to_string()),
+        }
+    }
+
+    fn pattern(&self, next: Option<char>) -> String {
+        match self {
+            Token::Status => r"\d{3}".to_string(),
+            Token::Bytes | Token::Seconds | Token::Millis | Token::Micros | Token::Epoch => r"[\d.]+|-".to_string(),
+            Token::Time => r"\d{1,2}:\d{2}:\d{2}".to_string(),
+            // Free text runs up to the literal that follows it in the template
+            _ => match next {
+                Some(c) => format!("[^{}]*", regex::escape(&c.to_string())),
+                None => r"\S*".to_string(),
+            },
+        }
+    }
+}
+
+// A log layout compiled from goaccess-style tokens (%h %r %s ...) or nginx
+// log_format variables ($remote_addr $request $status ...)
+#[derive(Debug, Clone)]
+pub struct Template {
+    regex: Regex,
+    tokens: Vec<Token>,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '%' => match chars.next() {
+                    Some('%') => None,
+                    Some(specifier) => Some(Token::goaccess(specifier)
+                        .ok_or_else(|| format!("unknown log format token '%{}'", specifier))?),
+                    None => return Err("log format ends with a lone '%'".to_string()),
+                },
+                '$' => {
+                    let braced = chars.next_if_eq(&'{').is_some();
+                    let mut variable = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                        variable.push(c);
+                    }
+                    if braced && chars.next() != Some('}') {
+                        return Err(format!("unterminated '${{{}' in log format", variable));
+                    }
+                    if variable.is_empty() {
+                        return Err("log format has a '$' without a variable name".to_string());
+                    }
+                    Some(Token::nginx(&variable))
+                }
+                _ => None,
+            };
+            match token {
+                Some(token) => pieces.push((std::mem::take(&mut literal), Some(token))),
+                None => literal.push(c),
+            }
+        }
+        pieces.push((literal, None));
+
+        if !pieces.iter().any(|(_, token)| matches!(token, Some(Token::Request | Token::Path))) {
+            return Err("log format needs the request (%r, $request) or path (%U, $request_uri)".to_string());
+        }
+        if !pieces.iter().any(|(_, token)| token == &Some(Token::Status)) {
+            return Err("log format needs the status (%s, $status)".to_string());
+        }
+
+        let mut pattern = String::from("^");
+        let mut tokens = Vec::new();
+        for (i, (literal, token)) in pieces.iter().enumerate() {
+            pattern.push_str(&regex::escape(literal));
+            if let Some(token) = token {
+                let next = pieces.get(i + 1).and_then(|(literal, _)| literal.chars().next());
+                pattern.push_str(&format!("({})", token.pattern(next)));
+                tokens.push(token.clone());
+            }
+        }
+        // Trailing key=value fields, as with the built-in formats
+        pattern.push_str("(.*)$");
+
+        let regex = Regex::new(&pattern).map_err(|e| format!("invalid log format: {}", e))?;
+        Ok(Template { regex, tokens })
+    }
+}
+
+impl Template {
+    pub fn parse(&self, line: &str) -> Option<Request> {
+        let caps = self.regex.captures(line)?;
+
+        let mut ip = "-";
+        let (mut date, mut time, mut timestamp) = (None, None, None);
+        let (mut method, mut path, mut query) = (None, None, None);
+        let mut protocol = None;
+        let mut status_code = None;
+        let mut bytes_sent = 0;
+        let (mut referrer, mut user_agent) = (None, None);
+        let mut response_time = 0.0;
+        let mut fields = Vec::new();
+
+        for (i, token) in self.tokens.iter().enumerate() {
+            let value = caps.get(i + 1)?.as_str();
+            let number = || value.parse::<f64>().ok();
+            match token {
+                Token::Ip => ip = value,
+                Token::Date => date = Some(value),
+                Token::Time => time = Some(value),
+                Token::DateTime => timestamp = parse_datetime(value),
+                Token::Epoch => {
+                    timestamp = number().and_then(|seconds| Utc.timestamp_millis_opt((seconds * 1000.0) as i64).single());
+                }
+                Token::Request => {
+                    let mut parts = value.split(' ');
+                    method = parts.next();
+                    path = parts.next();
+                    protocol = parts.next();
+                }
+                Token::Method => method = Some(value),
+                Token::Path => path = Some(value),
+                Token::Protocol => protocol = Some(value),
+                Token::Query => query = Some(value).filter(|query| !query.is_empty() && *query != "-"),
+                Token::Status => status_code = value.parse().ok(),
+                Token::Bytes => bytes_sent = value.parse().unwrap_or(0),
+                Token::Referrer => referrer = Some(value),
+                Token::UserAgent => user_agent = Some(value),
+                Token::Seconds => response_time = number().unwrap_or(0.0),
+                Token::Millis => response_time = number().unwrap_or(0.0) / 1_000.0,
+                Token::Micros => response_time = number().unwrap_or(0.0) / 1_000_000.0,
+                Token::Skip => {}
+                Token::Field(name) => {
+                    if !value.is_empty() && value != "-" {
+                        fields.push((name.clone(), value.to_string()));
+                    }
+                }
+            }
+        }
+
+        if let (Some(date), Some(time)) = (date, time) {
+            timestamp = parse_datetime(&format!("{}:{}", date, time));
+        }
+        let method = method.filter(|method| !method.is_empty() && *method != "-")?;
+        let mut path = path?.to_string();
+        if let Some(query) = query {
+            path.push_str(if query.starts_with('?') { "" } else { "?" });
+            path.push_str(query);
+        }
+
+        let mut request = Request::new(timestamp.unwrap_or_else(Utc::now), ip, method, &path, status_code?, bytes_sent);
+        request.protocol = protocol.and_then(formats::http_version);
+        request.response_time = response_time;
+        if let Some(referrer) = referrer {
+            request.referrer = referrer.to_string();
+        }
+        if let Some(user_agent) = user_agent {
+            request.user_agent = user_agent.to_string();
+        }
+        fields.extend(parse_fields(caps.get(self.tokens.len() + 1)?.as_str()));
+        Some(request.with_fields(fields))
+    }
+}
+
+// $time_local, ISO 8601, or a goaccess %d:%t pair; times without a zone are UTC
+pub fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_str(value, "%d/%b/%Y:%H:%M:%S %z")
+        .or_else(|_| DateTime::parse_from_rfc3339(value)) {
+        return Some(time.with_timezone(&Utc));
+    }
+    ["%d/%b/%Y:%H:%M:%S", "%Y-%m-%d:%H:%M:%S", "%Y-%m-%dT%H:%M:%S"].iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|time| time.and_utc())
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+
+use crate::Request;
+use crate::cache;
+use crate::memory;
+use crate::quantiles::Quantiles;
+use crate::rate::MinuteRate;
+use crate::uniques::Uniques;
+
+// The most requests logged within one slot of log time, a second or a
+// minute, going by the timestamps rather than when lines were read
+#[derive(Debug, Clone, Default)]
+pub struct Peak {
+    slot: i64,
+    count: usize,
+    pub max: usize,
+}
+
+impl Peak {
+    fn record(&mut self, slot: i64, count: usize) {
+        if slot != self.slot {
+            self.slot = slot;
+            self.count = 0;
+        }
+        self.count += count;
+        self.max = self.max.max(self.count);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TenantEntry {
+    pub count: usize,
+    pub client_errors: usize,
+    pub server_errors: usize,
+    // 401 and 403 responses, also counted as client errors
+    pub denied: usize,
+    pub bytes: usize,
+    // Of the requests with a cache status logged
+    pub cache_hits: usize,
+    pub cache_lookups: usize,
+    pub total_response_time: f64,
+    pub max_response_time: f64,
+    // Of the requests with an upstream time logged: that time, and their
+    // whole response time
+    pub upstream_requests: usize,
+    pub total_upstream_time: f64,
+    pub total_proxied_time: f64,
+    // Of the requests with a response time logged
+    pub response_times: Quantiles,
+    pub rate: MinuteRate,
+    // Bytes sent per second
+    pub throughput: MinuteRate,
+    // Distinct client IPs, estimated
+    pub visitors: Uniques,
+    pub last_seen: Instant,
+    // Logged times of the first and latest request
+    pub first_logged: DateTime<Utc>,
+    pub last_logged: DateTime<Utc>,
+    pub peak_second: Peak,
+    pub peak_minute: Peak,
+    // Requests for paths scanners probe for, answered with a 4xx; only
+    // counted for client IPs
+    pub probes: usize,
+    // Requests matching an attack signature, and the signatures as
+    // attacks::Signature bits; only counted for client IPs
+    pub attacks: usize,
+    pub attack_kinds: u8,
+    // Requests the key may have had before it was last added, once rarer
+    // keys were evicted: its count is low by at most this much
+    pub overcount: usize,
+}
+
+impl TenantEntry {
+    fn new(now: Instant, logged: DateTime<Utc>) -> Self {
+        TenantEntry {
+            count: 0,
+            client_errors: 0,
+            server_errors: 0,
+            denied: 0,
+            bytes: 0,
+            cache_hits: 0,
+            cache_lookups: 0,
+            total_response_time: 0.0,
+            max_response_time: 0.0,
+            upstream_requests: 0,
+            total_upstream_time: 0.0,
+            total_proxied_time: 0.0,
+            response_times: Quantiles::default(),
+            rate: MinuteRate::new(now),
+            throughput: MinuteRate::new(now),
+            visitors: Uniques::default(),
+            last_seen: now,
+            first_logged: logged,
+            last_logged: logged,
+            peak_second: Peak::default(),
+            peak_minute: Peak::default(),
+            probes: 0,
+            attacks: 0,
+            attack_kinds: 0,
+            overcount: 0,
+        }
+    }
+
+    pub fn error_percent(&self, errors: usize) -> f64 {
+        if self.count > 0 {
+            errors as f64 / self.count as f64 * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    pub fn avg_response_time(&self) -> f64 {
+        if self.count > 0 {
+            self.total_response_time / self.count as f64
+        } else {
+            0.0
+        }
+    }
+
+    pub fn p95_response_time(&self) -> f64 {
+        self.response_times.quantile(0.95).unwrap_or(0.0)
+    }
+
+    // Average upstream time, and the rest of the response time the proxy
+    // added: connecting, queueing, sending the response to the client
+    pub fn upstream_split(&self) -> Option<(f64, f64)> {
+        upstream_split(self.upstream_requests, self.total_upstream_time, self.total_proxied_time)
+    }
+
+    // Requests per minute between the first and latest logged request, or
+    // over a minute if they span less
+    pub fn average_per_minute(&self) -> f64 {
+        let minutes = (self.last_logged - self.first_logged).num_seconds() as f64 / 60.0;
+        self.count as f64 / minutes.max(1.0)
+    }
+
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        (self.cache_lookups > 0).then(|| self.cache_hits as f64 / self.cache_lookups as f64)
+    }
+}
+
+// From totals over `requests`; a proxy timing the upstream more finely than
+// the request leaves no overhead rather than a negative one
+pub fn upstream_split(requests: usize, upstream_time: f64, proxied_time: f64) -> Option<(f64, f64)> {
+    (requests > 0).then(|| {
+        let requests = requests as f64;
+        (upstream_time / requests, (proxied_time - upstream_time).max(0.0) / requests)
+    })
+}
+
+// Keys a table keeps by default, see --max-keys
+pub const DEFAULT_MAX_KEYS: usize = 10_000;
+
+#[derive(Debug, Clone, Default)]
+pub struct TenantStats {
+    pub tenants: HashMap<String, TenantEntry>,
+    // Keys dropped to stay within --max-keys, and their requests and bytes
+    pub evicted: usize,
+    pub evicted_requests: usize,
+    pub evicted_bytes: usize,
+    // The most requests an evicted key was thought to have had, which a
+    // key added since may have had too
+    floor: usize,
+}
+
+impl TenantStats {
+    pub fn record(&mut self, now: Instant, tenant: &str, request: &Request) {
+        let floor = self.floor;
+        let entry = self.tenants.entry(tenant.to_string())
+            .or_insert_with(|| TenantEntry { overcount: floor, ..TenantEntry::new(now, request.timestamp) });
+        // Sampled requests stand for `weight` of them, response time
+        // distributions being the same either way
+        let weight = request.weight;
+        entry.count += weight;
+        entry.bytes += request.bytes_sent * weight;
+        entry.total_response_time += request.response_time * weight as f64;
+        entry.max_response_time = entry.max_response_time.max(request.response_time);
+        if request.response_time > 0.0 {
+            entry.response_times.record(request.response_time);
+        }
+        if let Some(upstream_time) = request.upstream_time {
+            entry.upstream_requests += weight;
+            entry.total_upstream_time += upstream_time * weight as f64;
+            entry.total_proxied_time += request.response_time * weight as f64;
+        }
+        entry.visitors.insert(&request.ip);
+        if let Some(status) = &request.cache_status {
+            entry.cache_lookups += weight;
+            if cache::is_hit(status) {
+                entry.cache_hits += weight;
+            }
+        }
+        match request.status_code {
+            401 | 403 => {
+                entry.client_errors += weight;
+                entry.denied += weight;
+            }
+            400..=499 => entry.client_errors += weight,
+            500..=599 => entry.server_errors += weight,
+            _ => {}
+        }
+        entry.rate.record(now, weight as f64);
+        entry.throughput.record(now, (request.bytes_sent * weight) as f64);
+        entry.last_seen = now;
+        entry.first_logged = entry.first_logged.min(request.timestamp);
+        entry.last_logged = entry.last_logged.max(request.timestamp);
+        let second = request.timestamp.timestamp();
+        entry.peak_second.record(second, weight);
+        entry.peak_minute.record(second.div_euclid(60), weight);
+    }
+
+    // Past `max_keys`, the keys with the fewest requests, counting those they
+    // may have missed, go, see memory::limit. Frequent keys stay and keep
+    // counting, while those of the long tail come and go. True if any went
+    pub fn limit(&mut self, max_keys: usize) -> bool {
+        match memory::limit(&self.tenants, estimate, max_keys) {
+            Some((threshold, mut ties)) => self.evict(threshold, &mut ties),
+            None => false,
+        }
+    }
+
+    // Evicts the keys with fewer requests than `threshold`, and `ties` of
+    // those with as many. True if any went
+    pub fn evict(&mut self, threshold: usize, ties: &mut usize) -> bool {
+        let before = self.tenants.len();
+        let (mut requests, mut bytes, mut floor) = (0, 0, self.floor);
+        memory::evict(&mut self.tenants, estimate, threshold, ties, |entry| {
+            requests += entry.count;
+            bytes += entry.bytes;
+            floor = floor.max(estimate(entry));
+        });
+        self.evicted += before - self.tenants.len();
+        self.evicted_requests += requests;
+        self.evicted_bytes += bytes;
+        self.floor = floor;
+        self.tenants.len() < before
+    }
+
+    // Bytes the keys take, roughly
+    pub fn memory(&self) -> usize {
+        self.tenants.iter().map(|(key, entry)| entry_memory(key, entry)).sum::<usize>() + memory::spare(&self.tenants)
+    }
+
+    // Each key's requests and bytes, for memory::threshold
+    pub fn weigh(&self, keys: &mut Vec<(usize, usize)>) {
+        keys.extend(self.tenants.iter().map(|(key, entry)| (estimate(entry), entry_memory(key, entry))));
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        for entry in self.tenants.values_mut() {
+            entry.rate.tick(now);
+            entry.throughput.tick(now);
+        }
+    }
+}
+
+// Requests a key had, counting those it may have missed
+fn estimate(entry: &TenantEntry) -> usize {
+    entry.count + entry.overcount
+}
+
+fn entry_memory(key: &str, entry: &TenantEntry) -> usize {
+    memory::entry::<TenantEntry>(key) + entry.response_times.memory() + entry.visitors.memory()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_log_line;
+    use crate::stats::group_totals;
+
+    // Keys of the long tail go past --max-keys, and those added after may
+    // have had as many requests as the most an evicted one had
+    #[test]
+    fn evicted_overcount() {
+        let line = r#"192.0.2.1 - - [29/Nov/2021:12:34:56 +0000] "GET / HTTP/1.1" 200 100 "-" "-" 0.002"#;
+        let request = parse_log_line(line).unwrap();
+        let now = Instant::now();
+        let mut groups = TenantStats::default();
+        for _ in 0..10 {
+            groups.record(now, "/busy", &request);
+        }
+        for n in 0..8 {
+            for _ in 0..=n % 2 {
+                groups.record(now, &format!("/rare/{}", n), &request);
+            }
+        }
+        assert!(!groups.limit(9));
+        assert!(groups.limit(4));
+        assert_eq!(groups.tenants.len(), 3);
+        assert_eq!(groups.tenants["/busy"].count, 10);
+        assert_eq!(groups.evicted, 6);
+        assert_eq!(groups.evicted_requests, 8);
+        assert_eq!(group_totals(&groups), (22, 2200));
+
+        groups.record(now, "/new", &request);
+        let new = &groups.tenants["/new"];
+        assert_eq!((new.count, new.overcount), (1, 2));
+        assert_eq!(estimate(new), 3);
+        assert_eq!(groups.tenants["/busy"].overcount, 0);
+    }
+}
@@ -0,0 +1,170 @@
+use std::cell::RefCell;
+use std::io::{self, Stdout, Write};
+use std::panic;
+use std::thread;
+#[cfg(not(unix))]
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(unix))]
+use std::time::Duration;
+
+use crossterm::event::{self, Event};
+use crossterm::{cursor, execute, terminal};
+use ratatui::Frame;
+use ratatui::backend::{CrosstermBackend, TestBackend};
+use ratatui::buffer::Buffer;
+use signal_hook::consts::{SIGINT, SIGTERM};
+#[cfg(unix)]
+use signal_hook::iterator::Signals;
+
+type Screen = ratatui::Terminal<CrosstermBackend<Stdout>>;
+
+// Whether raw mode is on, so it is undone once on exit or panic
+static RAW: AtomicBool = AtomicBool::new(false);
+
+// Raw mode on the terminal for as long as it is alive: keys arrive one by
+// one without Enter or echo, and the display lives on the alternate
+// screen so the shell's scrollback is left as it was
+pub struct Terminal {
+    _private: (),
+}
+
+impl Terminal {
+    pub fn open() -> io::Result<Self> {
+        // Ctrl-C arrives as a key too, so quitting always restores the terminal
+        terminal::enable_raw_mode()?;
+        RAW.store(true, Ordering::SeqCst);
+        let terminal = Terminal { _private: () };
+
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            previous(info);
+        }));
+
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        let screen = Screen::new(CrosstermBackend::new(io::stdout()))?;
+        SCREEN.with(|current| *current.borrow_mut() = Some(screen));
+        Ok(terminal)
+    }
+
+    // Key presses and window resizes, read on the input thread
+    pub fn events(&self) -> Events {
+        Events { _private: () }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        SCREEN.with(|screen| screen.borrow_mut().take());
+        restore();
+    }
+}
+
+fn restore() {
+    if RAW.swap(false, Ordering::SeqCst) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub struct Events {
+    _private: (),
+}
+
+impl Iterator for Events {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        event::read().ok()
+    }
+}
+
+// Calls `terminate` from a thread of its own on SIGTERM, or SIGINT from
+// outside the terminal, so the display is left the way q leaves it
+#[cfg(unix)]
+pub fn on_terminate(terminate: impl FnOnce() + Send + 'static) -> io::Result<()> {
+    let mut signals = Signals::new([SIGTERM, SIGINT])?;
+    thread::spawn(move || {
+        if signals.forever().next().is_some() {
+            terminate();
+        }
+    });
+    Ok(())
+}
+
+// Windows has no signal iterator, so a flag set by the console's Ctrl-Break
+// or close is polled instead
+#[cfg(not(unix))]
+pub fn on_terminate(terminate: impl FnOnce() + Send + 'static) -> io::Result<()> {
+    let flag = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGTERM, Arc::clone(&flag))?;
+    signal_hook::flag::register(SIGINT, Arc::clone(&flag))?;
+    thread::spawn(move || {
+        while !flag.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(100));
+        }
+        terminate();
+    });
+    Ok(())
+}
+
+// Rows and columns of the terminal, when there is one
+pub fn size() -> Option<(usize, usize)> {
+    match terminal::size() {
+        Ok((columns, rows)) if rows > 0 => Some((rows as usize, columns as usize)),
+        _ => None,
+    }
+}
+
+// The size drawn to when stdout is no terminal, columns by rows
+const OFFSCREEN: (u16, u16) = (160, 60);
+
+thread_local! {
+    // The last frame drawn, kept for pausing
+    static SHOWN: RefCell<Buffer> = RefCell::new(Buffer::default());
+    // What is on the terminal, so draw() only sends the cells that changed
+    static SCREEN: RefCell<Option<Screen>> = const { RefCell::new(None) };
+}
+
+// Replaces the screen with what `render` draws. ratatui compares it with
+// the previous frame and sends only the cells that changed, so nothing
+// flickers. Without raw mode the frame is drawn over the normal screen,
+// and where stdout is no terminal it is written out as text
+pub fn draw(render: impl FnOnce(&mut Frame)) -> io::Result<()> {
+    SCREEN.with(|screen| {
+        let mut screen = screen.borrow_mut();
+        if screen.is_none() {
+            *screen = Screen::new(CrosstermBackend::new(io::stdout())).ok();
+            if let Some(screen) = screen.as_mut() {
+                screen.clear()?;
+            }
+        }
+        let shown = match screen.as_mut() {
+            Some(screen) => screen.draw(render)?.buffer.clone(),
+            None => {
+                let Ok(mut offscreen) = ratatui::Terminal::new(TestBackend::new(OFFSCREEN.0, OFFSCREEN.1));
+                let Ok(frame) = offscreen.draw(render);
+                let buffer = frame.buffer.clone();
+                write_text(&buffer)?;
+                buffer
+            }
+        };
+        SHOWN.with(|last| *last.borrow_mut() = shown);
+        Ok(())
+    })
+}
+
+fn write_text(buffer: &Buffer) -> io::Result<()> {
+    let area = buffer.area;
+    let mut stdout = io::stdout().lock();
+    for y in area.top()..area.bottom() {
+        let line: String = (area.left()..area.right()).map(|x| buffer[(x, y)].symbol()).collect();
+        writeln!(stdout, "{}", line.trim_end())?;
+    }
+    stdout.flush()
+}
+
+pub fn last_frame() -> Buffer {
+    SHOWN.with(|shown| shown.borrow().clone())
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::Path;
+
+use toml::Table;
+
+use crate::highlight::Color;
+
+// The colors of the display: the title line, the headers of the tables,
+// the selected row, status classes and slow responses, and the lines and
+// rows that call for attention
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub header: Option<Color>,
+    pub table: Option<Color>,
+    pub selection: Color,
+    // 2xx, 3xx, 4xx and 5xx
+    pub status: [Option<Color>; 4],
+    pub slow: Option<Color>,
+    pub very_slow: Option<Color>,
+    // Filters, toggles, sampling and other notices that counts are off
+    pub warning: Option<Color>,
+    // Firing alerts, violated SLOs and input that does not parse at all
+    pub alert: Option<Color>,
+    // Anomaly banners and the rows of the paths and clients behind them
+    pub anomaly: Option<Color>,
+    // Rows new to the Paths table, and rows that just grew
+    pub new_row: Option<Color>,
+    pub grown_row: Option<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            header: None,
+            table: Some(Color::Bold),
+            selection: Color::Reverse,
+            status: [Some(Color::Green), Some(Color::Cyan), Some(Color::Yellow), Some(Color::Red)],
+            slow: Some(Color::Yellow),
+            very_slow: Some(Color::Red),
+            warning: Some(Color::Yellow),
+            alert: Some(Color::Red),
+            anomaly: Some(Color::Magenta),
+            new_row: Some(Color::Green),
+            grown_row: Some(Color::Yellow),
+        }
+    }
+
+    // Yellow and cyan are unreadable on white, so darker shades from the
+    // extended palette stand in
+    pub fn light() -> Self {
+        Theme {
+            header: None,
+            table: Some(Color::Bold),
+            selection: Color::Reverse,
+            status: [Some(Color::Fixed(28)), Some(Color::Fixed(25)), Some(Color::Fixed(130)), Some(Color::Fixed(160))],
+            slow: Some(Color::Fixed(130)),
+            very_slow: Some(Color::Fixed(160)),
+            warning: Some(Color::Fixed(130)),
+            alert: Some(Color::Fixed(160)),
+            anomaly: Some(Color::Fixed(90)),
+            new_row: Some(Color::Fixed(28)),
+            grown_row: Some(Color::Fixed(130)),
+        }
+    }
+
+    // dark, light, or the path of a theme file
+    pub fn from_arg(value: &str) -> Result<Self, String> {
+        match value {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            path => Theme::load(Path::new(path)),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read theme {}: {}", path.display(), e))?;
+        Theme::parse(&text).map_err(|e| format!("theme {}: {}", path.display(), e))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        Theme::from_table(&text.parse::<Table>().map_err(|e| e.to_string())?)
+    }
+
+    // `key = "value"` lines, as in a theme file or the [colors] of the
+    // config file: a base theme to start from, then any roles to color
+    // differently, "none" for no color
+    pub fn from_table(table: &Table) -> Result<Self, String> {
+        let mut settings = Vec::new();
+        for (key, value) in table {
+            let value = value.as_str().ok_or_else(|| format!("{}: expected a \"color\"", key))?;
+            settings.push((key.as_str(), value));
+        }
+
+        let mut theme = match settings.iter().find(|(key, _)| *key == "base") {
+            None | Some((_, "dark")) => Theme::dark(),
+            Some((_, "light")) => Theme::light(),
+            Some((_, base)) => return Err(format!("unknown base theme '{}' (dark or light)", base)),
+        };
+        for (key, value) in settings.into_iter().filter(|(key, _)| *key != "base") {
+            let color = match value {
+                "none" => None,
+                _ => Some(value.parse::<Color>().map_err(|e| format!("{}: {}", key, e))?),
+            };
+            match key {
+                "header" => theme.header = color,
+                "table" => theme.table = color,
+                "selection" => theme.selection = color.unwrap_or(Color::Reverse),
+                "status_2xx" => theme.status[0] = color,
+                "status_3xx" => theme.status[1] = color,
+                "status_4xx" => theme.status[2] = color,
+                "status_5xx" => theme.status[3] = color,
+                "slow" => theme.slow = color,
+                "very_slow" => theme.very_slow = color,
+                "warning" => theme.warning = color,
+                "alert" => theme.alert = color,
+                "anomaly" => theme.anomaly = color,
+                "new_row" => theme.new_row = color,
+                "grown_row" => theme.grown_row = color,
+                _ => return Err(format!("unknown key '{}' (base, header, table, selection, status_2xx, \
+                    status_3xx, status_4xx, status_5xx, slow, very_slow, warning, alert, anomaly, new_row, \
+                    grown_row)", key)),
+            }
+        }
+        Ok(theme)
+    }
+
+    pub fn status_color(&self, status_code: u16) -> Option<Color> {
+        match status_code {
+            200..=599 => self.status[(status_code / 100 - 2) as usize],
+            _ => None,
+        }
+    }
+
+    // From the slow threshold on, and the very slow one
+    pub fn latency_color(&self, seconds: f64, slow: f64, very_slow: f64) -> Option<Color> {
+        if seconds >= very_slow {
+            self.very_slow
+        } else if seconds >= slow {
+            self.slow
+        } else {
+            None
+        }
+    }
+}
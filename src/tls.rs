@@ -0,0 +1,91 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::memory;
+use crate::uniques::Uniques;
+
+// nginx $ssl_protocol/$ssl_cipher, under either prefix
+pub const PROTOCOL_FIELDS: &[&str] = &["ssl_protocol", "tls_protocol"];
+pub const CIPHER_FIELDS: &[&str] = &["ssl_cipher", "tls_cipher"];
+
+// Protocols most clients and compliance regimes have moved past
+pub fn is_legacy(protocol: &str) -> bool {
+    matches!(protocol, "SSLv2" | "SSLv3" | "TLSv1" | "TLSv1.0" | "TLSv1.1")
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LegacyClient {
+    pub count: usize,
+    pub protocols: HashSet<String>,
+    pub ips: Uniques,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TlsStats {
+    pub protocols: HashMap<String, usize>,
+    pub ciphers: HashMap<String, usize>,
+    // Requests without a protocol field: plain HTTP, or not logged
+    pub without_tls: usize,
+    // By user agent, the clients that would break if legacy TLS went away
+    pub legacy_clients: HashMap<String, LegacyClient>,
+}
+
+impl TlsStats {
+    pub fn record(&mut self, protocol: Option<&str>, cipher: Option<&str>, ip: &str, user_agent: &str, weight: usize) {
+        let Some(protocol) = protocol else {
+            self.without_tls += weight;
+            return;
+        };
+
+        *self.protocols.entry(protocol.to_string()).or_insert(0) += weight;
+        if let Some(cipher) = cipher {
+            *self.ciphers.entry(cipher.to_string()).or_insert(0) += weight;
+        }
+
+        if is_legacy(protocol) {
+            let client = self.legacy_clients.entry(user_agent.to_string()).or_default();
+            client.count += weight;
+            client.protocols.insert(protocol.to_string());
+            client.ips.insert(ip);
+        }
+    }
+
+    // Legacy clients are only remembered while `kept` has their user agent
+    pub fn forget_user_agents<T>(&mut self, kept: &HashMap<String, T>) {
+        self.legacy_clients.retain(|user_agent, _| kept.contains_key(user_agent));
+    }
+
+    pub fn memory(&self) -> usize {
+        let clients: usize = self.legacy_clients.iter()
+            .map(|(user_agent, client)| legacy_client_memory(user_agent, client))
+            .sum();
+        memory::keyed(&self.protocols) + memory::keyed(&self.ciphers) + clients + memory::spare(&self.legacy_clients)
+    }
+
+    // Past max_memory, the legacy clients with fewer requests than
+    // `threshold`, and `ties` of those with as many, go
+    pub fn evict(&mut self, threshold: usize, ties: &mut usize) {
+        memory::evict(&mut self.legacy_clients, |client| client.count, threshold, ties, |_| {});
+        self.legacy_clients.shrink_to_fit();
+    }
+
+    // Each legacy client's requests and bytes, for memory::threshold
+    pub fn weigh(&self, keys: &mut Vec<(usize, usize)>) {
+        keys.extend(self.legacy_clients.iter()
+            .map(|(user_agent, client)| (client.count, legacy_client_memory(user_agent, client))));
+    }
+
+    pub fn tls_requests(&self) -> usize {
+        self.protocols.values().sum()
+    }
+
+    pub fn legacy_requests(&self) -> usize {
+        self.protocols.iter()
+            .filter(|(protocol, _)| is_legacy(protocol))
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+fn legacy_client_memory(user_agent: &str, client: &LegacyClient) -> usize {
+    memory::entry::<LegacyClient>(user_agent) + memory::set(&client.protocols) + client.ips.memory()
+}
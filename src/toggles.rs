@@ -0,0 +1,50 @@
+use crate::Request;
+use crate::content::StaticAssets;
+
+// Filters switched on and off with a key while httop runs. Like a search,
+// they recount every view from the retained requests
+#[derive(Debug, Clone, Default)]
+pub struct Toggles {
+    pub hide_bots: bool,
+    // 4 or 5 for only 4xx or only 5xx responses
+    pub status_class: Option<u16>,
+    pub hide_static: bool,
+    // What counts as a static asset, with any --static-prefix
+    pub static_assets: StaticAssets,
+}
+
+impl Toggles {
+    pub fn any(&self) -> bool {
+        self.hide_bots || self.status_class.is_some() || self.hide_static
+    }
+
+    pub fn allows(&self, request: &Request) -> bool {
+        !(self.hide_bots && request.bot.is_some())
+            && self.status_class.is_none_or(|class| request.status_code / 100 == class)
+            && !(self.hide_static && self.static_assets.bucket(&request.path).is_some())
+    }
+
+    // All responses, then only 4xx, then only 5xx
+    pub fn next_status(&mut self) {
+        self.status_class = match self.status_class {
+            None => Some(4),
+            Some(4) => Some(5),
+            _ => None,
+        };
+    }
+
+    // E.g. "5xx only" and "bots hidden", for the header and help screen
+    pub fn describe(&self) -> Vec<String> {
+        let mut toggles = Vec::new();
+        if let Some(class) = self.status_class {
+            toggles.push(format!("{}xx only", class));
+        }
+        if self.hide_bots {
+            toggles.push("bots hidden".to_string());
+        }
+        if self.hide_static {
+            toggles.push("static assets hidden".to_string());
+        }
+        toggles
+    }
+}
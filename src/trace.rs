@@ -0,0 +1,37 @@
+use std::io;
+use std::process::{Command, Stdio};
+
+pub const DEFAULT_FIELDS: &[&str] = &["trace_id", "request_id", "x_request_id", "traceparent"];
+
+// The first named field present; a W3C traceparent
+// (`00-<trace id>-<span id>-<flags>`) yields just its trace id
+pub fn trace_id(fields: &[(String, String)], names: &[String]) -> Option<String> {
+    let value = names.iter()
+        .find_map(|name| fields.iter().find(|(key, _)| key == name))
+        .map(|(_, value)| value.as_str())?;
+
+    match value.split('-').collect::<Vec<_>>()[..] {
+        [version, trace_id, span_id, flags]
+            if version.len() == 2 && trace_id.len() == 32 && span_id.len() == 16 && flags.len() == 2 => {
+            Some(trace_id.to_string())
+        }
+        _ => Some(value.to_string()),
+    }
+}
+
+// Fills `{id}` in a template such as
+// https://jaeger.example.com/trace/{id}
+pub fn link(template: &str, id: &str) -> String {
+    template.replace("{id}", id)
+}
+
+pub fn open(url: &str) -> io::Result<()> {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    Command::new(opener)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
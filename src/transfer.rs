@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+// Bytes received and compression effectiveness, only fed by requests whose
+// log format carries the extra sizes
+#[derive(Debug, Clone, Default)]
+pub struct TransferEntry {
+    pub count: usize,
+    pub received: usize,
+    // Sent and pre-compression size of the requests where both are known
+    pub compressed: usize,
+    pub uncompressed: usize,
+}
+
+impl TransferEntry {
+    fn record(&mut self, bytes_sent: usize, received: Option<usize>, uncompressed: Option<usize>, weight: usize) {
+        self.count += weight;
+        self.received += received.unwrap_or(0) * weight;
+        if let Some(uncompressed) = uncompressed {
+            self.compressed += bytes_sent * weight;
+            self.uncompressed += uncompressed * weight;
+        }
+    }
+
+    pub fn avg_received(&self) -> usize {
+        self.received.checked_div(self.count).unwrap_or(0)
+    }
+
+    // Uncompressed size divided by bytes on the wire, like nginx's $gzip_ratio
+    pub fn compression_ratio(&self) -> Option<f64> {
+        if self.compressed > 0 {
+            Some(self.uncompressed as f64 / self.compressed as f64)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TransferStats {
+    pub total: TransferEntry,
+    pub paths: HashMap<String, TransferEntry>,
+}
+
+impl TransferStats {
+    pub fn record(&mut self, path: &str, bytes_sent: usize, received: Option<usize>, uncompressed: Option<usize>, weight: usize) {
+        if received.is_none() && uncompressed.is_none() {
+            return;
+        }
+
+        self.total.record(bytes_sent, received, uncompressed, weight);
+        self.paths.entry(path.to_string())
+            .or_default()
+            .record(bytes_sent, received, uncompressed, weight);
+    }
+}
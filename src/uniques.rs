@@ -0,0 +1,108 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+// 2^12 registers, for a standard error of about 1.6%
+const PRECISION: u32 = 12;
+const REGISTERS: usize = 1 << PRECISION;
+// Past this many registers set, a full array takes less room than pairs
+const SPARSE_LIMIT: usize = REGISTERS / 4;
+
+// Distinct values counted with HyperLogLog. Most keys see a handful of
+// clients, so registers start out as a short list of the ones set and
+// become a full 4K array only once that would be smaller
+#[derive(Debug, Clone)]
+pub enum Uniques {
+    Sparse(Vec<(u16, u8)>),
+    Dense(Box<[u8; REGISTERS]>),
+}
+
+impl Default for Uniques {
+    fn default() -> Self {
+        Uniques::Sparse(Vec::new())
+    }
+}
+
+impl Uniques {
+    pub fn insert(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let register = (hash >> (64 - PRECISION)) as u16;
+        // Position of the first 1 bit in what is left of the hash
+        let rank = ((hash << PRECISION) | (1 << (PRECISION - 1))).leading_zeros() as u8 + 1;
+
+        match self {
+            Uniques::Sparse(pairs) => {
+                match pairs.binary_search_by_key(&register, |(index, _)| *index) {
+                    Ok(at) => pairs[at].1 = pairs[at].1.max(rank),
+                    Err(at) => pairs.insert(at, (register, rank)),
+                }
+                if pairs.len() > SPARSE_LIMIT {
+                    let mut registers = Box::new([0; REGISTERS]);
+                    for (index, rank) in pairs.iter() {
+                        registers[*index as usize] = *rank;
+                    }
+                    *self = Uniques::Dense(registers);
+                }
+            }
+            Uniques::Dense(registers) => {
+                let slot = &mut registers[register as usize];
+                *slot = (*slot).max(rank);
+            }
+        }
+    }
+
+    // Bytes of registers on the heap
+    pub fn memory(&self) -> usize {
+        match self {
+            Uniques::Sparse(pairs) => pairs.capacity() * size_of::<(u16, u8)>(),
+            Uniques::Dense(_) => REGISTERS,
+        }
+    }
+
+    pub fn estimate(&self) -> usize {
+        let ranks: Vec<u8> = match self {
+            Uniques::Sparse(pairs) => pairs.iter().map(|(_, rank)| *rank).collect(),
+            Uniques::Dense(registers) => registers.iter().copied().filter(|rank| *rank > 0).collect(),
+        };
+        let m = REGISTERS as f64;
+        let empty = REGISTERS - ranks.len();
+        let sum = empty as f64 + ranks.iter().map(|rank| 2f64.powi(-(*rank as i32))).sum::<f64>();
+        let estimate = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        // Linear counting is closer while many registers are still empty
+        if estimate <= 2.5 * m && empty > 0 {
+            (m * (m / empty as f64).ln()).round() as usize
+        } else {
+            estimate.round() as usize
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn within(estimate: usize, actual: usize, percent: f64) -> bool {
+        (estimate as f64 - actual as f64).abs() <= actual as f64 * percent / 100.0
+    }
+
+    #[test]
+    fn estimate() {
+        let mut uniques = Uniques::default();
+        assert_eq!(uniques.estimate(), 0);
+        for n in 0..500 {
+            // Repeats count once
+            uniques.insert(&format!("192.0.2.{}", n));
+            uniques.insert(&format!("192.0.2.{}", n));
+        }
+        assert!(matches!(uniques, Uniques::Sparse(_)));
+        assert!(within(uniques.estimate(), 500, 5.0), "{}", uniques.estimate());
+
+        // Registers become a full array once that is smaller
+        for n in 500..100_000 {
+            uniques.insert(&format!("192.0.2.{}", n));
+        }
+        assert!(matches!(uniques, Uniques::Dense(_)));
+        assert_eq!(uniques.memory(), REGISTERS);
+        assert!(within(uniques.estimate(), 100_000, 5.0), "{}", uniques.estimate());
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Local};
+
+// The latest unparsed lines kept for the Unparsed Lines view
+pub const KEPT: usize = 100;
+// Of each kept line; the rest is cut
+const MAX_LINE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    // Not laid out as the --format has it
+    Format,
+    // With bytes that are not UTF-8, read as U+FFFD
+    Encoding,
+    // Starting with whitespace, as the lines after the first of a stack
+    // trace or another entry over several lines do
+    Continuation,
+}
+
+impl Reason {
+    pub const ALL: [Reason; 3] = [Reason::Format, Reason::Encoding, Reason::Continuation];
+
+    pub fn of(line: &str, lossy: bool) -> Self {
+        if lossy {
+            Reason::Encoding
+        } else if line.starts_with(char::is_whitespace) {
+            Reason::Continuation
+        } else {
+            Reason::Format
+        }
+    }
+
+    // E.g. "not nginx"
+    pub fn describe(self, format: &str) -> String {
+        match self {
+            Reason::Format => format!("not {}", format),
+            Reason::Encoding => "not UTF-8".to_string(),
+            Reason::Continuation => "continuation".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnparsedLine {
+    pub time: DateTime<Local>,
+    pub source: Arc<str>,
+    pub reason: Reason,
+    // Control characters escaped, so a line cannot drive the terminal
+    pub text: String,
+}
+
+// Lines the format did not parse, by reason, and the latest of them
+#[derive(Debug, Clone, Default)]
+pub struct Unparsed {
+    pub counts: [usize; 3],
+    // Newest first
+    pub recent: VecDeque<UnparsedLine>,
+}
+
+impl Unparsed {
+    pub fn record(&mut self, source: Arc<str>, reason: Reason, line: &str) {
+        self.counts[reason as usize] += 1;
+        if self.recent.len() == KEPT {
+            self.recent.pop_back();
+        }
+        let text = line.chars().take(MAX_LINE)
+            .map(|c| if c.is_control() { c.escape_default().to_string() } else { c.to_string() })
+            .collect();
+        self.recent.push_front(UnparsedLine { time: Local::now(), source, reason, text });
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    // E.g. "1200 not nginx, 34 not UTF-8"
+    pub fn describe(&self, format: &str) -> String {
+        Reason::ALL.iter()
+            .filter(|reason| self.counts[**reason as usize] > 0)
+            .map(|reason| format!("{} {}", self.counts[*reason as usize], reason.describe(format)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::memory;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone)]
+struct Visit {
+    last_page: String,
+    last_seen: DateTime<Utc>,
+    pages: usize,
+}
+
+// Page views grouped into visits per client IP and user agent, a visit
+// ending after `timeout` without a page view. Times are the logged ones, so
+// a log read from the start splits into the visits it had
+#[derive(Debug, Clone)]
+pub struct VisitStats {
+    pub timeout: Duration,
+    open: HashMap<String, Visit>,
+    // Closed visits and their page views
+    pub closed: usize,
+    pub closed_pages: usize,
+    // Closed visits of a single page
+    pub bounces: usize,
+    pub entry_pages: HashMap<String, usize>,
+    pub exit_pages: HashMap<String, usize>,
+    latest: Option<DateTime<Utc>>,
+}
+
+impl VisitStats {
+    pub fn new(timeout: Duration) -> Self {
+        VisitStats {
+            timeout,
+            open: HashMap::new(),
+            closed: 0,
+            closed_pages: 0,
+            bounces: 0,
+            entry_pages: HashMap::new(),
+            exit_pages: HashMap::new(),
+            latest: None,
+        }
+    }
+
+    // No visits yet, but the same timeout
+    pub fn cleared(&self) -> Self {
+        VisitStats::new(self.timeout)
+    }
+
+    pub fn record(&mut self, ip: &str, user_agent: &str, path: &str, timestamp: DateTime<Utc>) {
+        let key = format!("{}\t{}", ip, user_agent);
+        if let Some(visit) = self.open.get_mut(&key)
+            && !expired(visit.last_seen, timestamp, self.timeout) {
+            visit.last_page = path.to_string();
+            visit.last_seen = visit.last_seen.max(timestamp);
+            visit.pages += 1;
+        } else {
+            let visit = Visit { last_page: path.to_string(), last_seen: timestamp, pages: 1 };
+            if let Some(previous) = self.open.insert(key, visit) {
+                self.close(previous);
+            }
+            *self.entry_pages.entry(path.to_string()).or_insert(0) += 1;
+        }
+
+        self.latest = Some(self.latest.map_or(timestamp, |latest| latest.max(timestamp)));
+    }
+
+    // Closes the visits idle for longer than the timeout, as of the latest
+    // page view
+    pub fn sweep(&mut self) {
+        let Some(latest) = self.latest else {
+            return;
+        };
+        let idle: Vec<String> = self.open.iter()
+            .filter(|(_, visit)| expired(visit.last_seen, latest, self.timeout))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in idle {
+            if let Some(visit) = self.open.remove(&key) {
+                self.close(visit);
+            }
+        }
+    }
+
+    fn close(&mut self, visit: Visit) {
+        self.closed += 1;
+        self.closed_pages += visit.pages;
+        if visit.pages == 1 {
+            self.bounces += 1;
+        }
+        *self.exit_pages.entry(visit.last_page).or_insert(0) += 1;
+    }
+
+    // Open visits are only kept while `kept` has their client, those of
+    // the rest closing early
+    pub fn forget_clients<T>(&mut self, kept: &HashMap<String, T>) {
+        let forgotten: Vec<String> = self.open.keys()
+            .filter(|key| key.split_once('\t').is_none_or(|(ip, _)| !kept.contains_key(ip)))
+            .cloned()
+            .collect();
+        for key in forgotten {
+            if let Some(visit) = self.open.remove(&key) {
+                self.close(visit);
+            }
+        }
+    }
+
+    // Entry and exit pages are only kept while `kept` has them
+    pub fn forget_pages<T>(&mut self, kept: &HashMap<String, T>) {
+        self.entry_pages.retain(|path, _| kept.contains_key(path));
+        self.exit_pages.retain(|path, _| kept.contains_key(path));
+    }
+
+    // Visits still open, as of the latest page view
+    pub fn active(&self) -> usize {
+        self.latest.map_or(0, |latest| {
+            self.open.values().filter(|visit| !expired(visit.last_seen, latest, self.timeout)).count()
+        })
+    }
+
+    pub fn visits(&self) -> usize {
+        self.closed + self.open.len()
+    }
+
+    pub fn pages_per_visit(&self) -> f64 {
+        let pages = self.closed_pages + self.open.values().map(|visit| visit.pages).sum::<usize>();
+        pages as f64 / self.visits().max(1) as f64
+    }
+
+    // Bytes of the pages and open visits, which --max-memory counts but
+    // leaves to close once idle
+    pub fn memory(&self) -> usize {
+        let open: usize = self.open.iter()
+            .map(|(key, visit)| memory::entry::<Visit>(key) + memory::entry::<()>(&visit.last_page))
+            .sum();
+        open + memory::keyed(&self.entry_pages) + memory::keyed(&self.exit_pages)
+    }
+
+    pub fn bounce_percent(&self) -> f64 {
+        self.bounces as f64 / self.closed.max(1) as f64 * 100.0
+    }
+}
+
+fn expired(last_seen: DateTime<Utc>, now: DateTime<Utc>, timeout: Duration) -> bool {
+    (now - last_seen).to_std().is_ok_and(|idle| idle > timeout)
+}
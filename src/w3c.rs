@@ -0,0 +1,96 @@
+use chrono::NaiveDateTime;
+
+use crate::campaigns::percent_decode;
+use crate::{formats, Request};
+
+// CloudFront's standard log fields, used until a #Fields: header is seen
+const CLOUDFRONT_FIELDS: &[&str] = &[
+    "date", "time", "x-edge-location", "sc-bytes", "c-ip", "cs-method", "cs(Host)", "cs-uri-stem",
+    "sc-status", "cs(Referer)", "cs(User-Agent)", "cs-uri-query", "cs(Cookie)", "x-edge-result-type",
+    "x-edge-request-id", "x-host-header", "cs-protocol", "cs-bytes", "time-taken", "x-forwarded-for",
+    "ssl-protocol", "ssl-cipher", "x-edge-response-result-type", "cs-protocol-version", "fle-status",
+    "fle-encrypted-fields", "c-port", "time-to-first-byte", "x-edge-detailed-result-type",
+    "sc-content-type", "sc-content-len", "sc-range-start", "sc-range-end",
+];
+
+// W3C extended log format (CloudFront, IIS): whitespace-separated values whose
+// positions are named by the most recent #Fields: directive
+#[derive(Debug, Clone)]
+pub struct W3c {
+    fields: Vec<String>,
+}
+
+impl Default for W3c {
+    fn default() -> Self {
+        W3c { fields: CLOUDFRONT_FIELDS.iter().map(|field| field.to_string()).collect() }
+    }
+}
+
+impl W3c {
+    pub fn parse(&mut self, line: &str) -> Option<Request> {
+        if let Some(directive) = line.strip_prefix('#') {
+            if let Some(fields) = directive.strip_prefix("Fields:") {
+                self.fields = fields.split_whitespace().map(str::to_string).collect();
+            }
+            return None;
+        }
+
+        // CloudFront separates with tabs, IIS with spaces
+        let values: Vec<&str> = if line.contains('\t') {
+            line.split('\t').collect()
+        } else {
+            line.split(' ').collect()
+        };
+        let value = |name: &str| {
+            self.fields.iter().position(|field| field.eq_ignore_ascii_case(name))
+                .and_then(|i| values.get(i).copied())
+                .filter(|value| !value.is_empty() && *value != "-")
+        };
+
+        // Times are always UTC in this format
+        let timestamp = NaiveDateTime::parse_from_str(&format!("{} {}", value("date")?, value("time")?), "%Y-%m-%d %H:%M:%S")
+            .ok()?
+            .and_utc();
+        let mut path = value("cs-uri-stem")?.to_string();
+        if let Some(query) = value("cs-uri-query") {
+            path.push('?');
+            path.push_str(query);
+        }
+        let bytes_sent = value("sc-bytes").and_then(|bytes| bytes.parse().ok()).unwrap_or(0);
+
+        let mut request = Request::new(
+            timestamp,
+            value("c-ip").unwrap_or("-"),
+            value("cs-method")?,
+            &path,
+            value("sc-status")?.parse().ok()?,
+            bytes_sent,
+        );
+        // CloudFront logs HTTP/2.0, IIS HTTP/1.1
+        request.protocol = value("cs-protocol-version").or_else(|| value("cs-version")).and_then(formats::http_version);
+        // CloudFront logs seconds ("0.002"), IIS whole milliseconds
+        request.response_time = value("time-taken")
+            .and_then(|time| Some(if time.contains('.') { time.parse().ok()? } else { time.parse::<f64>().ok()? / 1_000.0 }))
+            .unwrap_or(0.0);
+        if let Some(referrer) = value("cs(Referer)").or_else(|| value("cs(Referrer)")) {
+            request.referrer = percent_decode(referrer);
+        }
+        if let Some(user_agent) = value("cs(User-Agent)") {
+            request.user_agent = percent_decode(user_agent);
+        }
+
+        // The rest become custom fields with nginx-like names (ssl_protocol,
+        // x_edge_location, ...) and cs-bytes as the request size
+        let fields = self.fields.iter().zip(&values)
+            .filter(|(_, value)| !value.is_empty() && **value != "-")
+            .map(|(field, value)| {
+                let name = match field.as_str() {
+                    "cs-bytes" => "request_length".to_string(),
+                    field => field.replace(['-', '(', ')'], "_").trim_end_matches('_').to_lowercase(),
+                };
+                (name, value.to_string())
+            })
+            .collect();
+        Some(request.with_fields(fields))
+    }
+}